@@ -0,0 +1,27 @@
+use std::env;
+
+fn main() -> anyhow::Result<()> {
+    let args = env::args().collect::<Vec<String>>();
+    let args = args.iter().map(|v| v.as_str()).collect::<Vec<_>>();
+
+    let abi = crucible_abi::describe_abi();
+
+    match args.as_slice() {
+        [_bin_name] => print!("{abi}"),
+        [_bin_name, "--hashes"] => {
+            for port in &abi.ports {
+                println!(
+                    "{:016x}\t{}.{}",
+                    port.signature_hash(),
+                    port.module,
+                    port.func_name
+                );
+            }
+
+            println!("{:016x}\t*", abi.signature_hash());
+        }
+        _ => anyhow::bail!("invalid usage"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use bytemuck::{Pod, Zeroable};
+use wasmlink::{Marshal, PodMarshal, Port, marshal_struct};
+
+use crate::AbiError;
+
+// === Buffers === //
+
+/// Creates a sound buffer from interleaved PCM samples in the range `-1.0..=1.0`. Only mono and
+/// stereo buffers are supported.
+pub const AUDIO_CREATE_BUFFER: Port<AudioPcm, Result<AudioBufferHandle, AbiError>> =
+    Port::new("crucible", "audio_create_buffer");
+
+/// Creates a sound buffer from the contents of a WAV or Ogg Vorbis file.
+pub const AUDIO_DECODE_BUFFER: Port<Vec<u8>, Result<AudioBufferHandle, AbiError>> =
+    Port::new("crucible", "audio_decode_buffer");
+
+/// Destroys a sound buffer. Voices which are still playing it keep playing until they finish.
+pub const AUDIO_DESTROY_BUFFER: Port<AudioBufferHandle> =
+    Port::new("crucible", "audio_destroy_buffer");
+
+marshal_struct! {
+    pub struct AudioPcm {
+        pub samples: Vec<f32>,
+        pub channels: u32,
+        pub sample_rate: u32,
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct AudioBufferHandle {
+    pub raw: u32,
+}
+
+impl Marshal for AudioBufferHandle {
+    type Strategy = PodMarshal<Self>;
+}
+
+// === Buses === //
+
+/// Creates a bus feeding into the given parent bus or into the master bus if there is none.
+pub const AUDIO_CREATE_BUS: Port<Option<AudioBusHandle>, AudioBusHandle> =
+    Port::new("crucible", "audio_create_bus");
+
+pub const AUDIO_SET_BUS_VOLUME: Port<AudioBusVolumeArgs> =
+    Port::new("crucible", "audio_set_bus_volume");
+
+/// Destroys a bus which has no child buses, stopping every voice playing into it.
+pub const AUDIO_DESTROY_BUS: Port<AudioBusHandle> = Port::new("crucible", "audio_destroy_bus");
+
+marshal_struct! {
+    pub struct AudioBusVolumeArgs {
+        pub bus: AudioBusHandle,
+        pub volume: f32,
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct AudioBusHandle {
+    pub raw: u32,
+}
+
+impl Marshal for AudioBusHandle {
+    type Strategy = PodMarshal<Self>;
+}
+
+// === Voices === //
+
+/// Starts playing a sound buffer. Voice handles are never reused so operations on a voice which
+/// has finished playing are ignored.
+pub const AUDIO_PLAY: Port<AudioPlayArgs, AudioVoiceHandle> = Port::new("crucible", "audio_play");
+
+pub const AUDIO_SET_VOICE_PARAMS: Port<AudioSetVoiceParamsArgs> =
+    Port::new("crucible", "audio_set_voice_params");
+
+pub const AUDIO_STOP_VOICE: Port<AudioVoiceHandle> = Port::new("crucible", "audio_stop_voice");
+
+marshal_struct! {
+    pub struct AudioPlayArgs {
+        pub buffer: AudioBufferHandle,
+        /// The bus to play into, or the master bus if there is none.
+        pub bus: Option<AudioBusHandle>,
+        pub params: AudioVoiceParams,
+        pub looping: bool,
+    }
+
+    pub struct AudioSetVoiceParamsArgs {
+        pub voice: AudioVoiceHandle,
+        pub params: AudioVoiceParams,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct AudioVoiceParams {
+    /// The gain applied to the voice, where `1.0` leaves it unchanged.
+    pub volume: f32,
+    /// The stereo balance, from `-1.0` for the left channel to `1.0` for the right one.
+    pub pan: f32,
+    /// The playback speed, where `1.0` plays the buffer at its own sample rate.
+    pub pitch: f32,
+}
+
+impl Marshal for AudioVoiceParams {
+    type Strategy = PodMarshal<Self>;
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct AudioVoiceHandle {
+    pub raw: u32,
+}
+
+impl Marshal for AudioVoiceHandle {
+    type Strategy = PodMarshal<Self>;
+}
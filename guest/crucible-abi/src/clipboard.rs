@@ -0,0 +1,28 @@
+use wasmlink::{AsyncPort, Port, marshal_struct};
+
+use crate::{
+    AbiError,
+    math::{Bgra8Color, UVec2},
+};
+
+// === Clipboard === //
+
+/// Reads the text on the clipboard, resolving to `None` if it holds something else. Fails with
+/// `ErrorKind::PermissionDenied` unless the guest was granted the `clipboard` capability.
+pub const CLIPBOARD_READ_TEXT: AsyncPort<(), Result<Option<String>, AbiError>> =
+    AsyncPort::new("crucible", "clipboard_read_text");
+
+/// Replaces the contents of the clipboard with text.
+pub const CLIPBOARD_WRITE_TEXT: Port<String, Result<(), AbiError>> =
+    Port::new("crucible", "clipboard_write_text");
+
+/// Replaces the contents of the clipboard with an image.
+pub const CLIPBOARD_WRITE_IMAGE: Port<ClipboardImage, Result<(), AbiError>> =
+    Port::new("crucible", "clipboard_write_image");
+
+marshal_struct! {
+    pub struct ClipboardImage {
+        pub buffer: Vec<Bgra8Color>,
+        pub size: UVec2,
+    }
+}
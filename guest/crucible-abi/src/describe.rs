@@ -0,0 +1,94 @@
+use wasmlink::AbiDesc;
+
+use crate::*;
+
+/// Describes every port in the Crucible ABI.
+///
+/// New ports must be registered here to be covered by the host's compatibility check, which the
+/// `describe_abi_covers_every_port` test enforces.
+pub fn describe_abi() -> AbiDesc {
+    AbiDesc::new([
+        // audio
+        AUDIO_CREATE_BUFFER.describe(),
+        AUDIO_DECODE_BUFFER.describe(),
+        AUDIO_DESTROY_BUFFER.describe(),
+        AUDIO_CREATE_BUS.describe(),
+        AUDIO_SET_BUS_VOLUME.describe(),
+        AUDIO_DESTROY_BUS.describe(),
+        AUDIO_PLAY.describe(),
+        AUDIO_SET_VOICE_PARAMS.describe(),
+        AUDIO_STOP_VOICE.describe(),
+        // clipboard
+        CLIPBOARD_READ_TEXT.describe(),
+        CLIPBOARD_WRITE_TEXT.describe(),
+        CLIPBOARD_WRITE_IMAGE.describe(),
+        // env
+        GET_RUN_MODE.describe(),
+        GET_SUPPORTED_PORTS.describe(),
+        GET_CURRENT_TIME.describe(),
+        SPAWN_TIMEOUT.describe(),
+        CLEAR_TIMEOUT.describe(),
+        LOG_MESSAGE.describe(),
+        HOT_RELOAD_BIND_HANDLER.describe(),
+        HOT_RELOAD_SAVE_STATE.describe(),
+        HOT_RELOAD_TAKE_STATE.describe(),
+        // gamepad
+        GAMEPAD_BIND_HANDLERS.describe(),
+        GAMEPAD_UNBIND_HANDLERS.describe(),
+        // gpu
+        GPU_CREATE_TEXTURE.describe(),
+        GPU_CLEAR_TEXTURE.describe(),
+        GPU_UPLOAD_TEXTURE.describe(),
+        GPU_DRAW_TEXTURE.describe(),
+        GPU_DESTROY_TEXTURE.describe(),
+        // mods
+        MOD_LIST.describe(),
+        MOD_GET_SELF.describe(),
+        EXTENSION_DECLARE.describe(),
+        EXTENSION_JOIN.describe(),
+        EXTENSION_SEND.describe(),
+        EXTENSION_CLOSE.describe(),
+        // network
+        GAME_SOCKET_GET_ID.describe(),
+        GAME_SOCKET_GET_RTT.describe(),
+        GAME_SOCKET_SEND_MSG.describe(),
+        GAME_SOCKET_RECV_MSG.describe(),
+        GAME_SOCKET_OPEN_CHANNEL.describe(),
+        GAME_SOCKET_CLOSE.describe(),
+        // shell
+        LOGIN_SOCKET_CONNECT.describe(),
+        LOGIN_SOCKET_GET_RTT.describe(),
+        LOGIN_SOCKET_GET_INFO.describe(),
+        LOGIN_SOCKET_DOWNLOAD.describe(),
+        LOGIN_SOCKET_PLAY.describe(),
+        LOGIN_SOCKET_CLOSE.describe(),
+        SHELL_PROCESS_START.describe(),
+        SHELL_PROCESS_SET_PAUSED.describe(),
+        SHELL_PROCESS_REQUEST_STOP.describe(),
+        SHELL_PROCESS_STOP.describe(),
+        // storage
+        STORAGE_READ.describe(),
+        STORAGE_WRITE.describe(),
+        STORAGE_DELETE.describe(),
+        STORAGE_LIST.describe(),
+        STORAGE_GET_USAGE.describe(),
+        // window
+        WINDOW_BIND_HANDLERS.describe(),
+        WINDOW_OPEN.describe(),
+        WINDOW_CLOSE.describe(),
+        WINDOW_REQUEST_REDRAW.describe(),
+        WINDOW_SET_TITLE.describe(),
+        WINDOW_SET_INNER_SIZE.describe(),
+        WINDOW_SET_MIN_INNER_SIZE.describe(),
+        WINDOW_SET_RESIZABLE.describe(),
+        WINDOW_SET_FULLSCREEN.describe(),
+        WINDOW_SET_ICON.describe(),
+        WINDOW_GET_INNER_SIZE.describe(),
+        WINDOW_GET_SCALE_FACTOR.describe(),
+        WINDOW_SET_CURSOR_VISIBLE.describe(),
+        WINDOW_SET_CURSOR_GRAB.describe(),
+        WINDOW_SET_CURSOR_ICON.describe(),
+        WINDOW_SET_IME_ALLOWED.describe(),
+        WINDOW_SET_IME_CURSOR_AREA.describe(),
+    ])
+}
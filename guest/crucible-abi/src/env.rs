@@ -12,6 +12,20 @@ marshal_enum! {
     }
 }
 
+// === Capabilities === //
+
+/// Lists every port the host implements. Unlike other ports, guests may assume this one exists and
+/// use it to probe for optional or newer ports before calling them.
+pub const GET_SUPPORTED_PORTS: Port<(), Vec<PortId>> = Port::new("crucible", "get_supported_ports");
+
+marshal_struct! {
+    pub struct PortId {
+        pub module: String,
+        pub func_name: String,
+        pub version: u32,
+    }
+}
+
 // === Time === //
 
 pub const GET_CURRENT_TIME: Port<(), f64> = Port::new("crucible", "get_current_time");
@@ -38,6 +52,18 @@ impl Marshal for TimeoutHandle {
     type Strategy = PodMarshal<Self>;
 }
 
+// === Hot Reload === //
+
+/// Registers the handler the host calls on the running instance right before replacing it with a
+/// rebuilt module. The handler passes the state it wants to keep to [`HOT_RELOAD_SAVE_STATE`].
+pub const HOT_RELOAD_BIND_HANDLER: Port<fn(())> = Port::new("crucible", "hot_reload_bind_handler");
+
+pub const HOT_RELOAD_SAVE_STATE: Port<Vec<u8>> = Port::new("crucible", "hot_reload_save_state");
+
+/// Takes the state saved by the instance this one replaced, if any.
+pub const HOT_RELOAD_TAKE_STATE: Port<(), Option<Vec<u8>>> =
+    Port::new("crucible", "hot_reload_take_state");
+
 // === Logging === //
 
 pub const LOG_MESSAGE: Port<MessageLogArgs> = Port::new("crucible", "log_message");
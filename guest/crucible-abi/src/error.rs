@@ -0,0 +1,57 @@
+use wasmlink::marshal_struct;
+
+/// The kind of failure an [`AbiError`] reports. Discriminants are part of the ABI and must never
+/// be reused; new kinds are only ever appended.
+///
+/// Kinds are sent as their raw discriminant so that guests built before a kind was appended can
+/// still receive it. Use [`ErrorKind::from_raw`] to decode them.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[repr(u16)]
+pub enum ErrorKind {
+    /// A failure which doesn't fit any of the other kinds.
+    Other = 0,
+    Timeout = 1,
+    ConnectionRefused = 2,
+    /// The peer's certificate could not be verified.
+    CertMismatch = 3,
+    // 4 was used for a kind the host never reported.
+    ClosedByPeer = 5,
+    QuotaExceeded = 6,
+    NotFound = 7,
+    /// The guest was not granted the capability the operation requires.
+    PermissionDenied = 8,
+}
+
+impl ErrorKind {
+    pub const ALL: [Self; 8] = [
+        Self::Other,
+        Self::Timeout,
+        Self::ConnectionRefused,
+        Self::CertMismatch,
+        Self::ClosedByPeer,
+        Self::QuotaExceeded,
+        Self::NotFound,
+        Self::PermissionDenied,
+    ];
+
+    /// Decodes a raw discriminant, falling back to [`ErrorKind::Other`] for kinds this crate
+    /// doesn't know about.
+    pub fn from_raw(raw: u16) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|&kind| kind as u16 == raw)
+            .unwrap_or(Self::Other)
+    }
+}
+
+marshal_struct! {
+    /// An error reported by the host.
+    pub struct AbiError {
+        /// The raw discriminant of the error's [`ErrorKind`].
+        pub kind: u16,
+        /// A human-readable description of the error.
+        pub message: Option<String>,
+        /// The descriptions of the errors which caused this one, outermost first.
+        pub sources: Vec<String>,
+    }
+}
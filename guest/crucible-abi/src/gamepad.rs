@@ -0,0 +1,79 @@
+use wasmlink::{Port, marshal_enum, marshal_struct};
+
+// === Gamepad === //
+
+// Gamepads are identified by an ID which the host assigns when they connect. IDs of disconnected
+// gamepads may be reused for gamepads connected later on.
+
+pub const GAMEPAD_BIND_HANDLERS: Port<GamepadHandlers> =
+    Port::new("crucible", "gamepad_bind_handlers");
+
+pub const GAMEPAD_UNBIND_HANDLERS: Port<()> = Port::new("crucible", "gamepad_unbind_handlers");
+
+marshal_struct! {
+    pub struct GamepadHandlers {
+        /// Called for every gamepad which is already connected once the handlers are bound and for
+        /// every gamepad connected afterwards.
+        pub connected: fn(GamepadInfo),
+        pub disconnected: fn(u32),
+        pub button: fn(GamepadButtonEvent),
+        pub axis: fn(GamepadAxisEvent),
+    }
+
+    pub struct GamepadInfo {
+        pub id: u32,
+        pub name: String,
+    }
+
+    pub struct GamepadButtonEvent {
+        pub id: u32,
+        pub button: GamepadButton,
+        pub pressed: bool,
+    }
+
+    /// Reports the raw position of an axis. Hosts don't apply a deadzone.
+    pub struct GamepadAxisEvent {
+        pub id: u32,
+        pub axis: GamepadAxis,
+        pub value: f32,
+    }
+}
+
+marshal_enum! {
+    /// The buttons of the standard gamepad layout. Face buttons are named after their position so
+    /// that they mean the same thing on every brand of controller.
+    pub enum GamepadButton : u8 {
+        South,
+        East,
+        North,
+        West,
+        LeftBumper,
+        RightBumper,
+        /// Reported as pressed once the left trigger is pulled past its actuation point. Its analog
+        /// position is reported as `GamepadAxis::LeftTrigger`.
+        LeftTrigger,
+        RightTrigger,
+        Select,
+        Start,
+        /// The button in the center of the controller, e.g. the Xbox or PlayStation button.
+        Mode,
+        LeftStick,
+        RightStick,
+        DPadUp,
+        DPadDown,
+        DPadLeft,
+        DPadRight,
+    }
+
+    pub enum GamepadAxis : u8 {
+        /// Ranges from `-1` (left) to `1` (right).
+        LeftStickX,
+        /// Ranges from `-1` (down) to `1` (up).
+        LeftStickY,
+        RightStickX,
+        RightStickY,
+        /// Ranges from `0` (released) to `1` (fully pulled).
+        LeftTrigger,
+        RightTrigger,
+    }
+}
@@ -1,17 +1,43 @@
+mod audio;
+pub use self::audio::*;
+
+mod clipboard;
+pub use self::clipboard::*;
+
+mod describe;
+pub use self::describe::*;
+
 mod env;
 pub use self::env::*;
 
+mod error;
+pub use self::error::*;
+
+mod gamepad;
+pub use self::gamepad::*;
+
 mod gpu;
 pub use self::gpu::*;
 
+mod manifest;
+pub use self::manifest::*;
+
 mod math;
 pub use self::math::*;
 
+mod mods;
+pub use self::mods::*;
+
 mod network;
 pub use self::network::*;
 
 mod shell;
 pub use self::shell::*;
 
+mod storage;
+pub use self::storage::*;
+
+mod tests;
+
 mod window;
 pub use self::window::*;
@@ -0,0 +1,257 @@
+use std::{fmt, str::FromStr};
+
+// === Manifest === //
+
+/// The name of the custom section through which guests declare their [`Manifest`].
+pub const MANIFEST_SECTION: &str = "crucible_manifest";
+
+/// The capabilities a guest asks the user for. Manifests are embedded into the guest's module as
+/// a custom section holding one [`Capability`] per line so that hosts can read them before running
+/// the guest.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Manifest {
+    pub capabilities: Vec<Capability>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Self, ManifestError> {
+        let mut capabilities = Vec::new();
+
+        for (i, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |reason| ManifestError {
+                line: i + 1,
+                reason,
+            };
+            let capability = line.parse::<Capability>().map_err(error)?;
+
+            if let Capability::Storage { .. } = capability
+                && capabilities
+                    .iter()
+                    .any(|other| matches!(other, Capability::Storage { .. }))
+            {
+                return Err(error(
+                    "only one `storage` capability may be declared".to_string(),
+                ));
+            }
+
+            capabilities.push(capability);
+        }
+
+        Ok(Self { capabilities })
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for capability in &self.capabilities {
+            writeln!(f, "{capability}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ManifestError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid manifest entry on line {}: {}",
+            self.line, self.reason
+        )
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+// === Capability === //
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum Capability {
+    /// Opening connections to `host`, written as `net <host>[:<port>]`. A host of `*` matches
+    /// every host and an omitted port matches every port. IPv6 addresses must be enclosed in
+    /// brackets, as in `net [::1]:8080`, and are stored without them.
+    Network { host: String, port: Option<u16> },
+    /// Keeping data on the user's device, written as `storage <id>`. The data is kept under `id`
+    /// rather than the module's hash so that it survives updates. Ids may only contain ASCII
+    /// letters, digits, `.`, `-` and `_`.
+    Storage { id: String },
+    /// Reading the clipboard, written as `clipboard`. Writing to it needs no capability.
+    Clipboard,
+    /// Making windows fullscreen, written as `fullscreen`.
+    Fullscreen,
+}
+
+impl Capability {
+    /// Checks whether this capability allows connecting to `addr`, given as `<host>:<port>` or
+    /// `[<IPv6 address>]:<port>`.
+    pub fn allows_connection(&self, addr: &str) -> bool {
+        let Capability::Network { host, port } = self else {
+            return false;
+        };
+
+        let Ok((addr_host, Some(addr_port))) = split_host_port(addr) else {
+            return false;
+        };
+
+        (host == "*" || host.eq_ignore_ascii_case(addr_host))
+            && port.is_none_or(|port| addr_port.parse::<u16>() == Ok(port))
+    }
+}
+
+impl FromStr for Capability {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let kind = parts.next().unwrap_or_default();
+
+        let capability = match kind {
+            "net" => {
+                let target = parts.next().ok_or("`net` requires a host")?;
+                let (host, port) = split_host_port(target)?;
+
+                Capability::Network {
+                    host: host.to_string(),
+                    port: port
+                        .map(|port| port.parse().map_err(|_| format!("invalid port `{port}`")))
+                        .transpose()?,
+                }
+            }
+            "storage" => {
+                let id = parts.next().ok_or("`storage` requires an id")?;
+
+                if !is_valid_storage_id(id) {
+                    return Err(format!("invalid storage id `{id}`"));
+                }
+
+                Capability::Storage { id: id.to_string() }
+            }
+            "clipboard" => Capability::Clipboard,
+            "fullscreen" => Capability::Fullscreen,
+            kind => return Err(format!("unknown capability `{kind}`")),
+        };
+
+        if let Some(extra) = parts.next() {
+            return Err(format!("unexpected `{extra}` after `{kind}`"));
+        }
+
+        Ok(capability)
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capability::Network { host, port: None } if host.contains(':') => {
+                write!(f, "net [{host}]")
+            }
+            Capability::Network { host, port: None } => write!(f, "net {host}"),
+            Capability::Network {
+                host,
+                port: Some(port),
+            } if host.contains(':') => write!(f, "net [{host}]:{port}"),
+            Capability::Network {
+                host,
+                port: Some(port),
+            } => write!(f, "net {host}:{port}"),
+            Capability::Storage { id } => write!(f, "storage {id}"),
+            Capability::Clipboard => f.write_str("clipboard"),
+            Capability::Fullscreen => f.write_str("fullscreen"),
+        }
+    }
+}
+
+/// Splits `target` into its host and port. IPv6 hosts must be enclosed in brackets since their
+/// colons would otherwise be mistaken for the port separator.
+fn split_host_port(target: &str) -> Result<(&str, Option<&str>), String> {
+    let (host, port) = match target.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| format!("unclosed `[` in `{target}`"))?;
+
+            let port = match rest {
+                "" => None,
+                rest => Some(
+                    rest.strip_prefix(':')
+                        .ok_or_else(|| format!("unexpected `{rest}` after `[{host}]`"))?,
+                ),
+            };
+
+            (host, port)
+        }
+        None => match target.split_once(':') {
+            Some((_, port)) if port.contains(':') => {
+                return Err(format!(
+                    "IPv6 addresses must be written as `[<address>]:<port>`, not `{target}`"
+                ));
+            }
+            Some((host, port)) => (host, Some(port)),
+            None => (target, None),
+        },
+    };
+
+    if host.is_empty() {
+        return Err(format!("missing host in `{target}`"));
+    }
+
+    Ok((host, port))
+}
+
+fn is_valid_storage_id(id: &str) -> bool {
+    !id.starts_with('.')
+        && id
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'.' | b'-' | b'_'))
+}
+
+// === Embedding === //
+
+#[doc(hidden)]
+pub mod declare_manifest_internals {
+    pub use std::concat;
+
+    pub const fn to_array<const N: usize>(text: &str) -> [u8; N] {
+        let bytes = text.as_bytes();
+        let mut out = [0; N];
+        let mut i = 0;
+
+        while i < N {
+            out[i] = bytes[i];
+            i += 1;
+        }
+
+        out
+    }
+}
+
+/// Embeds a [`Manifest`] into the guest's module. Each argument is a single [`Capability`] in its
+/// textual form.
+///
+/// ```ignore
+/// crucible_abi::declare_manifest!("net play.example.com:8080", "storage com.example.game");
+/// ```
+#[macro_export]
+macro_rules! declare_manifest {
+    ($($capability:literal),* $(,)?) => {
+        #[cfg(target_arch = "wasm32")]
+        const _: () = {
+            const TEXT: &str = $crate::declare_manifest_internals::concat!($($capability, "\n"),*);
+
+            #[used]
+            #[unsafe(link_section = "crucible_manifest")]
+            static MANIFEST: [u8; TEXT.len()] =
+                $crate::declare_manifest_internals::to_array(TEXT);
+        };
+    };
+}
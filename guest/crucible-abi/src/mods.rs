@@ -0,0 +1,73 @@
+use bytemuck::{Pod, Zeroable};
+use wasmlink::{Marshal, PodMarshal, Port, marshal_struct};
+
+use crate::{AbiError, ContentHash};
+
+// === Mods === //
+
+/// Lists the mods loaded alongside the game in load order. The mod at index `i` is peer `i + 1`;
+/// peer `0` is always the game itself.
+pub const MOD_LIST: Port<(), Vec<ModInfo>> = Port::new("crucible", "mod_list");
+
+/// Gets the peer ID of the calling instance.
+pub const MOD_GET_SELF: Port<(), u32> = Port::new("crucible", "mod_get_self");
+
+marshal_struct! {
+    pub struct ModInfo {
+        pub name: String,
+        pub content_hash: ContentHash,
+    }
+}
+
+// === Extension Points === //
+
+/// Declares an extension point which mods can join. Only the game may declare extension points
+/// and their names must be unique.
+pub const EXTENSION_DECLARE: Port<ExtensionOpenArgs, Result<ExtensionHandle, AbiError>> =
+    Port::new("crucible", "extension_declare");
+
+/// Joins an extension point declared by the game. Fails with [`ErrorKind::NotFound`] if the game
+/// hasn't declared it.
+///
+/// [`ErrorKind::NotFound`]: crate::ErrorKind::NotFound
+pub const EXTENSION_JOIN: Port<ExtensionOpenArgs, Result<ExtensionHandle, AbiError>> =
+    Port::new("crucible", "extension_join");
+
+/// Queues a message to the other members of an extension point. Messages are delivered by the host
+/// once the current call into the guest returns, in the order they were sent.
+pub const EXTENSION_SEND: Port<ExtensionSendArgs, Result<(), AbiError>> =
+    Port::new("crucible", "extension_send");
+
+/// Leaves an extension point. Closing an extension point declared by the game removes it for
+/// every member.
+pub const EXTENSION_CLOSE: Port<ExtensionHandle> = Port::new("crucible", "extension_close");
+
+marshal_struct! {
+    pub struct ExtensionOpenArgs {
+        pub name: String,
+        pub handler: fn(ExtensionMessage),
+    }
+
+    pub struct ExtensionSendArgs {
+        pub extension: ExtensionHandle,
+        /// The peer to send the message to or `None` to send it to every other member.
+        pub peer: Option<u32>,
+        pub message: Vec<u8>,
+    }
+
+    pub struct ExtensionMessage {
+        /// The peer which sent the message.
+        pub peer: u32,
+        pub message: Vec<u8>,
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct ExtensionHandle {
+    pub raw: u32,
+}
+
+impl Marshal for ExtensionHandle {
+    type Strategy = PodMarshal<Self>;
+}
@@ -1,5 +1,7 @@
 use bytemuck::{Pod, Zeroable};
-use wasmlink::{Marshal, PodMarshal, Port, marshal_struct};
+use wasmlink::{AsyncPort, Marshal, PodMarshal, Port, marshal_struct};
+
+use crate::AbiError;
 
 pub const GAME_SOCKET_GET_ID: Port<GameSocketHandle, u64> =
     Port::new("crucible", "game_socket_get_id");
@@ -7,13 +9,10 @@ pub const GAME_SOCKET_GET_ID: Port<GameSocketHandle, u64> =
 pub const GAME_SOCKET_GET_RTT: Port<GameSocketHandle, Option<f64>> =
     Port::new("crucible", "game_socket_get_rtt");
 
-pub const GAME_SOCKET_SEND_MSG: Port<GameSocketSendMsgArgs> =
-    Port::new("crucible", "game_socket_send_msg");
-
-pub const GAME_SOCKET_CANCEL_SEND_MSG: Port<GameSocketHandle> =
-    Port::new("crucible", "game_socket_cancel_send_msg");
+pub const GAME_SOCKET_SEND_MSG: AsyncPort<GameSocketSendMsgArgs, Result<(), AbiError>> =
+    AsyncPort::new("crucible", "game_socket_send_msg");
 
-pub const GAME_SOCKET_RECV_MSG: Port<GameSocketHandle, Result<Vec<u8>, String>> =
+pub const GAME_SOCKET_RECV_MSG: Port<GameSocketHandle, Result<Vec<u8>, AbiError>> =
     Port::new("crucible", "game_socket_recv_msg");
 
 pub const GAME_SOCKET_OPEN_CHANNEL: Port<GameSocketHandle, GameSocketHandle> =
@@ -25,7 +24,6 @@ marshal_struct! {
     pub struct GameSocketSendMsgArgs {
         pub socket: GameSocketHandle,
         pub message: Vec<u8>,
-        pub callback: fn(Result<(), String>),
     }
 }
 
@@ -1,41 +1,38 @@
 use bytemuck::{Pod, Zeroable};
-use wasmlink::{Marshal, PodMarshal, Port, marshal_struct, marshal_tagged_union};
+use wasmlink::{AsyncPort, Marshal, PodMarshal, Port, marshal_struct, marshal_tagged_union};
 
-use crate::GameSocketHandle;
+use crate::{AbiError, GameSocketHandle, ModInfo};
 
 // === Socket === //
 
-pub const LOGIN_SOCKET_CONNECT: Port<LoginSocketConnectArgs> =
-    Port::new("crucible", "login_socket_connect");
+pub const LOGIN_SOCKET_CONNECT: AsyncPort<String, Result<LoginSocketHandle, AbiError>> =
+    AsyncPort::new("crucible", "login_socket_connect");
 
 pub const LOGIN_SOCKET_GET_RTT: Port<LoginSocketHandle, Option<f64>> =
     Port::new("crucible", "login_socket_get_rtt");
 
-pub const LOGIN_SOCKET_GET_INFO: Port<LoginSocketGetInfoArgs> =
-    Port::new("crucible", "login_socket_get_info");
+pub const LOGIN_SOCKET_GET_INFO: AsyncPort<LoginSocketHandle, Result<LoginServerInfo, AbiError>> =
+    AsyncPort::new("crucible", "login_socket_get_info");
 
 pub const LOGIN_SOCKET_DOWNLOAD: Port<LoginSocketDownloadArgs> =
     Port::new("crucible", "login_socket_download");
 
-pub const LOGIN_SOCKET_PLAY: Port<LoginSocketPlayArgs> = Port::new("crucible", "login_socket_play");
+pub const LOGIN_SOCKET_PLAY: AsyncPort<
+    LoginSocketPlayArgs,
+    Result<Result<GameSocketHandle, ContentHash>, AbiError>,
+> = AsyncPort::new("crucible", "login_socket_play");
 
 pub const LOGIN_SOCKET_CLOSE: Port<LoginSocketHandle> = Port::new("crucible", "login_socket_close");
 
 marshal_struct! {
-    pub struct LoginSocketConnectArgs {
-        pub addr: String,
-        pub callback: fn(Result<LoginSocketHandle, String>),
-    }
-
-    pub struct LoginSocketGetInfoArgs {
-        pub socket: LoginSocketHandle,
-        pub callback: fn(Result<LoginServerInfo, String>),
-    }
-
     pub struct LoginServerInfo {
         pub motd: String,
         pub content_hash: ContentHash,
         pub content_server: Option<String>,
+        /// The mods the server requires, in load order. Mods are always downloaded from the server
+        /// directly, and those which aren't loaded yet are fetched before the info is returned and
+        /// started right after.
+        pub mods: Vec<ModInfo>,
     }
 
     pub struct LoginSocketDownloadArgs {
@@ -47,7 +44,6 @@ marshal_struct! {
     pub struct LoginSocketPlayArgs {
         pub socket: LoginSocketHandle,
         pub content_hash: ContentHash,
-        pub callback: fn(Result<Result<GameSocketHandle, ContentHash>, String>),
     }
 }
 
@@ -55,7 +51,7 @@ marshal_tagged_union! {
     pub enum LoginSocketDownloadEvent: u16 {
         Finished(()),
         Progress(f64),
-        Error(String),
+        Error(AbiError),
     }
 }
 
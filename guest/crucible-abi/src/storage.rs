@@ -0,0 +1,43 @@
+use wasmlink::{AsyncPort, Port, marshal_struct};
+
+use crate::AbiError;
+
+// === Storage === //
+
+// Guests store data as a flat set of keys, each of which holds a byte blob. Keys may only contain
+// ASCII letters, digits, `.`, `-` and `_` and may not start with a `.`. Every port fails with
+// `ErrorKind::PermissionDenied` unless the guest was granted the `storage` capability.
+
+/// Reads the value of a key, resolving to `None` if the key was never written.
+pub const STORAGE_READ: AsyncPort<String, Result<Option<Vec<u8>>, AbiError>> =
+    AsyncPort::new("crucible", "storage_read");
+
+/// Replaces the value of a key. Writes are atomic: a crash never leaves a partially written value
+/// behind. Fails with `ErrorKind::QuotaExceeded` if the write would take the guest over its quota.
+pub const STORAGE_WRITE: Port<StorageWriteArgs, Result<(), AbiError>> =
+    Port::new("crucible", "storage_write");
+
+/// Removes a key. Removing a key which doesn't exist succeeds.
+pub const STORAGE_DELETE: Port<String, Result<(), AbiError>> =
+    Port::new("crucible", "storage_delete");
+
+/// Lists every key holding a value, in no particular order.
+pub const STORAGE_LIST: Port<(), Result<Vec<String>, AbiError>> =
+    Port::new("crucible", "storage_list");
+
+pub const STORAGE_GET_USAGE: Port<(), Result<StorageUsage, AbiError>> =
+    Port::new("crucible", "storage_get_usage");
+
+marshal_struct! {
+    pub struct StorageWriteArgs {
+        pub key: String,
+        pub data: Vec<u8>,
+    }
+
+    pub struct StorageUsage {
+        /// The number of bytes taken up by the guest's values.
+        pub used: u64,
+        /// The number of bytes the guest may use in total.
+        pub quota: u64,
+    }
+}
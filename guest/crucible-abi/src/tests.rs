@@ -0,0 +1,158 @@
+#![cfg(test)]
+
+use std::{fs, path::Path};
+
+use crate::*;
+
+#[test]
+fn describe_abi_covers_every_port() {
+    let abi = describe_abi();
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+
+    let mut defined = Vec::new();
+
+    for entry in fs::read_dir(&src).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.ends_with("tests.rs") {
+            continue;
+        }
+
+        let text = fs::read_to_string(&path).unwrap();
+
+        // Every port is defined through a `Port::new` or `AsyncPort::new` call whose arguments are
+        // the module and function names.
+        for (start, pattern) in text.match_indices("Port::new(") {
+            let args = &text[start + pattern.len()..];
+            let args = &args[..args.find(')').unwrap()];
+
+            let [module, func_name] = args
+                .split(',')
+                .map(|arg| arg.trim().trim_matches('"'))
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap_or_else(|_| panic!("unexpected port definition in {}", path.display()));
+
+            defined.push((module.to_string(), func_name.to_string()));
+        }
+    }
+
+    assert!(!defined.is_empty());
+
+    for (module, func_name) in &defined {
+        assert!(
+            abi.find(module, func_name).is_some(),
+            "port `{module}.{func_name}` is missing from `describe_abi`"
+        );
+    }
+
+    assert_eq!(abi.ports.len(), defined.len());
+}
+
+#[test]
+fn manifests_parse_one_capability_per_line() {
+    let manifest = Manifest::parse(
+        "net play.example.com:8080\n\n  storage com.example.game  \nclipboard\nfullscreen\nnet *\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        manifest.capabilities,
+        [
+            Capability::Network {
+                host: "play.example.com".to_string(),
+                port: Some(8080),
+            },
+            Capability::Storage {
+                id: "com.example.game".to_string(),
+            },
+            Capability::Clipboard,
+            Capability::Fullscreen,
+            Capability::Network {
+                host: "*".to_string(),
+                port: None,
+            },
+        ]
+    );
+
+    assert_eq!(Manifest::parse(&manifest.to_string()).unwrap(), manifest);
+    assert_eq!(Manifest::parse("").unwrap(), Manifest::default());
+
+    let err = Manifest::parse("clipboard\nmicrophone").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert_eq!(err.reason, "unknown capability `microphone`");
+
+    let err = Manifest::parse("storage a\nstorage b").unwrap_err();
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn capabilities_reject_malformed_entries() {
+    for text in [
+        "",
+        "net",
+        "net :8080",
+        "net example.com:http",
+        "net example.com:65536",
+        "net example.com 8080",
+        "storage",
+        "storage ../escape",
+        "storage .hidden",
+        "clipboard please",
+        "net ::1",
+        "net ::1:8080",
+        "net fe80::1",
+        "net [::1",
+        "net [::1]8080",
+        "net []:8080",
+    ] {
+        assert!(text.parse::<Capability>().is_err(), "{text:?} was accepted");
+    }
+}
+
+#[test]
+fn ipv6_capabilities_require_brackets() {
+    let capability = "net [::1]:8080".parse::<Capability>().unwrap();
+
+    assert_eq!(
+        capability,
+        Capability::Network {
+            host: "::1".to_string(),
+            port: Some(8080),
+        }
+    );
+    assert_eq!(capability.to_string(), "net [::1]:8080");
+
+    let capability = "net [fe80::1]".parse::<Capability>().unwrap();
+    assert_eq!(capability.to_string(), "net [fe80::1]");
+    assert_eq!(capability.to_string().parse::<Capability>(), Ok(capability));
+}
+
+#[test]
+fn network_capabilities_match_connections() {
+    let allows = |capability: &str, addr: &str| {
+        capability
+            .parse::<Capability>()
+            .unwrap()
+            .allows_connection(addr)
+    };
+
+    assert!(allows("net example.com:8080", "example.com:8080"));
+    assert!(allows("net example.com:8080", "EXAMPLE.com:8080"));
+    assert!(!allows("net example.com:8080", "example.com:8081"));
+    assert!(!allows("net example.com:8080", "other.com:8080"));
+    assert!(!allows("net example.com:8080", "example.com"));
+
+    assert!(allows("net example.com", "example.com:1"));
+    assert!(!allows("net example.com", "sub.example.com:1"));
+    assert!(allows("net *", "anything.net:443"));
+    assert!(allows("net *:443", "anything.net:443"));
+    assert!(!allows("net *:443", "anything.net:80"));
+
+    assert!(allows("net [::1]:8080", "[::1]:8080"));
+    assert!(!allows("net [::1]:8080", "[::2]:8080"));
+    assert!(!allows("net [::1]:8080", "::1:8080"));
+    assert!(allows("net *", "[::1]:8080"));
+
+    assert!(!allows("clipboard", "example.com:8080"));
+}
@@ -1,16 +1,250 @@
-use wasmlink::{Port, marshal_struct, marshal_tagged_union};
+use bytemuck::{Pod, Zeroable};
+use wasmlink::{Marshal, PodMarshal, Port, marshal_enum, marshal_struct, marshal_tagged_union};
 
 use crate::{
+    AbiError,
     gpu::GpuTextureHandle,
-    math::{DVec2, UVec2},
+    math::{Bgra8Color, DVec2, UVec2},
 };
 
-pub const WINDOW_REQUEST_REDRAW: Port<()> = Port::new("crucible", "window_request_redraw");
+// === Window === //
 
-pub const WINDOW_BIND_HANDLERS: Port<WindowHandlers> =
+// Every window port takes the handle of the window it acts on. The main window is created by the
+// host and bound with `WINDOW_BIND_HANDLERS` while secondary windows are opened by the guest.
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct WindowHandle {
+    pub raw: u32,
+}
+
+impl Marshal for WindowHandle {
+    type Strategy = PodMarshal<Self>;
+}
+
+/// Binds the handlers of the main window, returning its handle.
+pub const WINDOW_BIND_HANDLERS: Port<WindowHandlers, WindowHandle> =
     Port::new("crucible", "window_bind_handlers");
 
-pub const WINDOW_UNBIND_HANDLERS: Port<()> = Port::new("crucible", "window_unbind_handlers");
+/// Opens a secondary window. The host creates the window once control returns to its event loop
+/// so the handlers only start receiving events after that.
+pub const WINDOW_OPEN: Port<WindowOpenArgs, WindowHandle> = Port::new("crucible", "window_open");
+
+/// Unbinds the window's handlers and closes it. Closing the main window ends the session once
+/// control returns to the host.
+pub const WINDOW_CLOSE: Port<WindowHandle> = Port::new("crucible", "window_close");
+
+pub const WINDOW_REQUEST_REDRAW: Port<WindowHandle> =
+    Port::new("crucible", "window_request_redraw");
+
+pub const WINDOW_SET_TITLE: Port<WindowSetTitleArgs> = Port::new("crucible", "window_set_title");
+
+/// Requests a new size for the window's client area, in physical pixels. The platform may pick a
+/// different size or ignore the request entirely; the actual size is reported through the
+/// `resized` handler.
+pub const WINDOW_SET_INNER_SIZE: Port<WindowSetSizeArgs> =
+    Port::new("crucible", "window_set_inner_size");
+
+/// Sets the size below which the user can't shrink the window's client area, in physical pixels.
+/// `None` removes the limit.
+pub const WINDOW_SET_MIN_INNER_SIZE: Port<WindowSetMinSizeArgs> =
+    Port::new("crucible", "window_set_min_inner_size");
+
+pub const WINDOW_SET_RESIZABLE: Port<WindowFlagArgs> =
+    Port::new("crucible", "window_set_resizable");
+
+/// Fails with `ErrorKind::PermissionDenied` unless the guest was granted the `fullscreen`
+/// capability. Returning to `FullscreenMode::Windowed` is always allowed.
+pub const WINDOW_SET_FULLSCREEN: Port<WindowSetFullscreenArgs, Result<(), AbiError>> =
+    Port::new("crucible", "window_set_fullscreen");
+
+/// Sets the icon shown in the window's title bar and the task bar. `None` restores the default
+/// icon.
+pub const WINDOW_SET_ICON: Port<WindowSetIconArgs> = Port::new("crucible", "window_set_icon");
+
+/// Returns the size of the window's client area, in physical pixels.
+pub const WINDOW_GET_INNER_SIZE: Port<WindowHandle, UVec2> =
+    Port::new("crucible", "window_get_inner_size");
+
+/// Returns the number of physical pixels per logical pixel of the display showing the window.
+pub const WINDOW_GET_SCALE_FACTOR: Port<WindowHandle, f64> =
+    Port::new("crucible", "window_get_scale_factor");
+
+marshal_struct! {
+    pub struct WindowOpenArgs {
+        pub title: String,
+        /// The initial size of the window's client area, in physical pixels.
+        pub size: UVec2,
+        pub handlers: WindowHandlers,
+    }
+
+    /// The arguments of ports which turn a setting of the window on or off.
+    pub struct WindowFlagArgs {
+        pub window: WindowHandle,
+        pub enabled: bool,
+    }
+
+    pub struct WindowSetTitleArgs {
+        pub window: WindowHandle,
+        pub title: String,
+    }
+
+    pub struct WindowSetSizeArgs {
+        pub window: WindowHandle,
+        pub size: UVec2,
+    }
+
+    pub struct WindowSetMinSizeArgs {
+        pub window: WindowHandle,
+        pub size: Option<UVec2>,
+    }
+
+    pub struct WindowSetFullscreenArgs {
+        pub window: WindowHandle,
+        pub mode: FullscreenMode,
+    }
+
+    pub struct WindowSetIconArgs {
+        pub window: WindowHandle,
+        pub icon: Option<WindowIcon>,
+    }
+}
+
+marshal_enum! {
+    pub enum FullscreenMode : u8 {
+        Windowed,
+        /// Covers the current monitor with a borderless window.
+        Borderless,
+        /// Switches the current monitor to its native video mode. Hosts fall back to `Borderless`
+        /// on platforms which don't support exclusive fullscreen.
+        Exclusive,
+    }
+}
+
+marshal_struct! {
+    pub struct WindowIcon {
+        pub buffer: Vec<Bgra8Color>,
+        pub size: UVec2,
+    }
+}
+
+// === Cursor === //
+
+pub const WINDOW_SET_CURSOR_VISIBLE: Port<WindowFlagArgs> =
+    Port::new("crucible", "window_set_cursor_visible");
+
+/// Confines or locks the cursor to the window. Hosts fall back to the other grab mode if the
+/// platform doesn't support the requested one and re-apply the grab when the window regains focus.
+pub const WINDOW_SET_CURSOR_GRAB: Port<WindowSetCursorGrabArgs> =
+    Port::new("crucible", "window_set_cursor_grab");
+
+pub const WINDOW_SET_CURSOR_ICON: Port<WindowSetCursorIconArgs> =
+    Port::new("crucible", "window_set_cursor_icon");
+
+marshal_struct! {
+    pub struct WindowSetCursorGrabArgs {
+        pub window: WindowHandle,
+        pub mode: CursorGrabMode,
+    }
+
+    pub struct WindowSetCursorIconArgs {
+        pub window: WindowHandle,
+        pub icon: CursorIcon,
+    }
+}
+
+marshal_enum! {
+    pub enum CursorGrabMode : u8 {
+        None,
+        /// The cursor can move but not leave the window.
+        Confined,
+        /// The cursor can't move at all. Games read the mouse through the `mouse_motion` handler
+        /// instead.
+        Locked,
+    }
+
+    /// The cursor icons every host supports. These mirror the CSS `cursor` values.
+    pub enum CursorIcon : u8 {
+        Default,
+        ContextMenu,
+        Help,
+        Pointer,
+        Progress,
+        Wait,
+        Cell,
+        Crosshair,
+        Text,
+        VerticalText,
+        Alias,
+        Copy,
+        Move,
+        NoDrop,
+        NotAllowed,
+        Grab,
+        Grabbing,
+        EResize,
+        NResize,
+        NeResize,
+        NwResize,
+        SResize,
+        SeResize,
+        SwResize,
+        WResize,
+        EwResize,
+        NsResize,
+        NeswResize,
+        NwseResize,
+        ColResize,
+        RowResize,
+        AllScroll,
+        ZoomIn,
+        ZoomOut,
+    }
+}
+
+// === IME === //
+
+/// Allows the platform's input method editor to be used for text input. While allowed, keys which
+/// feed the IME are reported through the `ime` handler rather than `key_event`.
+pub const WINDOW_SET_IME_ALLOWED: Port<WindowFlagArgs> =
+    Port::new("crucible", "window_set_ime_allowed");
+
+/// Tells the IME where the text being edited is so that it can place its candidate window nearby.
+pub const WINDOW_SET_IME_CURSOR_AREA: Port<ImeCursorArea> =
+    Port::new("crucible", "window_set_ime_cursor_area");
+
+marshal_struct! {
+    /// A rectangle in the window's client area, in physical pixels.
+    pub struct ImeCursorArea {
+        pub window: WindowHandle,
+        pub position: DVec2,
+        pub size: DVec2,
+    }
+
+    pub struct ImePreedit {
+        pub text: String,
+        /// The byte range of `text` the IME's cursor covers. `None` hides the cursor.
+        pub cursor: Option<ImeCursorRange>,
+    }
+
+    pub struct ImeCursorRange {
+        pub start: u32,
+        pub end: u32,
+    }
+}
+
+marshal_tagged_union! {
+    pub enum ImeEvent : u8 {
+        Enabled(()),
+        /// The text being composed changed. An empty preedit clears the composition.
+        Preedit(ImePreedit),
+        /// The composition was finished, producing the given text.
+        Commit(String),
+        Disabled(()),
+    }
+}
+
+// === Events === //
 
 marshal_struct! {
     pub struct WindowHandlers {
@@ -19,6 +253,18 @@ marshal_struct! {
         pub mouse_event: fn(MouseEvent),
         pub key_event: fn(KeyEvent),
         pub exit_requested: fn(()),
+        pub mouse_wheel: fn(MouseScrollDelta),
+        pub cursor_entered: fn(()),
+        pub cursor_left: fn(()),
+        pub focused: fn(bool),
+        pub occluded: fn(bool),
+        /// The new size of the window's client area, in physical pixels.
+        pub resized: fn(UVec2),
+        pub scale_factor_changed: fn(f64),
+        /// Raw, unaccelerated mouse movement, delivered only while the window has focus. Unlike
+        /// `mouse_moved`, this keeps reporting motion while the cursor is locked.
+        pub mouse_motion: fn(DVec2),
+        pub ime: fn(ImeEvent),
     }
 
     pub struct MouseEvent {
@@ -58,6 +304,13 @@ marshal_tagged_union! {
         Web(String),
     }
 
+    pub enum MouseScrollDelta : u8 {
+        /// A scroll in lines and rows, as reported by most mouse wheels.
+        Line(DVec2),
+        /// A scroll in physical pixels, as reported by touchpads.
+        Pixel(DVec2),
+    }
+
     pub enum MouseButton : u8 {
         Left(()),
         Right(()),
@@ -0,0 +1,9 @@
+use std::{env, fs, path::PathBuf};
+
+fn main() {
+    // The ABI descriptor is embedded as a custom section, so its text has to be known at compile
+    // time. We render it from the port definitions here rather than keeping a copy in sync by hand.
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("abi.txt");
+
+    fs::write(out, crucible_abi::describe_abi().to_string()).unwrap();
+}
@@ -0,0 +1,220 @@
+//! Playback of [sounds](Sound) through a tree of buses.
+//!
+//! Every voice plays into a [`Bus`] or into the master bus if it isn't given one. A bus scales the
+//! volume of everything playing into it, including its child buses, which makes them useful for
+//! things like separate music and effects volume settings.
+
+use std::rc::Rc;
+
+use wasmlink::bind_port;
+
+use crate::{audio::sound::Sound, base::env::RunMode};
+
+// === Bus === //
+
+/// A group of voices whose volume is controlled together. The bus is destroyed once it and all of
+/// its child buses are dropped, stopping every voice playing into it.
+#[derive(Debug, Clone)]
+pub struct Bus {
+    inner: Rc<BusInner>,
+}
+
+#[derive(Debug)]
+struct BusInner {
+    handle: crucible_abi::AudioBusHandle,
+    _parent: Option<Bus>,
+}
+
+impl Bus {
+    /// Creates a bus feeding into the master bus.
+    pub fn new() -> Self {
+        Self::create(None)
+    }
+
+    /// Creates a bus feeding into this one.
+    pub fn child(&self) -> Self {
+        Self::create(Some(self.clone()))
+    }
+
+    fn create(parent: Option<Bus>) -> Self {
+        RunMode::get().assert_client();
+
+        bind_port! {
+            fn [crucible_abi::AUDIO_CREATE_BUS] "crucible".audio_create_bus(Option<crucible_abi::AudioBusHandle>)
+                -> crucible_abi::AudioBusHandle;
+        }
+
+        let handle = audio_create_bus(&parent.as_ref().map(|parent| parent.inner.handle).into());
+
+        Self {
+            inner: Rc::new(BusInner {
+                handle,
+                _parent: parent,
+            }),
+        }
+    }
+
+    /// Sets the gain applied to everything playing into the bus, where `1.0` leaves it unchanged.
+    pub fn set_volume(&self, volume: f32) {
+        bind_port! {
+            fn [crucible_abi::AUDIO_SET_BUS_VOLUME] "crucible".audio_set_bus_volume(crucible_abi::AudioBusVolumeArgs);
+        }
+
+        audio_set_bus_volume(&crucible_abi::AudioBusVolumeArgs {
+            bus: self.inner.handle,
+            volume,
+        });
+    }
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for BusInner {
+    fn drop(&mut self) {
+        bind_port! {
+            fn [crucible_abi::AUDIO_DESTROY_BUS] "crucible".audio_destroy_bus(crucible_abi::AudioBusHandle);
+        }
+
+        audio_destroy_bus(&self.handle);
+    }
+}
+
+// === Voice === //
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VoiceParams {
+    /// The gain applied to the voice, where `1.0` leaves it unchanged.
+    pub volume: f32,
+    /// The stereo balance, from `-1.0` for the left channel to `1.0` for the right one.
+    pub pan: f32,
+    /// The playback speed, where `1.0` plays the sound at its natural pitch.
+    pub pitch: f32,
+}
+
+impl Default for VoiceParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VoiceParams {
+    pub const fn new() -> Self {
+        Self {
+            volume: 1.,
+            pan: 0.,
+            pitch: 1.,
+        }
+    }
+
+    pub const fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub const fn pan(mut self, pan: f32) -> Self {
+        self.pan = pan;
+        self
+    }
+
+    pub const fn pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+
+    fn to_abi(self) -> crucible_abi::AudioVoiceParams {
+        crucible_abi::AudioVoiceParams {
+            volume: self.volume,
+            pan: self.pan,
+            pitch: self.pitch,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct PlayArgs<'a> {
+    pub bus: Option<&'a Bus>,
+    pub params: VoiceParams,
+    pub looping: bool,
+}
+
+impl Default for PlayArgs<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> PlayArgs<'a> {
+    pub const fn new() -> Self {
+        Self {
+            bus: None,
+            params: VoiceParams::new(),
+            looping: false,
+        }
+    }
+
+    pub const fn bus(mut self, bus: &'a Bus) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    pub const fn params(mut self, params: VoiceParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub const fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+}
+
+/// A sound being played. Voices stop on their own once their sound ends unless they are looping,
+/// after which controlling them does nothing.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct Voice {
+    handle: crucible_abi::AudioVoiceHandle,
+}
+
+impl Voice {
+    pub fn set_params(self, params: VoiceParams) {
+        bind_port! {
+            fn [crucible_abi::AUDIO_SET_VOICE_PARAMS] "crucible".audio_set_voice_params(crucible_abi::AudioSetVoiceParamsArgs);
+        }
+
+        audio_set_voice_params(&crucible_abi::AudioSetVoiceParamsArgs {
+            voice: self.handle,
+            params: params.to_abi(),
+        });
+    }
+
+    pub fn stop(self) {
+        bind_port! {
+            fn [crucible_abi::AUDIO_STOP_VOICE] "crucible".audio_stop_voice(crucible_abi::AudioVoiceHandle);
+        }
+
+        audio_stop_voice(&self.handle);
+    }
+}
+
+/// Starts playing `sound`.
+pub fn play(sound: &Sound, args: PlayArgs<'_>) -> Voice {
+    RunMode::get().assert_client();
+
+    bind_port! {
+        fn [crucible_abi::AUDIO_PLAY] "crucible".audio_play(crucible_abi::AudioPlayArgs)
+            -> crucible_abi::AudioVoiceHandle;
+    }
+
+    Voice {
+        handle: audio_play(&crucible_abi::AudioPlayArgs {
+            buffer: sound.handle,
+            bus: args.bus.map(|bus| bus.inner.handle).into(),
+            params: args.params.to_abi(),
+            looping: args.looping,
+        }),
+    }
+}
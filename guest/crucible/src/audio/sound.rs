@@ -0,0 +1,59 @@
+//! Sound buffers which can be played through the [mixer](super::mixer).
+
+use wasmlink::{GuestSliceRef, bind_port};
+
+use crate::base::{env::RunMode, error::HostError};
+
+/// A sound loaded into the host's mixer. The sound is unloaded once dropped although voices which
+/// are already playing it keep playing until they finish.
+#[derive(Debug)]
+pub struct Sound {
+    pub(crate) handle: crucible_abi::AudioBufferHandle,
+}
+
+impl Sound {
+    /// Loads interleaved PCM `samples` in the range `-1.0..=1.0`. Only mono and stereo sounds are
+    /// supported.
+    pub fn from_pcm(samples: &[f32], channels: u32, sample_rate: u32) -> Result<Self, HostError> {
+        RunMode::get().assert_client();
+
+        bind_port! {
+            fn [crucible_abi::AUDIO_CREATE_BUFFER] "crucible".audio_create_buffer(crucible_abi::AudioPcm)
+                -> Result<crucible_abi::AudioBufferHandle, crucible_abi::AbiError>;
+        }
+
+        audio_create_buffer(&crucible_abi::AudioPcm {
+            samples: GuestSliceRef::new(samples),
+            channels,
+            sample_rate,
+        })
+        .decode()
+        .map(|handle| Self { handle })
+        .map_err(HostError::from_abi)
+    }
+
+    /// Loads the contents of a WAV or Ogg Vorbis file.
+    pub fn decode(file: &[u8]) -> Result<Self, HostError> {
+        RunMode::get().assert_client();
+
+        bind_port! {
+            fn [crucible_abi::AUDIO_DECODE_BUFFER] "crucible".audio_decode_buffer(Vec<u8>)
+                -> Result<crucible_abi::AudioBufferHandle, crucible_abi::AbiError>;
+        }
+
+        audio_decode_buffer(&GuestSliceRef::new(file))
+            .decode()
+            .map(|handle| Self { handle })
+            .map_err(HostError::from_abi)
+    }
+}
+
+impl Drop for Sound {
+    fn drop(&mut self) {
+        bind_port! {
+            fn [crucible_abi::AUDIO_DESTROY_BUFFER] "crucible".audio_destroy_buffer(crucible_abi::AudioBufferHandle);
+        }
+
+        audio_destroy_buffer(&self.handle);
+    }
+}
@@ -1,11 +1,12 @@
 use std::{
     cell::{Cell, OnceCell},
+    collections::HashSet,
     num::NonZeroU32,
 };
 
 use futures::channel::oneshot;
 use scopeguard::ScopeGuard;
-use wasmlink::{OwnedGuestClosure, bind_port};
+use wasmlink::{Marshal, OwnedGuestClosure, Port, bind_port};
 
 use crate::base::task::wake_executor;
 
@@ -62,6 +63,70 @@ impl RunMode {
     }
 }
 
+// === Capabilities === //
+
+/// Checks whether the host implements the port given as a constant. Games can use this to degrade
+/// gracefully on hosts which predate a port rather than trapping on its first call. The answer is
+/// cached at each call site.
+///
+/// ```ignore
+/// if crucible::supports!(crucible_abi::WINDOW_SET_ICON) {
+///     window.set_icon(icon);
+/// }
+/// ```
+#[macro_export]
+macro_rules! supports {
+    ($port:expr $(,)?) => {{
+        ::std::thread_local! {
+            static SUPPORTED: bool = $crate::base::env::supports_port(const { $port });
+        }
+
+        SUPPORTED.with(|v| *v)
+    }};
+}
+
+#[doc(hidden)]
+pub fn supports_port<I: Marshal, O: Marshal>(port: Port<I, O>) -> bool {
+    thread_local! {
+        static CACHE: OnceCell<HashSet<(String, String, u32)>> = const { OnceCell::new() };
+    }
+
+    CACHE.with(|v| {
+        v.get_or_init(|| {
+            bind_port! {
+                optional fn [crucible_abi::GET_SUPPORTED_PORTS] "crucible".get_supported_ports(())
+                    -> Vec<crucible_abi::PortId>;
+            }
+
+            // Hosts which predate the query can't tell us about any port.
+            get_supported_ports(&())
+                .map(|ports| {
+                    ports
+                        .decode()
+                        .into_iter()
+                        .map(|port| (port.module.decode(), port.func_name.decode(), port.version))
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .contains(&(
+            port.module().to_string(),
+            port.func_name().to_string(),
+            port.version(),
+        ))
+    })
+}
+
+// === Diagnostics === //
+
+pub use wasmlink::{ClosureSiteStats, ClosureStats};
+
+/// Summarizes the callbacks the game currently has registered with the host. Enable the
+/// `closure-diagnostics` feature to break them down by the code which created them.
+pub fn closure_stats() -> ClosureStats {
+    wasmlink::closure_stats()
+}
+
 // === Time === //
 
 pub fn current_time() -> f64 {
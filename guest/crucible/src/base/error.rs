@@ -0,0 +1,127 @@
+use std::{error::Error, fmt};
+
+use crucible_abi as abi;
+use wasmlink::GuestboundOf;
+
+// === ErrorKind === //
+
+/// The kind of failure a [`HostError`] reports.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    Other,
+    Timeout,
+    ConnectionRefused,
+    CertMismatch,
+    ClosedByPeer,
+    QuotaExceeded,
+    NotFound,
+    PermissionDenied,
+}
+
+impl ErrorKind {
+    fn from_abi(kind: u16) -> Self {
+        match abi::ErrorKind::from_raw(kind) {
+            abi::ErrorKind::Other => Self::Other,
+            abi::ErrorKind::Timeout => Self::Timeout,
+            abi::ErrorKind::ConnectionRefused => Self::ConnectionRefused,
+            abi::ErrorKind::CertMismatch => Self::CertMismatch,
+            abi::ErrorKind::ClosedByPeer => Self::ClosedByPeer,
+            abi::ErrorKind::QuotaExceeded => Self::QuotaExceeded,
+            abi::ErrorKind::NotFound => Self::NotFound,
+            abi::ErrorKind::PermissionDenied => Self::PermissionDenied,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::Other => "operation failed",
+            ErrorKind::Timeout => "operation timed out",
+            ErrorKind::ConnectionRefused => "connection refused",
+            ErrorKind::CertMismatch => "certificate mismatch",
+            ErrorKind::ClosedByPeer => "connection closed by peer",
+            ErrorKind::QuotaExceeded => "quota exceeded",
+            ErrorKind::NotFound => "not found",
+            ErrorKind::PermissionDenied => "permission denied",
+        })
+    }
+}
+
+// === HostError === //
+
+/// An error reported by the host.
+///
+/// Match on [`kind`](HostError::kind) to handle specific failures. The host's full error chain is
+/// available through [`Error::source`].
+#[derive(Debug, Clone)]
+pub struct HostError {
+    kind: ErrorKind,
+    message: Option<String>,
+    source: Option<Box<HostErrorSource>>,
+}
+
+impl HostError {
+    pub(crate) fn from_abi(err: GuestboundOf<abi::AbiError>) -> Self {
+        let source = err
+            .sources
+            .decode()
+            .into_iter()
+            .rev()
+            .fold(None, |source, message| {
+                Some(Box::new(HostErrorSource {
+                    message: message.decode(),
+                    source,
+                }))
+            });
+
+        Self {
+            kind: ErrorKind::from_abi(err.kind),
+            message: err.message.decode().map(|v| v.decode()),
+            source,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+impl fmt::Display for HostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => f.write_str(message),
+            None => fmt::Display::fmt(&self.kind, f),
+        }
+    }
+}
+
+impl Error for HostError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|v| v as &(dyn Error + 'static))
+    }
+}
+
+/// One of the host-side causes of a [`HostError`].
+#[derive(Debug, Clone)]
+pub struct HostErrorSource {
+    message: String,
+    source: Option<Box<HostErrorSource>>,
+}
+
+impl fmt::Display for HostErrorSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for HostErrorSource {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|v| v as &(dyn Error + 'static))
+    }
+}
@@ -0,0 +1,83 @@
+//! Persistent storage for settings and save files.
+//!
+//! Each game gets its own set of keys, kept on the user's device under the id of the `storage`
+//! capability it declares in its [manifest](crate::declare_manifest). Since the id rather than the
+//! module's hash identifies the game, stored data survives updates.
+
+use crucible_abi as abi;
+use wasmlink::{GuestSliceRef, GuestStrRef, bind_port};
+
+use crate::base::error::HostError;
+
+/// How much of its quota the game has used, in bytes.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct StorageUsage {
+    pub used: u64,
+    pub quota: u64,
+}
+
+/// Reads the value of `key`, returning `None` if it was never written.
+pub async fn read(key: &str) -> Result<Option<Vec<u8>>, HostError> {
+    bind_port! {
+        async fn [abi::STORAGE_READ] "crucible".storage_read(String)
+            -> Result<Option<Vec<u8>>, abi::AbiError>;
+    }
+
+    match storage_read(&GuestStrRef::new(key)).await.decode() {
+        Ok(data) => Ok(data.decode().map(|data| data.decode())),
+        Err(err) => Err(HostError::from_abi(err)),
+    }
+}
+
+/// Replaces the value of `key` with `data`. The write either happens entirely or not at all.
+pub fn write(key: &str, data: &[u8]) -> Result<(), HostError> {
+    bind_port! {
+        fn [abi::STORAGE_WRITE] "crucible".storage_write(abi::StorageWriteArgs)
+            -> Result<(), abi::AbiError>;
+    }
+
+    storage_write(&abi::StorageWriteArgs {
+        key: GuestStrRef::new(key),
+        data: GuestSliceRef::new(data),
+    })
+    .decode()
+    .map_err(HostError::from_abi)
+}
+
+/// Removes `key`. Removing a key which doesn't exist does nothing.
+pub fn delete(key: &str) -> Result<(), HostError> {
+    bind_port! {
+        fn [abi::STORAGE_DELETE] "crucible".storage_delete(String) -> Result<(), abi::AbiError>;
+    }
+
+    storage_delete(&GuestStrRef::new(key))
+        .decode()
+        .map_err(HostError::from_abi)
+}
+
+/// Lists every key holding a value, in no particular order.
+pub fn list() -> Result<Vec<String>, HostError> {
+    bind_port! {
+        fn [abi::STORAGE_LIST] "crucible".storage_list(()) -> Result<Vec<String>, abi::AbiError>;
+    }
+
+    match storage_list(&()).decode() {
+        Ok(keys) => Ok(keys.decode().into_iter().map(|key| key.decode()).collect()),
+        Err(err) => Err(HostError::from_abi(err)),
+    }
+}
+
+pub fn usage() -> Result<StorageUsage, HostError> {
+    bind_port! {
+        fn [abi::STORAGE_GET_USAGE] "crucible".storage_get_usage(())
+            -> Result<abi::StorageUsage, abi::AbiError>;
+    }
+
+    match storage_get_usage(&()).decode() {
+        Ok(usage) => Ok(StorageUsage {
+            used: usage.used,
+            quota: usage.quota,
+        }),
+        Err(err) => Err(HostError::from_abi(err)),
+    }
+}
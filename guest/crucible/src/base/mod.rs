@@ -1,4 +1,6 @@
 pub mod env;
+pub mod error;
 pub mod files;
 pub mod logging;
+pub mod reload;
 pub mod task;
@@ -0,0 +1,42 @@
+use std::cell::RefCell;
+
+use wasmlink::{GuestSliceRef, OwnedGuestClosure, bind_port};
+
+thread_local! {
+    static SAVE_HANDLER: RefCell<Option<OwnedGuestClosure<()>>> = const { RefCell::new(None) };
+}
+
+/// Registers `save` to be called when a development host is about to replace this instance with a
+/// rebuilt module. The bytes it returns are handed to the new instance, which can pick them up
+/// with [`take_saved_state`].
+///
+/// Registering a handler replaces the previous one. Hosts which don't hot reload never call it.
+pub fn on_reload(mut save: impl 'static + FnMut() -> Vec<u8>) {
+    bind_port! {
+        fn [crucible_abi::HOT_RELOAD_BIND_HANDLER] "crucible".hot_reload_bind_handler(fn(()));
+
+        fn [crucible_abi::HOT_RELOAD_SAVE_STATE] "crucible".hot_reload_save_state(Vec<u8>);
+    }
+
+    let handler = OwnedGuestClosure::<()>::new_mut(move |()| {
+        let state = save();
+        hot_reload_save_state(&GuestSliceRef::new(&state));
+    });
+
+    hot_reload_bind_handler(&handler.handle());
+
+    SAVE_HANDLER.with(|v| *v.borrow_mut() = Some(handler));
+}
+
+/// Takes the state saved by the [`on_reload`] handler of the instance this one replaced. Returns
+/// `None` if this instance wasn't started by a hot reload or if the state was already taken.
+pub fn take_saved_state() -> Option<Vec<u8>> {
+    bind_port! {
+        fn [crucible_abi::HOT_RELOAD_TAKE_STATE] "crucible".hot_reload_take_state(())
+            -> Option<Vec<u8>>;
+    }
+
+    hot_reload_take_state(&())
+        .decode()
+        .map(|state| state.decode())
+}
@@ -13,6 +13,9 @@ pub extern crate futures;
 
 thread_local! {
     static EXECUTE_SPAWNER: (RefCell<LocalPool>, LocalSpawner) = {
+        // Async port calls only wake their tasks so we have to run them ourselves.
+        wasmlink::set_async_completion_hook(wake_executor);
+
         let pool = LocalPool::new();
         let spawner = pool.spawner();
 
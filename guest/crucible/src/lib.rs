@@ -1,5 +1,14 @@
+pub mod audio;
 pub mod base;
 pub mod gfx;
+pub mod mods;
 pub mod net;
 pub mod shell;
 pub mod window;
+
+mod tests;
+
+wasmlink::embed_abi_descriptor!(include_str!(concat!(env!("OUT_DIR"), "/abi.txt")));
+
+/// Declares the capabilities the game needs. See [`crucible_abi::Manifest`].
+pub use crucible_abi::declare_manifest;
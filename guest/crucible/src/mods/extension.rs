@@ -0,0 +1,161 @@
+use std::{fmt, marker::PhantomData};
+
+use crucible_abi as abi;
+use futures::{StreamExt, channel::mpsc};
+use wasmlink::{GuestSliceRef, GuestStrRef, OwnedGuestClosure, bind_port};
+
+use crate::{
+    base::{error::HostError, task::wake_executor},
+    mods::Peer,
+};
+
+// === Message === //
+
+/// A value which can be sent through an [`ExtensionPoint`]. The game and its mods must agree on
+/// the encoding of every extension point they share.
+pub trait Message: Sized {
+    fn encode(&self) -> Vec<u8>;
+
+    /// Decodes a message sent by a peer, returning `None` if it is malformed.
+    fn decode(bytes: Vec<u8>) -> Option<Self>;
+}
+
+impl Message for Vec<u8> {
+    fn encode(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn decode(bytes: Vec<u8>) -> Option<Self> {
+        Some(bytes)
+    }
+}
+
+impl Message for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: Vec<u8>) -> Option<Self> {
+        String::from_utf8(bytes).ok()
+    }
+}
+
+// === ExtensionPoint === //
+
+/// A named channel through which the game and its mods exchange messages of type `T`.
+///
+/// The game declares the extension points it offers with [`declare`](Self::declare) and mods join
+/// them with [`join`](Self::join). Mods are started after the game's `main` function returns so
+/// extension points must be declared before its first `.await`-point.
+pub struct ExtensionPoint<T> {
+    handle: abi::ExtensionHandle,
+    rx: mpsc::UnboundedReceiver<(Peer, Vec<u8>)>,
+    _handler: OwnedGuestClosure<abi::ExtensionMessage>,
+    _ty: PhantomData<fn(T) -> T>,
+}
+
+impl<T> fmt::Debug for ExtensionPoint<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtensionPoint")
+            .field("handle", &self.handle)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Message> ExtensionPoint<T> {
+    pub fn declare(name: &str) -> Result<Self, HostError> {
+        Self::open(name, true)
+    }
+
+    pub fn join(name: &str) -> Result<Self, HostError> {
+        Self::open(name, false)
+    }
+
+    fn open(name: &str, declare: bool) -> Result<Self, HostError> {
+        bind_port! {
+            fn [abi::EXTENSION_DECLARE] "crucible".extension_declare(abi::ExtensionOpenArgs)
+                -> Result<abi::ExtensionHandle, abi::AbiError>;
+
+            fn [abi::EXTENSION_JOIN] "crucible".extension_join(abi::ExtensionOpenArgs)
+                -> Result<abi::ExtensionHandle, abi::AbiError>;
+        }
+
+        let (tx, rx) = mpsc::unbounded();
+
+        let handler = OwnedGuestClosure::<abi::ExtensionMessage>::new(move |arg| {
+            tx.unbounded_send((Peer::from_raw(arg.peer), arg.message.decode()))
+                .unwrap();
+            wake_executor();
+        });
+
+        let args = abi::ExtensionOpenArgs {
+            name: GuestStrRef::new(name),
+            handler: handler.handle(),
+        };
+
+        let res = if declare {
+            extension_declare(&args)
+        } else {
+            extension_join(&args)
+        };
+
+        let handle = res.decode().map_err(HostError::from_abi)?;
+
+        Ok(Self {
+            handle,
+            rx,
+            _handler: handler,
+            _ty: PhantomData,
+        })
+    }
+
+    /// Sends `message` to `peer`. Messages are delivered once the current event has been handled.
+    pub fn send(&self, peer: Peer, message: &T) -> Result<(), HostError> {
+        self.send_inner(Some(peer), message)
+    }
+
+    /// Sends `message` to every other member of the extension point.
+    pub fn broadcast(&self, message: &T) -> Result<(), HostError> {
+        self.send_inner(None, message)
+    }
+
+    fn send_inner(&self, peer: Option<Peer>, message: &T) -> Result<(), HostError> {
+        bind_port! {
+            fn [abi::EXTENSION_SEND] "crucible".extension_send(abi::ExtensionSendArgs)
+                -> Result<(), abi::AbiError>;
+        }
+
+        let message = message.encode();
+
+        extension_send(&abi::ExtensionSendArgs {
+            extension: self.handle,
+            peer: peer.map(Peer::to_raw).into(),
+            message: GuestSliceRef::new(&message),
+        })
+        .decode()
+        .map_err(HostError::from_abi)
+    }
+
+    /// Receives the next well-formed message sent to this instance along with the peer which sent
+    /// it. Malformed messages are logged and skipped.
+    pub async fn recv(&mut self) -> (Peer, T) {
+        loop {
+            let (peer, message) = self.rx.next().await.unwrap();
+
+            match T::decode(message) {
+                Some(message) => return (peer, message),
+                None => tracing::warn!("dropped malformed extension message from {peer:?}"),
+            }
+        }
+    }
+}
+
+impl<T> Drop for ExtensionPoint<T> {
+    fn drop(&mut self) {
+        bind_port! {
+            fn [abi::EXTENSION_CLOSE] "crucible".extension_close(abi::ExtensionHandle);
+        }
+
+        extension_close(&self.handle);
+    }
+}
@@ -0,0 +1,78 @@
+use std::cell::OnceCell;
+
+use crucible_abi as abi;
+use wasmlink::bind_port;
+
+pub mod extension;
+
+// === Peer === //
+
+/// An instance taking part in a game: either the game itself or one of the mods loaded alongside
+/// it.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Peer {
+    Game,
+    /// The mod at the given index of [`loaded_mods`].
+    Mod(usize),
+}
+
+impl Peer {
+    /// The peer running this code.
+    pub fn current() -> Self {
+        thread_local! {
+            static CACHE: OnceCell<Peer> = const { OnceCell::new() };
+        }
+
+        CACHE.with(|v| {
+            *v.get_or_init(|| {
+                bind_port! {
+                    fn [abi::MOD_GET_SELF] "crucible".mod_get_self(()) -> u32;
+                }
+
+                Self::from_raw(mod_get_self(&()))
+            })
+        })
+    }
+
+    pub(crate) fn from_raw(raw: u32) -> Self {
+        match raw {
+            0 => Self::Game,
+            raw => Self::Mod(raw as usize - 1),
+        }
+    }
+
+    pub(crate) fn to_raw(self) -> u32 {
+        match self {
+            Peer::Game => 0,
+            Peer::Mod(index) => index as u32 + 1,
+        }
+    }
+
+    pub fn is_game(self) -> bool {
+        self == Self::Game
+    }
+}
+
+// === ModInfo === //
+
+#[derive(Debug, Clone)]
+pub struct ModInfo {
+    pub name: String,
+    pub content_hash: blake3::Hash,
+}
+
+/// Lists the mods loaded alongside the game in load order.
+pub fn loaded_mods() -> Vec<ModInfo> {
+    bind_port! {
+        fn [abi::MOD_LIST] "crucible".mod_list(()) -> Vec<abi::ModInfo>;
+    }
+
+    mod_list(&())
+        .decode()
+        .into_iter()
+        .map(|info| ModInfo {
+            name: info.name.decode(),
+            content_hash: blake3::Hash::from_bytes(info.content_hash.0),
+        })
+        .collect()
+}
@@ -1,15 +1,9 @@
-use crucible_abi as abi;
-use futures::channel::oneshot;
-use thiserror::Error;
-use wasmlink::{GuestSliceRef, OwnedGuestClosure, bind_port};
+use std::mem::ManuallyDrop;
 
-use crate::base::task::wake_executor;
+use crucible_abi as abi;
+use wasmlink::{GuestSliceRef, bind_port};
 
-#[derive(Debug, Clone, Error)]
-#[error("{msg}")]
-pub struct GameSocketError {
-    msg: String,
-}
+use crate::base::error::HostError;
 
 #[derive(Debug)]
 pub struct GameSocket {
@@ -17,6 +11,20 @@ pub struct GameSocket {
 }
 
 impl GameSocket {
+    /// Releases the socket without closing it, returning the host's handle to it. Sockets outlive
+    /// hot reloads so an instance can hand its connections over to its replacement through its
+    /// saved state.
+    pub fn into_raw(self) -> u32 {
+        ManuallyDrop::new(self).handle.raw
+    }
+
+    /// Adopts a socket released by [`into_raw`](Self::into_raw).
+    pub fn from_raw(raw: u32) -> Self {
+        Self {
+            handle: abi::GameSocketHandle { raw },
+        }
+    }
+
     pub fn id(&self) -> u64 {
         bind_port! {
             fn [abi::GAME_SOCKET_GET_ID] "crucible".game_socket_get_id(
@@ -37,52 +45,32 @@ impl GameSocket {
         game_socket_get_rtt(&self.handle).decode()
     }
 
-    pub async fn send(&mut self, msg: &[u8]) -> Result<(), GameSocketError> {
+    pub async fn send(&mut self, msg: &[u8]) -> Result<(), HostError> {
         bind_port! {
-            fn [abi::GAME_SOCKET_SEND_MSG] "crucible".game_socket_send_msg(
+            async fn [abi::GAME_SOCKET_SEND_MSG] "crucible".game_socket_send_msg(
                 abi::GameSocketSendMsgArgs
-            );
-
-            fn [abi::GAME_SOCKET_CANCEL_SEND_MSG] "crucible".game_socket_cancel_send_msg(
-                abi::GameSocketHandle
-            );
+            ) -> Result<(), abi::AbiError>;
         }
 
-        let (tx, rx) = oneshot::channel();
-
-        let callback = OwnedGuestClosure::<Result<(), String>>::new_once(move |res| {
-            tx.send(
-                res.decode()
-                    .map_err(|err| GameSocketError { msg: err.decode() }),
-            )
-            .unwrap();
-
-            wake_executor();
-        });
-
-        let guard = scopeguard::guard((), |()| game_socket_cancel_send_msg(&self.handle));
-
-        game_socket_send_msg(&abi::GameSocketSendMsgArgs {
+        let res = game_socket_send_msg(&abi::GameSocketSendMsgArgs {
             socket: self.handle,
             message: GuestSliceRef::new(msg),
-            callback: callback.handle(),
-        });
+        })
+        .await;
 
-        let res = rx.await.unwrap();
-        scopeguard::ScopeGuard::into_inner(guard);
-        res
+        res.decode().map_err(HostError::from_abi)
     }
 
-    pub fn recv(&self) -> Result<Vec<u8>, GameSocketError> {
+    pub fn recv(&self) -> Result<Vec<u8>, HostError> {
         bind_port! {
             fn [abi::GAME_SOCKET_RECV_MSG] "crucible".game_socket_recv_msg(
                 abi::GameSocketHandle
-            ) -> Result<Vec<u8>, String>;
+            ) -> Result<Vec<u8>, abi::AbiError>;
         }
 
         match game_socket_recv_msg(&self.handle).decode() {
             Ok(msg) => Ok(msg.decode()),
-            Err(err) => Err(GameSocketError { msg: err.decode() }),
+            Err(err) => Err(HostError::from_abi(err)),
         }
     }
 }
@@ -1,15 +1,9 @@
-use crucible_abi as abi;
-use futures::channel::oneshot;
-use thiserror::Error;
-use wasmlink::{GuestStrRef, OwnedGuestClosure, bind_port};
+use std::mem::ManuallyDrop;
 
-use crate::{base::task::wake_executor, net::client::GameSocket};
+use crucible_abi as abi;
+use wasmlink::{GuestStrRef, bind_port};
 
-#[derive(Debug, Clone, Error)]
-#[error("{msg}")]
-pub struct LoginSocketError {
-    msg: String,
-}
+use crate::{base::error::HostError, mods::ModInfo, net::client::GameSocket};
 
 #[derive(Debug)]
 pub struct LoginSocket {
@@ -17,31 +11,30 @@ pub struct LoginSocket {
 }
 
 impl LoginSocket {
-    pub async fn connect(addr: &str) -> Result<Self, LoginSocketError> {
-        bind_port! {
-            fn [abi::LOGIN_SOCKET_CONNECT] "crucible".login_socket_connect(
-                abi::LoginSocketConnectArgs
-            );
-        }
-
-        let (tx, rx) = oneshot::channel();
+    /// Releases the socket without closing it, returning the host's handle to it. Sockets outlive
+    /// hot reloads so an instance can hand its connections over to its replacement through its
+    /// saved state.
+    pub fn into_raw(self) -> u32 {
+        ManuallyDrop::new(self).handle.raw
+    }
 
-        let callback =
-            OwnedGuestClosure::<Result<abi::LoginSocketHandle, String>>::new_once(move |res| {
-                tx.send(match res.decode() {
-                    Ok(handle) => Ok(LoginSocket { handle }),
-                    Err(msg) => Err(LoginSocketError { msg: msg.decode() }),
-                })
-                .unwrap();
-                wake_executor();
-            });
+    /// Adopts a socket released by [`into_raw`](Self::into_raw).
+    pub fn from_raw(raw: u32) -> Self {
+        Self {
+            handle: abi::LoginSocketHandle { raw },
+        }
+    }
 
-        login_socket_connect(&abi::LoginSocketConnectArgs {
-            addr: GuestStrRef::new(addr),
-            callback: callback.handle(),
-        });
+    pub async fn connect(addr: &str) -> Result<Self, HostError> {
+        bind_port! {
+            async fn [abi::LOGIN_SOCKET_CONNECT] "crucible".login_socket_connect(String)
+                -> Result<abi::LoginSocketHandle, abi::AbiError>;
+        }
 
-        rx.await.unwrap()
+        match login_socket_connect(&GuestStrRef::new(addr)).await.decode() {
+            Ok(handle) => Ok(LoginSocket { handle }),
+            Err(err) => Err(HostError::from_abi(err)),
+        }
     }
 
     pub fn rtt(&self) -> Option<f64> {
@@ -54,70 +47,55 @@ impl LoginSocket {
         login_socket_get_rtt(&self.handle).decode()
     }
 
-    pub async fn info(&self) -> Result<LoginServerInfo, LoginSocketError> {
+    pub async fn info(&self) -> Result<LoginServerInfo, HostError> {
         bind_port! {
-            fn [abi::LOGIN_SOCKET_GET_INFO] "crucible".login_socket_get_info(
-                abi::LoginSocketGetInfoArgs
-            );
+            async fn [abi::LOGIN_SOCKET_GET_INFO] "crucible".login_socket_get_info(
+                abi::LoginSocketHandle
+            ) -> Result<abi::LoginServerInfo, abi::AbiError>;
         }
 
-        let (tx, rx) = oneshot::channel();
-
-        let callback =
-            OwnedGuestClosure::<Result<abi::LoginServerInfo, String>>::new_once(move |res| {
-                tx.send(match res.decode() {
-                    Ok(info) => Ok(LoginServerInfo {
-                        motd: info.motd.decode(),
+        match login_socket_get_info(&self.handle).await.decode() {
+            Ok(info) => Ok(LoginServerInfo {
+                motd: info.motd.decode(),
+                content_hash: blake3::Hash::from_bytes(info.content_hash.0),
+                content_server: info.content_server.decode().map(|v| v.decode()),
+                mods: info
+                    .mods
+                    .decode()
+                    .into_iter()
+                    .map(|info| ModInfo {
+                        name: info.name.decode(),
                         content_hash: blake3::Hash::from_bytes(info.content_hash.0),
-                        content_server: info.content_server.decode().map(|v| v.decode()),
-                    }),
-                    Err(msg) => Err(LoginSocketError { msg: msg.decode() }),
-                })
-                .unwrap();
-                wake_executor();
-            });
-
-        login_socket_get_info(&abi::LoginSocketGetInfoArgs {
-            socket: self.handle,
-            callback: callback.handle(),
-        });
-
-        rx.await.unwrap()
+                    })
+                    .collect(),
+            }),
+            Err(err) => Err(HostError::from_abi(err)),
+        }
     }
 
     pub async fn play(
         &self,
         hash: blake3::Hash,
-    ) -> Result<Result<GameSocket, blake3::Hash>, LoginSocketError> {
+    ) -> Result<Result<GameSocket, blake3::Hash>, HostError> {
         bind_port! {
-            fn [abi::LOGIN_SOCKET_PLAY] "crucible".login_socket_play(
+            async fn [abi::LOGIN_SOCKET_PLAY] "crucible".login_socket_play(
                 abi::LoginSocketPlayArgs
-            );
+            ) -> Result<Result<abi::GameSocketHandle, abi::ContentHash>, abi::AbiError>;
         }
 
-        let (tx, rx) = oneshot::channel();
-
-        let callback = OwnedGuestClosure::<
-            Result<Result<abi::GameSocketHandle, abi::ContentHash>, String>,
-        >::new_once(move |res| {
-            tx.send(match res.decode() {
-                Ok(handle) => Ok(match handle.decode() {
-                    Ok(handle) => Ok(GameSocket { handle }),
-                    Err(hash) => Err(blake3::Hash::from_bytes(hash.0)),
-                }),
-                Err(msg) => Err(LoginSocketError { msg: msg.decode() }),
-            })
-            .unwrap();
-            wake_executor();
-        });
-
-        login_socket_play(&abi::LoginSocketPlayArgs {
+        let res = login_socket_play(&abi::LoginSocketPlayArgs {
             socket: self.handle,
             content_hash: abi::ContentHash(*hash.as_bytes()),
-            callback: callback.handle(),
-        });
-
-        rx.await.unwrap()
+        })
+        .await;
+
+        match res.decode() {
+            Ok(handle) => Ok(match handle.decode() {
+                Ok(handle) => Ok(GameSocket { handle }),
+                Err(hash) => Err(blake3::Hash::from_bytes(hash.0)),
+            }),
+            Err(err) => Err(HostError::from_abi(err)),
+        }
     }
 }
 
@@ -138,4 +116,7 @@ pub struct LoginServerInfo {
     pub motd: String,
     pub content_hash: blake3::Hash,
     pub content_server: Option<String>,
+    /// The mods the server requires, in load order. The host starts those which aren't loaded yet
+    /// once this info is returned.
+    pub mods: Vec<ModInfo>,
 }
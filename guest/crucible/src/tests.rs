@@ -0,0 +1,489 @@
+#![cfg(test)]
+
+use glam::Vec2;
+use wasmlink::{GuestboundViewVariant, native::NativeHost};
+
+use crate::{
+    base::{error::ErrorKind, files},
+    window::{
+        app::WindowEvent,
+        defs::{Key, KeyCode, KeyEvent, KeyLocation, MouseButton, PhysicalKey},
+        gamepad::{Deadzone, GamepadAxis, GamepadButton, GamepadEvent, GamepadId, Gamepads},
+        input::{AxisBinding, Bindings, Button, Input},
+    },
+};
+
+// === Capabilities === //
+
+#[test]
+fn supports_only_listed_ports() {
+    let host = NativeHost::new();
+
+    host.define(crucible_abi::GET_SUPPORTED_PORTS, |cx, (), out| {
+        let ports = [crucible_abi::PortId::<GuestboundViewVariant<'_>> {
+            module: "crucible",
+            func_name: "get_current_time",
+            version: 0,
+        }];
+
+        out.finish(cx, &ports.as_slice())
+    })
+    .unwrap();
+
+    assert!(crate::supports!(crucible_abi::GET_CURRENT_TIME));
+    assert!(!crate::supports!(
+        crucible_abi::GET_CURRENT_TIME.with_version(2)
+    ));
+    assert!(!crate::supports!(crucible_abi::GET_RUN_MODE));
+}
+
+#[test]
+fn supports_nothing_without_the_query() {
+    let _host = NativeHost::new();
+
+    assert!(!crate::supports!(crucible_abi::GET_CURRENT_TIME));
+}
+
+// === Errors === //
+
+#[test]
+fn unknown_error_kinds_decode_as_other() {
+    let host = NativeHost::new();
+
+    host.define(crucible_abi::STORAGE_DELETE, |cx, key, out| {
+        let kind = match key.read(cx)? {
+            "missing" => crucible_abi::ErrorKind::NotFound as u16,
+            _ => 1000,
+        };
+
+        out.finish(
+            cx,
+            &Err(crucible_abi::AbiError::<GuestboundViewVariant<'_>> {
+                kind,
+                message: None,
+                sources: &[],
+            }),
+        )
+    })
+    .unwrap();
+
+    assert_eq!(
+        files::delete("missing").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+    assert_eq!(files::delete("other").unwrap_err().kind(), ErrorKind::Other);
+}
+
+// === Gamepads === //
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-5,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn deadzones_rescale_axes() {
+    let deadzone = Deadzone {
+        inner: 0.2,
+        outer: 0.8,
+    };
+
+    assert_eq!(deadzone.apply(0.1), 0.0);
+    assert_eq!(deadzone.apply(-0.2), 0.0);
+    assert_close(deadzone.apply(0.5), 0.5);
+    assert_close(deadzone.apply(-0.65), -0.75);
+    assert_eq!(deadzone.apply(0.9), 1.0);
+    assert_eq!(deadzone.apply(-1.0), -1.0);
+    assert_eq!(Deadzone::NONE.apply(0.3), 0.3);
+
+    // Radial deadzones measure the whole stick so diagonals aren't cut off early.
+    assert_eq!(deadzone.apply_radial(Vec2::new(0.1, 0.1)), Vec2::ZERO);
+    assert_ne!(deadzone.apply_radial(Vec2::new(0.15, 0.15)), Vec2::ZERO);
+
+    let stick = deadzone.apply_radial(Vec2::new(0.0, -0.5));
+    assert_close(stick.x, 0.0);
+    assert_close(stick.y, -0.5);
+
+    let stick = deadzone.apply_radial(Vec2::new(0.7, 0.7));
+    assert_close(stick.x, 1.0 / 2f32.sqrt());
+    assert_close(stick.y, 1.0 / 2f32.sqrt());
+}
+
+#[test]
+fn gamepads_track_connections_and_state() {
+    let host = NativeHost::new();
+
+    host.define(crucible_abi::GET_RUN_MODE, |cx, (), out| {
+        out.finish(cx, &crucible_abi::RunMode::Client)
+    })
+    .unwrap();
+
+    host.define(crucible_abi::GAMEPAD_BIND_HANDLERS, |cx, handlers, out| {
+        for (id, name) in [(0, "Left"), (1, "Right")] {
+            handlers.connected.call(
+                cx,
+                &crucible_abi::GamepadInfo::<GuestboundViewVariant<'_>> { id, name },
+            )?;
+        }
+
+        handlers.button.call(
+            cx,
+            &crucible_abi::GamepadButtonEvent {
+                id: 0,
+                button: crucible_abi::GamepadButton::South,
+                pressed: true,
+            },
+        )?;
+
+        for (axis, value) in [
+            (crucible_abi::GamepadAxis::LeftStickX, 0.1),
+            (crucible_abi::GamepadAxis::LeftTrigger, 0.55),
+        ] {
+            handlers
+                .axis
+                .call(cx, &crucible_abi::GamepadAxisEvent { id: 0, axis, value })?;
+        }
+
+        handlers.disconnected.call(cx, &1)?;
+
+        // Events of disconnected gamepads don't resurrect them.
+        handlers.button.call(
+            cx,
+            &crucible_abi::GamepadButtonEvent {
+                id: 1,
+                button: crucible_abi::GamepadButton::East,
+                pressed: true,
+            },
+        )?;
+
+        out.finish(cx, &())
+    })
+    .unwrap();
+
+    host.define(crucible_abi::GAMEPAD_UNBIND_HANDLERS, |cx, (), out| {
+        out.finish(cx, &())
+    })
+    .unwrap();
+
+    let mut gamepads = Gamepads::acquire();
+
+    assert_eq!(
+        gamepads.poll(),
+        [
+            GamepadEvent::Connected {
+                id: GamepadId(0),
+                name: "Left".to_string(),
+            },
+            GamepadEvent::Connected {
+                id: GamepadId(1),
+                name: "Right".to_string(),
+            },
+            GamepadEvent::Button {
+                id: GamepadId(0),
+                button: GamepadButton::South,
+                pressed: true,
+            },
+            GamepadEvent::Axis {
+                id: GamepadId(0),
+                axis: GamepadAxis::LeftStickX,
+                value: 0.1,
+            },
+            GamepadEvent::Axis {
+                id: GamepadId(0),
+                axis: GamepadAxis::LeftTrigger,
+                value: 0.55,
+            },
+            GamepadEvent::Disconnected(GamepadId(1)),
+            GamepadEvent::Button {
+                id: GamepadId(1),
+                button: GamepadButton::East,
+                pressed: true,
+            },
+        ]
+    );
+    assert!(gamepads.poll().is_empty());
+
+    let ids = gamepads.iter().map(|pad| pad.id()).collect::<Vec<_>>();
+    assert_eq!(ids, [GamepadId(0)]);
+    assert!(gamepads.get(GamepadId(1)).is_none());
+
+    let pad = gamepads.get(GamepadId(0)).unwrap();
+    assert_eq!(pad.name(), "Left");
+    assert!(pad.is_pressed(GamepadButton::South));
+    assert!(!pad.is_pressed(GamepadButton::East));
+
+    // The default deadzone swallows the stick's drift and rescales the trigger.
+    assert_eq!(pad.raw_axis(GamepadAxis::LeftStickX), 0.1);
+    assert_eq!(pad.axis(GamepadAxis::LeftStickX), 0.0);
+    assert_eq!(pad.left_stick(), Vec2::ZERO);
+    assert_close(pad.axis(GamepadAxis::LeftTrigger), 0.5);
+
+    gamepads.set_deadzone(Deadzone::NONE);
+
+    let pad = gamepads.get(GamepadId(0)).unwrap();
+    assert_close(pad.axis(GamepadAxis::LeftStickX), 0.1);
+    assert_close(pad.axis(GamepadAxis::LeftTrigger), 0.55);
+}
+
+// === Input === //
+
+fn key_event(key: KeyCode, pressed: bool, repeat: bool) -> WindowEvent {
+    WindowEvent::KeyEvent(KeyEvent {
+        physical_key: PhysicalKey::KeyCode(key),
+        logical_key: Key::Character(String::new()),
+        text: None,
+        location: KeyLocation::Standard,
+        pressed,
+        repeat,
+    })
+}
+
+fn key(key: KeyCode, pressed: bool) -> WindowEvent {
+    key_event(key, pressed, false)
+}
+
+fn pad_button(id: u32, button: GamepadButton, pressed: bool) -> GamepadEvent {
+    GamepadEvent::Button {
+        id: GamepadId(id),
+        button,
+        pressed,
+    }
+}
+
+fn pad_axis(id: u32, axis: GamepadAxis, value: f32) -> GamepadEvent {
+    GamepadEvent::Axis {
+        id: GamepadId(id),
+        axis,
+        value,
+    }
+}
+
+#[test]
+fn bindings_round_trip_through_text() {
+    let bindings = Bindings::new()
+        .with_action(
+            "jump",
+            [
+                Button::Key(KeyCode::Space),
+                Button::Gamepad(GamepadButton::South),
+            ],
+        )
+        .with_action(
+            "fire",
+            [
+                Button::Mouse(MouseButton::Left),
+                Button::Mouse(MouseButton::Other(8)),
+            ],
+        )
+        .with_action("unbound", [])
+        .with_axis(
+            "move_x",
+            [
+                AxisBinding::Buttons {
+                    negative: Button::Key(KeyCode::KeyA),
+                    positive: Button::Key(KeyCode::KeyD),
+                },
+                AxisBinding::Gamepad(GamepadAxis::LeftStickX),
+            ],
+        );
+
+    let text = bindings.to_string();
+
+    assert_eq!(
+        text,
+        "action fire = mouse Left, mouse 8\n\
+         action jump = key Space, gamepad South\n\
+         action unbound =\n\
+         axis move_x = key KeyA / key KeyD, gamepad-axis LeftStickX\n"
+    );
+    assert_eq!(text.parse::<Bindings>().unwrap(), bindings);
+
+    // Comments, blank lines and extra whitespace are ignored.
+    let parsed = Bindings::parse(
+        "# Controls\n\
+         \n\
+         \x20 action jump=key Space ,gamepad   South,\n\
+         axis move_x = key KeyA/key KeyD",
+    )
+    .unwrap();
+
+    assert_eq!(parsed.action("jump"), bindings.action("jump"));
+    assert_eq!(parsed.axis("move_x"), &bindings.axis("move_x")[..1]);
+    assert_eq!(parsed.action("fire"), []);
+}
+
+#[test]
+fn bindings_report_invalid_lines() {
+    for (text, reason) in [
+        ("action jump key Space", "expected `=` after the name"),
+        (
+            "button jump = key Space",
+            "expected `action` or `axis` but found `button`",
+        ),
+        ("action bad/name = key Space", "invalid name `bad/name`"),
+        ("action jump = key Spacebar", "unknown key `Spacebar`"),
+        (
+            "action jump = keyboard Space",
+            "unknown kind of button `keyboard`",
+        ),
+        (
+            "action jump = mouse Sideways",
+            "unknown mouse button `Sideways`",
+        ),
+        (
+            "axis move_x = key KeyA",
+            "expected an axis binding but found `key KeyA`",
+        ),
+        (
+            "axis move_x = gamepad-axis Throttle",
+            "unknown gamepad axis `Throttle`",
+        ),
+    ] {
+        let err = Bindings::parse(&format!("# Controls\n{text}")).unwrap_err();
+
+        assert_eq!(err.line, 2, "{text}");
+        assert_eq!(err.reason, reason, "{text}");
+    }
+}
+
+#[test]
+fn input_tracks_held_pressed_and_released_actions() {
+    let mut input = Input::new(Bindings::new().with_action(
+        "jump",
+        [
+            Button::Key(KeyCode::Space),
+            Button::Gamepad(GamepadButton::South),
+        ],
+    ));
+
+    input.handle_window_event(&key(KeyCode::Space, true));
+    input.handle_window_event(&key_event(KeyCode::Space, true, true));
+
+    assert!(input.is_held("jump"));
+    assert!(input.was_pressed("jump"));
+    assert!(!input.was_released("jump"));
+    assert_eq!(input.pressed_buttons(), [Button::Key(KeyCode::Space)]);
+
+    input.end_frame();
+
+    assert!(input.is_held("jump"));
+    assert!(!input.was_pressed("jump"));
+    assert!(input.pressed_buttons().is_empty());
+
+    // Actions stay held while any of their buttons are, on any gamepad.
+    input.handle_gamepad_event(&pad_button(0, GamepadButton::South, true));
+    input.handle_window_event(&key(KeyCode::Space, false));
+    input.handle_gamepad_event(&pad_button(1, GamepadButton::South, true));
+    input.handle_gamepad_event(&pad_button(0, GamepadButton::South, false));
+
+    assert!(input.is_held("jump"));
+    assert!(!input.was_pressed("jump"));
+    assert!(!input.was_released("jump"));
+    assert_eq!(
+        input.pressed_buttons(),
+        [
+            Button::Gamepad(GamepadButton::South),
+            Button::Gamepad(GamepadButton::South),
+        ]
+    );
+
+    // Disconnecting a gamepad releases its buttons.
+    input.handle_gamepad_event(&GamepadEvent::Disconnected(GamepadId(1)));
+
+    assert!(!input.is_held("jump"));
+    assert!(input.was_released("jump"));
+
+    // Taps shorter than a frame are seen as both a press and a release.
+    input.end_frame();
+    input.handle_window_event(&key(KeyCode::Space, true));
+    input.handle_window_event(&key(KeyCode::Space, false));
+
+    assert!(!input.is_held("jump"));
+    assert!(input.was_pressed("jump"));
+    assert!(input.was_released("jump"));
+
+    // Losing focus releases the keys whose releases would never be delivered.
+    input.end_frame();
+    input.handle_window_event(&key(KeyCode::Space, true));
+    input.handle_window_event(&WindowEvent::Focused(false));
+
+    assert!(!input.is_held("jump"));
+    assert!(input.was_released("jump"));
+
+    // Rebinding doesn't report the actions it releases.
+    input.end_frame();
+    input.handle_window_event(&key(KeyCode::Space, true));
+    input.end_frame();
+    input.set_bindings(Bindings::new().with_action("jump", [Button::Key(KeyCode::Enter)]));
+
+    assert!(!input.is_held("jump"));
+    assert!(!input.was_released("jump"));
+}
+
+#[test]
+fn input_combines_axis_bindings() {
+    let mut input = Input::new(
+        Bindings::new()
+            .with_axis(
+                "move_x",
+                [
+                    AxisBinding::Buttons {
+                        negative: Button::Key(KeyCode::KeyA),
+                        positive: Button::Key(KeyCode::KeyD),
+                    },
+                    AxisBinding::Gamepad(GamepadAxis::LeftStickX),
+                ],
+            )
+            .with_axis("move_y", [AxisBinding::Gamepad(GamepadAxis::LeftStickY)]),
+    );
+
+    assert_eq!(input.axis("move_x"), 0.0);
+    assert_eq!(input.axis("unbound"), 0.0);
+
+    input.handle_window_event(&key(KeyCode::KeyD, true));
+    assert_eq!(input.axis("move_x"), 1.0);
+
+    input.handle_window_event(&key(KeyCode::KeyA, true));
+    assert_eq!(input.axis("move_x"), 0.0);
+
+    input.handle_window_event(&key(KeyCode::KeyD, false));
+    assert_eq!(input.axis("move_x"), -1.0);
+
+    // Bindings add up but the axis stays within range.
+    input.handle_gamepad_event(&pad_axis(0, GamepadAxis::LeftStickX, -0.55));
+    assert_eq!(input.axis("move_x"), -1.0);
+
+    input.handle_window_event(&key(KeyCode::KeyA, false));
+    assert_close(input.axis("move_x"), -0.5);
+
+    // The gamepad moved furthest wins and drift within the deadzone is ignored.
+    input.handle_gamepad_event(&pad_axis(1, GamepadAxis::LeftStickX, 0.95));
+    assert_eq!(input.axis("move_x"), 1.0);
+
+    input.handle_gamepad_event(&pad_axis(1, GamepadAxis::LeftStickX, 0.1));
+    assert_close(input.axis("move_x"), -0.5);
+
+    input.handle_gamepad_event(&pad_axis(0, GamepadAxis::LeftStickX, 0.1));
+    assert_eq!(input.axis("move_x"), 0.0);
+
+    input.set_deadzone(Deadzone::NONE);
+    assert_close(input.axis("move_x"), 0.1);
+
+    // Vectors are no longer than one, even diagonally.
+    input.handle_gamepad_event(&pad_axis(0, GamepadAxis::LeftStickY, 1.0));
+    input.handle_gamepad_event(&pad_axis(1, GamepadAxis::LeftStickX, 1.0));
+
+    let vector = input.vector("move_x", "move_y");
+    assert_close(vector.length(), 1.0);
+    assert_close(vector.x, vector.y);
+
+    // Disconnected gamepads stop contributing.
+    input.handle_gamepad_event(&GamepadEvent::Disconnected(GamepadId(1)));
+    assert_close(input.axis("move_x"), 0.1);
+    assert_eq!(
+        input.vector("move_x", "move_y"),
+        Vec2::new(0.1, 1.0).normalize()
+    );
+}
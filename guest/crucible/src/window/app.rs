@@ -1,14 +1,15 @@
 use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
 
 use futures::{StreamExt, channel::mpsc};
-use glam::DVec2;
-use wasmlink::{OwnedGuestClosure, bind_port};
+use glam::{DVec2, UVec2};
+use wasmlink::{GuestSliceRef, GuestStrRef, OwnedGuestClosure, bind_port};
 
 use crate::{
-    base::{env::RunMode, task::wake_executor},
-    gfx::texture::GpuTexture,
+    base::{env::RunMode, error::HostError, task::wake_executor},
+    gfx::texture::{CpuTexture, GpuTexture},
     window::defs::{
-        Key, KeyCode, KeyLocation, MouseButton, MouseEvent, NamedKey, NativeKey, PhysicalKey,
+        CursorGrab, CursorIcon, Fullscreen, Ime, Key, KeyCode, KeyLocation, MouseButton,
+        MouseEvent, NamedKey, NativeKey, PhysicalKey, ScrollDelta,
     },
 };
 
@@ -18,14 +19,27 @@ use super::defs::KeyEvent;
 
 static HAS_WINDOW_SINGLETON: AtomicBool = AtomicBool::new(false);
 
+/// A window owned by the guest. Each window receives its own events, including the redraws
+/// providing the framebuffer it is drawn into.
 #[derive(Debug)]
 pub struct Window {
+    handle: crucible_abi::WindowHandle,
+    is_main: bool,
     rx: mpsc::UnboundedReceiver<WindowEvent>,
     _redraw_requested: OwnedGuestClosure<crucible_abi::RedrawRequestedArgs>,
     _mouse_moved: OwnedGuestClosure<crucible_abi::DVec2>,
     _mouse_event: OwnedGuestClosure<crucible_abi::MouseEvent>,
     _key_event: OwnedGuestClosure<crucible_abi::KeyEvent>,
     _exit_requested: OwnedGuestClosure<()>,
+    _mouse_wheel: OwnedGuestClosure<crucible_abi::MouseScrollDelta>,
+    _cursor_entered: OwnedGuestClosure<()>,
+    _cursor_left: OwnedGuestClosure<()>,
+    _focused: OwnedGuestClosure<bool>,
+    _occluded: OwnedGuestClosure<bool>,
+    _resized: OwnedGuestClosure<crucible_abi::UVec2>,
+    _scale_factor_changed: OwnedGuestClosure<f64>,
+    _mouse_motion: OwnedGuestClosure<crucible_abi::DVec2>,
+    _ime: OwnedGuestClosure<crucible_abi::ImeEvent>,
 }
 
 #[derive(Debug)]
@@ -36,9 +50,27 @@ pub enum WindowEvent {
     MouseEvent(MouseEvent),
     KeyEvent(KeyEvent),
     ExitRequested,
+    MouseWheel(ScrollDelta),
+    CursorEntered,
+    CursorLeft,
+    /// The window gained (`true`) or lost (`false`) keyboard focus.
+    Focused(bool),
+    /// The window became hidden from (`true`) or visible to (`false`) the user. Games may want to
+    /// stop rendering while occluded.
+    Occluded(bool),
+    /// The window's client area was resized to the given size in physical pixels.
+    Resized(UVec2),
+    /// The window moved to a display with a different scale factor.
+    ScaleFactorChanged(f64),
+    /// Raw, unaccelerated mouse movement, delivered only while the window has focus. Unlike
+    /// [`MouseMoved`](Self::MouseMoved), this keeps reporting motion while the cursor is
+    /// [locked](CursorGrab::Locked).
+    MouseMotion(DVec2),
+    Ime(Ime),
 }
 
 impl Window {
+    /// Acquires the main window, which the host creates on its own. Dropping it ends the session.
     pub fn acquire() -> Self {
         RunMode::get().assert_client();
 
@@ -49,8 +81,28 @@ impl Window {
             "`Window` singleton already acquired"
         );
 
+        Self::new(None)
+    }
+
+    /// Opens a secondary window, e.g. for a tool palette or a debug view, whose client area has
+    /// the given `size` in physical pixels. The window is closed once dropped.
+    ///
+    /// The host creates the window asynchronously so its first events only arrive once the
+    /// executor yields.
+    pub fn open(title: &str, size: UVec2) -> Self {
+        RunMode::get().assert_client();
+
+        Self::new(Some((title, size)))
+    }
+
+    fn new(open: Option<(&str, UVec2)>) -> Self {
         bind_port! {
-            fn [crucible_abi::WINDOW_BIND_HANDLERS] "crucible".window_bind_handlers(crucible_abi::WindowHandlers);
+            fn [crucible_abi::WINDOW_BIND_HANDLERS] "crucible".window_bind_handlers(
+                crucible_abi::WindowHandlers
+            ) -> crucible_abi::WindowHandle;
+
+            fn [crucible_abi::WINDOW_OPEN] "crucible".window_open(crucible_abi::WindowOpenArgs)
+                -> crucible_abi::WindowHandle;
         }
 
         let (tx, rx) = mpsc::unbounded();
@@ -147,30 +199,364 @@ impl Window {
             }
         });
 
-        window_bind_handlers(&crucible_abi::WindowHandlers {
+        let mouse_wheel = OwnedGuestClosure::<crucible_abi::MouseScrollDelta>::new({
+            let tx = tx.clone();
+
+            move |arg| {
+                tx.unbounded_send(WindowEvent::MouseWheel(match arg {
+                    crucible_abi::MouseScrollDelta::Line(delta) => {
+                        ScrollDelta::Lines(bytemuck::cast(delta))
+                    }
+                    crucible_abi::MouseScrollDelta::Pixel(delta) => {
+                        ScrollDelta::Pixels(bytemuck::cast(delta))
+                    }
+                }))
+                .unwrap();
+
+                wake_executor();
+            }
+        });
+
+        let cursor_entered = OwnedGuestClosure::<()>::new({
+            let tx = tx.clone();
+
+            move |()| {
+                tx.unbounded_send(WindowEvent::CursorEntered).unwrap();
+                wake_executor();
+            }
+        });
+
+        let cursor_left = OwnedGuestClosure::<()>::new({
+            let tx = tx.clone();
+
+            move |()| {
+                tx.unbounded_send(WindowEvent::CursorLeft).unwrap();
+                wake_executor();
+            }
+        });
+
+        let focused = OwnedGuestClosure::<bool>::new({
+            let tx = tx.clone();
+
+            move |focused| {
+                tx.unbounded_send(WindowEvent::Focused(focused)).unwrap();
+                wake_executor();
+            }
+        });
+
+        let occluded = OwnedGuestClosure::<bool>::new({
+            let tx = tx.clone();
+
+            move |occluded| {
+                tx.unbounded_send(WindowEvent::Occluded(occluded)).unwrap();
+                wake_executor();
+            }
+        });
+
+        let resized = OwnedGuestClosure::<crucible_abi::UVec2>::new({
+            let tx = tx.clone();
+
+            move |size| {
+                tx.unbounded_send(WindowEvent::Resized(bytemuck::cast(size)))
+                    .unwrap();
+                wake_executor();
+            }
+        });
+
+        let scale_factor_changed = OwnedGuestClosure::<f64>::new({
+            let tx = tx.clone();
+
+            move |scale_factor| {
+                tx.unbounded_send(WindowEvent::ScaleFactorChanged(scale_factor))
+                    .unwrap();
+                wake_executor();
+            }
+        });
+
+        let mouse_motion = OwnedGuestClosure::<crucible_abi::DVec2>::new({
+            let tx = tx.clone();
+
+            move |delta| {
+                tx.unbounded_send(WindowEvent::MouseMotion(bytemuck::cast(delta)))
+                    .unwrap();
+                wake_executor();
+            }
+        });
+
+        let ime = OwnedGuestClosure::<crucible_abi::ImeEvent>::new({
+            let tx = tx.clone();
+
+            move |arg| {
+                tx.unbounded_send(WindowEvent::Ime(match arg {
+                    crucible_abi::ImeEvent::Enabled(()) => Ime::Enabled,
+                    crucible_abi::ImeEvent::Preedit(preedit) => Ime::Preedit {
+                        text: preedit.text.decode(),
+                        cursor: preedit
+                            .cursor
+                            .decode()
+                            .map(|range| range.start as usize..range.end as usize),
+                    },
+                    crucible_abi::ImeEvent::Commit(text) => Ime::Commit(text.decode()),
+                    crucible_abi::ImeEvent::Disabled(()) => Ime::Disabled,
+                }))
+                .unwrap();
+
+                wake_executor();
+            }
+        });
+
+        let handlers = crucible_abi::WindowHandlers {
             redraw_requested: redraw_requested.handle(),
             mouse_moved: mouse_moved.handle(),
             mouse_event: mouse_event.handle(),
             key_event: key_event.handle(),
             exit_requested: exit_requested.handle(),
-        });
+            mouse_wheel: mouse_wheel.handle(),
+            cursor_entered: cursor_entered.handle(),
+            cursor_left: cursor_left.handle(),
+            focused: focused.handle(),
+            occluded: occluded.handle(),
+            resized: resized.handle(),
+            scale_factor_changed: scale_factor_changed.handle(),
+            mouse_motion: mouse_motion.handle(),
+            ime: ime.handle(),
+        };
+
+        let handle = match open {
+            Some((title, size)) => window_open(&crucible_abi::WindowOpenArgs {
+                title: GuestStrRef::new(title),
+                size: bytemuck::cast(size),
+                handlers,
+            }),
+            None => window_bind_handlers(&handlers),
+        };
 
         Self {
+            handle,
+            is_main: open.is_none(),
             rx,
             _redraw_requested: redraw_requested,
             _mouse_moved: mouse_moved,
             _mouse_event: mouse_event,
             _key_event: key_event,
             _exit_requested: exit_requested,
+            _mouse_wheel: mouse_wheel,
+            _cursor_entered: cursor_entered,
+            _cursor_left: cursor_left,
+            _focused: focused,
+            _occluded: occluded,
+            _resized: resized,
+            _scale_factor_changed: scale_factor_changed,
+            _mouse_motion: mouse_motion,
+            _ime: ime,
         }
     }
 
     pub fn request_redraw(&mut self) {
         bind_port! {
-            fn [crucible_abi::WINDOW_REQUEST_REDRAW] "crucible".window_request_redraw(());
+            fn [crucible_abi::WINDOW_REQUEST_REDRAW] "crucible".window_request_redraw(
+                crucible_abi::WindowHandle
+            );
         }
 
-        window_request_redraw(&());
+        window_request_redraw(&self.handle);
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_TITLE] "crucible".window_set_title(
+                crucible_abi::WindowSetTitleArgs
+            );
+        }
+
+        window_set_title(&crucible_abi::WindowSetTitleArgs {
+            window: self.handle,
+            title: GuestStrRef::new(title),
+        });
+    }
+
+    /// Requests a new size for the window's client area, in physical pixels. The platform may pick
+    /// a different size or ignore the request entirely; the actual size is reported through
+    /// [`WindowEvent::Resized`].
+    pub fn set_inner_size(&mut self, size: UVec2) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_INNER_SIZE] "crucible".window_set_inner_size(
+                crucible_abi::WindowSetSizeArgs
+            );
+        }
+
+        window_set_inner_size(&crucible_abi::WindowSetSizeArgs {
+            window: self.handle,
+            size: bytemuck::cast(size),
+        });
+    }
+
+    /// Sets the size below which the user can't shrink the window's client area, in physical
+    /// pixels. `None` removes the limit.
+    pub fn set_min_inner_size(&mut self, size: Option<UVec2>) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_MIN_INNER_SIZE] "crucible".window_set_min_inner_size(
+                crucible_abi::WindowSetMinSizeArgs
+            );
+        }
+
+        window_set_min_inner_size(&crucible_abi::WindowSetMinSizeArgs {
+            window: self.handle,
+            size: size.map(bytemuck::cast).into(),
+        });
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_RESIZABLE] "crucible".window_set_resizable(
+                crucible_abi::WindowFlagArgs
+            );
+        }
+
+        window_set_resizable(&crucible_abi::WindowFlagArgs {
+            window: self.handle,
+            enabled: resizable,
+        });
+    }
+
+    /// Enters or leaves fullscreen. Entering fullscreen requires the `fullscreen` capability to be
+    /// declared in the game's [manifest](crate::declare_manifest) and granted by the user.
+    pub fn set_fullscreen(&mut self, mode: Fullscreen) -> Result<(), HostError> {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_FULLSCREEN] "crucible".window_set_fullscreen(
+                crucible_abi::WindowSetFullscreenArgs
+            ) -> Result<(), crucible_abi::AbiError>;
+        }
+
+        window_set_fullscreen(&crucible_abi::WindowSetFullscreenArgs {
+            window: self.handle,
+            mode: match mode {
+                Fullscreen::Windowed => crucible_abi::FullscreenMode::Windowed,
+                Fullscreen::Borderless => crucible_abi::FullscreenMode::Borderless,
+                Fullscreen::Exclusive => crucible_abi::FullscreenMode::Exclusive,
+            },
+        })
+        .decode()
+        .map_err(HostError::from_abi)
+    }
+
+    /// Sets the icon shown in the window's title bar and the task bar. `None` restores the default
+    /// icon.
+    pub fn set_icon(&mut self, icon: Option<&CpuTexture>) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_ICON] "crucible".window_set_icon(
+                crucible_abi::WindowSetIconArgs
+            );
+        }
+
+        window_set_icon(&crucible_abi::WindowSetIconArgs {
+            window: self.handle,
+            icon: icon
+                .map(|icon| crucible_abi::WindowIcon {
+                    buffer: GuestSliceRef::new(bytemuck::cast_slice(icon.pixels())),
+                    size: bytemuck::cast(icon.size()),
+                })
+                .into(),
+        });
+    }
+
+    /// The size of the window's client area, in physical pixels.
+    pub fn inner_size(&self) -> UVec2 {
+        bind_port! {
+            fn [crucible_abi::WINDOW_GET_INNER_SIZE] "crucible".window_get_inner_size(
+                crucible_abi::WindowHandle
+            ) -> crucible_abi::UVec2;
+        }
+
+        bytemuck::cast(window_get_inner_size(&self.handle))
+    }
+
+    /// The number of physical pixels per logical pixel of the display showing the window.
+    pub fn scale_factor(&self) -> f64 {
+        bind_port! {
+            fn [crucible_abi::WINDOW_GET_SCALE_FACTOR] "crucible".window_get_scale_factor(
+                crucible_abi::WindowHandle
+            ) -> f64;
+        }
+
+        window_get_scale_factor(&self.handle)
+    }
+
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_CURSOR_VISIBLE] "crucible".window_set_cursor_visible(
+                crucible_abi::WindowFlagArgs
+            );
+        }
+
+        window_set_cursor_visible(&crucible_abi::WindowFlagArgs {
+            window: self.handle,
+            enabled: visible,
+        });
+    }
+
+    /// Confines or locks the cursor to the window. If the platform doesn't support the requested
+    /// mode, the host falls back to the other one.
+    pub fn set_cursor_grab(&mut self, grab: CursorGrab) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_CURSOR_GRAB] "crucible".window_set_cursor_grab(
+                crucible_abi::WindowSetCursorGrabArgs
+            );
+        }
+
+        window_set_cursor_grab(&crucible_abi::WindowSetCursorGrabArgs {
+            window: self.handle,
+            mode: match grab {
+                CursorGrab::None => crucible_abi::CursorGrabMode::None,
+                CursorGrab::Confined => crucible_abi::CursorGrabMode::Confined,
+                CursorGrab::Locked => crucible_abi::CursorGrabMode::Locked,
+            },
+        });
+    }
+
+    pub fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_CURSOR_ICON] "crucible".window_set_cursor_icon(
+                crucible_abi::WindowSetCursorIconArgs
+            );
+        }
+
+        window_set_cursor_icon(&crucible_abi::WindowSetCursorIconArgs {
+            window: self.handle,
+            icon: icon.to_abi(),
+        });
+    }
+
+    /// Allows the platform's input method editor to be used, which text fields accepting languages
+    /// such as Chinese or Japanese need. While allowed, the keys feeding the IME are reported as
+    /// [`WindowEvent::Ime`] rather than [`WindowEvent::KeyEvent`].
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_IME_ALLOWED] "crucible".window_set_ime_allowed(
+                crucible_abi::WindowFlagArgs
+            );
+        }
+
+        window_set_ime_allowed(&crucible_abi::WindowFlagArgs {
+            window: self.handle,
+            enabled: allowed,
+        });
+    }
+
+    /// Tells the IME where the text being edited is, in physical pixels, so that it can place its
+    /// candidate window nearby.
+    pub fn set_ime_cursor_area(&mut self, position: DVec2, size: DVec2) {
+        bind_port! {
+            fn [crucible_abi::WINDOW_SET_IME_CURSOR_AREA] "crucible".window_set_ime_cursor_area(
+                crucible_abi::ImeCursorArea
+            );
+        }
+
+        window_set_ime_cursor_area(&crucible_abi::ImeCursorArea {
+            window: self.handle,
+            position: bytemuck::cast(position),
+            size: bytemuck::cast(size),
+        });
     }
 
     pub async fn next_event(&mut self) -> WindowEvent {
@@ -181,11 +567,13 @@ impl Window {
 impl Drop for Window {
     fn drop(&mut self) {
         bind_port! {
-            fn [crucible_abi::WINDOW_UNBIND_HANDLERS] "crucible".window_unbind_handlers(());
+            fn [crucible_abi::WINDOW_CLOSE] "crucible".window_close(crucible_abi::WindowHandle);
         }
 
-        window_unbind_handlers(&());
+        window_close(&self.handle);
 
-        HAS_WINDOW_SINGLETON.store(false, Relaxed);
+        if self.is_main {
+            HAS_WINDOW_SINGLETON.store(false, Relaxed);
+        }
     }
 }
@@ -0,0 +1,58 @@
+//! Access to the system clipboard.
+//!
+//! Anything may be written to the clipboard but reading it requires the `clipboard` capability to
+//! be declared in the game's [manifest](crate::declare_manifest) and granted by the user.
+
+use crucible_abi as abi;
+use wasmlink::{GuestSliceRef, GuestStrRef, bind_port};
+
+use crate::{
+    base::{env::RunMode, error::HostError},
+    gfx::texture::CpuTexture,
+};
+
+/// Reads the text on the clipboard, returning `None` if it holds something other than text.
+pub async fn read_text() -> Result<Option<String>, HostError> {
+    RunMode::get().assert_client();
+
+    bind_port! {
+        async fn [abi::CLIPBOARD_READ_TEXT] "crucible".clipboard_read_text(())
+            -> Result<Option<String>, abi::AbiError>;
+    }
+
+    match clipboard_read_text(&()).await.decode() {
+        Ok(text) => Ok(text.decode().map(|text| text.decode())),
+        Err(err) => Err(HostError::from_abi(err)),
+    }
+}
+
+/// Replaces the contents of the clipboard with `text`.
+pub fn write_text(text: &str) -> Result<(), HostError> {
+    RunMode::get().assert_client();
+
+    bind_port! {
+        fn [abi::CLIPBOARD_WRITE_TEXT] "crucible".clipboard_write_text(String)
+            -> Result<(), abi::AbiError>;
+    }
+
+    clipboard_write_text(&GuestStrRef::new(text))
+        .decode()
+        .map_err(HostError::from_abi)
+}
+
+/// Replaces the contents of the clipboard with `image`.
+pub fn write_image(image: &CpuTexture) -> Result<(), HostError> {
+    RunMode::get().assert_client();
+
+    bind_port! {
+        fn [abi::CLIPBOARD_WRITE_IMAGE] "crucible".clipboard_write_image(abi::ClipboardImage)
+            -> Result<(), abi::AbiError>;
+    }
+
+    clipboard_write_image(&abi::ClipboardImage {
+        buffer: GuestSliceRef::new(bytemuck::cast_slice(image.pixels())),
+        size: bytemuck::cast(image.size()),
+    })
+    .decode()
+    .map_err(HostError::from_abi)
+}
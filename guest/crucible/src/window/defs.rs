@@ -1,4 +1,7 @@
+use std::ops::Range;
+
 use enum_ordinalize::Ordinalize;
+use glam::DVec2;
 
 // === Mouse === //
 
@@ -18,6 +21,143 @@ pub enum MouseButton {
     Other(u16),
 }
 
+/// How far the mouse wheel or touchpad scrolled.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScrollDelta {
+    /// A scroll in lines and rows, as reported by most mouse wheels. Positive values scroll up and
+    /// to the right.
+    Lines(DVec2),
+    /// A scroll in physical pixels, as reported by touchpads.
+    Pixels(DVec2),
+}
+
+// === Fullscreen === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+pub enum Fullscreen {
+    #[default]
+    Windowed,
+    /// Covers the current monitor with a borderless window.
+    Borderless,
+    /// Switches the current monitor to its native video mode, falling back to
+    /// [`Borderless`](Self::Borderless) on platforms which don't support it.
+    Exclusive,
+}
+
+// === Cursor === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+pub enum CursorGrab {
+    #[default]
+    None,
+    /// Keeps the cursor inside the window.
+    Confined,
+    /// Keeps the cursor in place. The mouse can still be read through
+    /// [`WindowEvent::MouseMotion`](super::app::WindowEvent::MouseMotion).
+    Locked,
+}
+
+/// The icon shown for the cursor while it is over the window. These mirror the CSS `cursor`
+/// values.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+#[non_exhaustive]
+pub enum CursorIcon {
+    #[default]
+    Default,
+    ContextMenu,
+    Help,
+    Pointer,
+    Progress,
+    Wait,
+    Cell,
+    Crosshair,
+    Text,
+    VerticalText,
+    Alias,
+    Copy,
+    Move,
+    NoDrop,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    EResize,
+    NResize,
+    NeResize,
+    NwResize,
+    SResize,
+    SeResize,
+    SwResize,
+    WResize,
+    EwResize,
+    NsResize,
+    NeswResize,
+    NwseResize,
+    ColResize,
+    RowResize,
+    AllScroll,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl CursorIcon {
+    pub(super) fn to_abi(self) -> crucible_abi::CursorIcon {
+        match self {
+            CursorIcon::Default => crucible_abi::CursorIcon::Default,
+            CursorIcon::ContextMenu => crucible_abi::CursorIcon::ContextMenu,
+            CursorIcon::Help => crucible_abi::CursorIcon::Help,
+            CursorIcon::Pointer => crucible_abi::CursorIcon::Pointer,
+            CursorIcon::Progress => crucible_abi::CursorIcon::Progress,
+            CursorIcon::Wait => crucible_abi::CursorIcon::Wait,
+            CursorIcon::Cell => crucible_abi::CursorIcon::Cell,
+            CursorIcon::Crosshair => crucible_abi::CursorIcon::Crosshair,
+            CursorIcon::Text => crucible_abi::CursorIcon::Text,
+            CursorIcon::VerticalText => crucible_abi::CursorIcon::VerticalText,
+            CursorIcon::Alias => crucible_abi::CursorIcon::Alias,
+            CursorIcon::Copy => crucible_abi::CursorIcon::Copy,
+            CursorIcon::Move => crucible_abi::CursorIcon::Move,
+            CursorIcon::NoDrop => crucible_abi::CursorIcon::NoDrop,
+            CursorIcon::NotAllowed => crucible_abi::CursorIcon::NotAllowed,
+            CursorIcon::Grab => crucible_abi::CursorIcon::Grab,
+            CursorIcon::Grabbing => crucible_abi::CursorIcon::Grabbing,
+            CursorIcon::EResize => crucible_abi::CursorIcon::EResize,
+            CursorIcon::NResize => crucible_abi::CursorIcon::NResize,
+            CursorIcon::NeResize => crucible_abi::CursorIcon::NeResize,
+            CursorIcon::NwResize => crucible_abi::CursorIcon::NwResize,
+            CursorIcon::SResize => crucible_abi::CursorIcon::SResize,
+            CursorIcon::SeResize => crucible_abi::CursorIcon::SeResize,
+            CursorIcon::SwResize => crucible_abi::CursorIcon::SwResize,
+            CursorIcon::WResize => crucible_abi::CursorIcon::WResize,
+            CursorIcon::EwResize => crucible_abi::CursorIcon::EwResize,
+            CursorIcon::NsResize => crucible_abi::CursorIcon::NsResize,
+            CursorIcon::NeswResize => crucible_abi::CursorIcon::NeswResize,
+            CursorIcon::NwseResize => crucible_abi::CursorIcon::NwseResize,
+            CursorIcon::ColResize => crucible_abi::CursorIcon::ColResize,
+            CursorIcon::RowResize => crucible_abi::CursorIcon::RowResize,
+            CursorIcon::AllScroll => crucible_abi::CursorIcon::AllScroll,
+            CursorIcon::ZoomIn => crucible_abi::CursorIcon::ZoomIn,
+            CursorIcon::ZoomOut => crucible_abi::CursorIcon::ZoomOut,
+        }
+    }
+}
+
+// === IME === //
+
+/// An event from the platform's input method editor. See
+/// [`Window::set_ime_allowed`](super::app::Window::set_ime_allowed).
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum Ime {
+    Enabled,
+    /// The text being composed changed. `cursor` is the byte range of `text` covered by the IME's
+    /// cursor, or `None` if the cursor should be hidden. An empty `text` clears the composition.
+    Preedit {
+        text: String,
+        cursor: Option<Range<usize>>,
+    },
+    /// The composition was finished, producing the given text.
+    Commit(String),
+    Disabled,
+}
+
 // === Keyboard === //
 
 /// Describes a keyboard input targeting a window.
@@ -124,6 +264,7 @@ pub enum PhysicalKey {
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ordinalize)]
 #[ordinalize(impl_trait = false)]
 #[ordinalize(from_ordinal(pub(super) fn from_winit))]
+#[ordinalize(variants(pub(super) const VARIANTS))]
 #[repr(u32)]
 pub enum KeyCode {
     /// <kbd>`</kbd> on a US keyboard. This is also called a backtick or grave.
@@ -0,0 +1,426 @@
+//! Gamepad input.
+//!
+//! [`Gamepads`] tracks the state of every connected controller. Buttons and axes follow the
+//! standard gamepad layout so that a game's controls mean the same thing on every brand of
+//! controller. The host reports the raw position of each axis; deadzones are applied by
+//! [`Gamepad::axis`] and friends according to the [`Deadzone`] the game chooses.
+
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+};
+
+use enum_ordinalize::Ordinalize;
+use futures::{StreamExt, channel::mpsc};
+use glam::Vec2;
+use wasmlink::{OwnedGuestClosure, bind_port};
+
+use crate::base::{env::RunMode, task::wake_executor};
+
+// === Layout === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct GamepadId(pub u32);
+
+/// The buttons of the standard gamepad layout. Face buttons are named after their position: on an
+/// Xbox controller, [`South`](Self::South) is `A` and [`East`](Self::East) is `B`.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Ordinalize)]
+#[ordinalize(impl_trait = false)]
+#[ordinalize(variants(pub(super) const VARIANTS))]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    /// Pressed once the left trigger is pulled past its actuation point. Its analog position is
+    /// available as [`GamepadAxis::LeftTrigger`].
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// The button in the center of the controller, e.g. the Xbox or PlayStation button.
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const COUNT: usize = 17;
+
+    fn from_abi(button: crucible_abi::GamepadButton) -> Self {
+        match button {
+            crucible_abi::GamepadButton::South => Self::South,
+            crucible_abi::GamepadButton::East => Self::East,
+            crucible_abi::GamepadButton::North => Self::North,
+            crucible_abi::GamepadButton::West => Self::West,
+            crucible_abi::GamepadButton::LeftBumper => Self::LeftBumper,
+            crucible_abi::GamepadButton::RightBumper => Self::RightBumper,
+            crucible_abi::GamepadButton::LeftTrigger => Self::LeftTrigger,
+            crucible_abi::GamepadButton::RightTrigger => Self::RightTrigger,
+            crucible_abi::GamepadButton::Select => Self::Select,
+            crucible_abi::GamepadButton::Start => Self::Start,
+            crucible_abi::GamepadButton::Mode => Self::Mode,
+            crucible_abi::GamepadButton::LeftStick => Self::LeftStick,
+            crucible_abi::GamepadButton::RightStick => Self::RightStick,
+            crucible_abi::GamepadButton::DPadUp => Self::DPadUp,
+            crucible_abi::GamepadButton::DPadDown => Self::DPadDown,
+            crucible_abi::GamepadButton::DPadLeft => Self::DPadLeft,
+            crucible_abi::GamepadButton::DPadRight => Self::DPadRight,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Ordinalize)]
+#[ordinalize(impl_trait = false)]
+#[ordinalize(variants(pub(super) const VARIANTS))]
+pub enum GamepadAxis {
+    /// Ranges from `-1` (left) to `1` (right).
+    LeftStickX,
+    /// Ranges from `-1` (down) to `1` (up).
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    /// Ranges from `0` (released) to `1` (fully pulled).
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const COUNT: usize = 6;
+
+    fn from_abi(axis: crucible_abi::GamepadAxis) -> Self {
+        match axis {
+            crucible_abi::GamepadAxis::LeftStickX => Self::LeftStickX,
+            crucible_abi::GamepadAxis::LeftStickY => Self::LeftStickY,
+            crucible_abi::GamepadAxis::RightStickX => Self::RightStickX,
+            crucible_abi::GamepadAxis::RightStickY => Self::RightStickY,
+            crucible_abi::GamepadAxis::LeftTrigger => Self::LeftTrigger,
+            crucible_abi::GamepadAxis::RightTrigger => Self::RightTrigger,
+        }
+    }
+}
+
+// === Deadzone === //
+
+/// Filters out the drift of worn sticks and triggers. Magnitudes below `inner` read as zero, those
+/// above `outer` read as one and those in between are rescaled to cover the full range.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Deadzone {
+    pub inner: f32,
+    pub outer: f32,
+}
+
+impl Default for Deadzone {
+    fn default() -> Self {
+        Self {
+            inner: 0.15,
+            outer: 0.95,
+        }
+    }
+}
+
+impl Deadzone {
+    pub const NONE: Self = Self {
+        inner: 0.0,
+        outer: 1.0,
+    };
+
+    /// Applies the deadzone to the position of a single axis.
+    pub fn apply(self, value: f32) -> f32 {
+        self.rescale(value.abs()).copysign(value)
+    }
+
+    /// Applies the deadzone to the distance of a stick from its center so that the stick responds
+    /// the same way in every direction.
+    pub fn apply_radial(self, stick: Vec2) -> Vec2 {
+        let len = stick.length();
+
+        if len <= self.inner {
+            return Vec2::ZERO;
+        }
+
+        stick / len * self.rescale(len)
+    }
+
+    fn rescale(self, magnitude: f32) -> f32 {
+        ((magnitude - self.inner) / (self.outer - self.inner)).clamp(0.0, 1.0)
+    }
+}
+
+// === Gamepads === //
+
+static HAS_GAMEPADS_SINGLETON: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadEvent {
+    Connected {
+        id: GamepadId,
+        name: String,
+    },
+    Disconnected(GamepadId),
+    Button {
+        id: GamepadId,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    /// The raw position of an axis changed. No deadzone is applied to `value`.
+    Axis {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// The state of a connected gamepad.
+#[derive(Debug, Clone)]
+pub struct Gamepad {
+    id: GamepadId,
+    name: String,
+    deadzone: Deadzone,
+    buttons: [bool; GamepadButton::COUNT],
+    axes: [f32; GamepadAxis::COUNT],
+}
+
+impl Gamepad {
+    pub fn id(&self) -> GamepadId {
+        self.id
+    }
+
+    /// The name the controller reports, e.g. `"Xbox Wireless Controller"`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize]
+    }
+
+    /// The position of `axis` with the deadzone applied. Stick axes use the deadzone of the whole
+    /// stick, as returned by [`left_stick`](Self::left_stick) and
+    /// [`right_stick`](Self::right_stick).
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        match axis {
+            GamepadAxis::LeftStickX => self.left_stick().x,
+            GamepadAxis::LeftStickY => self.left_stick().y,
+            GamepadAxis::RightStickX => self.right_stick().x,
+            GamepadAxis::RightStickY => self.right_stick().y,
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => {
+                self.deadzone.apply(self.raw_axis(axis))
+            }
+        }
+    }
+
+    /// The position of `axis` as reported by the controller.
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    pub fn left_stick(&self) -> Vec2 {
+        self.stick(GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)
+    }
+
+    pub fn right_stick(&self) -> Vec2 {
+        self.stick(GamepadAxis::RightStickX, GamepadAxis::RightStickY)
+    }
+
+    fn stick(&self, x: GamepadAxis, y: GamepadAxis) -> Vec2 {
+        self.deadzone
+            .apply_radial(Vec2::new(self.raw_axis(x), self.raw_axis(y)))
+    }
+}
+
+/// Receives the events of every gamepad and tracks their state. Games can either await events as
+/// they come in with [`next_event`](Self::next_event) or drain them once per frame with
+/// [`poll`](Self::poll) and read the state of each [`Gamepad`].
+#[derive(Debug)]
+pub struct Gamepads {
+    rx: mpsc::UnboundedReceiver<GamepadEvent>,
+    pads: BTreeMap<GamepadId, Gamepad>,
+    deadzone: Deadzone,
+    _connected: OwnedGuestClosure<crucible_abi::GamepadInfo>,
+    _disconnected: OwnedGuestClosure<u32>,
+    _button: OwnedGuestClosure<crucible_abi::GamepadButtonEvent>,
+    _axis: OwnedGuestClosure<crucible_abi::GamepadAxisEvent>,
+}
+
+impl Gamepads {
+    pub fn acquire() -> Self {
+        RunMode::get().assert_client();
+
+        assert!(
+            HAS_GAMEPADS_SINGLETON
+                .compare_exchange(false, true, Relaxed, Relaxed)
+                .is_ok(),
+            "`Gamepads` singleton already acquired"
+        );
+
+        bind_port! {
+            fn [crucible_abi::GAMEPAD_BIND_HANDLERS] "crucible".gamepad_bind_handlers(crucible_abi::GamepadHandlers);
+        }
+
+        let (tx, rx) = mpsc::unbounded();
+
+        let connected = OwnedGuestClosure::<crucible_abi::GamepadInfo>::new({
+            let tx = tx.clone();
+
+            move |arg| {
+                tx.unbounded_send(GamepadEvent::Connected {
+                    id: GamepadId(arg.id),
+                    name: arg.name.decode(),
+                })
+                .unwrap();
+
+                wake_executor();
+            }
+        });
+
+        let disconnected = OwnedGuestClosure::<u32>::new({
+            let tx = tx.clone();
+
+            move |id| {
+                tx.unbounded_send(GamepadEvent::Disconnected(GamepadId(id)))
+                    .unwrap();
+                wake_executor();
+            }
+        });
+
+        let button = OwnedGuestClosure::<crucible_abi::GamepadButtonEvent>::new({
+            let tx = tx.clone();
+
+            move |arg| {
+                tx.unbounded_send(GamepadEvent::Button {
+                    id: GamepadId(arg.id),
+                    button: GamepadButton::from_abi(arg.button),
+                    pressed: arg.pressed,
+                })
+                .unwrap();
+
+                wake_executor();
+            }
+        });
+
+        let axis = OwnedGuestClosure::<crucible_abi::GamepadAxisEvent>::new({
+            let tx = tx.clone();
+
+            move |arg| {
+                tx.unbounded_send(GamepadEvent::Axis {
+                    id: GamepadId(arg.id),
+                    axis: GamepadAxis::from_abi(arg.axis),
+                    value: arg.value,
+                })
+                .unwrap();
+
+                wake_executor();
+            }
+        });
+
+        gamepad_bind_handlers(&crucible_abi::GamepadHandlers {
+            connected: connected.handle(),
+            disconnected: disconnected.handle(),
+            button: button.handle(),
+            axis: axis.handle(),
+        });
+
+        Self {
+            rx,
+            pads: BTreeMap::new(),
+            deadzone: Deadzone::default(),
+            _connected: connected,
+            _disconnected: disconnected,
+            _button: button,
+            _axis: axis,
+        }
+    }
+
+    pub fn deadzone(&self) -> Deadzone {
+        self.deadzone
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: Deadzone) {
+        self.deadzone = deadzone;
+
+        for pad in self.pads.values_mut() {
+            pad.deadzone = deadzone;
+        }
+    }
+
+    /// Iterates over the connected gamepads in the order of their IDs.
+    pub fn iter(&self) -> impl Iterator<Item = &Gamepad> {
+        self.pads.values()
+    }
+
+    pub fn get(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.pads.get(&id)
+    }
+
+    /// Waits for the next event, updating the state of its gamepad.
+    pub async fn next_event(&mut self) -> GamepadEvent {
+        let event = self.rx.next().await.unwrap();
+        self.apply(&event);
+        event
+    }
+
+    /// Takes every event received since the last call, updating the state of their gamepads.
+    pub fn poll(&mut self) -> Vec<GamepadEvent> {
+        let mut events = Vec::new();
+
+        while let Ok(event) = self.rx.try_recv() {
+            self.apply(&event);
+            events.push(event);
+        }
+
+        events
+    }
+
+    fn apply(&mut self, event: &GamepadEvent) {
+        match event {
+            GamepadEvent::Connected { id, name } => {
+                self.pads.insert(
+                    *id,
+                    Gamepad {
+                        id: *id,
+                        name: name.clone(),
+                        deadzone: self.deadzone,
+                        buttons: [false; GamepadButton::COUNT],
+                        axes: [0.; GamepadAxis::COUNT],
+                    },
+                );
+            }
+            GamepadEvent::Disconnected(id) => {
+                self.pads.remove(id);
+            }
+            GamepadEvent::Button {
+                id,
+                button,
+                pressed,
+            } => {
+                if let Some(pad) = self.pads.get_mut(id) {
+                    pad.buttons[*button as usize] = *pressed;
+                }
+            }
+            GamepadEvent::Axis { id, axis, value } => {
+                if let Some(pad) = self.pads.get_mut(id) {
+                    pad.axes[*axis as usize] = *value;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Gamepads {
+    fn drop(&mut self) {
+        bind_port! {
+            fn [crucible_abi::GAMEPAD_UNBIND_HANDLERS] "crucible".gamepad_unbind_handlers(());
+        }
+
+        gamepad_unbind_handlers(&());
+
+        HAS_GAMEPADS_SINGLETON.store(false, Relaxed);
+    }
+}
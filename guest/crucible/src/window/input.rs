@@ -0,0 +1,570 @@
+//! Action mapping.
+//!
+//! Games read named actions such as `"jump"` and axes such as `"move_x"` rather than specific keys
+//! so that players can rebind their controls. [`Bindings`] maps each name to the buttons which
+//! trigger it and can be saved as text, e.g. to [storage](crate::base::files). [`Input`] is fed the
+//! game's window and gamepad events and tracks the state of every action from one frame to the
+//! next.
+//!
+//! ```ignore
+//! let bindings = Bindings::new()
+//!     .with_action("jump", [Button::Key(KeyCode::Space), Button::Gamepad(GamepadButton::South)])
+//!     .with_axis("move_x", [
+//!         AxisBinding::Buttons {
+//!             negative: Button::Key(KeyCode::KeyA),
+//!             positive: Button::Key(KeyCode::KeyD),
+//!         },
+//!         AxisBinding::Gamepad(GamepadAxis::LeftStickX),
+//!     ]);
+//!
+//! let mut input = Input::new(bindings);
+//!
+//! // For every event...
+//! input.handle_window_event(&event);
+//!
+//! // ...and once per frame.
+//! if input.was_pressed("jump") {
+//!     player.jump();
+//! }
+//!
+//! player.walk(input.axis("move_x"));
+//! input.end_frame();
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use glam::Vec2;
+
+use crate::window::{
+    app::WindowEvent,
+    defs::{KeyCode, MouseButton, PhysicalKey},
+    gamepad::{Deadzone, GamepadAxis, GamepadButton, GamepadEvent, GamepadId},
+};
+
+// === Bindings === //
+
+/// A button which can trigger an action.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum Button {
+    /// A key, identified by its position on the keyboard so that bindings work the same way on
+    /// every layout.
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// A button on any of the connected gamepads.
+    Gamepad(GamepadButton),
+}
+
+/// An input which controls an axis.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum AxisBinding {
+    /// Reads `-1` while `negative` is held, `1` while `positive` is held and `0` while both or
+    /// neither are.
+    Buttons { negative: Button, positive: Button },
+    /// The position of an axis on any of the connected gamepads, with the [`Input`]'s deadzone
+    /// applied.
+    Gamepad(GamepadAxis),
+}
+
+/// Maps the names of actions and axes to the inputs which control them.
+///
+/// Bindings are written as text with one action or axis per line:
+///
+/// ```text
+/// action jump = key Space, gamepad South
+/// action fire = mouse Left
+/// axis move_x = key KeyA / key KeyD, gamepad-axis LeftStickX
+/// ```
+///
+/// An axis bound to two buttons lists the negative one first. Names may only contain ASCII
+/// letters, digits, `.`, `-` and `_`. Lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Bindings {
+    actions: BTreeMap<String, Vec<Button>>,
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_action(
+        mut self,
+        action: impl Into<String>,
+        buttons: impl IntoIterator<Item = Button>,
+    ) -> Self {
+        self.set_action(action, buttons);
+        self
+    }
+
+    pub fn with_axis(
+        mut self,
+        axis: impl Into<String>,
+        bindings: impl IntoIterator<Item = AxisBinding>,
+    ) -> Self {
+        self.set_axis(axis, bindings);
+        self
+    }
+
+    /// The buttons bound to `action`. Actions which were never bound have none.
+    pub fn action(&self, action: &str) -> &[Button] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axis(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replaces the buttons bound to `action`. Binding no buttons keeps the action around so that
+    /// [`merge`](Self::merge) can tell it apart from one that was never rebound.
+    pub fn set_action(
+        &mut self,
+        action: impl Into<String>,
+        buttons: impl IntoIterator<Item = Button>,
+    ) {
+        let action = action.into();
+        assert!(is_valid_name(&action), "invalid action name `{action}`");
+
+        self.actions.insert(action, buttons.into_iter().collect());
+    }
+
+    pub fn set_axis(
+        &mut self,
+        axis: impl Into<String>,
+        bindings: impl IntoIterator<Item = AxisBinding>,
+    ) {
+        let axis = axis.into();
+        assert!(is_valid_name(&axis), "invalid axis name `{axis}`");
+
+        self.axes.insert(axis, bindings.into_iter().collect());
+    }
+
+    /// Iterates over the actions and the buttons bound to them, ordered by name.
+    pub fn actions(&self) -> impl Iterator<Item = (&str, &[Button])> {
+        self.actions
+            .iter()
+            .map(|(action, buttons)| (action.as_str(), buttons.as_slice()))
+    }
+
+    pub fn axes(&self) -> impl Iterator<Item = (&str, &[AxisBinding])> {
+        self.axes
+            .iter()
+            .map(|(axis, bindings)| (axis.as_str(), bindings.as_slice()))
+    }
+
+    /// Replaces the bindings of every action and axis in `overrides`. Games typically merge the
+    /// player's saved bindings into their defaults so that actions added by an update keep their
+    /// default bindings.
+    pub fn merge(&mut self, overrides: Bindings) {
+        self.actions.extend(overrides.actions);
+        self.axes.extend(overrides.axes);
+    }
+
+    pub fn parse(text: &str) -> Result<Self, BindingsError> {
+        let mut bindings = Self::default();
+
+        for (i, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            parse_line(&mut bindings, line).map_err(|reason| BindingsError {
+                line: i + 1,
+                reason,
+            })?;
+        }
+
+        Ok(bindings)
+    }
+}
+
+impl FromStr for Bindings {
+    type Err = BindingsError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for Bindings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (action, buttons) in &self.actions {
+            write!(f, "action {action} =")?;
+            write_list(f, buttons)?;
+        }
+
+        for (axis, bindings) in &self.axes {
+            write!(f, "axis {axis} =")?;
+            write_list(f, bindings)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, items: &[impl fmt::Display]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        let sep = if i == 0 { " " } else { ", " };
+        write!(f, "{sep}{item}")?;
+    }
+
+    writeln!(f)
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Button::Key(key) => write!(f, "key {key:?}"),
+            Button::Mouse(MouseButton::Other(id)) => write!(f, "mouse {id}"),
+            Button::Mouse(button) => write!(f, "mouse {button:?}"),
+            Button::Gamepad(button) => write!(f, "gamepad {button:?}"),
+        }
+    }
+}
+
+impl FromStr for Button {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (kind, name) = text
+            .split_once(' ')
+            .ok_or_else(|| format!("expected a button but found `{text}`"))?;
+
+        let name = name.trim();
+
+        Ok(match kind {
+            "key" => Button::Key(find_variant(&KeyCode::VARIANTS, name, "key")?),
+            "mouse" => Button::Mouse(match name {
+                "Left" => MouseButton::Left,
+                "Right" => MouseButton::Right,
+                "Middle" => MouseButton::Middle,
+                "Back" => MouseButton::Back,
+                "Forward" => MouseButton::Forward,
+                _ => MouseButton::Other(
+                    name.parse()
+                        .map_err(|_| format!("unknown mouse button `{name}`"))?,
+                ),
+            }),
+            "gamepad" => Button::Gamepad(find_variant(
+                &GamepadButton::VARIANTS,
+                name,
+                "gamepad button",
+            )?),
+            _ => return Err(format!("unknown kind of button `{kind}`")),
+        })
+    }
+}
+
+impl fmt::Display for AxisBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AxisBinding::Buttons { negative, positive } => write!(f, "{negative} / {positive}"),
+            AxisBinding::Gamepad(axis) => write!(f, "gamepad-axis {axis:?}"),
+        }
+    }
+}
+
+impl FromStr for AxisBinding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if let Some(name) = text.strip_prefix("gamepad-axis ") {
+            return Ok(AxisBinding::Gamepad(find_variant(
+                &GamepadAxis::VARIANTS,
+                name.trim(),
+                "gamepad axis",
+            )?));
+        }
+
+        let (negative, positive) = text
+            .split_once('/')
+            .ok_or_else(|| format!("expected an axis binding but found `{text}`"))?;
+
+        Ok(AxisBinding::Buttons {
+            negative: negative.trim().parse()?,
+            positive: positive.trim().parse()?,
+        })
+    }
+}
+
+fn parse_line(bindings: &mut Bindings, line: &str) -> Result<(), String> {
+    let (head, list) = line
+        .split_once('=')
+        .ok_or_else(|| "expected `=` after the name".to_string())?;
+
+    let (kind, name) = head
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| "expected `action` or `axis` followed by a name".to_string())?;
+
+    let name = name.trim();
+
+    if !is_valid_name(name) {
+        return Err(format!("invalid name `{name}`"));
+    }
+
+    let items = list
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty());
+
+    match kind {
+        "action" => {
+            let buttons = items.map(str::parse).collect::<Result<Vec<_>, _>>()?;
+            bindings.actions.insert(name.to_string(), buttons);
+        }
+        "axis" => {
+            let axis = items.map(str::parse).collect::<Result<Vec<_>, _>>()?;
+            bindings.axes.insert(name.to_string(), axis);
+        }
+        _ => return Err(format!("expected `action` or `axis` but found `{kind}`")),
+    }
+
+    Ok(())
+}
+
+fn find_variant<T: Copy + fmt::Debug>(variants: &[T], name: &str, what: &str) -> Result<T, String> {
+    variants
+        .iter()
+        .copied()
+        .find(|variant| format!("{variant:?}") == name)
+        .ok_or_else(|| format!("unknown {what} `{name}`"))
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '-' | '_'))
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("invalid binding on line {line}: {reason}")]
+pub struct BindingsError {
+    pub line: usize,
+    pub reason: String,
+}
+
+// === Input === //
+
+/// Tracks the state of the actions and axes of a set of [`Bindings`].
+///
+/// Actions are held while any of their buttons are held. Presses and releases are remembered until
+/// [`end_frame`](Self::end_frame) is called so that a tap shorter than a frame is still seen as
+/// both a press and a release.
+#[derive(Debug, Clone)]
+pub struct Input {
+    bindings: Bindings,
+    deadzone: Deadzone,
+    /// The buttons being held. Gamepad buttons are tracked per gamepad so that releasing a button
+    /// on one gamepad doesn't release it on another.
+    held_buttons: HashSet<(Button, Option<GamepadId>)>,
+    gamepad_axes: HashMap<(GamepadId, GamepadAxis), f32>,
+    held_actions: HashSet<String>,
+    pressed_actions: HashSet<String>,
+    released_actions: HashSet<String>,
+    pressed_buttons: Vec<Button>,
+}
+
+impl Input {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            deadzone: Deadzone::default(),
+            held_buttons: HashSet::new(),
+            gamepad_axes: HashMap::new(),
+            held_actions: HashSet::new(),
+            pressed_actions: HashSet::new(),
+            released_actions: HashSet::new(),
+            pressed_buttons: Vec::new(),
+        }
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    /// Replaces the bindings, e.g. once the player rebinds an action. Actions which become held or
+    /// released because of the change aren't reported as pressed or released.
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+        self.held_actions = self.compute_held_actions();
+        self.pressed_actions.clear();
+        self.released_actions.clear();
+    }
+
+    pub fn deadzone(&self) -> Deadzone {
+        self.deadzone
+    }
+
+    /// Sets the deadzone applied to the gamepad axes bound to axes. Unlike
+    /// [`Gamepad::axis`](super::gamepad::Gamepad::axis), it is applied to every axis of a stick
+    /// separately.
+    pub fn set_deadzone(&mut self, deadzone: Deadzone) {
+        self.deadzone = deadzone;
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyEvent(event) => {
+                let PhysicalKey::KeyCode(key) = event.physical_key else {
+                    return;
+                };
+
+                if !event.repeat {
+                    self.set_button(Button::Key(key), None, event.pressed);
+                }
+            }
+            WindowEvent::MouseEvent(event) => {
+                self.set_button(Button::Mouse(event.button), None, event.pressed);
+            }
+            WindowEvent::Focused(false) => {
+                // Releases happening while the window is unfocused are never delivered.
+                self.held_buttons
+                    .retain(|(button, _)| matches!(button, Button::Gamepad(_)));
+
+                self.update_actions();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn handle_gamepad_event(&mut self, event: &GamepadEvent) {
+        match *event {
+            GamepadEvent::Connected { .. } => {}
+            GamepadEvent::Disconnected(id) => {
+                self.held_buttons.retain(|&(_, pad)| pad != Some(id));
+                self.gamepad_axes.retain(|&(pad, _), _| pad != id);
+
+                self.update_actions();
+            }
+            GamepadEvent::Button {
+                id,
+                button,
+                pressed,
+            } => {
+                self.set_button(Button::Gamepad(button), Some(id), pressed);
+            }
+            GamepadEvent::Axis { id, axis, value } => {
+                self.gamepad_axes.insert((id, axis), value);
+            }
+        }
+    }
+
+    fn set_button(&mut self, button: Button, pad: Option<GamepadId>, pressed: bool) {
+        if pressed {
+            if self.held_buttons.insert((button, pad)) {
+                self.pressed_buttons.push(button);
+            }
+        } else {
+            self.held_buttons.remove(&(button, pad));
+        }
+
+        self.update_actions();
+    }
+
+    fn update_actions(&mut self) {
+        let held = self.compute_held_actions();
+
+        for action in held.difference(&self.held_actions) {
+            self.pressed_actions.insert(action.clone());
+        }
+
+        for action in self.held_actions.difference(&held) {
+            self.released_actions.insert(action.clone());
+        }
+
+        self.held_actions = held;
+    }
+
+    fn compute_held_actions(&self) -> HashSet<String> {
+        self.bindings
+            .actions()
+            .filter(|(_, buttons)| buttons.iter().any(|&button| self.is_button_held(button)))
+            .map(|(action, _)| action.to_string())
+            .collect()
+    }
+
+    /// Whether `button` is held, on any gamepad for gamepad buttons.
+    pub fn is_button_held(&self, button: Button) -> bool {
+        self.held_buttons.iter().any(|&(held, _)| held == button)
+    }
+
+    /// The buttons pressed since the last [`end_frame`](Self::end_frame), in the order they were
+    /// pressed. Rebinding menus can use this to capture the button the player wants to bind.
+    pub fn pressed_buttons(&self) -> &[Button] {
+        &self.pressed_buttons
+    }
+
+    pub fn is_held(&self, action: &str) -> bool {
+        self.held_actions.contains(action)
+    }
+
+    /// Whether `action` became held since the last [`end_frame`](Self::end_frame).
+    pub fn was_pressed(&self, action: &str) -> bool {
+        self.pressed_actions.contains(action)
+    }
+
+    /// Whether `action` stopped being held since the last [`end_frame`](Self::end_frame).
+    pub fn was_released(&self, action: &str) -> bool {
+        self.released_actions.contains(action)
+    }
+
+    /// The value of `axis`, between `-1` and `1`. The values of its bindings are added together.
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.bindings
+            .axis(axis)
+            .iter()
+            .map(|binding| self.axis_binding_value(*binding))
+            .sum::<f32>()
+            .clamp(-1.0, 1.0)
+    }
+
+    /// Combines two axes into a vector no longer than one, e.g. for movement.
+    pub fn vector(&self, x: &str, y: &str) -> Vec2 {
+        Vec2::new(self.axis(x), self.axis(y)).clamp_length_max(1.0)
+    }
+
+    fn axis_binding_value(&self, binding: AxisBinding) -> f32 {
+        match binding {
+            AxisBinding::Buttons { negative, positive } => {
+                let value = |button| {
+                    if self.is_button_held(button) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                };
+                value(positive) - value(negative)
+            }
+            AxisBinding::Gamepad(axis) => {
+                // The gamepad moved furthest from its rest position wins.
+                let value = self
+                    .gamepad_axes
+                    .iter()
+                    .filter(|&(&(_, other), _)| other == axis)
+                    .map(|(_, &value)| value)
+                    .fold(
+                        0.0f32,
+                        |acc, value| {
+                            if value.abs() > acc.abs() { value } else { acc }
+                        },
+                    );
+
+                self.deadzone.apply(value)
+            }
+        }
+    }
+
+    /// Forgets the presses and releases of the current frame. Call this once the frame's input has
+    /// been read.
+    pub fn end_frame(&mut self) {
+        self.pressed_actions.clear();
+        self.released_actions.clear();
+        self.pressed_buttons.clear();
+    }
+}
@@ -1,2 +1,5 @@
 pub mod app;
+pub mod clipboard;
 pub mod defs;
+pub mod gamepad;
+pub mod input;
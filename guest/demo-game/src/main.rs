@@ -1,5 +1,3 @@
-use std::collections::HashSet;
-
 use crucible::{
     base::{
         env::IntervalTimer,
@@ -16,10 +14,13 @@ use crucible::{
     shell::socket::LoginSocket,
     window::{
         app::{Window, WindowEvent},
-        defs::{KeyCode, PhysicalKey},
+        defs::KeyCode,
+        input::{AxisBinding, Bindings, Button, Input},
     },
 };
-use glam::Vec2;
+use glam::{UVec2, Vec2};
+
+crucible::declare_manifest!("net 127.0.0.1:8080");
 
 fn main() {
     setup_logger();
@@ -49,34 +50,51 @@ async fn main_loop() {
     .make_gpu();
 
     let mut pos = Vec2::ZERO;
-    let mut keys_down = HashSet::<KeyCode>::default();
+    let mut input = Input::new(
+        Bindings::new()
+            .with_axis(
+                "move_x",
+                [AxisBinding::Buttons {
+                    negative: Button::Key(KeyCode::KeyA),
+                    positive: Button::Key(KeyCode::KeyD),
+                }],
+            )
+            .with_axis(
+                "move_y",
+                [AxisBinding::Buttons {
+                    negative: Button::Key(KeyCode::KeyW),
+                    positive: Button::Key(KeyCode::KeyS),
+                }],
+            )
+            .with_action("toggle_inspector", [Button::Key(KeyCode::KeyI)]),
+    );
+
+    // A secondary window showing where the player is.
+    let mut inspector = None::<Window>;
 
     loop {
         futures::select! {
             (times_ticked, _alpha) = timer.next().fuse() => {
                 for _ in 0..times_ticked.get() {
-                    let mut heading = Vec2::ZERO;
+                    let heading = input.vector("move_x", "move_y");
 
-                    if keys_down.contains(&KeyCode::KeyA) {
-                        heading += Vec2::NEG_X;
-                    }
+                    pos += heading * timer.interval() as f32 * 500.;
+                }
 
-                    if keys_down.contains(&KeyCode::KeyD) {
-                        heading += Vec2::X;
-                    }
+                if input.was_pressed("toggle_inspector") {
+                    inspector = match inspector {
+                        Some(_) => None,
+                        None => Some(Window::open("Inspector", UVec2::new(320, 240))),
+                    };
+                }
 
-                    if keys_down.contains(&KeyCode::KeyW) {
-                        heading += Vec2::NEG_Y;
-                    }
+                input.end_frame();
 
-                    if keys_down.contains(&KeyCode::KeyS) {
-                        heading += Vec2::Y;
-                    }
+                window.request_redraw();
 
-                    pos += heading.normalize_or_zero() * timer.interval() as f32 * 500.;
+                if let Some(inspector) = &mut inspector {
+                    inspector.request_redraw();
                 }
-
-                window.request_redraw();
             }
             ev = window.next_event().fuse() => {
                 match ev {
@@ -91,23 +109,46 @@ async fn main_loop() {
                                 .translate(pos),
                         );
                     }
-                    WindowEvent::KeyEvent(ev) => {
-                        let PhysicalKey::KeyCode(key) = ev.physical_key else {
-                            continue;
-                        };
-
-                        if ev.pressed {
-                            keys_down.insert(key);
-                        }    else {
-                            keys_down.remove(&key);
-                        }
-                    }
                     WindowEvent::ExitRequested => {
+                        tracing::info!("Exiting at {pos}");
                         break;
                     }
-                    _ => {}
+                    ev => {
+                        if let WindowEvent::MouseMotion(delta) = ev {
+                            tracing::info!("Main window received mouse motion {delta}");
+                        }
+
+                        input.handle_window_event(&ev);
+                    }
+                }
+            }
+            ev = next_event(&mut inspector).fuse() => {
+                match ev {
+                    WindowEvent::Redraw(mut fb) => {
+                        fb.clear(Bgra8::BLACK);
+
+                        fb.draw(
+                            GpuDrawArgs::new()
+                                .textured(&my_texture)
+                                .scale(Vec2::splat(10.))
+                                .translate(pos / 5.),
+                        );
+                    }
+                    WindowEvent::ExitRequested => inspector = None,
+                    WindowEvent::MouseMotion(delta) => {
+                        tracing::info!("Inspector received mouse motion {delta}");
+                    }
+                    ev => tracing::info!("Inspector received {ev:?}"),
                 }
             }
         }
     }
 }
+
+/// The next event of `window`, never resolving if there's no window.
+async fn next_event(window: &mut Option<Window>) -> WindowEvent {
+    match window {
+        Some(window) => window.next_event().await,
+        None => futures::future::pending().await,
+    }
+}
@@ -1,26 +1,92 @@
-use std::{env, fs, sync::Arc};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::Context;
 use arid::{Strong, World};
 use arid_entity::EntityHandle;
 use crucible_host_shared::lang;
-use wasmlink_wasmtime::{WslLinker, WslStore, WslStoreExt, WslStoreState};
+use derive_where::derive_where;
+use wasmlink::Tracer;
 use winit::{
-    event::{KeyEvent, MouseButton, StartCause, WindowEvent},
+    dpi::PhysicalSize,
+    event::{DeviceEvent, DeviceId, StartCause, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
-    keyboard,
-    window::{WindowAttributes, WindowId},
+    window::{Window, WindowAttributes, WindowId},
 };
 
 use crate::{
-    bindings::{env::EnvBindingsHandle, gfx::GfxBindingsHandle, network::NetworkBindingsHandle},
-    services::window::{WindowManagerHandle, WindowStateHandle, create_gfx_context},
+    bindings::{
+        audio::AudioBindingsHandle,
+        clipboard::ClipboardBindingsHandle,
+        env::EnvBindingsHandle,
+        gamepad::GamepadBindingsHandle,
+        gfx::{GfxBindingsHandle, WindowCallbacks, WindowOpenRequest},
+        mods::{GAME_PEER, LoadedMod, ModBindingsHandle},
+        network::{self, NetworkBindingsHandle},
+        reload::ReloadBindingsHandle,
+        storage::StorageBindingsHandle,
+        window::WindowBindingsHandle,
+    },
+    headless::main_headless,
+    replay::main_replay,
+    services::{
+        audio::Audio,
+        clipboard::{ClipboardBackend, system_clipboard},
+        gamepad::Gamepads,
+        permissions::{PermissionsHandle, resolve_permissions},
+        session::{
+            GamepadInput, InputSessionHandle, Recorder, SessionInput, WindowInput,
+            recording_trace_path,
+        },
+        watch::ModuleWatcher,
+        window::{GfxContext, WindowManagerHandle, WindowStateHandle, create_gfx_context},
+    },
     utils::winit::{WinitHandler, run_winit},
+    wsl::{
+        WslEngine, WslInstance, WslLinker, WslLinkerExt, WslModule, WslStore, WslStoreExt,
+        WslStoreState, check_wsl_abi,
+    },
 };
 
-pub type BackgroundTasks = lang::BackgroundTasks<ActiveEventLoop, App>;
+/// The tasks running alongside the app. They aren't given the event loop so that headless sessions,
+/// which have none, can run them too.
+pub type BackgroundTasks = lang::BackgroundTasks<(), App>;
+
+const USAGE: &str = "usage: crucible-host-client \
+                     [--watch | --record <file> | --replay <file> | --headless <script> \
+                     | (--mod <module>)...] <module>";
 
 pub fn main_inner() -> anyhow::Result<()> {
+    let args = env::args().collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    // Mods can't be hot reloaded and are left out of recordings so they are only supported by
+    // plain sessions.
+    let (module_path, recording_path, watch, mod_paths) = match args.as_slice() {
+        [_, module_path] => (*module_path, None, false, Vec::new()),
+        [_, "--watch", module_path] => (*module_path, None, true, Vec::new()),
+        [_, "--record", recording_path, module_path] => {
+            (*module_path, Some(*recording_path), false, Vec::new())
+        }
+        [_, "--replay", recording_path, module_path] => {
+            return main_replay(Path::new(module_path), Path::new(recording_path));
+        }
+        [_, "--headless", script_path, module_path] => {
+            return main_headless(Path::new(module_path), Path::new(script_path));
+        }
+        [_, mod_args @ .., module_path]
+            if mod_args.len().is_multiple_of(2) && mod_args.chunks(2).all(|v| v[0] == "--mod") =>
+        {
+            let mod_paths = mod_args.chunks(2).map(|v| v[1]).collect::<Vec<_>>();
+
+            (*module_path, None, false, mod_paths)
+        }
+        _ => anyhow::bail!("{USAGE}"),
+    };
+
     // Creating windowing services
     tracing::info!("Setting up windowing and graphics contexts.");
 
@@ -32,16 +98,67 @@ pub fn main_inner() -> anyhow::Result<()> {
 
     // Setup WASM runtime
     tracing::info!("Setting up WASM runtime.");
-    let engine = wasmtime::Engine::new(&wasmtime::Config::default())?;
+    let engine = WslEngine::default();
 
     // Load module
     tracing::info!("Loading module.");
 
-    let module_path = env::args().nth(1).context("no module supplied")?;
-    let module = fs::read(&module_path)
+    let module = fs::read(module_path)
         .with_context(|| format!("failed to read module at `{module_path}`"))?;
 
-    let module = wasmtime::Module::new(&engine, module)?;
+    let (session, tracer) = match recording_path {
+        Some(recording_path) => {
+            let recording_path = Path::new(recording_path);
+
+            tracing::info!("Recording session to `{}`.", recording_path.display());
+
+            let recorder = Recorder::create(recording_path, blake3::hash(&module))?;
+
+            (
+                InputSessionHandle::recording(root.as_weak(), recorder, &mut world),
+                Some(create_tracer(&recording_trace_path(recording_path))?),
+            )
+        }
+        None => (
+            InputSessionHandle::live(root.as_weak(), &mut world),
+            env_tracer()?,
+        ),
+    };
+
+    // Ask for the capabilities the game declares. Mods only get the environment and extension
+    // point ports so they have nothing to ask for.
+    let permissions = resolve_permissions(&module, Path::new(module_path))?;
+    let permissions = PermissionsHandle::new(root.as_weak(), permissions, &mut world);
+
+    let watcher = if watch {
+        tracing::info!("Watching `{module_path}` for changes.");
+        Some(ModuleWatcher::new(module_path)?)
+    } else {
+        None
+    };
+
+    let module = load_module(&engine, &module)?;
+
+    // Load mods
+    let mut mods = Vec::new();
+
+    for mod_path in mod_paths {
+        let path = Path::new(mod_path);
+        let module =
+            fs::read(path).with_context(|| format!("failed to read mod at `{mod_path}`"))?;
+
+        let info = LoadedMod {
+            name: path.file_stem().map_or_else(
+                || mod_path.to_string(),
+                |v| v.to_string_lossy().into_owned(),
+            ),
+            content_hash: blake3::hash(&module),
+        };
+
+        tracing::info!("Loading mod `{}` from `{mod_path}`.", info.name);
+
+        mods.push((info, load_module(&engine, &module)?));
+    }
 
     // Start main loop
     tracing::info!("Starting main loop!");
@@ -53,6 +170,14 @@ pub fn main_inner() -> anyhow::Result<()> {
             root,
             engine,
             module,
+            module_path: PathBuf::from(module_path),
+            session,
+            permissions,
+            tracer,
+            watcher,
+            gamepads: Gamepads::system(),
+            mods,
+            virtual_clock: false,
             init: None,
         },
     )?;
@@ -64,97 +189,734 @@ pub fn main_inner() -> anyhow::Result<()> {
 pub struct App {
     pub world: World,
     pub root: Strong<EntityHandle>,
-    pub engine: wasmtime::Engine,
-    pub module: wasmtime::Module,
+    pub engine: WslEngine,
+    pub module: WslModule,
+    /// The path from which the game's module was loaded.
+    pub module_path: PathBuf,
+    pub session: Strong<InputSessionHandle>,
+    pub permissions: Strong<PermissionsHandle>,
+    /// Records the host calls of the game's instance. It is handed over to the instance when the
+    /// app starts.
+    pub tracer: Option<Tracer>,
+    pub watcher: Option<ModuleWatcher>,
+    pub gamepads: Gamepads,
+    pub mods: Vec<(LoadedMod, WslModule)>,
+    /// Whether the game sees a virtual clock which only advances when told to rather than the real
+    /// time.
+    pub virtual_clock: bool,
     pub init: Option<AppInitState>,
 }
 
-#[derive(Debug)]
+#[derive_where(Debug)]
 pub struct AppInitState {
     pub window_mgr: WindowManagerHandle,
     pub env_bindings: Strong<EnvBindingsHandle>,
     pub gfx_bindings: Strong<GfxBindingsHandle>,
-    pub _net_bindings: Strong<NetworkBindingsHandle>,
-    pub main_window: WindowStateHandle,
+    pub gamepad_bindings: Strong<GamepadBindingsHandle>,
+    pub _window_bindings: Strong<WindowBindingsHandle>,
+    /// The live network bindings. Replays answer network calls from the recording instead.
+    pub _net_bindings: Option<Strong<NetworkBindingsHandle>>,
+    pub _storage_bindings: Strong<StorageBindingsHandle>,
+    pub _clipboard_bindings: Strong<ClipboardBindingsHandle>,
+    pub audio_bindings: Strong<AudioBindingsHandle>,
+    pub reload_bindings: Strong<ReloadBindingsHandle>,
+    pub mod_bindings: Strong<ModBindingsHandle>,
+    #[derive_where(skip)]
+    pub linker: WslLinker,
     pub store: WslStore,
-    pub _instance: wasmtime::Instance,
+    pub _instance: WslInstance,
+    /// Incremented every time the module is hot reloaded.
+    pub generation: u64,
+    /// The instances of the loaded mods, indexed by their peer ID minus one.
+    pub mods: Vec<ModInstance>,
 }
 
-impl WinitHandler for App {
-    fn resumed(
+/// A mod running in its own store. Mods only have access to the environment and extension point
+/// ports.
+#[derive(Debug)]
+pub struct ModInstance {
+    pub env_bindings: Strong<EnvBindingsHandle>,
+    pub store: WslStore,
+    pub _instance: WslInstance,
+}
+
+impl AppInitState {
+    /// Drops the state the bindings hold on behalf of the game's instance.
+    fn reset_instance(&self, w: &mut World) {
+        self.env_bindings.reset_instance(w);
+        self.gfx_bindings.reset_instance(w);
+        self.gamepad_bindings.reset_instance(w);
+        self.audio_bindings.reset_instance(w);
+    }
+
+    /// Delivers the extension point messages queued so far. Messages queued while delivering them
+    /// are left for the next iteration of the event loop.
+    fn deliver_mod_messages(&mut self, w: &mut World) -> anyhow::Result<()> {
+        for _ in 0..self.mod_bindings.pending_count(w) {
+            let Some(message) = self.mod_bindings.take_message(w) else {
+                break;
+            };
+
+            let store = match message.to {
+                GAME_PEER => &mut self.store,
+                peer => &mut self.mods[peer as usize - 1].store,
+            };
+
+            store.run_wsl_root(w, |cx| message.deliver(cx))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl App {
+    /// Instantiates the game and its mods. The main window is shown in `main_window` if there is
+    /// one; headless sessions leave it out.
+    pub fn start(
         &mut self,
-        event_loop: &ActiveEventLoop,
+        gfx: GfxContext,
+        main_window: Option<(Arc<Window>, wgpu::Surface<'static>)>,
+        audio: Audio,
+        clipboard: Box<dyn ClipboardBackend>,
         background: &BackgroundTasks,
     ) -> anyhow::Result<()> {
         let w = &mut self.world;
 
-        if self.init.is_some() {
-            return Ok(());
+        let root = self.root.as_weak();
+
+        let window_mgr = WindowManagerHandle::new(root, gfx, w);
+
+        // Setup WASM linker
+        let mut linker = WslLinker::new(&self.engine);
+
+        let session = self.session.as_weak();
+
+        let env_bindings = EnvBindingsHandle::new(root, session, w);
+        env_bindings.install(&mut linker)?;
+
+        if self.virtual_clock {
+            env_bindings.use_virtual_clock(w);
         }
 
-        smol::block_on(async {
-            // Setup graphics
-            let window = Arc::new(
-                event_loop.create_window(
-                    WindowAttributes::default()
-                        .with_title("Crucible")
-                        .with_visible(false),
-                )?,
-            );
+        let gfx_bindings = GfxBindingsHandle::new(root, window_mgr.as_weak(), w);
 
-            let root = self.root.as_weak();
+        if let Some((window, surface)) = main_window {
+            let main_window = window_mgr.create_window(window, surface, w);
+            gfx_bindings.set_main_window(main_window, w);
+        }
 
-            let (gfx, surface) = create_gfx_context(window.clone()).await?;
-            let window_mgr = WindowManagerHandle::new(root, gfx, w);
+        gfx_bindings.install(&mut linker)?;
 
-            let main_window = window_mgr.create_window(window.clone(), surface, w);
+        let window_bindings = WindowBindingsHandle::new(
+            root,
+            session,
+            self.permissions.as_weak(),
+            gfx_bindings.as_weak(),
+            w,
+        );
+        window_bindings.install(&mut linker)?;
 
-            // Setup WASM linker
-            let mut linker = WslLinker::new(&self.engine);
+        let gamepad_bindings = GamepadBindingsHandle::new(root, w);
+        gamepad_bindings.install(&mut linker)?;
 
-            let env_bindings = EnvBindingsHandle::new(root, w);
-            env_bindings.install(&mut linker)?;
+        let mod_bindings = ModBindingsHandle::new(
+            root,
+            self.mods.iter().map(|(info, _)| info.clone()).collect(),
+            w,
+        );
+        mod_bindings.install(GAME_PEER, &mut linker)?;
 
-            let gfx_bindings = GfxBindingsHandle::new(root, window_mgr.as_weak(), w);
-            gfx_bindings.install(&mut linker)?;
+        let net_bindings = if session.is_replaying(w) {
+            network::install_replay(session, &mut linker)?;
 
-            let net_bindings = NetworkBindingsHandle::new(root, background.clone(), w)?;
+            None
+        } else {
+            let net_bindings = NetworkBindingsHandle::new(
+                root,
+                background.clone(),
+                session,
+                self.permissions.as_weak(),
+                mod_bindings.as_weak(),
+                w,
+            )?;
             net_bindings.install(&mut linker)?;
 
-            linker.define_unknown_imports_as_traps(&self.module)?;
+            Some(net_bindings)
+        };
+
+        let storage_bindings =
+            StorageBindingsHandle::new(root, session, self.permissions.as_weak(), w);
+        storage_bindings.install(&mut linker)?;
+
+        let clipboard_bindings =
+            ClipboardBindingsHandle::new(root, session, self.permissions.as_weak(), clipboard, w);
+        clipboard_bindings.install(&mut linker)?;
+
+        let audio_bindings = AudioBindingsHandle::new(root, audio, w);
+        audio_bindings.install(&mut linker)?;
+
+        let reload_bindings = ReloadBindingsHandle::new(root, w);
+        reload_bindings.install(&mut linker)?;
+
+        // Instantiate module
+        let mut store = create_store(&self.engine, background, 0);
+
+        if let Some(tracer) = self.tracer.take() {
+            store.set_wsl_tracer(tracer);
+        }
+
+        env_bindings.set_supported_ports(linker.wsl_definitions(&mut store), w);
+
+        let instance = start_instance(
+            &mut store,
+            &mut linker,
+            &self.module,
+            gfx_bindings.as_weak(),
+            w,
+        )?;
+
+        // Instantiate mods
+        let mut mods = Vec::new();
+
+        for (index, (info, module)) in self.mods.iter().enumerate() {
+            let instance = start_mod(
+                &self.engine,
+                root,
+                session,
+                mod_bindings.as_weak(),
+                index as u32 + 1,
+                module,
+                w,
+            )
+            .with_context(|| format!("failed to start mod `{}`", info.name))?;
+
+            mods.push(instance);
+        }
+
+        self.init = Some(AppInitState {
+            window_mgr: window_mgr.as_weak(),
+            env_bindings,
+            gfx_bindings,
+            gamepad_bindings,
+            _window_bindings: window_bindings,
+            _net_bindings: net_bindings,
+            _storage_bindings: storage_bindings,
+            _clipboard_bindings: clipboard_bindings,
+            audio_bindings,
+            reload_bindings,
+            mod_bindings,
+            linker,
+            store,
+            _instance: instance,
+            generation: 0,
+            mods,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the running instance with one of the rebuilt `module`, handing over the state the
+    /// old instance chooses to save.
+    ///
+    /// Failures of the new module are logged rather than returned. The old instance keeps running
+    /// if the new module can't be instantiated. Once the old instance has been torn down, a failure
+    /// to start the new one restarts the old module with the state it saved instead.
+    pub fn reload(&mut self, module: &[u8], background: &BackgroundTasks) -> anyhow::Result<()> {
+        let w = &mut self.world;
+
+        let Some(init) = &mut self.init else {
+            return Ok(());
+        };
+
+        tracing::info!("Module changed; reloading.");
+
+        let new_module = match load_module(&self.engine, module) {
+            Ok(module) => module,
+            Err(err) => {
+                tracing::error!("Failed to load the changed module, keeping the old one: {err:#}");
+                return Ok(());
+            }
+        };
+
+        let granted = match resolve_permissions(module, &self.module_path) {
+            Ok(granted) => granted,
+            Err(err) => {
+                tracing::error!(
+                    "Failed to resolve the changed module's permissions, keeping the old one: \
+                     {err:#}"
+                );
+                return Ok(());
+            }
+        };
+
+        let mut store = create_store(&self.engine, background, init.generation + 1);
+
+        let instance = match instantiate(&mut store, &mut init.linker, &new_module) {
+            Ok(instance) => instance,
+            Err(err) => {
+                tracing::error!(
+                    "Failed to instantiate the changed module, keeping the old one: {err:#}"
+                );
+                return Ok(());
+            }
+        };
+
+        // Tear down the old instance
+        if let Err(err) = init
+            .store
+            .run_wsl_root(w, |cx| init.reload_bindings.save_state(cx))
+        {
+            tracing::error!("Failed to save state across the reload: {err:#}");
+        }
+
+        let saved_state = init.reload_bindings.saved_state(w);
+        let old_granted = self.permissions.replace_granted(granted, w);
+
+        init.reset_instance(w);
+        init.generation += 1;
+
+        if let Some(tracer) = init.store.take_wsl_tracer() {
+            store.set_wsl_tracer(tracer);
+        }
+
+        // Start the new one
+        match run_main(&mut store, instance, init.gfx_bindings.as_weak(), w) {
+            Ok(()) => {
+                init.store = store;
+                init._instance = instance;
+                self.module = new_module;
+
+                tracing::info!("Reloaded module.");
+
+                return Ok(());
+            }
+            Err(err) => {
+                tracing::error!(
+                    "Failed to start the changed module, restarting the old one: {err:#}"
+                );
+            }
+        }
+
+        // Restart the old module with the state it saved
+        init.reset_instance(w);
+        init.generation += 1;
+        init.reload_bindings.set_saved_state(saved_state, w);
+        self.permissions.replace_granted(old_granted, w);
+
+        let mut old_store = create_store(&self.engine, background, init.generation);
+
+        if let Some(tracer) = store.take_wsl_tracer() {
+            old_store.set_wsl_tracer(tracer);
+        }
+
+        init._instance = start_instance(
+            &mut old_store,
+            &mut init.linker,
+            &self.module,
+            init.gfx_bindings.as_weak(),
+            w,
+        )
+        .context("failed to restart the old module")?;
+
+        init.store = old_store;
+
+        Ok(())
+    }
 
-            // Instantiate module
-            let mut store = wasmtime::Store::new(&self.engine, WslStoreState::default());
+    /// Fires the timeouts of the game and its mods which expired by the time they see.
+    pub fn poll_timeouts(&mut self) -> anyhow::Result<()> {
+        let Some(init) = &mut self.init else {
+            return Ok(());
+        };
 
-            let instance = linker.instantiate(&mut store, &self.module)?;
+        let now = init.env_bindings.current_time(&self.world);
 
-            store.setup_wsl_exports(instance)?;
+        self.session
+            .record(SessionInput::PollTimeouts { now }, &mut self.world)?;
 
-            store.run_wsl_root(w, |cx| -> anyhow::Result<()> {
-                instance
-                    .get_typed_func::<(u32, u32), u32>(cx.cx_mut(), "main")?
-                    .call(cx.cx_mut(), (0, 0))?;
+        init.store.run_wsl_root(&mut self.world, |cx| {
+            init.env_bindings.poll_timeouts(cx, now)
+        })?;
 
-                Ok(())
+        for instance in &mut init.mods {
+            let now = instance.env_bindings.current_time(&self.world);
+
+            instance.store.run_wsl_root(&mut self.world, |cx| {
+                instance.env_bindings.poll_timeouts(cx, now)
             })?;
+        }
+
+        Ok(())
+    }
+
+    /// Records `input` and delivers it to the guest window with the given `handle`.
+    pub fn deliver_window_input(
+        &mut self,
+        handle: u32,
+        cbs: WindowCallbacks,
+        input: WindowInput,
+        fb: Option<u32>,
+    ) -> anyhow::Result<()> {
+        let w = &mut self.world;
+
+        let Some(init) = &mut self.init else {
+            return Ok(());
+        };
+
+        self.session.record(
+            SessionInput::Window {
+                window: handle,
+                input: input.clone(),
+            },
+            w,
+        )?;
+
+        init.store
+            .run_wsl_root(w, |cx| cbs.deliver(cx, &input, fb))?;
+
+        Ok(())
+    }
+
+    /// Delivers raw mouse motion to the guest window which has focus, if any. The motion is
+    /// reported by the mouse rather than by a window so no other window may receive it.
+    pub fn deliver_mouse_motion(&mut self, x: f64, y: f64) -> anyhow::Result<()> {
+        let Some(init) = &self.init else {
+            return Ok(());
+        };
+
+        let Some((handle, Some(cbs))) = init.gfx_bindings.focused(&self.world) else {
+            return Ok(());
+        };
+
+        self.deliver_window_input(handle, cbs, WindowInput::MouseMotion { x, y }, None)
+    }
+
+    /// Opens the secondary windows the guest asked for since the last call. `open` shows each in a
+    /// window of its own or returns `None` to have it drawn offscreen. Windows which can't be opened
+    /// simply never receive any events.
+    ///
+    /// Returns the handles of the guest windows which were opened, in the order they were asked
+    /// for.
+    pub fn open_guest_windows(
+        &mut self,
+        mut open: impl FnMut(
+            &WindowOpenRequest,
+            WindowManagerHandle,
+            &mut World,
+        ) -> anyhow::Result<Option<WindowStateHandle>>,
+    ) -> Vec<u32> {
+        let w = &mut self.world;
+
+        let Some(init) = &mut self.init else {
+            return Vec::new();
+        };
 
-            if gfx_bindings.user_callbacks(w).is_none() {
-                anyhow::bail!("`Window` must be `acquire`'d before the first `.await`-point");
+        let mut opened = Vec::new();
+
+        for request in init.gfx_bindings.take_open_requests(w) {
+            let window = match open(&request, init.window_mgr, w) {
+                Ok(window) => window,
+                Err(err) => {
+                    tracing::error!("Failed to open a window for the guest: {err:#}");
+                    continue;
+                }
+            };
+
+            if init.gfx_bindings.attach_window(request.handle, window, w) {
+                opened.push(request.handle);
+            } else if let Some(window) = window {
+                init.window_mgr.close_window(window, w);
             }
+        }
+
+        opened
+    }
+
+    /// Does the work which isn't triggered by any event: hot reloading the module, delivering mod
+    /// messages and polling gamepads.
+    pub fn update(&mut self, background: &BackgroundTasks) -> anyhow::Result<()> {
+        if let Some(watcher) = &mut self.watcher
+            && let Some(module) = watcher.poll()
+        {
+            self.reload(&module, background)?;
+        }
+
+        let w = &mut self.world;
+
+        let Some(init) = &mut self.init else {
+            return Ok(());
+        };
+
+        // Start the mods required by the servers the game asked about
+        for (info, module) in init.mod_bindings.take_downloaded(w) {
+            tracing::info!("Starting mod `{}` required by the server.", info.name);
+
+            let module = load_module(&self.engine, &module)
+                .with_context(|| format!("failed to load mod `{}`", info.name))?;
+
+            let peer = init.mod_bindings.add_mod(info.clone(), w);
+
+            let instance = start_mod(
+                &self.engine,
+                self.root.as_weak(),
+                self.session.as_weak(),
+                init.mod_bindings.as_weak(),
+                peer,
+                &module,
+                w,
+            )
+            .with_context(|| format!("failed to start mod `{}`", info.name))?;
+
+            init.mods.push(instance);
+            self.mods.push((info, module));
+        }
+
+        init.deliver_mod_messages(w)?;
+
+        // Guests are told about the gamepads which are already connected when they bind their
+        // handlers. Events received while no handlers are bound are dropped.
+        let mut gamepad_inputs = Vec::new();
+
+        if init.gamepad_bindings.take_enumeration_request(w) {
+            gamepad_inputs.extend(self.gamepads.connected().map(|(id, name)| {
+                GamepadInput::Connected {
+                    id,
+                    name: name.to_string(),
+                }
+            }));
+        }
+
+        gamepad_inputs.extend(self.gamepads.poll());
+
+        if let Some(cbs) = init.gamepad_bindings.user_callbacks(w) {
+            for input in gamepad_inputs {
+                self.session
+                    .record(SessionInput::Gamepad(input.clone()), w)?;
+
+                init.store.run_wsl_root(w, |cx| cbs.deliver(cx, &input))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ends the session, reporting the closures the guest leaked.
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        self.session.finish(&mut self.world)?;
+
+        let Some(init) = &mut self.init else {
+            return Ok(());
+        };
+
+        if let Some(report) = init.store.wsl_closure_report(&mut self.world)?
+            && report.live > 0
+        {
+            tracing::warn!("guest exited with {} closures alive", report.live);
+
+            for site in &report.sites {
+                tracing::warn!("- {} created at {site}", site.live);
+            }
+        }
+
+        if let Some(tracer) = init.store.take_wsl_tracer() {
+            tracer.finish()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates a tracer recording host calls to the file at `path`.
+pub fn create_tracer(path: &Path) -> anyhow::Result<Tracer> {
+    tracing::info!("Recording host calls to `{}`.", path.display());
+
+    Tracer::create(path)
+}
+
+/// Creates a tracer recording host calls to the file named by the `CRUCIBLE_TRACE` environment
+/// variable, if it is set.
+pub fn env_tracer() -> anyhow::Result<Option<Tracer>> {
+    env::var_os("CRUCIBLE_TRACE")
+        .map(|path| create_tracer(Path::new(&path)))
+        .transpose()
+}
+
+/// Creates a store whose async port calls are driven by the event loop. Calls made by the
+/// instances of previous `generation`s are never completed.
+fn create_store(engine: &WslEngine, background: &BackgroundTasks, generation: u64) -> WslStore {
+    let mut store = WslStore::new(engine, WslStoreState::default());
+
+    store.set_wsl_spawner({
+        let background = background.clone();
+
+        move |task| {
+            background
+                .spawn_responder(task, move |_event_loop, app, completion| {
+                    let Some(completion) = completion else {
+                        // The guest cancelled the call.
+                        return Ok(());
+                    };
+
+                    let init = app.init.as_mut().unwrap();
+
+                    if init.generation != generation {
+                        // The instance which made the call was hot reloaded.
+                        return Ok(());
+                    }
+
+                    init.store.complete_wsl_task(&mut app.world, completion)
+                })
+                .detach();
+        }
+    });
+
+    store
+}
+
+/// Compiles the guest's `binary` after checking that the ABI it was built against is compatible
+/// with the host's.
+pub fn load_module(engine: &WslEngine, binary: &[u8]) -> anyhow::Result<WslModule> {
+    let module = WslModule::new(engine, binary)?;
+
+    for missing in check_wsl_abi(binary, &module, &crucible_abi::describe_abi())? {
+        tracing::warn!("{missing}; calling it will trap");
+    }
+
+    Ok(module)
+}
+
+/// Instantiates the game's `module` and runs its `main` function.
+fn start_instance(
+    store: &mut WslStore,
+    linker: &mut WslLinker,
+    module: &WslModule,
+    gfx_bindings: GfxBindingsHandle,
+    w: &mut World,
+) -> anyhow::Result<WslInstance> {
+    let instance = instantiate(store, linker, module)?;
+
+    run_main(store, instance, gfx_bindings, w)?;
+
+    Ok(instance)
+}
+
+/// Runs the `main` function of the game's `instance`, which must acquire its window.
+fn run_main(
+    store: &mut WslStore,
+    instance: WslInstance,
+    gfx_bindings: GfxBindingsHandle,
+    w: &mut World,
+) -> anyhow::Result<()> {
+    call_main(store, instance, w)?;
+
+    if gfx_bindings.main_callbacks(w).is_none() {
+        anyhow::bail!("`Window` must be `acquire`'d before the first `.await`-point");
+    }
+
+    Ok(())
+}
+
+/// Instantiates the `module` of the mod with the given `peer` ID in its own store and runs its
+/// `main` function.
+fn start_mod(
+    engine: &WslEngine,
+    root: EntityHandle,
+    session: InputSessionHandle,
+    mod_bindings: ModBindingsHandle,
+    peer: u32,
+    module: &WslModule,
+    w: &mut World,
+) -> anyhow::Result<ModInstance> {
+    let mut linker = WslLinker::new(engine);
+
+    let env_bindings = EnvBindingsHandle::new(root, session, w);
+    env_bindings.install(&mut linker)?;
+
+    mod_bindings.install(peer, &mut linker)?;
+
+    let mut store = WslStore::new(engine, WslStoreState::default());
+
+    env_bindings.set_supported_ports(linker.wsl_definitions(&mut store), w);
+
+    let instance = run_instance(&mut store, &mut linker, module, w)?;
+
+    Ok(ModInstance {
+        env_bindings,
+        store,
+        _instance: instance,
+    })
+}
+
+/// Instantiates `module` and runs its `main` function.
+fn run_instance(
+    store: &mut WslStore,
+    linker: &mut WslLinker,
+    module: &WslModule,
+    w: &mut World,
+) -> anyhow::Result<WslInstance> {
+    let instance = instantiate(store, linker, module)?;
+
+    call_main(store, instance, w)?;
+
+    Ok(instance)
+}
+
+/// Instantiates `module` without running any of its code.
+fn instantiate(
+    store: &mut WslStore,
+    linker: &mut WslLinker,
+    module: &WslModule,
+) -> anyhow::Result<WslInstance> {
+    let instance = linker.instantiate_wsl(store, module)?;
+
+    store.setup_wsl_exports(instance)?;
+
+    Ok(instance)
+}
+
+fn call_main(store: &mut WslStore, instance: WslInstance, w: &mut World) -> anyhow::Result<()> {
+    store.run_wsl_root(w, |cx| -> anyhow::Result<()> {
+        instance
+            .get_typed_func::<(u32, u32), u32>(cx.cx_mut(), "main")?
+            .call(cx.cx_mut(), (0, 0))?;
+
+        Ok(())
+    })
+}
+
+impl WinitHandler for App {
+    fn resumed(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        background: &BackgroundTasks,
+    ) -> anyhow::Result<()> {
+        if self.init.is_some() {
+            return Ok(());
+        }
+
+        smol::block_on(async {
+            // Setup graphics
+            let window = Arc::new(
+                event_loop.create_window(
+                    WindowAttributes::default()
+                        .with_title("Crucible")
+                        .with_visible(false),
+                )?,
+            );
+
+            let (gfx, surface) = create_gfx_context(window.clone()).await?;
+
+            self.start(
+                gfx,
+                Some((window.clone(), surface)),
+                Audio::system(),
+                system_clipboard(),
+                background,
+            )?;
 
             // Mark as initialized
             window.set_visible(true);
 
-            self.init = Some(AppInitState {
-                window_mgr: window_mgr.as_weak(),
-                env_bindings,
-                gfx_bindings,
-                _net_bindings: net_bindings,
-                main_window,
-                store,
-                _instance: instance,
-            });
-
             Ok(())
         })
     }
@@ -165,13 +927,8 @@ impl WinitHandler for App {
         _background: &BackgroundTasks,
         cause: StartCause,
     ) -> anyhow::Result<()> {
-        let Some(init) = &mut self.init else {
-            return Ok(());
-        };
-
         if matches!(cause, StartCause::ResumeTimeReached { .. }) {
-            init.store
-                .run_wsl_root(&mut self.world, |cx| init.env_bindings.poll_timeouts(cx))?;
+            self.poll_timeouts()?;
         }
 
         Ok(())
@@ -190,172 +947,115 @@ impl WinitHandler for App {
             return Ok(());
         };
 
-        match &event {
-            WindowEvent::RedrawRequested => {
-                let Some(cbs) = init.gfx_bindings.user_callbacks(w) else {
-                    return Ok(());
-                };
-
-                let window = init.window_mgr.lookup(window_id, w);
-
-                let Some(texture) = window.start_redraw(w)? else {
-                    return Ok(());
-                };
-
-                let handle = init
-                    .gfx_bindings
-                    .create_texture(texture.clone(), Some(window), w)?;
-
-                init.store.run_wsl_root(w, |cx| {
-                    cbs.redraw_requested.call(
-                        cx,
-                        &crucible_abi::RedrawRequestedArgs {
-                            fb: crucible_abi::GpuTextureHandle { raw: handle },
-                            size: crucible_abi::UVec2 {
-                                x: texture.width(),
-                                y: texture.height(),
-                            },
-                        },
-                    )
-                })?;
-            }
-            WindowEvent::CursorMoved { position, .. } => {
-                let Some(cbs) = init.gfx_bindings.user_callbacks(w) else {
-                    return Ok(());
-                };
-
-                init.store.run_wsl_root(w, |cx| {
-                    cbs.mouse_moved.call(
-                        cx,
-                        &crucible_abi::DVec2 {
-                            x: position.x,
-                            y: position.y,
-                        },
-                    )
-                })?;
-            }
-            WindowEvent::MouseInput { state, button, .. } => {
-                let Some(cbs) = init.gfx_bindings.user_callbacks(w) else {
-                    return Ok(());
-                };
-
-                init.store.run_wsl_root(w, |cx| {
-                    cbs.mouse_event.call(
-                        cx,
-                        &crucible_abi::MouseEvent {
-                            button: match button {
-                                MouseButton::Left => crucible_abi::MouseButton::Left(()),
-                                MouseButton::Right => crucible_abi::MouseButton::Right(()),
-                                MouseButton::Middle => crucible_abi::MouseButton::Middle(()),
-                                MouseButton::Back => crucible_abi::MouseButton::Back(()),
-                                MouseButton::Forward => crucible_abi::MouseButton::Forward(()),
-                                MouseButton::Other(id) => crucible_abi::MouseButton::Other(*id),
-                            },
-                            pressed: state.is_pressed(),
-                        },
-                    )
-                })?;
-            }
-            WindowEvent::KeyboardInput {
-                event:
-                    KeyEvent {
-                        physical_key,
-                        logical_key,
-                        text,
-                        location,
-                        state,
-                        repeat,
-                        ..
-                    },
-                ..
-            } => {
-                let Some(cbs) = init.gfx_bindings.user_callbacks(w) else {
-                    return Ok(());
-                };
-
-                init.store.run_wsl_root(w, |cx| {
-                    cbs.key_event.call(
-                        cx,
-                        &crucible_abi::KeyEvent {
-                            physical_key: match physical_key {
-                                keyboard::PhysicalKey::Code(code) => Some(*code as u32),
-                                keyboard::PhysicalKey::Unidentified(_) => None,
-                            },
-                            logical_key: match logical_key {
-                                keyboard::Key::Named(named_key) => {
-                                    crucible_abi::LogicalKey::Named(*named_key as u32)
-                                }
-                                keyboard::Key::Character(ch) => {
-                                    crucible_abi::LogicalKey::Character(ch.as_str())
-                                }
-                                keyboard::Key::Unidentified(code) => {
-                                    crucible_abi::LogicalKey::Unidentified(match code {
-                                        keyboard::NativeKey::Unidentified => {
-                                            crucible_abi::NativeKey::Unidentified(())
-                                        }
-                                        keyboard::NativeKey::Android(v) => {
-                                            crucible_abi::NativeKey::Android(*v)
-                                        }
-                                        keyboard::NativeKey::MacOS(v) => {
-                                            crucible_abi::NativeKey::MacOS(*v)
-                                        }
-                                        keyboard::NativeKey::Windows(v) => {
-                                            crucible_abi::NativeKey::Windows(*v)
-                                        }
-                                        keyboard::NativeKey::Xkb(v) => {
-                                            crucible_abi::NativeKey::Xkb(*v)
-                                        }
-                                        keyboard::NativeKey::Web(v) => {
-                                            crucible_abi::NativeKey::Web(v.as_str())
-                                        }
-                                    })
-                                }
-                                keyboard::Key::Dead(ch) => crucible_abi::LogicalKey::Dead(*ch),
-                            },
-                            text: text.as_deref(),
-                            location: *location as u32,
-                            pressed: state.is_pressed(),
-                            repeat: *repeat,
-                        },
-                    )
-                })?;
-            }
-            WindowEvent::CloseRequested => {
-                let Some(cbs) = init.gfx_bindings.user_callbacks(w) else {
-                    return Ok(());
-                };
+        let Some((handle, cbs)) = init.gfx_bindings.lookup(window_id, w) else {
+            return Ok(());
+        };
 
-                init.store
-                    .run_wsl_root(w, |cx| cbs.exit_requested.call(cx, &()))?;
-            }
-            _ => {}
+        if let WindowEvent::Focused(focused) = event {
+            init.gfx_bindings.set_focused(handle, focused, w);
         }
 
-        Ok(())
+        let Some(cbs) = cbs else {
+            return Ok(());
+        };
+
+        let (input, fb) = if let WindowEvent::RedrawRequested = event {
+            let window = init.window_mgr.lookup(window_id, w);
+
+            let Some(texture) = window.start_redraw(w)? else {
+                return Ok(());
+            };
+
+            let fb = init
+                .gfx_bindings
+                .create_texture(texture.clone(), Some(window), w)?;
+
+            let input = WindowInput::Redraw {
+                width: texture.width(),
+                height: texture.height(),
+            };
+
+            (input, Some(fb))
+        } else {
+            let Some(input) = WindowInput::from_winit(&event) else {
+                return Ok(());
+            };
+
+            (input, None)
+        };
+
+        self.deliver_window_input(handle, cbs, input, fb)
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _background: &BackgroundTasks,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    ) -> anyhow::Result<()> {
+        let DeviceEvent::MouseMotion { delta: (x, y) } = event else {
+            return Ok(());
+        };
+
+        self.deliver_mouse_motion(x, y)
     }
 
     fn about_to_wait(
         &mut self,
         event_loop: &ActiveEventLoop,
-        _background: &BackgroundTasks,
+        background: &BackgroundTasks,
     ) -> anyhow::Result<()> {
+        self.update(background)?;
+
+        self.open_guest_windows(|request, window_mgr, w| {
+            let window = window_mgr.open_window(
+                event_loop,
+                WindowAttributes::default()
+                    .with_title(&request.title)
+                    .with_inner_size(PhysicalSize::new(request.width, request.height)),
+                w,
+            )?;
+
+            Ok(Some(window))
+        });
+
         let w = &mut self.world;
 
         let Some(init) = &mut self.init else {
             return Ok(());
         };
 
-        if init.gfx_bindings.take_redraw_request(w) && !init.main_window.is_in_live_resize(w) {
-            init.main_window.window(w).request_redraw();
+        for (_, window) in init.gfx_bindings.take_redraw_requests(w) {
+            if let Some(window) = window
+                && !window.is_in_live_resize(w)
+            {
+                window.window(w).request_redraw();
+            }
         }
 
-        if let Some(timeout) = init.env_bindings.earliest_timeout(w) {
-            event_loop.set_control_flow(ControlFlow::WaitUntil(timeout));
+        let wake_at = init
+            .env_bindings
+            .earliest_timeout(w)
+            .into_iter()
+            .chain(
+                init.mods
+                    .iter()
+                    .filter_map(|instance| instance.env_bindings.earliest_timeout(w)),
+            )
+            .chain(self.watcher.as_ref().map(ModuleWatcher::next_poll))
+            .chain(self.gamepads.next_poll())
+            .min();
+
+        if init.mod_bindings.pending_count(w) > 0 {
+            event_loop.set_control_flow(ControlFlow::Poll);
+        } else if let Some(wake_at) = wake_at {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(wake_at));
         } else {
             event_loop.set_control_flow(ControlFlow::Wait);
         }
 
-        if init.gfx_bindings.user_callbacks(w).is_none() {
+        if init.gfx_bindings.main_callbacks(w).is_none() {
             event_loop.exit();
         }
 
@@ -363,4 +1063,12 @@ impl WinitHandler for App {
 
         Ok(())
     }
+
+    fn exiting(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _background: &BackgroundTasks,
+    ) -> anyhow::Result<()> {
+        self.finish()
+    }
 }
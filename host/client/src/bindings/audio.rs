@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use arid::{Handle, Strong, W, Wr};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_abi as abi;
+use crucible_host_shared::guest::arena::GuestArena;
+
+use crate::{
+    bindings::error::GuestError,
+    services::audio::{Audio, MASTER_BUS, SoundBuffer, VoiceParams},
+    wsl::{WslLinker, WslLinkerExt},
+};
+
+/// Lets the game play sounds through an [`Audio`] output. Nothing the game can observe depends on
+/// the output so sessions play audio without recording anything.
+#[derive(Debug)]
+pub struct AudioBindings {
+    audio: Audio,
+    buffers: GuestArena<Arc<SoundBuffer>>,
+    /// The buses created by the guest, whose handles double as their IDs in the mixer.
+    buses: GuestArena<()>,
+    next_voice: u32,
+}
+
+component!(pub AudioBindings);
+
+impl AudioBindingsHandle {
+    pub fn new(owner: EntityHandle, audio: Audio, w: W) -> Strong<Self> {
+        AudioBindings {
+            audio,
+            buffers: GuestArena::default(),
+            buses: GuestArena::default(),
+            next_voice: 0,
+        }
+        .attach(owner, w)
+    }
+
+    pub fn audio(self, w: Wr<'_>) -> &Audio {
+        &self.r(w).audio
+    }
+
+    /// Stops every sound of an instance which is being replaced.
+    pub fn reset_instance(self, w: W) {
+        let me = self.m(w);
+
+        me.audio.mixer().clear();
+        me.buffers = GuestArena::default();
+        me.buses = GuestArena::default();
+    }
+
+    fn bus_id(self, bus: Option<abi::AudioBusHandle>, w: W) -> anyhow::Result<u32> {
+        match bus {
+            Some(bus) => {
+                self.r(w).buses.get(bus.raw)?;
+                Ok(bus.raw)
+            }
+            None => Ok(MASTER_BUS),
+        }
+    }
+
+    fn add_buffer(
+        self,
+        buffer: anyhow::Result<SoundBuffer>,
+        w: W,
+    ) -> anyhow::Result<Result<abi::AudioBufferHandle, GuestError>> {
+        let buffer = match buffer {
+            Ok(buffer) => buffer,
+            Err(err) => return Ok(Err(GuestError::new(&err))),
+        };
+
+        let raw = self.m(w).buffers.add(Arc::new(buffer))?;
+
+        Ok(Ok(abi::AudioBufferHandle { raw }))
+    }
+
+    pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
+        linker.define_wsl(abi::AUDIO_CREATE_BUFFER, move |cx, args, ret| {
+            let (w, mem) = cx.world_and_memory();
+            let samples = args.samples.slice().read(mem)?.to_vec();
+
+            let res = self.add_buffer(
+                SoundBuffer::from_pcm(samples, args.channels, args.sample_rate),
+                w,
+            )?;
+
+            match res {
+                Ok(handle) => ret.finish(cx, &Ok(handle)),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::AUDIO_DECODE_BUFFER, move |cx, file, ret| {
+            let (w, mem) = cx.world_and_memory();
+            let buffer = SoundBuffer::decode(file.slice().read(mem)?);
+
+            match self.add_buffer(buffer, w)? {
+                Ok(handle) => ret.finish(cx, &Ok(handle)),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::AUDIO_DESTROY_BUFFER, move |cx, handle, ret| {
+            self.m(cx.w()).buffers.remove(handle.raw)?;
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::AUDIO_CREATE_BUS, move |cx, parent, ret| {
+            let w = cx.w();
+            let parent = self.bus_id(parent, w)?;
+
+            let me = self.m(w);
+            let raw = me.buses.add(())?;
+            me.audio.mixer().add_bus(raw, parent)?;
+
+            ret.finish(cx, &abi::AudioBusHandle { raw })
+        })?;
+
+        linker.define_wsl(abi::AUDIO_SET_BUS_VOLUME, move |cx, args, ret| {
+            let w = cx.w();
+
+            anyhow::ensure!(args.volume.is_finite(), "bus volume must be finite");
+
+            let me = self.m(w);
+            me.buses.get(args.bus.raw)?;
+            me.audio
+                .mixer()
+                .set_bus_volume(args.bus.raw, args.volume.max(0.))?;
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::AUDIO_DESTROY_BUS, move |cx, bus, ret| {
+            let me = self.m(cx.w());
+
+            me.buses.get(bus.raw)?;
+            me.audio.mixer().remove_bus(bus.raw)?;
+            me.buses.remove(bus.raw)?;
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::AUDIO_PLAY, move |cx, args, ret| {
+            let w = cx.w();
+            let bus = self.bus_id(args.bus, w)?;
+            let params = voice_params(args.params)?;
+
+            let me = self.m(w);
+            let buffer = me.buffers.get(args.buffer.raw)?.clone();
+
+            let raw = me.next_voice;
+            me.next_voice = raw.checked_add(1).context("too many voices")?;
+            me.audio
+                .mixer()
+                .play(raw, buffer, bus, params, args.looping)?;
+
+            ret.finish(cx, &abi::AudioVoiceHandle { raw })
+        })?;
+
+        linker.define_wsl(abi::AUDIO_SET_VOICE_PARAMS, move |cx, args, ret| {
+            let params = voice_params(args.params)?;
+
+            self.r(cx.w())
+                .audio
+                .mixer()
+                .set_voice_params(args.voice.raw, params);
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::AUDIO_STOP_VOICE, move |cx, voice, ret| {
+            self.r(cx.w()).audio.mixer().stop(voice.raw);
+
+            ret.finish(cx, &())
+        })?;
+
+        Ok(())
+    }
+}
+
+fn voice_params(params: abi::AudioVoiceParams) -> anyhow::Result<VoiceParams> {
+    let abi::AudioVoiceParams { volume, pan, pitch } = params;
+
+    anyhow::ensure!(
+        volume.is_finite() && pan.is_finite() && pitch.is_finite(),
+        "voice parameters must be finite"
+    );
+
+    Ok(VoiceParams {
+        volume: volume.max(0.),
+        pan: pan.clamp(-1., 1.),
+        pitch: pitch.max(0.),
+    })
+}
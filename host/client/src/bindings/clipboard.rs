@@ -0,0 +1,155 @@
+use arid::{Handle, Strong, W};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_abi::{self as abi, Capability};
+
+use crate::{
+    bindings::{error::GuestError, network::finish_net_call},
+    services::{
+        clipboard::{ClipboardBackend, ClipboardContents, ClipboardImage},
+        permissions::PermissionsHandle,
+        session::{InputSessionHandle, NetOutcome, SessionInput},
+    },
+    wsl::{WslLinker, WslLinkerExt},
+};
+
+/// Gives the game access to a [`ClipboardBackend`]. Outcomes are recorded so that replays never
+/// touch the clipboard.
+#[derive(Debug)]
+pub struct ClipboardBindings {
+    session: InputSessionHandle,
+    permissions: PermissionsHandle,
+    backend: Box<dyn ClipboardBackend>,
+}
+
+component!(pub ClipboardBindings);
+
+impl ClipboardBindingsHandle {
+    pub fn new(
+        owner: EntityHandle,
+        session: InputSessionHandle,
+        permissions: PermissionsHandle,
+        backend: Box<dyn ClipboardBackend>,
+        w: W,
+    ) -> Strong<Self> {
+        ClipboardBindings {
+            session,
+            permissions,
+            backend,
+        }
+        .attach(owner, w)
+    }
+
+    pub fn read_text(self, w: W) -> Result<Option<String>, GuestError> {
+        self.r(w)
+            .permissions
+            .check(&Capability::Clipboard, w)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| self.m(w).backend.read_text())
+            .map_err(|err| GuestError::new(&err))
+    }
+
+    /// Writes `contents` to the clipboard, taking the outcome from the recording while replaying.
+    pub fn write(
+        self,
+        contents: ClipboardContents,
+        w: W,
+    ) -> anyhow::Result<Result<(), GuestError>> {
+        let session = self.r(w).session;
+
+        session.requested(
+            w,
+            |w| {
+                Ok(self
+                    .m(w)
+                    .backend
+                    .write(contents)
+                    .map_err(|err| GuestError::new(&err)))
+            },
+            SessionInput::Clipboard,
+            |input| match input {
+                SessionInput::Clipboard(res) => Some(res),
+                _ => None,
+            },
+        )
+    }
+
+    pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
+        linker.define_wsl_async(
+            abi::CLIPBOARD_READ_TEXT,
+            move |cx, ()| {
+                let w = cx.w();
+                let session = self.r(w).session;
+                let call = session.next_net_call(w);
+
+                // Clipboard reads share their numbering with network calls so that replays can
+                // resolve them from the same recorded outcomes.
+                let source = if session.is_replaying(w) {
+                    Err(session.replayed_net_outcome(call, w))
+                } else {
+                    Ok(self.read_text(w))
+                };
+
+                Ok(async move {
+                    let outcome = match source {
+                        Ok(text) => Ok(NetOutcome::ClipboardRead(text)),
+                        Err(replayed) => replayed.await,
+                    };
+
+                    (call, outcome)
+                })
+            },
+            move |cx, (call, outcome), ret| {
+                let outcome = outcome?;
+                let w = cx.w();
+
+                self.r(w).session.record(
+                    SessionInput::Net {
+                        call,
+                        outcome: outcome.clone(),
+                    },
+                    w,
+                )?;
+
+                finish_net_call(cx, outcome, ret)
+            },
+        )?;
+
+        linker.define_wsl(abi::CLIPBOARD_WRITE_TEXT, move |cx, text, ret| {
+            let text = text.read(cx)?.to_string();
+
+            match self.write(ClipboardContents::Text(text), cx.w())? {
+                Ok(()) => ret.finish(cx, &Ok(())),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::CLIPBOARD_WRITE_IMAGE, move |cx, image, ret| {
+            let (w, mem) = cx.world_and_memory();
+            let pixels = image.buffer.slice().read(mem)?;
+
+            anyhow::ensure!(
+                pixels.len() as u64 == u64::from(image.size.x) * u64::from(image.size.y),
+                "clipboard image has {} pixels but is {}x{}",
+                pixels.len(),
+                image.size.x,
+                image.size.y,
+            );
+
+            let image = ClipboardImage {
+                width: image.size.x,
+                height: image.size.y,
+                rgba: pixels
+                    .iter()
+                    .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a])
+                    .collect(),
+            };
+
+            match self.write(ClipboardContents::Image(image), w)? {
+                Ok(()) => ret.finish(cx, &Ok(())),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        Ok(())
+    }
+}
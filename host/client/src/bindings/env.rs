@@ -10,13 +10,22 @@ use arid::{Handle, Strong, W, Wr};
 use arid_entity::{Component as _, EntityHandle, component};
 use crucible_abi::{self as abi, RunMode};
 use crucible_host_shared::guest::arena::GuestArena;
-use wasmlink_wasmtime::{WslContext, WslLinker, WslLinkerExt};
+
+use crate::{
+    services::session::{InputSessionHandle, SessionInput},
+    wsl::{WslContext, WslLinker, WslLinkerExt},
+};
 
 #[derive(Debug)]
 pub struct EnvBindings {
     epoch: Instant,
+    /// The current time of a virtual clock, which only advances when told to. Sessions without one
+    /// use the time elapsed since the `epoch`.
+    virtual_now: Option<f64>,
+    session: InputSessionHandle,
     timeout_handles: GuestArena<f64>,
     timeout_queue: BTreeMap<IdentifiedTimeout, wasmlink::HostClosure<()>>,
+    supported_ports: Vec<(String, String)>,
 }
 
 #[derive(Debug)]
@@ -54,11 +63,14 @@ impl PartialOrd for IdentifiedTimeout {
 component!(pub EnvBindings);
 
 impl EnvBindingsHandle {
-    pub fn new(owner: EntityHandle, w: W) -> Strong<Self> {
+    pub fn new(owner: EntityHandle, session: InputSessionHandle, w: W) -> Strong<Self> {
         EnvBindings {
             epoch: Instant::now(),
+            virtual_now: None,
+            session,
             timeout_handles: GuestArena::default(),
             timeout_queue: BTreeMap::default(),
+            supported_ports: Vec::new(),
         }
         .attach(owner, w)
     }
@@ -68,6 +80,24 @@ impl EnvBindingsHandle {
             out.finish(cx, &RunMode::Client)
         })?;
 
+        linker.define_wsl(abi::GET_SUPPORTED_PORTS, move |cx, (), out| {
+            let ports = self.r(cx.wr()).supported_ports.clone();
+            let ports = ports
+                .iter()
+                .map(|(module, import_name)| {
+                    let (func_name, version) = wasmlink::split_import_name(import_name);
+
+                    abi::PortId::<wasmlink::GuestboundViewVariant<'_>> {
+                        module: module.as_str(),
+                        func_name,
+                        version,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            out.finish(cx, &ports.as_slice())
+        })?;
+
         linker.define_wsl(abi::LOG_MESSAGE, |cx, msg, out| {
             tracing::info!(
                 target = "guest",
@@ -82,7 +112,19 @@ impl EnvBindingsHandle {
         })?;
 
         linker.define_wsl(abi::GET_CURRENT_TIME, move |cx, (), out| {
-            out.finish(cx, &self.current_time(cx.wr()))
+            let w = cx.w();
+
+            let now = self.r(w).session.requested(
+                w,
+                |w| Ok(self.current_time(w)),
+                SessionInput::CurrentTime,
+                |input| match input {
+                    SessionInput::CurrentTime(now) => Some(now),
+                    _ => None,
+                },
+            )?;
+
+            out.finish(cx, &now)
         })?;
 
         linker.define_wsl(abi::SPAWN_TIMEOUT, move |cx, req, out| {
@@ -125,21 +167,62 @@ impl EnvBindingsHandle {
         Ok(())
     }
 
+    /// Records the `(module, import_name)` pairs of every port defined by the host so they can be
+    /// reported to the guest.
+    pub fn set_supported_ports(self, ports: impl IntoIterator<Item = (String, String)>, w: W) {
+        self.m(w).supported_ports = ports.into_iter().collect();
+    }
+
+    /// Forgets the timeouts of an instance which is being replaced.
+    pub fn reset_instance(self, w: W) {
+        let me = self.m(w);
+        me.timeout_handles = GuestArena::default();
+        me.timeout_queue.clear();
+    }
+
+    /// Replaces the clock the guest sees with a virtual one starting at zero. It only advances when
+    /// [`advance_clock`](Self::advance_clock) is called.
+    pub fn use_virtual_clock(self, w: W) {
+        self.m(w).virtual_now = Some(0.);
+    }
+
+    /// Advances the virtual clock by `by` seconds.
+    pub fn advance_clock(self, by: f64, w: W) {
+        let now = self
+            .m(w)
+            .virtual_now
+            .as_mut()
+            .expect("the session doesn't use a virtual clock");
+
+        *now += by;
+    }
+
     pub fn current_time(self, w: Wr) -> f64 {
-        self.r(w).epoch.elapsed().as_secs_f64()
+        let me = self.r(w);
+
+        match me.virtual_now {
+            Some(now) => now,
+            None => me.epoch.elapsed().as_secs_f64(),
+        }
     }
 
+    /// The real time at which the earliest timeout expires. Timeouts never expire in real time
+    /// under a virtual clock.
     pub fn earliest_timeout(self, w: Wr) -> Option<Instant> {
         let me = self.r(w);
 
+        if me.virtual_now.is_some() {
+            return None;
+        }
+
         let (IdentifiedTimeout { expires_at, .. }, _) = me.timeout_queue.first_key_value()?;
 
         me.epoch.checked_add(Duration::from_secs_f64(*expires_at))
     }
 
-    pub fn poll_timeouts(self, cx: &mut WslContext<'_>) -> anyhow::Result<()> {
-        let now = self.current_time(cx.w());
-
+    /// Fires every timeout expiring at or before `now`, which is usually the
+    /// [`current_time`](Self::current_time) but is taken from the recording during a replay.
+    pub fn poll_timeouts(self, cx: &mut WslContext<'_>, now: f64) -> anyhow::Result<()> {
         while let Some(first) = self.m(cx.w()).timeout_queue.first_entry() {
             let IdentifiedTimeout { expires_at, handle } = *first.key();
 
@@ -0,0 +1,119 @@
+use std::io;
+
+use crucible_abi as abi;
+use quinn::rustls;
+use serde::{Deserialize, Serialize};
+use wasmlink::GuestboundViewVariant;
+
+use crate::services::{permissions::PermissionDenied, storage::QuotaExceeded};
+
+/// A host error in the form it is reported to guests as an [`abi::AbiError`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuestError {
+    #[serde(with = "error_kind")]
+    pub kind: abi::ErrorKind,
+    pub message: String,
+    pub sources: Vec<String>,
+}
+
+impl GuestError {
+    /// Captures `err`'s message and source chain, inferring its kind from the first cause which
+    /// can be classified.
+    pub fn new(err: &anyhow::Error) -> Self {
+        Self {
+            kind: err
+                .chain()
+                .find_map(classify)
+                .unwrap_or(abi::ErrorKind::Other),
+            message: err.to_string(),
+            sources: err.chain().skip(1).map(|v| v.to_string()).collect(),
+        }
+    }
+
+    pub fn with_view<R>(&self, f: impl FnOnce(abi::AbiError<GuestboundViewVariant<'_>>) -> R) -> R {
+        let sources = self.sources.iter().map(String::as_str).collect::<Vec<_>>();
+
+        f(abi::AbiError {
+            kind: self.kind as u16,
+            message: Some(&self.message),
+            sources: &sources,
+        })
+    }
+}
+
+/// Serializes an [`abi::ErrorKind`] as its ABI discriminant so that recorded errors stay readable
+/// as kinds are appended.
+mod error_kind {
+    use crucible_abi as abi;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(kind: &abi::ErrorKind, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u16(*kind as u16)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<abi::ErrorKind, D::Error> {
+        Ok(abi::ErrorKind::from_raw(u16::deserialize(d)?))
+    }
+}
+
+fn classify(err: &(dyn std::error::Error + 'static)) -> Option<abi::ErrorKind> {
+    if err.is::<PermissionDenied>() {
+        return Some(abi::ErrorKind::PermissionDenied);
+    }
+
+    if err.is::<QuotaExceeded>() {
+        return Some(abi::ErrorKind::QuotaExceeded);
+    }
+
+    if let Some(err) = err.downcast_ref::<quinn::ConnectionError>() {
+        return match err {
+            quinn::ConnectionError::TimedOut => Some(abi::ErrorKind::Timeout),
+            quinn::ConnectionError::ApplicationClosed(_)
+            | quinn::ConnectionError::ConnectionClosed(_)
+            | quinn::ConnectionError::Reset => Some(abi::ErrorKind::ClosedByPeer),
+            quinn::ConnectionError::TransportError(err) if is_cert_alert(err.code) => {
+                Some(abi::ErrorKind::CertMismatch)
+            }
+            _ => None,
+        };
+    }
+
+    if let Some(err) = err.downcast_ref::<rustls::Error>() {
+        return matches!(
+            err,
+            rustls::Error::InvalidCertificate(_) | rustls::Error::NoCertificatesPresented
+        )
+        .then_some(abi::ErrorKind::CertMismatch);
+    }
+
+    if let Some(err) = err.downcast_ref::<io::Error>() {
+        return match err.kind() {
+            io::ErrorKind::TimedOut => Some(abi::ErrorKind::Timeout),
+            io::ErrorKind::ConnectionRefused => Some(abi::ErrorKind::ConnectionRefused),
+            io::ErrorKind::NotFound => Some(abi::ErrorKind::NotFound),
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => Some(abi::ErrorKind::ClosedByPeer),
+            _ => None,
+        };
+    }
+
+    None
+}
+
+/// Certificate verification failures are only reported to us as the TLS alert the handshake
+/// was aborted with.
+fn is_cert_alert(code: quinn::TransportErrorCode) -> bool {
+    use rustls::AlertDescription::*;
+
+    [
+        BadCertificate,
+        UnsupportedCertificate,
+        CertificateRevoked,
+        CertificateExpired,
+        CertificateUnknown,
+        UnknownCA,
+    ]
+    .into_iter()
+    .any(|alert| code == quinn::TransportErrorCode::crypto(alert.into()))
+}
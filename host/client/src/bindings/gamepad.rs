@@ -0,0 +1,142 @@
+use std::mem;
+
+use arid::{Handle, Strong, W, Wr};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_abi as abi;
+use wasmlink::HostClosure;
+
+use crate::{
+    services::session::{GamepadAxisInput, GamepadButtonInput, GamepadInput},
+    wsl::{WslContext, WslLinker, WslLinkerExt},
+};
+
+#[derive(Debug)]
+pub struct GamepadBindings {
+    user_callbacks: Option<GamepadCallbacks>,
+    enumeration_requested: bool,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct GamepadCallbacks {
+    pub connected: HostClosure<abi::GamepadInfo>,
+    pub disconnected: HostClosure<u32>,
+    pub button: HostClosure<abi::GamepadButtonEvent>,
+    pub axis: HostClosure<abi::GamepadAxisEvent>,
+}
+
+impl GamepadCallbacks {
+    pub fn deliver(self, cx: &mut WslContext<'_>, input: &GamepadInput) -> anyhow::Result<()> {
+        match input {
+            GamepadInput::Connected { id, name } => self.connected.call(
+                cx,
+                &abi::GamepadInfo {
+                    id: *id,
+                    name: name.as_str(),
+                },
+            ),
+            GamepadInput::Disconnected { id } => self.disconnected.call(cx, id),
+            GamepadInput::Button {
+                id,
+                button,
+                pressed,
+            } => self.button.call(
+                cx,
+                &abi::GamepadButtonEvent {
+                    id: *id,
+                    button: match button {
+                        GamepadButtonInput::South => abi::GamepadButton::South,
+                        GamepadButtonInput::East => abi::GamepadButton::East,
+                        GamepadButtonInput::North => abi::GamepadButton::North,
+                        GamepadButtonInput::West => abi::GamepadButton::West,
+                        GamepadButtonInput::LeftBumper => abi::GamepadButton::LeftBumper,
+                        GamepadButtonInput::RightBumper => abi::GamepadButton::RightBumper,
+                        GamepadButtonInput::LeftTrigger => abi::GamepadButton::LeftTrigger,
+                        GamepadButtonInput::RightTrigger => abi::GamepadButton::RightTrigger,
+                        GamepadButtonInput::Select => abi::GamepadButton::Select,
+                        GamepadButtonInput::Start => abi::GamepadButton::Start,
+                        GamepadButtonInput::Mode => abi::GamepadButton::Mode,
+                        GamepadButtonInput::LeftStick => abi::GamepadButton::LeftStick,
+                        GamepadButtonInput::RightStick => abi::GamepadButton::RightStick,
+                        GamepadButtonInput::DPadUp => abi::GamepadButton::DPadUp,
+                        GamepadButtonInput::DPadDown => abi::GamepadButton::DPadDown,
+                        GamepadButtonInput::DPadLeft => abi::GamepadButton::DPadLeft,
+                        GamepadButtonInput::DPadRight => abi::GamepadButton::DPadRight,
+                    },
+                    pressed: *pressed,
+                },
+            ),
+            GamepadInput::Axis { id, axis, value } => self.axis.call(
+                cx,
+                &abi::GamepadAxisEvent {
+                    id: *id,
+                    axis: match axis {
+                        GamepadAxisInput::LeftStickX => abi::GamepadAxis::LeftStickX,
+                        GamepadAxisInput::LeftStickY => abi::GamepadAxis::LeftStickY,
+                        GamepadAxisInput::RightStickX => abi::GamepadAxis::RightStickX,
+                        GamepadAxisInput::RightStickY => abi::GamepadAxis::RightStickY,
+                        GamepadAxisInput::LeftTrigger => abi::GamepadAxis::LeftTrigger,
+                        GamepadAxisInput::RightTrigger => abi::GamepadAxis::RightTrigger,
+                    },
+                    value: *value,
+                },
+            ),
+        }
+    }
+}
+
+component!(pub GamepadBindings);
+
+impl GamepadBindingsHandle {
+    pub fn new(owner: EntityHandle, w: W) -> Strong<Self> {
+        GamepadBindings {
+            user_callbacks: None,
+            enumeration_requested: false,
+        }
+        .attach(owner, w)
+    }
+
+    pub fn user_callbacks(self, w: Wr) -> Option<GamepadCallbacks> {
+        self.r(w).user_callbacks
+    }
+
+    /// Returns whether the guest bound its handlers since the last call, in which case it must be
+    /// told about every gamepad which is already connected.
+    #[must_use]
+    pub fn take_enumeration_request(self, w: W) -> bool {
+        mem::take(&mut self.m(w).enumeration_requested)
+    }
+
+    /// Unbinds the handlers of an instance which is being replaced.
+    pub fn reset_instance(self, w: W) {
+        let me = self.m(w);
+        me.user_callbacks = None;
+        me.enumeration_requested = false;
+    }
+
+    pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
+        linker.define_wsl(abi::GAMEPAD_BIND_HANDLERS, move |cx, args, ret| {
+            let me = self.m(cx.w());
+
+            me.user_callbacks = Some(GamepadCallbacks {
+                connected: args.connected,
+                disconnected: args.disconnected,
+                button: args.button,
+                axis: args.axis,
+            });
+            me.enumeration_requested = true;
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::GAMEPAD_UNBIND_HANDLERS, move |cx, (), ret| {
+            let me = self.m(cx.w());
+
+            me.user_callbacks = None;
+            me.enumeration_requested = false;
+
+            ret.finish(cx, &())
+        })?;
+
+        Ok(())
+    }
+}
@@ -1,21 +1,81 @@
 use std::mem;
 
+use anyhow::Context as _;
 use arid::{Handle, MayDangle, Strong, W, Wr};
 use arid_entity::{Component, EntityHandle, component};
 use crucible_abi as abi;
 use crucible_host_shared::guest::arena::GuestArena;
 use glam::Affine2;
 use wasmlink::HostClosure;
-use wasmlink_wasmtime::{WslLinker, WslLinkerExt};
-
-use crate::services::window::{WindowManagerHandle, WindowStateHandle};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    window::{CursorGrabMode, CursorIcon, WindowId},
+};
+
+use crate::{
+    services::{
+        session::{
+            ImeInput, LogicalKeyInput, MouseButtonInput, NativeKeyInput, ScrollDeltaInput,
+            WindowInput,
+        },
+        window::{WindowManagerHandle, WindowStateHandle},
+    },
+    wsl::{WslContext, WslLinker, WslLinkerExt},
+};
 
 #[derive(Debug)]
 pub struct GfxBindings {
     window_mgr: WindowManagerHandle,
     handles: GuestArena<GfxTexture>,
-    user_callbacks: Option<WindowCallbacks>,
+    windows: GuestArena<GuestWindow>,
+    main_window: u32,
+    open_requests: Vec<WindowOpenRequest>,
+    /// The size reported for windows which aren't shown.
+    offscreen_size: (u32, u32),
+}
+
+/// A window the guest can bind handlers to. The main window always exists while the others are
+/// opened and closed by the guest.
+#[derive(Debug, Default)]
+struct GuestWindow {
+    /// The window being shown, if any. Headless sessions and secondary windows which the event
+    /// loop hasn't created yet have none.
+    state: Option<WindowStateHandle>,
+    /// Whether the event loop opened the window, either in a window of its own or offscreen. The
+    /// main window is always open.
+    opened: bool,
+    /// Whether the window has keyboard focus, as last reported by the event loop.
+    focused: bool,
+    callbacks: Option<WindowCallbacks>,
     redraw_requested: bool,
+    cursor: CursorState,
+}
+
+/// A secondary window the guest opened which the event loop must open.
+#[derive(Debug, Clone)]
+pub struct WindowOpenRequest {
+    pub handle: u32,
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The cursor configuration requested by the guest.
+#[derive(Debug, Copy, Clone)]
+struct CursorState {
+    visible: bool,
+    grab: abi::CursorGrabMode,
+    icon: abi::CursorIcon,
+}
+
+impl Default for CursorState {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            grab: abi::CursorGrabMode::None,
+            icon: abi::CursorIcon::Default,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -31,23 +91,314 @@ pub struct WindowCallbacks {
     pub mouse_moved: HostClosure<abi::DVec2>,
     pub key_event: HostClosure<abi::KeyEvent>,
     pub exit_requested: HostClosure<()>,
+    pub mouse_wheel: HostClosure<abi::MouseScrollDelta>,
+    pub cursor_entered: HostClosure<()>,
+    pub cursor_left: HostClosure<()>,
+    pub focused: HostClosure<bool>,
+    pub occluded: HostClosure<bool>,
+    pub resized: HostClosure<abi::UVec2>,
+    pub scale_factor_changed: HostClosure<f64>,
+    pub mouse_motion: HostClosure<abi::DVec2>,
+    pub ime: HostClosure<abi::ImeEvent>,
+}
+
+impl WindowCallbacks {
+    /// Delivers `input` to the guest's handlers. Redraws are drawn into the framebuffer texture
+    /// with the handle `fb`.
+    pub fn deliver(
+        self,
+        cx: &mut WslContext<'_>,
+        input: &WindowInput,
+        fb: Option<u32>,
+    ) -> anyhow::Result<()> {
+        match input {
+            WindowInput::Redraw { width, height } => {
+                let fb = fb.context("redraws must be given a framebuffer")?;
+
+                self.redraw_requested.call(
+                    cx,
+                    &abi::RedrawRequestedArgs {
+                        fb: abi::GpuTextureHandle { raw: fb },
+                        size: abi::UVec2 {
+                            x: *width,
+                            y: *height,
+                        },
+                    },
+                )
+            }
+            WindowInput::MouseMoved { x, y } => {
+                self.mouse_moved.call(cx, &abi::DVec2 { x: *x, y: *y })
+            }
+            WindowInput::MouseInput { button, pressed } => self.mouse_event.call(
+                cx,
+                &abi::MouseEvent {
+                    button: match *button {
+                        MouseButtonInput::Left => abi::MouseButton::Left(()),
+                        MouseButtonInput::Right => abi::MouseButton::Right(()),
+                        MouseButtonInput::Middle => abi::MouseButton::Middle(()),
+                        MouseButtonInput::Back => abi::MouseButton::Back(()),
+                        MouseButtonInput::Forward => abi::MouseButton::Forward(()),
+                        MouseButtonInput::Other(id) => abi::MouseButton::Other(id),
+                    },
+                    pressed: *pressed,
+                },
+            ),
+            WindowInput::Key(key) => self.key_event.call(
+                cx,
+                &abi::KeyEvent {
+                    physical_key: key.physical_key,
+                    logical_key: match &key.logical_key {
+                        LogicalKeyInput::Named(named_key) => abi::LogicalKey::Named(*named_key),
+                        LogicalKeyInput::Character(ch) => abi::LogicalKey::Character(ch.as_str()),
+                        LogicalKeyInput::Unidentified(code) => {
+                            abi::LogicalKey::Unidentified(match code {
+                                NativeKeyInput::Unidentified => abi::NativeKey::Unidentified(()),
+                                NativeKeyInput::Android(v) => abi::NativeKey::Android(*v),
+                                NativeKeyInput::MacOS(v) => abi::NativeKey::MacOS(*v),
+                                NativeKeyInput::Windows(v) => abi::NativeKey::Windows(*v),
+                                NativeKeyInput::Xkb(v) => abi::NativeKey::Xkb(*v),
+                                NativeKeyInput::Web(v) => abi::NativeKey::Web(v.as_str()),
+                            })
+                        }
+                        LogicalKeyInput::Dead(ch) => abi::LogicalKey::Dead(*ch),
+                    },
+                    text: key.text.as_deref(),
+                    location: key.location,
+                    pressed: key.pressed,
+                    repeat: key.repeat,
+                },
+            ),
+            WindowInput::CloseRequested => self.exit_requested.call(cx, &()),
+            WindowInput::MouseWheel(delta) => self.mouse_wheel.call(
+                cx,
+                &match *delta {
+                    ScrollDeltaInput::Line { x, y } => {
+                        abi::MouseScrollDelta::Line(abi::DVec2 { x, y })
+                    }
+                    ScrollDeltaInput::Pixel { x, y } => {
+                        abi::MouseScrollDelta::Pixel(abi::DVec2 { x, y })
+                    }
+                },
+            ),
+            WindowInput::CursorEntered => self.cursor_entered.call(cx, &()),
+            WindowInput::CursorLeft => self.cursor_left.call(cx, &()),
+            WindowInput::Focused(focused) => self.focused.call(cx, focused),
+            WindowInput::Occluded(occluded) => self.occluded.call(cx, occluded),
+            WindowInput::Resized { width, height } => self.resized.call(
+                cx,
+                &abi::UVec2 {
+                    x: *width,
+                    y: *height,
+                },
+            ),
+            WindowInput::ScaleFactorChanged(scale_factor) => {
+                self.scale_factor_changed.call(cx, scale_factor)
+            }
+            WindowInput::MouseMotion { x, y } => {
+                self.mouse_motion.call(cx, &abi::DVec2 { x: *x, y: *y })
+            }
+            WindowInput::Ime(ime) => self.ime.call(
+                cx,
+                &match ime {
+                    ImeInput::Enabled => abi::ImeEvent::Enabled(()),
+                    ImeInput::Preedit { text, cursor } => abi::ImeEvent::Preedit(abi::ImePreedit {
+                        text: text.as_str(),
+                        cursor: cursor.map(|(start, end)| abi::ImeCursorRange { start, end }),
+                    }),
+                    ImeInput::Commit(text) => abi::ImeEvent::Commit(text.as_str()),
+                    ImeInput::Disabled => abi::ImeEvent::Disabled(()),
+                },
+            ),
+        }
+    }
 }
 
 component!(pub GfxBindings);
 
+macro_rules! window_callbacks {
+    ($handlers:expr) => {
+        WindowCallbacks {
+            redraw_requested: $handlers.redraw_requested,
+            mouse_event: $handlers.mouse_event,
+            mouse_moved: $handlers.mouse_moved,
+            key_event: $handlers.key_event,
+            exit_requested: $handlers.exit_requested,
+            mouse_wheel: $handlers.mouse_wheel,
+            cursor_entered: $handlers.cursor_entered,
+            cursor_left: $handlers.cursor_left,
+            focused: $handlers.focused,
+            occluded: $handlers.occluded,
+            resized: $handlers.resized,
+            scale_factor_changed: $handlers.scale_factor_changed,
+            mouse_motion: $handlers.mouse_motion,
+            ime: $handlers.ime,
+        }
+    };
+}
+
 impl GfxBindingsHandle {
     pub fn new(owner: EntityHandle, window_mgr: WindowManagerHandle, w: W) -> Strong<Self> {
+        let mut windows = GuestArena::default();
+        let main_window = windows
+            .add(GuestWindow {
+                opened: true,
+                ..GuestWindow::default()
+            })
+            .unwrap();
+
         GfxBindings {
             window_mgr,
             handles: GuestArena::default(),
-            user_callbacks: None,
-            redraw_requested: false,
+            windows,
+            main_window,
+            open_requests: Vec::new(),
+            offscreen_size: (0, 0),
         }
         .attach(owner, w)
     }
 
-    pub fn user_callbacks(self, w: Wr) -> Option<WindowCallbacks> {
-        self.r(w).user_callbacks
+    /// Sets the window shown for the guest's main window. Headless sessions have no such window and
+    /// ignore the guest's cursor requests.
+    pub fn set_main_window(self, window: WindowStateHandle, w: W) {
+        let main_window = self.r(w).main_window;
+
+        self.m(w).windows.get_mut(main_window).unwrap().state = Some(window);
+        self.apply_cursor(main_window, w);
+    }
+
+    /// Sets the size reported for guest windows which aren't shown. Headless sessions set it to the
+    /// size of the framebuffers they draw to.
+    pub fn set_offscreen_size(self, width: u32, height: u32, w: W) {
+        self.m(w).offscreen_size = (width, height);
+    }
+
+    pub fn offscreen_size(self, w: Wr) -> (u32, u32) {
+        self.r(w).offscreen_size
+    }
+
+    /// The handle of the guest's main window.
+    pub fn main_window(self, w: Wr) -> u32 {
+        self.r(w).main_window
+    }
+
+    /// The handlers bound to the main window. The session ends once they are unbound.
+    pub fn main_callbacks(self, w: Wr) -> Option<WindowCallbacks> {
+        let me = self.r(w);
+        me.windows.get(me.main_window).unwrap().callbacks
+    }
+
+    /// The handlers bound to the guest window with the given `handle`, if it is still open.
+    pub fn callbacks(self, handle: u32, w: Wr) -> Option<WindowCallbacks> {
+        self.r(w)
+            .windows
+            .get(handle)
+            .ok()
+            .and_then(|window| window.callbacks)
+    }
+
+    /// The handles of the open guest windows which have handlers bound.
+    pub fn bound_windows(self, w: Wr) -> Vec<u32> {
+        self.r(w)
+            .windows
+            .iter()
+            .filter(|(_, window)| window.opened && window.callbacks.is_some())
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+
+    /// Finds the guest window shown in the window with the given `id`, returning its handle and
+    /// its handlers.
+    pub fn lookup(self, id: WindowId, w: Wr) -> Option<(u32, Option<WindowCallbacks>)> {
+        self.r(w).windows.iter().find_map(|(handle, window)| {
+            let state = window.state?;
+            (state.window(w).id() == id).then_some((handle, window.callbacks))
+        })
+    }
+
+    /// Finds the guest window which has focus, returning its handle and its handlers.
+    pub fn focused(self, w: Wr) -> Option<(u32, Option<WindowCallbacks>)> {
+        self.r(w)
+            .windows
+            .iter()
+            .find_map(|(handle, window)| window.focused.then_some((handle, window.callbacks)))
+    }
+
+    /// Records whether the guest window with the given `handle` has keyboard focus. Some platforms
+    /// release cursor grabs when a window loses focus so the guest's cursor configuration is
+    /// applied again once it regains it.
+    pub fn set_focused(self, handle: u32, focused: bool, w: W) {
+        let Ok(window) = self.m(w).windows.get_mut(handle) else {
+            return;
+        };
+
+        window.focused = focused;
+
+        if focused {
+            self.apply_cursor(handle, w);
+        }
+    }
+
+    /// The window shown for the guest window with the given `handle`. Fails if the guest passed an
+    /// invalid handle.
+    pub fn window_state(self, handle: u32, w: Wr) -> anyhow::Result<Option<WindowStateHandle>> {
+        Ok(self.r(w).windows.get(handle)?.state)
+    }
+
+    /// Takes the secondary windows the guest opened since the last call. They must be opened
+    /// through [`attach_window`](Self::attach_window).
+    pub fn take_open_requests(self, w: W) -> Vec<WindowOpenRequest> {
+        mem::take(&mut self.m(w).open_requests)
+    }
+
+    /// Opens the secondary guest window with the given `handle`, showing it in `window` if given
+    /// and drawing it offscreen otherwise. Returns `false` if the guest closed it in the meantime,
+    /// in which case the caller should close `window`.
+    #[must_use]
+    pub fn attach_window(self, handle: u32, window: Option<WindowStateHandle>, w: W) -> bool {
+        let Ok(guest_window) = self.m(w).windows.get_mut(handle) else {
+            return false;
+        };
+
+        guest_window.state = window;
+        guest_window.opened = true;
+        self.apply_cursor(handle, w);
+
+        true
+    }
+
+    /// Applies the cursor configuration requested by the guest to the window with the given
+    /// `handle`.
+    pub fn apply_cursor(self, handle: u32, w: Wr) {
+        let Ok(guest_window) = self.r(w).windows.get(handle) else {
+            return;
+        };
+
+        let Some(window) = guest_window.state else {
+            return;
+        };
+
+        let cursor = guest_window.cursor;
+        let window = window.window(w);
+
+        window.set_cursor_visible(cursor.visible);
+        window.set_cursor(cursor_icon(cursor.icon));
+
+        let (mode, fallback) = match cursor.grab {
+            abi::CursorGrabMode::None => (CursorGrabMode::None, None),
+            abi::CursorGrabMode::Confined => {
+                (CursorGrabMode::Confined, Some(CursorGrabMode::Locked))
+            }
+            abi::CursorGrabMode::Locked => (CursorGrabMode::Locked, Some(CursorGrabMode::Confined)),
+        };
+
+        let res = window.set_cursor_grab(mode).or_else(|err| match fallback {
+            Some(fallback) => window.set_cursor_grab(fallback),
+            None => Err(err),
+        });
+
+        if let Err(err) = res {
+            tracing::warn!("Failed to grab the cursor: {err}");
+        }
     }
 
     pub fn create_texture(
@@ -62,36 +413,163 @@ impl GfxBindingsHandle {
         })
     }
 
+    /// Closes the secondary window with the given `handle` or unbinds the handlers of the main
+    /// window.
+    fn close_window(self, handle: u32, w: W) -> anyhow::Result<()> {
+        if handle != self.r(w).main_window {
+            let window = self.m(w).windows.remove(handle)?;
+
+            self.m(w)
+                .open_requests
+                .retain(|request| request.handle != handle);
+
+            if let Some(state) = window.state {
+                self.r(w).window_mgr.close_window(state, w);
+            }
+
+            return Ok(());
+        }
+
+        let window = self.m(w).windows.get_mut(handle)?;
+        window.callbacks = None;
+        window.redraw_requested = false;
+        window.cursor = CursorState::default();
+
+        self.apply_cursor(handle, w);
+
+        if let Some(state) = self.r(w).windows.get(handle)?.state {
+            state.window(w).set_ime_allowed(false);
+        }
+
+        Ok(())
+    }
+
+    /// Closes the secondary windows, unbinds the main window's handlers, restores its cursor and
+    /// destroys the textures of an instance which is being replaced, presenting any framebuffer it
+    /// was still drawing to.
+    pub fn reset_instance(self, w: W) {
+        let handles = self
+            .r(w)
+            .windows
+            .iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            self.close_window(handle, w).unwrap();
+        }
+
+        let textures = self.m(w).handles.drain().collect::<Vec<_>>();
+
+        for texture in textures {
+            if let Some(fb_owned_by) = texture.fb_owned_by
+                && let Some(fb_owned_by) = fb_owned_by.get(w)
+            {
+                fb_owned_by.end_redraw(w);
+            }
+        }
+    }
+
+    /// Takes the guest windows which requested a redraw since the last call, along with the
+    /// windows they are shown in, if any.
     #[must_use]
-    pub fn take_redraw_request(self, w: W) -> bool {
-        mem::take(&mut self.m(w).redraw_requested)
+    pub fn take_redraw_requests(self, w: W) -> Vec<(u32, Option<WindowStateHandle>)> {
+        self.m(w)
+            .windows
+            .iter_mut()
+            .filter_map(|(handle, window)| {
+                mem::take(&mut window.redraw_requested).then_some((handle, window.state))
+            })
+            .collect()
     }
 
     pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
-        linker.define_wsl(abi::WINDOW_BIND_HANDLERS, move |cx, args, ret| {
+        linker.define_wsl(abi::WINDOW_BIND_HANDLERS, move |cx, handlers, ret| {
+            let w = cx.w();
+            let main_window = self.r(w).main_window;
+
+            self.m(w).windows.get_mut(main_window)?.callbacks = Some(window_callbacks!(handlers));
+
+            ret.finish(cx, &abi::WindowHandle { raw: main_window })
+        })?;
+
+        linker.define_wsl(abi::WINDOW_OPEN, move |cx, args, ret| {
+            let title = args.title.read(cx)?.to_string();
             let w = cx.w();
 
-            self.m(w).user_callbacks = Some(WindowCallbacks {
-                redraw_requested: args.redraw_requested,
-                mouse_event: args.mouse_event,
-                mouse_moved: args.mouse_moved,
-                key_event: args.key_event,
-                exit_requested: args.exit_requested,
+            let handle = self.m(w).windows.add(GuestWindow {
+                callbacks: Some(window_callbacks!(args.handlers)),
+                ..GuestWindow::default()
+            })?;
+
+            self.m(w).open_requests.push(WindowOpenRequest {
+                handle,
+                title,
+                width: args.size.x,
+                height: args.size.y,
             });
 
+            ret.finish(cx, &abi::WindowHandle { raw: handle })
+        })?;
+
+        linker.define_wsl(abi::WINDOW_CLOSE, move |cx, window, ret| {
+            self.close_window(window.raw, cx.w())?;
+
             ret.finish(cx, &())
         })?;
 
-        linker.define_wsl(abi::WINDOW_UNBIND_HANDLERS, move |cx, (), ret| {
+        linker.define_wsl(abi::WINDOW_SET_CURSOR_VISIBLE, move |cx, args, ret| {
             let w = cx.w();
 
-            self.m(w).user_callbacks = None;
+            self.m(w).windows.get_mut(args.window.raw)?.cursor.visible = args.enabled;
+            self.apply_cursor(args.window.raw, w);
 
             ret.finish(cx, &())
         })?;
 
-        linker.define_wsl(abi::WINDOW_REQUEST_REDRAW, move |cx, (), ret| {
-            self.m(cx.w()).redraw_requested = true;
+        linker.define_wsl(abi::WINDOW_SET_CURSOR_GRAB, move |cx, args, ret| {
+            let w = cx.w();
+
+            self.m(w).windows.get_mut(args.window.raw)?.cursor.grab = args.mode;
+            self.apply_cursor(args.window.raw, w);
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_SET_CURSOR_ICON, move |cx, args, ret| {
+            let w = cx.w();
+
+            self.m(w).windows.get_mut(args.window.raw)?.cursor.icon = args.icon;
+            self.apply_cursor(args.window.raw, w);
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_SET_IME_ALLOWED, move |cx, args, ret| {
+            let w = cx.w();
+
+            if let Some(window) = self.window_state(args.window.raw, w)? {
+                window.window(w).set_ime_allowed(args.enabled);
+            }
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_SET_IME_CURSOR_AREA, move |cx, area, ret| {
+            let w = cx.w();
+
+            if let Some(window) = self.window_state(area.window.raw, w)? {
+                window.window(w).set_ime_cursor_area(
+                    PhysicalPosition::new(area.position.x, area.position.y),
+                    PhysicalSize::new(area.size.x, area.size.y),
+                );
+            }
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_REQUEST_REDRAW, move |cx, window, ret| {
+            self.m(cx.w()).windows.get_mut(window.raw)?.redraw_requested = true;
 
             ret.finish(cx, &())
         })?;
@@ -134,11 +612,11 @@ impl GfxBindingsHandle {
             let (w, mem) = cx.world_and_memory();
 
             let texture = self.r(w).handles.get(args.handle.raw)?.wgpu.clone();
-            let buffer = bytemuck::cast_slice(args.buffer.slice().read(mem)?).to_vec();
+            let buffer = bytemuck::cast_slice(args.buffer.slice().read(mem)?);
 
             self.r(w).window_mgr.renderer_mut(w).upload_texture(
                 &texture,
-                &buffer,
+                buffer,
                 bytemuck::cast(args.buffer_size),
                 bytemuck::cast(args.at),
                 args.clip
@@ -186,3 +664,42 @@ impl GfxBindingsHandle {
         Ok(())
     }
 }
+
+fn cursor_icon(icon: abi::CursorIcon) -> CursorIcon {
+    match icon {
+        abi::CursorIcon::Default => CursorIcon::Default,
+        abi::CursorIcon::ContextMenu => CursorIcon::ContextMenu,
+        abi::CursorIcon::Help => CursorIcon::Help,
+        abi::CursorIcon::Pointer => CursorIcon::Pointer,
+        abi::CursorIcon::Progress => CursorIcon::Progress,
+        abi::CursorIcon::Wait => CursorIcon::Wait,
+        abi::CursorIcon::Cell => CursorIcon::Cell,
+        abi::CursorIcon::Crosshair => CursorIcon::Crosshair,
+        abi::CursorIcon::Text => CursorIcon::Text,
+        abi::CursorIcon::VerticalText => CursorIcon::VerticalText,
+        abi::CursorIcon::Alias => CursorIcon::Alias,
+        abi::CursorIcon::Copy => CursorIcon::Copy,
+        abi::CursorIcon::Move => CursorIcon::Move,
+        abi::CursorIcon::NoDrop => CursorIcon::NoDrop,
+        abi::CursorIcon::NotAllowed => CursorIcon::NotAllowed,
+        abi::CursorIcon::Grab => CursorIcon::Grab,
+        abi::CursorIcon::Grabbing => CursorIcon::Grabbing,
+        abi::CursorIcon::EResize => CursorIcon::EResize,
+        abi::CursorIcon::NResize => CursorIcon::NResize,
+        abi::CursorIcon::NeResize => CursorIcon::NeResize,
+        abi::CursorIcon::NwResize => CursorIcon::NwResize,
+        abi::CursorIcon::SResize => CursorIcon::SResize,
+        abi::CursorIcon::SeResize => CursorIcon::SeResize,
+        abi::CursorIcon::SwResize => CursorIcon::SwResize,
+        abi::CursorIcon::WResize => CursorIcon::WResize,
+        abi::CursorIcon::EwResize => CursorIcon::EwResize,
+        abi::CursorIcon::NsResize => CursorIcon::NsResize,
+        abi::CursorIcon::NeswResize => CursorIcon::NeswResize,
+        abi::CursorIcon::NwseResize => CursorIcon::NwseResize,
+        abi::CursorIcon::ColResize => CursorIcon::ColResize,
+        abi::CursorIcon::RowResize => CursorIcon::RowResize,
+        abi::CursorIcon::AllScroll => CursorIcon::AllScroll,
+        abi::CursorIcon::ZoomIn => CursorIcon::ZoomIn,
+        abi::CursorIcon::ZoomOut => CursorIcon::ZoomOut,
+    }
+}
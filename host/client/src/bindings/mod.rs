@@ -1,3 +1,11 @@
+pub mod audio;
+pub mod clipboard;
 pub mod env;
+pub mod error;
+pub mod gamepad;
 pub mod gfx;
+pub mod mods;
 pub mod network;
+pub mod reload;
+pub mod storage;
+pub mod window;
@@ -0,0 +1,348 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    mem,
+};
+
+use anyhow::Context as _;
+use arid::{Handle, Strong, W, Wr};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_abi as abi;
+use crucible_host_shared::guest::arena::GuestArena;
+use rustc_hash::FxHashMap;
+use wasmlink::HostClosure;
+
+use crate::{
+    bindings::error::GuestError,
+    wsl::{WslContext, WslLinker, WslLinkerExt},
+};
+
+/// The peer ID of the game. Mods are numbered from `1` in load order.
+pub const GAME_PEER: u32 = 0;
+
+#[derive(Debug, Clone)]
+pub struct LoadedMod {
+    pub name: String,
+    pub content_hash: blake3::Hash,
+}
+
+/// Routes extension point messages between the game and its mods. Every peer runs in its own store
+/// so messages are queued by the ports and delivered by the event loop once the sender returns.
+#[derive(Debug)]
+pub struct ModBindings {
+    mods: Vec<LoadedMod>,
+    /// Mods required by a server which have been downloaded but not yet started.
+    downloaded: Vec<(LoadedMod, Vec<u8>)>,
+    extensions: FxHashMap<String, Extension>,
+    handles: Vec<GuestArena<String>>,
+    queue: VecDeque<QueuedMessage>,
+}
+
+#[derive(Debug, Default)]
+struct Extension {
+    members: BTreeMap<u32, HostClosure<abi::ExtensionMessage>>,
+}
+
+#[derive(Debug)]
+struct QueuedMessage {
+    extension: String,
+    from: u32,
+    to: u32,
+    message: Vec<u8>,
+}
+
+/// A message ready to be delivered to the instance of peer `to`.
+#[derive(Debug)]
+pub struct PendingMessage {
+    pub to: u32,
+    pub from: u32,
+    handler: HostClosure<abi::ExtensionMessage>,
+    pub message: Vec<u8>,
+}
+
+impl PendingMessage {
+    pub fn deliver(self, cx: &mut WslContext<'_>) -> anyhow::Result<()> {
+        self.handler.call(
+            cx,
+            &abi::ExtensionMessage {
+                peer: self.from,
+                message: &self.message,
+            },
+        )
+    }
+}
+
+component!(pub ModBindings);
+
+impl ModBindingsHandle {
+    pub fn new(owner: EntityHandle, mods: Vec<LoadedMod>, w: W) -> Strong<Self> {
+        ModBindings {
+            handles: (0..=mods.len()).map(|_| GuestArena::default()).collect(),
+            mods,
+            downloaded: Vec::new(),
+            extensions: FxHashMap::default(),
+            queue: VecDeque::new(),
+        }
+        .attach(owner, w)
+    }
+
+    /// Defines the mod ports for the instance of `peer`.
+    pub fn install(self, peer: u32, linker: &mut WslLinker) -> anyhow::Result<()> {
+        linker.define_wsl(abi::MOD_LIST, move |cx, (), ret| {
+            let mods = self.r(cx.wr()).mods.clone();
+            let mods = mods
+                .iter()
+                .map(|info| abi::ModInfo {
+                    name: info.name.as_str(),
+                    content_hash: abi::ContentHash(*info.content_hash.as_bytes()),
+                })
+                .collect::<Vec<_>>();
+
+            ret.finish(cx, &mods.as_slice())
+        })?;
+
+        linker.define_wsl(abi::MOD_GET_SELF, move |cx, (), ret| ret.finish(cx, &peer))?;
+
+        linker.define_wsl(abi::EXTENSION_DECLARE, move |cx, args, ret| {
+            let name = args.name.read(cx)?.to_string();
+            let res = self.open(peer, name, args.handler, true, cx.w());
+
+            match res {
+                Ok(handle) => ret.finish(cx, &Ok(abi::ExtensionHandle { raw: handle })),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::EXTENSION_JOIN, move |cx, args, ret| {
+            let name = args.name.read(cx)?.to_string();
+            let res = self.open(peer, name, args.handler, false, cx.w());
+
+            match res {
+                Ok(handle) => ret.finish(cx, &Ok(abi::ExtensionHandle { raw: handle })),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::EXTENSION_SEND, move |cx, args, ret| {
+            let message = args.message.read(cx)?.to_vec();
+            let res = self.send(peer, args.extension.raw, args.peer, message, cx.w())?;
+
+            match res {
+                Ok(()) => ret.finish(cx, &Ok(())),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::EXTENSION_CLOSE, move |cx, handle, ret| {
+            self.close(peer, handle.raw, cx.w())?;
+
+            ret.finish(cx, &())
+        })?;
+
+        Ok(())
+    }
+
+    /// Opens the extension point `name` for `peer`, declaring it if `declare` is set and joining
+    /// it otherwise. Messages sent to `peer` through it are delivered to `handler`.
+    pub fn open(
+        self,
+        peer: u32,
+        name: String,
+        handler: HostClosure<abi::ExtensionMessage>,
+        declare: bool,
+        w: W,
+    ) -> Result<u32, GuestError> {
+        let me = self.m(w);
+
+        if declare {
+            if peer != GAME_PEER {
+                return Err(GuestError {
+                    kind: abi::ErrorKind::Other,
+                    message: "only the game can declare extension points".to_string(),
+                    sources: Vec::new(),
+                });
+            }
+
+            if me.extensions.contains_key(&name) {
+                return Err(GuestError {
+                    kind: abi::ErrorKind::Other,
+                    message: format!("extension point `{name}` was already declared"),
+                    sources: Vec::new(),
+                });
+            }
+
+            me.extensions.insert(name.clone(), Extension::default());
+        }
+
+        let Some(extension) = me.extensions.get_mut(&name) else {
+            return Err(GuestError {
+                kind: abi::ErrorKind::NotFound,
+                message: format!("the game declares no extension point named `{name}`"),
+                sources: Vec::new(),
+            });
+        };
+
+        if extension.members.contains_key(&peer) {
+            return Err(GuestError {
+                kind: abi::ErrorKind::Other,
+                message: format!("extension point `{name}` was already joined"),
+                sources: Vec::new(),
+            });
+        }
+
+        let handle = me.handles[peer as usize]
+            .add(name)
+            .map_err(|err| GuestError::new(&err))?;
+
+        extension.members.insert(peer, handler);
+
+        Ok(handle)
+    }
+
+    /// Queues `message` from `peer` to the other members of the extension point behind `handle`, or
+    /// to `to` alone if given. Errors the guest is expected to handle are returned in the inner
+    /// result.
+    pub fn send(
+        self,
+        peer: u32,
+        handle: u32,
+        to: Option<u32>,
+        message: Vec<u8>,
+        w: W,
+    ) -> anyhow::Result<Result<(), GuestError>> {
+        let me = self.m(w);
+        let name = me.handles[peer as usize]
+            .get(handle)
+            .context("invalid extension handle")?;
+
+        let Some(extension) = me.extensions.get(name) else {
+            return Ok(Err(closed_extension(name)));
+        };
+
+        if !extension.members.contains_key(&peer) {
+            return Ok(Err(closed_extension(name)));
+        }
+
+        let recipients = match to {
+            Some(to) => {
+                anyhow::ensure!(to != peer, "peers cannot send messages to themselves");
+
+                if !extension.members.contains_key(&to) {
+                    return Ok(Err(GuestError {
+                        kind: abi::ErrorKind::NotFound,
+                        message: format!("peer {to} is not a member of extension `{name}`"),
+                        sources: Vec::new(),
+                    }));
+                }
+
+                vec![to]
+            }
+            None => extension
+                .members
+                .keys()
+                .copied()
+                .filter(|&to| to != peer)
+                .collect(),
+        };
+
+        let name = name.clone();
+
+        for to in recipients {
+            me.queue.push_back(QueuedMessage {
+                extension: name.clone(),
+                from: peer,
+                to,
+                message: message.clone(),
+            });
+        }
+
+        Ok(Ok(()))
+    }
+
+    /// Closes the extension point behind `handle` for `peer`. The game closing an extension point
+    /// removes it for every member.
+    pub fn close(self, peer: u32, handle: u32, w: W) -> anyhow::Result<()> {
+        let me = self.m(w);
+        let name = me.handles[peer as usize]
+            .remove(handle)
+            .context("invalid extension handle")?;
+
+        if peer == GAME_PEER {
+            me.extensions.remove(&name);
+        } else if let Some(extension) = me.extensions.get_mut(&name) {
+            extension.members.remove(&peer);
+        }
+
+        Ok(())
+    }
+
+    /// The content hashes of the mods which have been loaded or downloaded.
+    pub fn known_mods(self, w: Wr) -> Vec<blake3::Hash> {
+        let me = self.r(w);
+
+        me.mods
+            .iter()
+            .chain(me.downloaded.iter().map(|(info, _)| info))
+            .map(|info| info.content_hash)
+            .collect()
+    }
+
+    /// Queues a mod required by a server to be started by the event loop. Mods which are already
+    /// known are ignored.
+    pub fn push_downloaded(self, info: LoadedMod, module: Vec<u8>, w: W) {
+        if !self.known_mods(w).contains(&info.content_hash) {
+            self.m(w).downloaded.push((info, module));
+        }
+    }
+
+    /// Takes the downloaded mods which are yet to be started.
+    pub fn take_downloaded(self, w: W) -> Vec<(LoadedMod, Vec<u8>)> {
+        mem::take(&mut self.m(w).downloaded)
+    }
+
+    /// Registers a mod started after the game, returning its peer ID.
+    pub fn add_mod(self, info: LoadedMod, w: W) -> u32 {
+        let me = self.m(w);
+
+        me.mods.push(info);
+        me.handles.push(GuestArena::default());
+
+        me.mods.len() as u32
+    }
+
+    /// The number of messages which are yet to be delivered.
+    pub fn pending_count(self, w: Wr) -> usize {
+        self.r(w).queue.len()
+    }
+
+    /// Takes the next queued message whose recipient is still listening.
+    pub fn take_message(self, w: W) -> Option<PendingMessage> {
+        let me = self.m(w);
+
+        while let Some(queued) = me.queue.pop_front() {
+            let Some(handler) = me
+                .extensions
+                .get(&queued.extension)
+                .and_then(|extension| extension.members.get(&queued.to))
+            else {
+                continue;
+            };
+
+            return Some(PendingMessage {
+                to: queued.to,
+                from: queued.from,
+                handler: *handler,
+                message: queued.message,
+            });
+        }
+
+        None
+    }
+}
+
+fn closed_extension(name: &str) -> GuestError {
+    GuestError {
+        kind: abi::ErrorKind::NotFound,
+        message: format!("extension point `{name}` was closed"),
+        sources: Vec::new(),
+    }
+}
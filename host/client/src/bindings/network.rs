@@ -1,13 +1,27 @@
-use anyhow::Context;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering::Relaxed},
+};
+
+use anyhow::Context as _;
 use arid::{Handle, Object as _, Strong, W, object};
 use arid_entity::{Component, EntityHandle, component};
 use crucible_abi as abi;
-use crucible_host_shared::guest::arena::GuestArena;
-use wasmlink_wasmtime::{WslLinker, WslLinkerExt, WslStoreExt};
+use crucible_host_shared::{guest::arena::GuestArena, lang::PromiseCrashed};
+use wasmlink::Marshal;
 
 use crate::{
     app::BackgroundTasks,
-    services::network::{CertValidationMode, GameSocket, LoginSocket},
+    bindings::{
+        error::GuestError,
+        mods::{LoadedMod, ModBindingsHandle},
+    },
+    services::{
+        network::{CertValidationMode, GameSocket, LoginSocket},
+        permissions::PermissionsHandle,
+        session::{InputSessionHandle, ModInfoInput, NetOutcome, ServerInfoInput, SessionInput},
+    },
+    wsl::{AsyncReturner, RetVal, WslContext, WslLinker, WslLinkerExt},
 };
 
 #[derive(Debug)]
@@ -16,6 +30,9 @@ pub struct NetworkBindings {
     login_sockets: GuestArena<LoginSocket>,
     game_sockets: GuestArena<Strong<GameSocketBindStateHandle>>,
     background: BackgroundTasks,
+    session: InputSessionHandle,
+    permissions: PermissionsHandle,
+    mods: ModBindingsHandle,
 }
 
 component!(pub NetworkBindings);
@@ -23,7 +40,7 @@ component!(pub NetworkBindings);
 #[derive(Debug)]
 struct GameSocketBindState {
     socket: GameSocket,
-    send_msg_task: Option<smol::Task<Option<()>>>,
+    sending_msg: Arc<AtomicBool>,
 }
 
 object!(GameSocketBindState);
@@ -32,6 +49,9 @@ impl NetworkBindingsHandle {
     pub fn new(
         owner: EntityHandle,
         background: BackgroundTasks,
+        session: InputSessionHandle,
+        permissions: PermissionsHandle,
+        mods: ModBindingsHandle,
         w: W,
     ) -> anyhow::Result<Strong<Self>> {
         let endpoint = quinn::Endpoint::client("0.0.0.0:0".parse().unwrap())?;
@@ -41,90 +61,163 @@ impl NetworkBindingsHandle {
             login_sockets: GuestArena::default(),
             game_sockets: GuestArena::default(),
             background,
+            session,
+            permissions,
+            mods,
         }
         .attach(owner, w))
     }
 
-    pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
-        linker.define_wsl(abi::LOGIN_SOCKET_CONNECT, move |cx, args, ret| {
-            let addr = args.addr.read(cx)?.to_string();
-
-            let w = cx.w();
+    /// Records the outcome of the `call`-th async network call before it is delivered to the
+    /// guest.
+    fn record_outcome(self, call: u64, outcome: &NetOutcome, w: W) -> anyhow::Result<()> {
+        self.r(w).session.record(
+            SessionInput::Net {
+                call,
+                outcome: outcome.clone(),
+            },
+            w,
+        )
+    }
 
-            let socket = LoginSocket::new(
-                self.r(w).background.clone(),
-                self.r(w).endpoint.clone(),
-                addr,
-                "localhost",
-                CertValidationMode::DontAuthenticate,
-            );
-
-            self.r(w)
-                .background
-                .spawn_responder(socket, move |_event_loop, app, res| {
-                    let w = &mut app.world;
-
-                    let init = app.init.as_mut().unwrap();
-
-                    let socket = match res {
-                        Ok(v) => v,
-                        Err(err) => {
-                            init.store.run_wsl_root(&mut app.world, |cx| {
-                                args.callback.call(cx, &Err(&err.to_string()))
-                            })?;
-
-                            return Ok(());
-                        }
+    pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
+        linker.define_wsl_async(
+            abi::LOGIN_SOCKET_CONNECT,
+            move |cx, addr| {
+                let addr = addr.read(cx)?.to_string();
+
+                let w = cx.w();
+                let call = self.r(w).session.next_net_call(w);
+                let allowed = self.r(w).permissions.check_connection(&addr, w);
+
+                let fut = LoginSocket::new(
+                    self.r(w).background.clone(),
+                    self.r(w).endpoint.clone(),
+                    addr,
+                    "localhost",
+                    CertValidationMode::DontAuthenticate,
+                );
+
+                // Denied connections still go through the call counter and are recorded like any
+                // other failure so that replays see the same outcome.
+                Ok(async move {
+                    let res = match allowed {
+                        Ok(()) => fut.await,
+                        Err(err) => Err(err.into()),
                     };
 
-                    let handle = self.m(w).login_sockets.add(socket)?;
-
-                    init.store.run_wsl_root(&mut app.world, |cx| {
-                        args.callback
-                            .call(cx, &Ok(abi::LoginSocketHandle { raw: handle }))
-                    })?;
-
-                    Ok(())
+                    (call, res)
                 })
-                .detach();
+            },
+            move |cx, (call, res), ret| {
+                let w = cx.w();
+
+                let outcome = match res {
+                    Ok(socket) => Ok(self.m(w).login_sockets.add(socket)?),
+                    Err(err) => Err(GuestError::new(&err)),
+                };
+
+                let outcome = NetOutcome::Connect(outcome);
+                self.record_outcome(call, &outcome, w)?;
+
+                finish_net_call(cx, outcome, ret)
+            },
+        )?;
+
+        linker.define_wsl_async(
+            abi::LOGIN_SOCKET_GET_INFO,
+            move |cx, socket| {
+                let w = cx.w();
+                let call = self.r(w).session.next_net_call(w);
+                let socket = self.r(w).login_sockets.get(socket.raw)?.clone();
+                let fut = socket.info();
+
+                // Mods are left out of recordings so recorded sessions play without them.
+                let download_mods = !self.r(w).session.is_recording(w);
+                let known_mods = self.r(w).mods.known_mods(w);
+
+                let fut = async move {
+                    let info = fut.await?;
+
+                    if !download_mods {
+                        if !info.mods.is_empty() {
+                            tracing::warn!(
+                                "The server requires mods but mods aren't loaded while recording."
+                            );
+                        }
 
-            ret.finish(cx, &())
-        })?;
+                        return Ok((info, Vec::new()));
+                    }
 
-        linker.define_wsl(abi::LOGIN_SOCKET_GET_INFO, move |cx, args, ret| {
-            let w = cx.w();
+                    // The mods are downloaded before the call resolves so that they're started
+                    // before the game can join.
+                    let mut downloaded = Vec::new();
 
-            self.r(w)
-                .background
-                .spawn_responder(
-                    self.r(w).login_sockets.get(args.socket.raw)?.info(),
-                    move |_event_loop, app, res| {
-                        let init = app.init.as_mut().unwrap();
-
-                        init.store.run_wsl_root(&mut app.world, |cx| match res {
-                            Ok(info) => args.callback.call(
-                                cx,
-                                &Ok(abi::LoginServerInfo {
-                                    motd: &info.motd,
-                                    content_hash: abi::ContentHash(*info.content_hash.as_bytes()),
-                                    content_server: info.content_server.as_deref(),
-                                }),
-                            ),
-                            Err(err) => args.callback.call(cx, &Err(&err.to_string())),
-                        })?;
-
-                        Ok(())
-                    },
-                )
-                .detach();
+                    for entry in &info.mods {
+                        if known_mods.contains(&entry.content_hash) {
+                            continue;
+                        }
 
-            ret.finish(cx, &())
-        })?;
+                        let module = socket
+                            .download_module(entry.content_hash)
+                            .await
+                            .with_context(|| format!("failed to download mod `{}`", entry.name))?;
+
+                        downloaded.push((
+                            LoadedMod {
+                                name: entry.name.clone(),
+                                content_hash: entry.content_hash,
+                            },
+                            module,
+                        ));
+                    }
+
+                    anyhow::Ok((info, downloaded))
+                };
+
+                Ok(async move { (call, fut.await) })
+            },
+            move |cx, (call, res), ret| {
+                let w = cx.w();
+                let mods = self.r(w).mods;
+
+                let res = res.map(|(info, downloaded)| {
+                    for (info, module) in downloaded {
+                        mods.push_downloaded(info, module, w);
+                    }
+
+                    info
+                });
+
+                let outcome = match res {
+                    Ok(info) => Ok(ServerInfoInput {
+                        motd: info.motd,
+                        content_hash: *info.content_hash.as_bytes(),
+                        content_server: info.content_server,
+                        mods: info
+                            .mods
+                            .into_iter()
+                            .map(|info| ModInfoInput {
+                                name: info.name,
+                                content_hash: *info.content_hash.as_bytes(),
+                            })
+                            .collect(),
+                    }),
+                    Err(err) => Err(GuestError::new(&err)),
+                };
+
+                let outcome = NetOutcome::Info(outcome);
+                self.record_outcome(call, &outcome, cx.w())?;
+
+                finish_net_call(cx, outcome, ret)
+            },
+        )?;
 
         linker.define_wsl(abi::LOGIN_SOCKET_GET_RTT, move |cx, args, ret| {
             let w = cx.w();
 
             let rtt = self.r(w).login_sockets.get(args.raw)?.rtt();
+            self.r(w).session.record(SessionInput::Rtt(rtt), w)?;
 
             ret.finish(cx, &rtt)
         })?;
@@ -135,112 +228,270 @@ impl NetworkBindingsHandle {
             ret.finish(cx, &())
         })?;
 
-        linker.define_wsl(abi::LOGIN_SOCKET_PLAY, move |cx, args, ret| {
-            let w = cx.w();
+        linker.define_wsl_async(
+            abi::LOGIN_SOCKET_PLAY,
+            move |cx, args| {
+                let w = cx.w();
+                let call = self.r(w).session.next_net_call(w);
+                let fut = self
+                    .r(w)
+                    .login_sockets
+                    .get(args.socket.raw)?
+                    .play(blake3::Hash::from_bytes(args.content_hash.0));
+
+                Ok(async move { (call, fut.await) })
+            },
+            move |cx, (call, res), ret| {
+                let w = cx.w();
+
+                let outcome = match res {
+                    Ok(Ok(socket)) => {
+                        let socket = GameSocketBindState {
+                            socket,
+                            sending_msg: Arc::default(),
+                        }
+                        .spawn(w);
 
-            self.r(w)
-                .background
-                .spawn_responder(
-                    self.r(w)
-                        .login_sockets
-                        .get(args.socket.raw)?
-                        .play(blake3::Hash::from_bytes(args.content_hash.0)),
-                    move |_event_loop, app, res| {
-                        let init = app.init.as_mut().unwrap();
-
-                        init.store
-                            .run_wsl_root::<anyhow::Result<()>>(&mut app.world, |cx| match res {
-                                Ok(Ok(socket)) => {
-                                    let socket = GameSocketBindState {
-                                        socket,
-                                        send_msg_task: None,
-                                    }
-                                    .spawn(cx.w());
-
-                                    let socket = self.m(cx.w()).game_sockets.add(socket)?;
-
-                                    args.callback
-                                        .call(cx, &Ok(Ok(abi::GameSocketHandle { raw: socket })))?;
-
-                                    Ok(())
-                                }
-                                Ok(Err(content_hash)) => {
-                                    args.callback.call(
-                                        cx,
-                                        &Ok(Err(abi::ContentHash(*content_hash.as_bytes()))),
-                                    )?;
-
-                                    Ok(())
-                                }
-                                Err(err) => {
-                                    args.callback.call(cx, &Err(&err.to_string()))?;
-
-                                    Ok(())
-                                }
-                            })?;
-
-                        Ok(())
-                    },
-                )
-                .detach();
+                        Ok(Ok(self.m(w).game_sockets.add(socket)?))
+                    }
+                    Ok(Err(content_hash)) => Ok(Err(*content_hash.as_bytes())),
+                    Err(err) => Err(GuestError::new(&err)),
+                };
 
-            ret.finish(cx, &())
-        })?;
+                let outcome = NetOutcome::Play(outcome);
+                self.record_outcome(call, &outcome, w)?;
+
+                finish_net_call(cx, outcome, ret)
+            },
+        )?;
 
         linker.define_wsl(abi::GAME_SOCKET_GET_ID, move |cx, args, ret| {
             let w = cx.w();
             let socket = self.r(w).game_sockets.get(args.raw)?.as_weak();
             let id = socket.r(w).socket.id();
 
+            self.r(w).session.record(SessionInput::SocketId(id), w)?;
+
             ret.finish(cx, &id)
         })?;
 
-        linker.define_wsl(abi::GAME_SOCKET_SEND_MSG, move |cx, args, ret| {
-            let w = cx.w();
-            let socket = self.r(w).game_sockets.get(args.socket.raw)?.as_weak();
+        linker.define_wsl_async(
+            abi::GAME_SOCKET_SEND_MSG,
+            move |cx, args| {
+                let w = cx.w();
+                let call = self.r(w).session.next_net_call(w);
+                let socket = self.r(w).game_sockets.get(args.socket.raw)?.as_weak();
 
-            if socket.r(w).send_msg_task.is_some() {
-                anyhow::bail!("cannot send multiple messages from the same socket simultaneously");
-            }
+                if socket.r(w).sending_msg.swap(true, Relaxed) {
+                    anyhow::bail!(
+                        "cannot send multiple messages from the same socket simultaneously"
+                    );
+                }
+
+                // Cleared once the message is sent or the guest cancels the send.
+                let sending_msg =
+                    scopeguard::guard(socket.r(w).sending_msg.clone(), |v| v.store(false, Relaxed));
 
-            socket.m(w).send_msg_task = Some(self.r(w).background.spawn_responder(
-                socket.r(w).socket.send_msg(args.message),
-                move |_event_loop, app, res| {
-                    socket.m(&mut app.world).send_msg_task = None;
+                let fut = socket.r(w).socket.send_msg(args.message);
+
+                Ok(async move {
+                    let _sending_msg = sending_msg;
+                    (call, fut.await)
+                })
+            },
+            move |cx, (call, res), ret| {
+                let outcome = NetOutcome::SendMsg(res.map_err(|err| GuestError::new(&err)));
+                self.record_outcome(call, &outcome, cx.w())?;
 
-                    let init = app.init.as_mut().unwrap();
+                finish_net_call(cx, outcome, ret)
+            },
+        )?;
 
-                    init.store
-                        .run_wsl_root::<anyhow::Result<()>>(&mut app.world, |cx| match res {
-                            Ok(()) => args.callback.call(cx, &Ok(())),
-                            Err(err) => args.callback.call(cx, &Err(&err.to_string())),
-                        })
-                },
-            ));
+        linker.define_wsl(abi::GAME_SOCKET_CLOSE, move |cx, args, ret| {
+            self.m(cx.w()).game_sockets.remove(args.raw)?;
 
             ret.finish(cx, &())
         })?;
 
-        linker.define_wsl(abi::GAME_SOCKET_CANCEL_SEND_MSG, move |cx, args, ret| {
-            let w = cx.w();
-            let socket = self.r(w).game_sockets.get(args.raw)?.as_weak();
-            let task = socket
-                .m(w)
-                .send_msg_task
-                .take()
-                .context("cannot cancel future which is not running")?;
-
-            drop(task);
+        Ok(())
+    }
+}
 
-            ret.finish(cx, &())
+/// Installs network ports which play back the outcomes of a recorded session rather than
+/// connecting to anything.
+pub fn install_replay(session: InputSessionHandle, linker: &mut WslLinker) -> anyhow::Result<()> {
+    linker.define_wsl_async(
+        abi::LOGIN_SOCKET_CONNECT,
+        move |cx, _addr| Ok(replayed_net_call(session, cx)),
+        |cx, res, ret| finish_net_call(cx, res?, ret),
+    )?;
+
+    linker.define_wsl_async(
+        abi::LOGIN_SOCKET_GET_INFO,
+        move |cx, _socket| Ok(replayed_net_call(session, cx)),
+        |cx, res, ret| finish_net_call(cx, res?, ret),
+    )?;
+
+    linker.define_wsl(abi::LOGIN_SOCKET_GET_RTT, move |cx, _socket, ret| {
+        let rtt = session.replayed(cx.w(), |input| match input {
+            SessionInput::Rtt(rtt) => Some(rtt),
+            _ => None,
         })?;
 
-        linker.define_wsl(abi::GAME_SOCKET_CLOSE, move |cx, args, ret| {
-            self.m(cx.w()).game_sockets.remove(args.raw)?;
+        ret.finish(cx, &rtt)
+    })?;
 
-            ret.finish(cx, &())
+    linker.define_wsl(abi::LOGIN_SOCKET_CLOSE, |cx, _socket, ret| {
+        ret.finish(cx, &())
+    })?;
+
+    linker.define_wsl_async(
+        abi::LOGIN_SOCKET_PLAY,
+        move |cx, _args| Ok(replayed_net_call(session, cx)),
+        |cx, res, ret| finish_net_call(cx, res?, ret),
+    )?;
+
+    linker.define_wsl(abi::GAME_SOCKET_GET_ID, move |cx, _socket, ret| {
+        let id = session.replayed(cx.w(), |input| match input {
+            SessionInput::SocketId(id) => Some(id),
+            _ => None,
         })?;
 
-        Ok(())
+        ret.finish(cx, &id)
+    })?;
+
+    linker.define_wsl_async(
+        abi::GAME_SOCKET_SEND_MSG,
+        move |cx, _args| Ok(replayed_net_call(session, cx)),
+        |cx, res, ret| finish_net_call(cx, res?, ret),
+    )?;
+
+    linker.define_wsl(abi::GAME_SOCKET_CLOSE, |cx, _socket, ret| {
+        ret.finish(cx, &())
+    })?;
+
+    Ok(())
+}
+
+fn replayed_net_call(
+    session: InputSessionHandle,
+    cx: &mut WslContext<'_>,
+) -> impl 'static + Future<Output = Result<NetOutcome, PromiseCrashed>> {
+    let w = cx.w();
+    let call = session.next_net_call(w);
+
+    session.replayed_net_outcome(call, w)
+}
+
+/// Delivers the outcome of an async network call to the guest.
+pub trait NetCallOutput: Marshal {
+    fn finish<'t>(
+        cx: &mut WslContext<'_>,
+        outcome: NetOutcome,
+        ret: AsyncReturner<'t, Self>,
+    ) -> anyhow::Result<RetVal<'t>>;
+}
+
+pub fn finish_net_call<'t, O: NetCallOutput>(
+    cx: &mut WslContext<'_>,
+    outcome: NetOutcome,
+    ret: AsyncReturner<'t, O>,
+) -> anyhow::Result<RetVal<'t>> {
+    O::finish(cx, outcome, ret)
+}
+
+fn unexpected_outcome(outcome: NetOutcome) -> anyhow::Error {
+    anyhow::anyhow!("replay diverged: the recorded network call resolved to {outcome:?}")
+}
+
+impl NetCallOutput for Result<abi::LoginSocketHandle, abi::AbiError> {
+    fn finish<'t>(
+        cx: &mut WslContext<'_>,
+        outcome: NetOutcome,
+        ret: AsyncReturner<'t, Self>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        match outcome {
+            NetOutcome::Connect(Ok(raw)) => ret.finish(cx, &Ok(abi::LoginSocketHandle { raw })),
+            NetOutcome::Connect(Err(err)) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            outcome => Err(unexpected_outcome(outcome)),
+        }
+    }
+}
+
+impl NetCallOutput for Result<abi::LoginServerInfo, abi::AbiError> {
+    fn finish<'t>(
+        cx: &mut WslContext<'_>,
+        outcome: NetOutcome,
+        ret: AsyncReturner<'t, Self>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        match outcome {
+            NetOutcome::Info(Ok(info)) => {
+                let mods = info
+                    .mods
+                    .iter()
+                    .map(|info| abi::ModInfo {
+                        name: info.name.as_str(),
+                        content_hash: abi::ContentHash(info.content_hash),
+                    })
+                    .collect::<Vec<_>>();
+
+                ret.finish(
+                    cx,
+                    &Ok(abi::LoginServerInfo {
+                        motd: &info.motd,
+                        content_hash: abi::ContentHash(info.content_hash),
+                        content_server: info.content_server.as_deref(),
+                        mods: &mods,
+                    }),
+                )
+            }
+            NetOutcome::Info(Err(err)) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            outcome => Err(unexpected_outcome(outcome)),
+        }
+    }
+}
+
+impl NetCallOutput for Result<Result<abi::GameSocketHandle, abi::ContentHash>, abi::AbiError> {
+    fn finish<'t>(
+        cx: &mut WslContext<'_>,
+        outcome: NetOutcome,
+        ret: AsyncReturner<'t, Self>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        match outcome {
+            NetOutcome::Play(Ok(Ok(raw))) => ret.finish(cx, &Ok(Ok(abi::GameSocketHandle { raw }))),
+            NetOutcome::Play(Ok(Err(content_hash))) => {
+                ret.finish(cx, &Ok(Err(abi::ContentHash(content_hash))))
+            }
+            NetOutcome::Play(Err(err)) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            outcome => Err(unexpected_outcome(outcome)),
+        }
+    }
+}
+
+impl NetCallOutput for Result<(), abi::AbiError> {
+    fn finish<'t>(
+        cx: &mut WslContext<'_>,
+        outcome: NetOutcome,
+        ret: AsyncReturner<'t, Self>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        match outcome {
+            NetOutcome::SendMsg(Ok(())) => ret.finish(cx, &Ok(())),
+            NetOutcome::SendMsg(Err(err)) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            outcome => Err(unexpected_outcome(outcome)),
+        }
+    }
+}
+
+impl NetCallOutput for Result<Option<String>, abi::AbiError> {
+    fn finish<'t>(
+        cx: &mut WslContext<'_>,
+        outcome: NetOutcome,
+        ret: AsyncReturner<'t, Self>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        match outcome {
+            NetOutcome::ClipboardRead(Ok(text)) => ret.finish(cx, &Ok(text.as_deref())),
+            NetOutcome::ClipboardRead(Err(err)) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            outcome => Err(unexpected_outcome(outcome)),
+        }
     }
 }
@@ -0,0 +1,69 @@
+use arid::{Handle, Strong, W, Wr};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_abi as abi;
+use wasmlink::HostClosure;
+
+use crate::wsl::{WslContext, WslLinker, WslLinkerExt};
+
+#[derive(Debug, Default)]
+pub struct ReloadBindings {
+    save_handler: Option<HostClosure<()>>,
+    saved_state: Option<Vec<u8>>,
+}
+
+component!(pub ReloadBindings);
+
+impl ReloadBindingsHandle {
+    pub fn new(owner: EntityHandle, w: W) -> Strong<Self> {
+        ReloadBindings::default().attach(owner, w)
+    }
+
+    pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
+        linker.define_wsl(abi::HOT_RELOAD_BIND_HANDLER, move |cx, handler, ret| {
+            self.m(cx.w()).save_handler = Some(handler);
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::HOT_RELOAD_SAVE_STATE, move |cx, state, ret| {
+            let state = state.read(cx)?.to_vec();
+
+            self.m(cx.w()).saved_state = Some(state);
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::HOT_RELOAD_TAKE_STATE, move |cx, (), ret| {
+            let state = self.m(cx.w()).saved_state.take();
+
+            ret.finish(cx, &state.as_deref())
+        })?;
+
+        Ok(())
+    }
+
+    /// The state saved by the last replaced instance which hasn't been taken yet.
+    pub fn saved_state(self, w: Wr) -> Option<Vec<u8>> {
+        self.r(w).saved_state.clone()
+    }
+
+    /// Restores state saved earlier, as when a replacement failed to start and the replaced
+    /// instance is started again.
+    pub fn set_saved_state(self, state: Option<Vec<u8>>, w: W) {
+        self.m(w).saved_state = state;
+    }
+
+    /// Asks the instance which is about to be replaced to save the state it wants handed over to
+    /// its replacement. The instance's handler is unbound either way.
+    pub fn save_state(self, cx: &mut WslContext<'_>) -> anyhow::Result<()> {
+        let me = self.m(cx.w());
+
+        me.saved_state = None;
+
+        let Some(handler) = me.save_handler.take() else {
+            return Ok(());
+        };
+
+        handler.call(cx, &())
+    }
+}
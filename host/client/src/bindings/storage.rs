@@ -0,0 +1,218 @@
+use arid::{Handle, Strong, W};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_abi as abi;
+
+use crate::{
+    bindings::error::GuestError,
+    services::{
+        permissions::PermissionsHandle,
+        session::{InputSessionHandle, SessionInput, StorageOutcome},
+        storage::GameStorage,
+    },
+    wsl::{WslLinker, WslLinkerExt},
+};
+
+/// Gives the game access to its [`GameStorage`]. Outcomes are recorded so that replays never touch
+/// the disk.
+#[derive(Debug)]
+pub struct StorageBindings {
+    session: InputSessionHandle,
+    permissions: PermissionsHandle,
+    /// The storage opened for the granted id, kept so that its usage is only measured once.
+    storage: Option<(String, GameStorage)>,
+}
+
+component!(pub StorageBindings);
+
+impl StorageBindingsHandle {
+    pub fn new(
+        owner: EntityHandle,
+        session: InputSessionHandle,
+        permissions: PermissionsHandle,
+        w: W,
+    ) -> Strong<Self> {
+        StorageBindings {
+            session,
+            permissions,
+            storage: None,
+        }
+        .attach(owner, w)
+    }
+
+    fn storage(self, w: W) -> anyhow::Result<GameStorage> {
+        let id = self.r(w).permissions.storage_id(w)?;
+
+        if let Some((open_id, storage)) = &self.r(w).storage
+            && *open_id == id
+        {
+            return Ok(storage.clone());
+        }
+
+        let storage = GameStorage::open(&id)?;
+        self.m(w).storage = Some((id, storage.clone()));
+
+        Ok(storage)
+    }
+
+    /// Performs a synchronous storage call, taking its outcome from the recording while replaying.
+    fn requested<T: Clone>(
+        self,
+        w: W,
+        live: impl FnOnce(&GameStorage) -> anyhow::Result<T>,
+        wrap: impl FnOnce(Result<T, GuestError>) -> StorageOutcome,
+        unwrap: impl FnOnce(StorageOutcome) -> Option<Result<T, GuestError>>,
+    ) -> anyhow::Result<Result<T, GuestError>> {
+        let session = self.r(w).session;
+
+        session.requested(
+            w,
+            |w| {
+                Ok(self
+                    .storage(w)
+                    .and_then(|storage| live(&storage))
+                    .map_err(|err| GuestError::new(&err)))
+            },
+            |res| SessionInput::Storage(wrap(res)),
+            |input| match input {
+                SessionInput::Storage(outcome) => unwrap(outcome),
+                _ => None,
+            },
+        )
+    }
+
+    pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
+        linker.define_wsl_async(
+            abi::STORAGE_READ,
+            move |cx, key| {
+                let key = key.read(cx)?.to_string();
+
+                let w = cx.w();
+                let session = self.r(w).session;
+                let call = session.next_storage_call(w);
+
+                let source = if session.is_replaying(w) {
+                    Err(session.replayed_storage_outcome(call, w))
+                } else {
+                    Ok(self.storage(w))
+                };
+
+                Ok(async move {
+                    let outcome = match source {
+                        Ok(storage) => {
+                            let data = match storage {
+                                Ok(storage) => smol::unblock(move || storage.read(&key)).await,
+                                Err(err) => Err(err),
+                            };
+
+                            Ok(StorageOutcome::Read(
+                                data.map_err(|err| GuestError::new(&err)),
+                            ))
+                        }
+                        Err(replayed) => replayed.await,
+                    };
+
+                    (call, outcome)
+                })
+            },
+            move |cx, (call, outcome), ret| {
+                let outcome = outcome?;
+                let w = cx.w();
+
+                self.r(w).session.record(
+                    SessionInput::StorageCall {
+                        call,
+                        outcome: outcome.clone(),
+                    },
+                    w,
+                )?;
+
+                match outcome {
+                    StorageOutcome::Read(Ok(data)) => ret.finish(cx, &Ok(data.as_deref())),
+                    StorageOutcome::Read(Err(err)) => {
+                        err.with_view(|err| ret.finish(cx, &Err(err)))
+                    }
+                    outcome => anyhow::bail!(
+                        "replay diverged: the recorded storage call resolved to {outcome:?}"
+                    ),
+                }
+            },
+        )?;
+
+        linker.define_wsl(abi::STORAGE_WRITE, move |cx, args, ret| {
+            let key = args.key.read(cx)?.to_string();
+            let data = args.data.read(cx)?.to_vec();
+
+            let res = self.requested(
+                cx.w(),
+                |storage| storage.write(&key, &data),
+                StorageOutcome::Write,
+                |outcome| match outcome {
+                    StorageOutcome::Write(res) => Some(res),
+                    _ => None,
+                },
+            )?;
+
+            match res {
+                Ok(()) => ret.finish(cx, &Ok(())),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::STORAGE_DELETE, move |cx, key, ret| {
+            let key = key.read(cx)?.to_string();
+
+            let res = self.requested(
+                cx.w(),
+                |storage| storage.delete(&key),
+                StorageOutcome::Delete,
+                |outcome| match outcome {
+                    StorageOutcome::Delete(res) => Some(res),
+                    _ => None,
+                },
+            )?;
+
+            match res {
+                Ok(()) => ret.finish(cx, &Ok(())),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::STORAGE_LIST, move |cx, (), ret| {
+            let res =
+                self.requested(cx.w(), GameStorage::list, StorageOutcome::List, |outcome| {
+                    match outcome {
+                        StorageOutcome::List(res) => Some(res),
+                        _ => None,
+                    }
+                })?;
+
+            match res {
+                Ok(keys) => {
+                    let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+
+                    ret.finish(cx, &Ok(keys.as_slice()))
+                }
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::STORAGE_GET_USAGE, move |cx, (), ret| {
+            let res = self.requested(
+                cx.w(),
+                |storage| Ok((storage.usage()?, storage.quota())),
+                StorageOutcome::Usage,
+                |outcome| match outcome {
+                    StorageOutcome::Usage(res) => Some(res),
+                    _ => None,
+                },
+            )?;
+
+            match res {
+                Ok((used, quota)) => ret.finish(cx, &Ok(abi::StorageUsage { used, quota })),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        Ok(())
+    }
+}
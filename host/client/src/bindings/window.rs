@@ -0,0 +1,211 @@
+use arid::{Handle, Strong, W, Wr};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_abi::{self as abi, Capability};
+use winit::{dpi::PhysicalSize, window::Icon};
+
+use crate::{
+    bindings::{error::GuestError, gfx::GfxBindingsHandle},
+    services::{
+        permissions::PermissionsHandle,
+        session::{InputSessionHandle, SessionInput},
+        window::WindowStateHandle,
+    },
+    wsl::{WslLinker, WslLinkerExt},
+};
+
+/// Lets the game manage its windows. Headless sessions have no windows so they ignore the game's
+/// requests and answer its queries from the recording.
+#[derive(Debug)]
+pub struct WindowBindings {
+    session: InputSessionHandle,
+    permissions: PermissionsHandle,
+    gfx: GfxBindingsHandle,
+}
+
+component!(pub WindowBindings);
+
+impl WindowBindingsHandle {
+    pub fn new(
+        owner: EntityHandle,
+        session: InputSessionHandle,
+        permissions: PermissionsHandle,
+        gfx: GfxBindingsHandle,
+        w: W,
+    ) -> Strong<Self> {
+        WindowBindings {
+            session,
+            permissions,
+            gfx,
+        }
+        .attach(owner, w)
+    }
+
+    /// The window shown for the guest window `handle`, if any.
+    fn window(self, handle: abi::WindowHandle, w: Wr) -> anyhow::Result<Option<WindowStateHandle>> {
+        self.r(w).gfx.window_state(handle.raw, w)
+    }
+
+    pub fn install(self, linker: &mut WslLinker) -> anyhow::Result<()> {
+        linker.define_wsl(abi::WINDOW_SET_TITLE, move |cx, args, ret| {
+            let title = args.title.read(cx)?.to_string();
+            let w = cx.w();
+
+            if let Some(window) = self.window(args.window, w)? {
+                window.window(w).set_title(&title);
+            }
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_SET_INNER_SIZE, move |cx, args, ret| {
+            let w = cx.w();
+
+            if let Some(window) = self.window(args.window, w)? {
+                _ = window
+                    .window(w)
+                    .request_inner_size(PhysicalSize::new(args.size.x, args.size.y));
+            }
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_SET_MIN_INNER_SIZE, move |cx, args, ret| {
+            let w = cx.w();
+
+            if let Some(window) = self.window(args.window, w)? {
+                window
+                    .window(w)
+                    .set_min_inner_size(args.size.map(|size| PhysicalSize::new(size.x, size.y)));
+            }
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_SET_RESIZABLE, move |cx, args, ret| {
+            let w = cx.w();
+
+            if let Some(window) = self.window(args.window, w)? {
+                window.window(w).set_resizable(args.enabled);
+            }
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_SET_FULLSCREEN, move |cx, args, ret| {
+            let w = cx.w();
+            let session = self.r(w).session;
+            let mode = args.mode;
+            let window = self.window(args.window, w)?;
+
+            let res = session.requested(
+                w,
+                |w| {
+                    Ok(match mode {
+                        abi::FullscreenMode::Windowed => Ok(()),
+                        abi::FullscreenMode::Borderless | abi::FullscreenMode::Exclusive => self
+                            .r(w)
+                            .permissions
+                            .check(&Capability::Fullscreen, w)
+                            .map_err(|err| GuestError::new(&err.into())),
+                    })
+                },
+                SessionInput::Fullscreen,
+                |input| match input {
+                    SessionInput::Fullscreen(res) => Some(res),
+                    _ => None,
+                },
+            )?;
+
+            if res.is_ok()
+                && let Some(window) = window
+            {
+                window.set_fullscreen(mode, w);
+            }
+
+            match res {
+                Ok(()) => ret.finish(cx, &Ok(())),
+                Err(err) => err.with_view(|err| ret.finish(cx, &Err(err))),
+            }
+        })?;
+
+        linker.define_wsl(abi::WINDOW_SET_ICON, move |cx, args, ret| {
+            let (w, mem) = cx.world_and_memory();
+
+            // Icons are validated even without a window so that headless sessions reject the
+            // same icons as windowed ones.
+            let icon = match args.icon {
+                Some(icon) => {
+                    let rgba = icon
+                        .buffer
+                        .slice()
+                        .read(mem)?
+                        .iter()
+                        .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a])
+                        .collect::<Vec<_>>();
+
+                    Some(Icon::from_rgba(rgba, icon.size.x, icon.size.y)?)
+                }
+                None => None,
+            };
+
+            if let Some(window) = self.window(args.window, w)? {
+                window.window(w).set_window_icon(icon);
+            }
+
+            ret.finish(cx, &())
+        })?;
+
+        linker.define_wsl(abi::WINDOW_GET_INNER_SIZE, move |cx, window, ret| {
+            let w = cx.w();
+            let session = self.r(w).session;
+            let gfx = self.r(w).gfx;
+            let window = self.window(window, w)?;
+
+            let (width, height) = session.requested(
+                w,
+                |w| {
+                    Ok(match window {
+                        Some(window) => {
+                            let size = window.window(w).inner_size();
+                            (size.width, size.height)
+                        }
+                        None => gfx.offscreen_size(w),
+                    })
+                },
+                |(width, height)| SessionInput::InnerSize { width, height },
+                |input| match input {
+                    SessionInput::InnerSize { width, height } => Some((width, height)),
+                    _ => None,
+                },
+            )?;
+
+            ret.finish(
+                cx,
+                &abi::UVec2 {
+                    x: width,
+                    y: height,
+                },
+            )
+        })?;
+
+        linker.define_wsl(abi::WINDOW_GET_SCALE_FACTOR, move |cx, window, ret| {
+            let w = cx.w();
+            let session = self.r(w).session;
+            let window = self.window(window, w)?;
+
+            let scale_factor = session.requested(
+                w,
+                |w| Ok(window.map_or(1., |window| window.window(w).scale_factor())),
+                SessionInput::ScaleFactor,
+                |input| match input {
+                    SessionInput::ScaleFactor(scale_factor) => Some(scale_factor),
+                    _ => None,
+                },
+            )?;
+
+            ret.finish(cx, &scale_factor)
+        })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,629 @@
+use std::{
+    collections::BTreeSet,
+    fs, future,
+    path::{Path, PathBuf},
+    task::Poll,
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use arid::World;
+use arid_entity::EntityHandle;
+use crucible_abi::Capability;
+use crucible_host_shared::lang::BackgroundTasksExecutor;
+use serde::de::{DeserializeOwned, IntoDeserializer as _, value::StrDeserializer};
+use winit::keyboard::KeyCode;
+
+use crate::{
+    app::{App, BackgroundTasks, env_tracer, load_module},
+    services::{
+        audio::{Audio, OFFLINE_SAMPLE_RATE},
+        clipboard::MemoryClipboard,
+        gamepad::{Gamepads, VirtualGamepads},
+        permissions::{PermissionsHandle, read_manifest},
+        session::{
+            GamepadAxisInput, GamepadButtonInput, ImeInput, InputSessionHandle, KeyInput,
+            LogicalKeyInput, MouseButtonInput, NativeKeyInput, ScrollDeltaInput, WindowInput,
+        },
+        window::create_headless_gfx_context,
+    },
+    wsl::WslEngine,
+};
+
+/// Runs the module without a window, feeding it the inputs listed in the script at `script_path`.
+/// Frames are drawn to offscreen framebuffers and the guest sees a virtual clock which advances by
+/// one frame at a time so runs don't depend on how fast the machine is.
+pub fn main_headless(module_path: &Path, script_path: &Path) -> anyhow::Result<()> {
+    let script = fs::read_to_string(script_path)
+        .with_context(|| format!("failed to read script at `{}`", script_path.display()))?;
+
+    let script = Script::parse(&script)
+        .with_context(|| format!("failed to parse script at `{}`", script_path.display()))?;
+
+    let module = fs::read(module_path)
+        .with_context(|| format!("failed to read module at `{}`", module_path.display()))?;
+
+    // Nobody is around to be asked for capabilities so the game gets exactly those the script
+    // grants.
+    let manifest = read_manifest(&module)?;
+
+    for capability in &script.grants {
+        if !manifest.capabilities.contains(capability) {
+            tracing::warn!("The script grants `{capability}` but the game never asks for it.");
+        }
+    }
+
+    let granted = script
+        .grants
+        .iter()
+        .filter(|capability| manifest.capabilities.contains(capability))
+        .cloned()
+        .collect();
+
+    let mut world = World::new();
+
+    let root = EntityHandle::new(None, &mut world);
+    root.set_label("root", &mut world);
+
+    let engine = WslEngine::default();
+    let module = load_module(&engine, &module)?;
+
+    let session = InputSessionHandle::live(root.as_weak(), &mut world);
+    let permissions = PermissionsHandle::new(root.as_weak(), granted, &mut world);
+
+    let mut app = App {
+        world,
+        root,
+        engine,
+        module,
+        module_path: module_path.to_path_buf(),
+        session,
+        permissions,
+        tracer: env_tracer()?,
+        watcher: None,
+        gamepads: Gamepads::new_virtual(VirtualGamepads::default()),
+        mods: Vec::new(),
+        virtual_clock: true,
+        init: None,
+    };
+
+    let mut background = BackgroundTasks::new().executor(future::pending::<anyhow::Result<()>>());
+
+    tracing::info!("Starting headless session.");
+
+    let res = smol::block_on(async {
+        let gfx = create_headless_gfx_context().await?;
+
+        app.start(
+            gfx,
+            None,
+            Audio::offline(OFFLINE_SAMPLE_RATE),
+            Box::new(MemoryClipboard::default()),
+            background.handle(),
+        )?;
+
+        let mut driver = Driver {
+            app: &mut app,
+            background: &mut background,
+            size: script.size,
+            frame_time: 1. / script.fps as f64,
+            drawn: BTreeSet::new(),
+            windows: Vec::new(),
+            target: 0,
+            audio_frames: 0,
+            audio_out: script.audio_path.as_ref().map(|_| Vec::new()),
+        };
+
+        driver.run(&script.steps).await?;
+
+        anyhow::Ok(driver.audio_out)
+    });
+
+    let finished = app.finish();
+    let audio_out = res?;
+    finished?;
+
+    if let (Some(path), Some(audio_out)) = (&script.audio_path, audio_out) {
+        write_wav(path, &audio_out)
+            .with_context(|| format!("failed to write audio to `{}`", path.display()))?;
+    }
+
+    tracing::info!("Headless session finished.");
+
+    Ok(())
+}
+
+fn write_wav(path: &Path, samples: &[f32]) -> anyhow::Result<()> {
+    let mut writer = hound::WavWriter::create(
+        path,
+        hound::WavSpec {
+            channels: 2,
+            sample_rate: OFFLINE_SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        },
+    )?;
+
+    for &sample in samples {
+        writer.write_sample(sample)?;
+    }
+
+    writer.finalize()?;
+
+    Ok(())
+}
+
+// === Driver === //
+
+struct Driver<'a> {
+    app: &'a mut App,
+    background: &'a mut BackgroundTasksExecutor<(), App, ()>,
+    size: (u32, u32),
+    /// The number of seconds the virtual clock advances by every frame.
+    frame_time: f64,
+    /// The guest windows which have been drawn since their handlers were bound.
+    drawn: BTreeSet<u32>,
+    /// The guest windows the game opened, starting with the main window, in the order they were
+    /// opened.
+    windows: Vec<u32>,
+    /// The index into `windows` of the window receiving window inputs.
+    target: usize,
+    /// The number of audio frames mixed so far.
+    audio_frames: u64,
+    /// The interleaved stereo samples mixed so far, if the script asks for them.
+    audio_out: Option<Vec<f32>>,
+}
+
+impl Driver<'_> {
+    async fn run(&mut self, steps: &[Step]) -> anyhow::Result<()> {
+        self.set_size(self.size);
+
+        let init = self.app.init.as_ref().unwrap();
+        self.windows
+            .push(init.gfx_bindings.main_window(&self.app.world));
+
+        for step in steps {
+            if self.has_exited() {
+                tracing::info!("The game closed its main window before the script ended.");
+                break;
+            }
+
+            match step {
+                Step::Frames(count) => {
+                    for _ in 0..*count {
+                        self.frame()?;
+
+                        if self.has_exited() {
+                            break;
+                        }
+                    }
+                }
+                Step::Sleep(duration) => self.sleep(*duration).await?,
+                Step::Resize { width, height } => {
+                    self.set_size((*width, *height));
+                    self.deliver(WindowInput::Resized {
+                        width: *width,
+                        height: *height,
+                    })?;
+
+                    // Shown windows are redrawn after being resized.
+                    self.drawn.clear();
+                }
+                Step::Window(input) => self.deliver(input.clone())?,
+                Step::Motion { x, y } => self.app.deliver_mouse_motion(*x, *y)?,
+                Step::Target(index) => {
+                    anyhow::ensure!(
+                        *index < self.windows.len(),
+                        "the game opened no window with index {index}"
+                    );
+
+                    self.target = *index;
+                }
+                Step::Gamepad(step) => {
+                    let pads = self.app.gamepads.virtual_pads().unwrap();
+
+                    match step {
+                        GamepadStep::Connect(name) => {
+                            pads.connect(name.clone());
+                        }
+                        GamepadStep::Disconnect(id) => pads.disconnect(*id),
+                        GamepadStep::Button {
+                            id,
+                            button,
+                            pressed,
+                        } => pads.set_button(*id, *button, *pressed),
+                        GamepadStep::Axis { id, axis, value } => pads.set_axis(*id, *axis, *value),
+                    }
+                }
+                Step::Reload(path) => {
+                    let module = fs::read(path).with_context(|| {
+                        format!("failed to read module at `{}`", path.display())
+                    })?;
+
+                    self.app.reload(&module, self.background.handle())?;
+
+                    // A reloaded instance binds its windows anew.
+                    self.drawn.clear();
+                }
+            }
+
+            self.app.world.flush();
+        }
+
+        Ok(())
+    }
+
+    fn has_exited(&self) -> bool {
+        let init = self.app.init.as_ref().unwrap();
+
+        init.gfx_bindings.main_callbacks(&self.app.world).is_none()
+    }
+
+    fn set_size(&mut self, (width, height): (u32, u32)) {
+        let init = self.app.init.as_ref().unwrap();
+
+        self.size = (width, height);
+        init.gfx_bindings
+            .set_offscreen_size(width, height, &mut self.app.world);
+    }
+
+    /// Delivers `input` to the targeted window. Inputs sent while its handlers are unbound are
+    /// dropped.
+    fn deliver(&mut self, input: WindowInput) -> anyhow::Result<()> {
+        let init = self.app.init.as_ref().unwrap();
+        let handle = self.windows[self.target];
+
+        if let WindowInput::Focused(focused) = input {
+            init.gfx_bindings
+                .set_focused(handle, focused, &mut self.app.world);
+        }
+
+        let Some(cbs) = init.gfx_bindings.callbacks(handle, &self.app.world) else {
+            return Ok(());
+        };
+
+        self.app.deliver_window_input(handle, cbs, input, None)
+    }
+
+    /// Advances the virtual clock by a frame, runs the work done by the event loop of a windowed
+    /// session and draws the windows which need it.
+    fn frame(&mut self) -> anyhow::Result<()> {
+        let init = self.app.init.as_ref().unwrap();
+        init.env_bindings
+            .advance_clock(self.frame_time, &mut self.app.world);
+
+        self.app.poll_timeouts()?;
+        self.app.update(self.background.handle())?;
+
+        // Windows opened by the game are drawn offscreen like the main window.
+        let opened = self.app.open_guest_windows(|_, _, _| Ok(None));
+        self.windows.extend(opened);
+
+        self.poll_background()?;
+        self.redraw()?;
+        self.mix_audio();
+
+        self.app.world.flush();
+
+        Ok(())
+    }
+
+    /// Runs the background tasks which are ready without waiting for any others.
+    fn poll_background(&mut self) -> anyhow::Result<()> {
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+
+        match self.background.poll(&(), self.app, &mut cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Ok(()),
+        }
+    }
+
+    /// Waits for `duration` in real time, letting the background tasks make progress. The virtual
+    /// clock is paused meanwhile.
+    async fn sleep(&mut self, duration: Duration) -> anyhow::Result<()> {
+        let timer = async {
+            smol::Timer::after(duration).await;
+            Ok(())
+        };
+
+        smol::future::or(timer, self.background.future(&(), self.app)).await
+    }
+
+    /// Draws the windows whose redraw was requested and those which were never drawn, much like a
+    /// window being shown for the first time.
+    fn redraw(&mut self) -> anyhow::Result<()> {
+        let w = &mut self.app.world;
+        let init = self.app.init.as_ref().unwrap();
+        let gfx_bindings = init.gfx_bindings.as_weak();
+        let window_mgr = init.window_mgr;
+
+        let bound = gfx_bindings.bound_windows(w);
+        self.drawn.retain(|handle| bound.contains(handle));
+
+        let mut pending = gfx_bindings
+            .take_redraw_requests(w)
+            .into_iter()
+            .map(|(handle, _)| handle)
+            .collect::<BTreeSet<_>>();
+
+        for handle in bound {
+            if self.drawn.insert(handle) {
+                pending.insert(handle);
+            }
+        }
+
+        let (width, height) = self.size;
+
+        for handle in pending {
+            let w = &mut self.app.world;
+
+            let Some(cbs) = gfx_bindings.callbacks(handle, w) else {
+                continue;
+            };
+
+            let texture = window_mgr.renderer_mut(w).create_texture(width, height);
+            let fb = gfx_bindings.create_texture(texture, None, w)?;
+
+            self.app.deliver_window_input(
+                handle,
+                cbs,
+                WindowInput::Redraw { width, height },
+                Some(fb),
+            )?;
+
+            window_mgr.submit(&mut self.app.world);
+        }
+
+        Ok(())
+    }
+
+    /// Mixes the audio played up until the current virtual time.
+    fn mix_audio(&mut self) {
+        let init = self.app.init.as_ref().unwrap();
+        let w = &self.app.world;
+
+        let now = init.env_bindings.current_time(w);
+        let target = (now * OFFLINE_SAMPLE_RATE as f64).round() as u64;
+        let frames = target.saturating_sub(self.audio_frames) as usize;
+
+        // The mix is rendered even if nobody listens to it so voices still finish playing.
+        let mut samples = vec![0.; frames * 2];
+        init.audio_bindings.audio(w).render_offline(&mut samples);
+
+        if let Some(audio_out) = &mut self.audio_out {
+            audio_out.extend(samples);
+        }
+
+        self.audio_frames = target;
+    }
+}
+
+// === Script === //
+
+/// A list of settings and steps, one per line. Blank lines and those starting with `#` are
+/// ignored.
+///
+/// Settings:
+///
+/// - `grant <capability>` grants a capability the game declares, written as in its manifest.
+/// - `size <width> <height>` sets the initial framebuffer size, defaulting to 1280 by 720.
+/// - `fps <count>` sets the number of frames per second of virtual time, defaulting to 60.
+/// - `audio <path>` writes everything the game plays to a WAV file.
+///
+/// Steps, run in order:
+///
+/// - `frames <count>` runs that many frames.
+/// - `sleep <seconds>` waits in real time without advancing the virtual clock, letting network
+///   calls complete.
+/// - `key press|release <code>` presses a key, named like `winit`'s `KeyCode` (e.g. `KeyA`).
+/// - `text <text>` commits the rest of the line as IME text.
+/// - `mouse move <x> <y>` and `mouse press|release <button>` (e.g. `Left`).
+/// - `scroll <x> <y>` scrolls by lines.
+/// - `focus true|false`, `resize <width> <height>` and `close`.
+/// - `motion <x> <y>` moves the mouse by a raw amount. Unlike other window inputs, it goes to the
+///   window with focus, if any.
+/// - `window <index>` sends the window inputs which follow to the `index`-th window the game
+///   opened, with the main window being the 0th. Windows open on the frame after the game asks for
+///   them.
+/// - `gamepad connect <name>` connects a gamepad. Gamepads are numbered from zero in the order
+///   they are connected.
+/// - `gamepad disconnect <id>`, `gamepad button <id> <button> press|release` and
+///   `gamepad axis <id> <axis> <value>`, with buttons and axes named like `South` and `LeftStickX`.
+/// - `reload <path>` hot reloads the game with the module at `path`, as if its file had changed.
+///
+/// Window inputs go to the main window unless another is selected and gamepad inputs are delivered
+/// on the next frame.
+#[derive(Debug)]
+struct Script {
+    grants: Vec<Capability>,
+    size: (u32, u32),
+    fps: u32,
+    audio_path: Option<PathBuf>,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug)]
+enum Step {
+    Frames(u32),
+    Sleep(Duration),
+    Resize { width: u32, height: u32 },
+    Window(WindowInput),
+    Motion { x: f64, y: f64 },
+    Target(usize),
+    Gamepad(GamepadStep),
+    Reload(PathBuf),
+}
+
+#[derive(Debug)]
+enum GamepadStep {
+    Connect(String),
+    Disconnect(u32),
+    Button {
+        id: u32,
+        button: GamepadButtonInput,
+        pressed: bool,
+    },
+    Axis {
+        id: u32,
+        axis: GamepadAxisInput,
+        value: f32,
+    },
+}
+
+impl Script {
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut script = Self {
+            grants: Vec::new(),
+            size: (1280, 720),
+            fps: 60,
+            audio_path: None,
+            steps: Vec::new(),
+        };
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            script
+                .parse_line(line)
+                .with_context(|| format!("on line {}", index + 1))?;
+        }
+
+        Ok(script)
+    }
+
+    fn parse_line(&mut self, line: &str) -> anyhow::Result<()> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        let step = match words.as_slice() {
+            ["grant", ..] => {
+                let capability = rest(line, 1).parse().map_err(anyhow::Error::msg)?;
+                self.grants.push(capability);
+                return Ok(());
+            }
+            ["size", width, height] => {
+                self.size = parse_size(width, height)?;
+                return Ok(());
+            }
+            ["fps", fps] => {
+                self.fps = parse_num(fps)?;
+                anyhow::ensure!(self.fps > 0, "the frame rate must be positive");
+                return Ok(());
+            }
+            ["audio", _, ..] => {
+                self.audio_path = Some(PathBuf::from(rest(line, 1)));
+                return Ok(());
+            }
+            ["frames", count] => Step::Frames(parse_num(count)?),
+            ["sleep", secs] => Step::Sleep(
+                Duration::try_from_secs_f64(parse_num(secs)?).context("invalid sleep duration")?,
+            ),
+            ["resize", width, height] => {
+                let (width, height) = parse_size(width, height)?;
+                Step::Resize { width, height }
+            }
+            ["key", action, code] => {
+                let code = parse_name::<KeyCode>("key", code)?;
+
+                Step::Window(WindowInput::Key(KeyInput {
+                    physical_key: Some(code as u32),
+                    logical_key: LogicalKeyInput::Unidentified(NativeKeyInput::Unidentified),
+                    text: None,
+                    location: 0,
+                    pressed: parse_action(action)?,
+                    repeat: false,
+                }))
+            }
+            ["text", _, ..] => Step::Window(WindowInput::Ime(ImeInput::Commit(
+                rest(line, 1).to_string(),
+            ))),
+            ["mouse", "move", x, y] => Step::Window(WindowInput::MouseMoved {
+                x: parse_num(x)?,
+                y: parse_num(y)?,
+            }),
+            ["mouse", action, button] => Step::Window(WindowInput::MouseInput {
+                button: parse_name::<MouseButtonInput>("mouse button", button)?,
+                pressed: parse_action(action)?,
+            }),
+            ["scroll", x, y] => Step::Window(WindowInput::MouseWheel(ScrollDeltaInput::Line {
+                x: parse_num(x)?,
+                y: parse_num(y)?,
+            })),
+            ["focus", focused] => Step::Window(WindowInput::Focused(parse_num(focused)?)),
+            ["close"] => Step::Window(WindowInput::CloseRequested),
+            ["motion", x, y] => Step::Motion {
+                x: parse_num(x)?,
+                y: parse_num(y)?,
+            },
+            ["window", index] => Step::Target(parse_num(index)?),
+            ["gamepad", "connect", _, ..] => {
+                Step::Gamepad(GamepadStep::Connect(rest(line, 2).to_string()))
+            }
+            ["gamepad", "disconnect", id] => Step::Gamepad(GamepadStep::Disconnect(parse_num(id)?)),
+            ["gamepad", "button", id, button, action] => Step::Gamepad(GamepadStep::Button {
+                id: parse_num(id)?,
+                button: parse_name("gamepad button", button)?,
+                pressed: parse_action(action)?,
+            }),
+            ["gamepad", "axis", id, axis, value] => Step::Gamepad(GamepadStep::Axis {
+                id: parse_num(id)?,
+                axis: parse_name("gamepad axis", axis)?,
+                value: parse_num(value)?,
+            }),
+            ["reload", _, ..] => Step::Reload(PathBuf::from(rest(line, 1))),
+            _ => anyhow::bail!("unknown command `{line}`"),
+        };
+
+        self.steps.push(step);
+
+        Ok(())
+    }
+}
+
+/// The rest of `line` after its first `words` words.
+fn rest(line: &str, words: usize) -> &str {
+    let mut rest = line;
+
+    for _ in 0..words {
+        rest = rest
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .map_or("", |(_, rest)| rest);
+    }
+
+    rest.trim()
+}
+
+fn parse_num<T>(word: &str) -> anyhow::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    word.parse()
+        .with_context(|| format!("invalid value `{word}`"))
+}
+
+fn parse_size(width: &str, height: &str) -> anyhow::Result<(u32, u32)> {
+    let size = (parse_num(width)?, parse_num(height)?);
+    anyhow::ensure!(size.0 > 0 && size.1 > 0, "sizes must be positive");
+
+    Ok(size)
+}
+
+fn parse_action(word: &str) -> anyhow::Result<bool> {
+    match word {
+        "press" => Ok(true),
+        "release" => Ok(false),
+        _ => anyhow::bail!("expected `press` or `release` but got `{word}`"),
+    }
+}
+
+/// Parses a unit variant of `T` by its name.
+fn parse_name<T: DeserializeOwned>(kind: &str, name: &str) -> anyhow::Result<T> {
+    let de: StrDeserializer<'_, serde::de::value::Error> = name.into_deserializer();
+
+    T::deserialize(de).map_err(|_| anyhow::anyhow!("unknown {kind} `{name}`"))
+}
@@ -7,9 +7,27 @@ use tracing_subscriber::EnvFilter;
 
 mod app;
 mod bindings;
+mod headless;
+mod replay;
 mod services;
+mod tests;
 mod utils;
 
+// The WASM runtime running the guest, selected through exactly one of the `wasmtime` and `wasmi`
+// features.
+#[cfg(all(feature = "wasmi", feature = "wasmtime"))]
+compile_error!(
+    "the `wasmi` and `wasmtime` features are mutually exclusive; build with \
+     `--no-default-features --features wasmi` to use `wasmi`"
+);
+#[cfg(not(any(feature = "wasmi", feature = "wasmtime")))]
+compile_error!("one of the `wasmi` and `wasmtime` features must be enabled");
+
+#[cfg(feature = "wasmi")]
+use wasmlink_wasmi as wsl;
+#[cfg(all(feature = "wasmtime", not(feature = "wasmi")))]
+use wasmlink_wasmtime as wsl;
+
 fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::fmt()
         .with_env_filter(
@@ -0,0 +1,224 @@
+use std::{
+    cell::RefCell,
+    fs, future, io,
+    path::Path,
+    rc::Rc,
+    task::{self, Poll},
+};
+
+use anyhow::Context as _;
+use arid::World;
+use arid_entity::EntityHandle;
+use crucible_host_shared::lang::BackgroundTasksExecutor;
+use wasmlink::{TraceReader, Tracer};
+
+use crate::{
+    app::{App, BackgroundTasks, load_module},
+    services::{
+        audio::{Audio, OFFLINE_SAMPLE_RATE},
+        clipboard::MemoryClipboard,
+        gamepad::{Gamepads, VirtualGamepads},
+        permissions::PermissionsHandle,
+        session::{
+            InputSessionHandle, SessionInput, WindowInput, find_divergence, read_recording,
+            recording_trace_path,
+        },
+        window::create_headless_gfx_context,
+    },
+    wsl::WslEngine,
+};
+
+/// Feeds the inputs of a recorded session back into a fresh instance of the module, without a
+/// window and with the clock the guest sees replaced by the recorded one. The host calls made by
+/// the replay are then checked against those made while recording.
+pub fn main_replay(module_path: &Path, recording_path: &Path) -> anyhow::Result<()> {
+    let module = fs::read(module_path)
+        .with_context(|| format!("failed to read module at `{}`", module_path.display()))?;
+
+    let inputs = read_recording(recording_path, blake3::hash(&module))?;
+
+    tracing::info!(
+        "Replaying {} inputs from `{}`.",
+        inputs.len(),
+        recording_path.display()
+    );
+
+    let mut world = World::new();
+
+    let root = EntityHandle::new(None, &mut world);
+    root.set_label("root", &mut world);
+
+    let engine = WslEngine::default();
+    let module = load_module(&engine, &module)?;
+
+    let session = InputSessionHandle::replaying(root.as_weak(), inputs, &mut world);
+
+    // Outcomes which depend on the granted capabilities come from the recording so the replay is
+    // granted nothing.
+    let permissions = PermissionsHandle::new(root.as_weak(), Vec::new(), &mut world);
+
+    let trace = TraceBuffer::default();
+
+    // Sessions with mods can't be recorded.
+    let mut app = App {
+        world,
+        root,
+        engine,
+        module,
+        module_path: module_path.to_path_buf(),
+        session,
+        permissions,
+        tracer: Some(Tracer::new(trace.clone())?),
+        watcher: None,
+        gamepads: Gamepads::new_virtual(VirtualGamepads::default()),
+        mods: Vec::new(),
+        virtual_clock: false,
+        init: None,
+    };
+
+    let mut background = BackgroundTasks::new().executor(future::pending::<anyhow::Result<()>>());
+
+    let res = smol::block_on(async {
+        let gfx = create_headless_gfx_context().await?;
+
+        app.start(
+            gfx,
+            None,
+            Audio::offline(OFFLINE_SAMPLE_RATE),
+            Box::new(MemoryClipboard::default()),
+            background.handle(),
+        )?;
+
+        Replay {
+            app: &mut app,
+            background: &mut background,
+        }
+        .run()
+    });
+
+    // Mirrors the report requested by a live session as it exits.
+    let finished = app.finish();
+
+    // Compare host calls
+    let recorded = TraceReader::open(recording_trace_path(recording_path))?;
+    let replayed = TraceReader::new(io::Cursor::new(trace.0.take()))?;
+
+    if let Some(divergence) = find_divergence(recorded, replayed)? {
+        if let Err(err) = &res {
+            tracing::error!("Replay failed: {err:?}");
+        }
+
+        anyhow::bail!("{divergence}");
+    }
+
+    res?;
+    finished?;
+
+    tracing::info!("Replay matched the recording.");
+
+    Ok(())
+}
+
+struct Replay<'a> {
+    app: &'a mut App,
+    background: &'a mut BackgroundTasksExecutor<(), App, ()>,
+}
+
+impl Replay<'_> {
+    fn run(&mut self) -> anyhow::Result<()> {
+        while let Some(input) = self.app.session.next_input(&mut self.app.world) {
+            self.feed(input)?;
+            self.app.update(self.background.handle())?;
+
+            // Windows are drawn offscreen and receive the recorded inputs.
+            self.app.open_guest_windows(|_, _, _| Ok(None));
+
+            self.poll_background()?;
+            self.app.world.flush();
+        }
+
+        Ok(())
+    }
+
+    fn feed(&mut self, input: SessionInput) -> anyhow::Result<()> {
+        let w = &mut self.app.world;
+        let init = self.app.init.as_mut().unwrap();
+
+        match input {
+            SessionInput::PollTimeouts { now } => {
+                init.store
+                    .run_wsl_root(w, |cx| init.env_bindings.poll_timeouts(cx, now))?;
+            }
+            SessionInput::Window { window, input } => {
+                let cbs = init.gfx_bindings.callbacks(window, w).context(
+                    "replay diverged: the guest window's handlers are unbound but the recording \
+                     has more events for it",
+                )?;
+
+                let fb = match &input {
+                    WindowInput::Redraw { width, height } => {
+                        let texture = init
+                            .window_mgr
+                            .renderer_mut(w)
+                            .create_texture(*width, *height);
+
+                        Some(init.gfx_bindings.create_texture(texture, None, w)?)
+                    }
+                    _ => None,
+                };
+
+                let window_mgr = init.window_mgr;
+
+                self.app.deliver_window_input(window, cbs, input, fb)?;
+
+                window_mgr.submit(&mut self.app.world);
+            }
+            SessionInput::Gamepad(input) => {
+                let cbs = init.gamepad_bindings.user_callbacks(w).context(
+                    "replay diverged: the guest's gamepad handlers are unbound but the recording \
+                     has more gamepad events",
+                )?;
+
+                init.store.run_wsl_root(w, |cx| cbs.deliver(cx, &input))?;
+            }
+            SessionInput::Net { call, outcome } => {
+                self.app.session.resolve_net_outcome(call, outcome, w)?;
+            }
+            SessionInput::StorageCall { call, outcome } => {
+                self.app.session.resolve_storage_outcome(call, outcome, w)?;
+            }
+            input => {
+                anyhow::bail!(
+                    "replay diverged: the recording has {input:?} but the guest never requested it"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delivers the outcomes of every async port call which resolved since the last poll.
+    fn poll_background(&mut self) -> anyhow::Result<()> {
+        let mut cx = task::Context::from_waker(task::Waker::noop());
+
+        match self.background.poll(&(), self.app, &mut cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Ok(()),
+        }
+    }
+}
+
+/// The in-memory destination of the replay's trace.
+#[derive(Debug, Default, Clone)]
+struct TraceBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl io::Write for TraceBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
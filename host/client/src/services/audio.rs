@@ -0,0 +1,471 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::Cursor,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use anyhow::Context as _;
+use cpal::{
+    FromSample, SizedSample,
+    traits::{DeviceTrait as _, HostTrait as _, StreamTrait as _},
+};
+
+/// The sample rate used when there is no output device to match.
+pub const OFFLINE_SAMPLE_RATE: u32 = 48_000;
+
+/// The bus every other bus ultimately feeds into.
+pub const MASTER_BUS: u32 = 0;
+
+// === SoundBuffer === //
+
+/// A sound held in memory as interleaved samples in the range `-1.0..=1.0`.
+#[derive(Debug, Clone)]
+pub struct SoundBuffer {
+    channels: u32,
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+impl SoundBuffer {
+    pub fn from_pcm(samples: Vec<f32>, channels: u32, sample_rate: u32) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            channels == 1 || channels == 2,
+            "only mono and stereo sounds are supported, got {channels} channels"
+        );
+        anyhow::ensure!(sample_rate > 0, "sample rate must be positive");
+        anyhow::ensure!(
+            samples.len().is_multiple_of(channels as usize),
+            "{} samples cannot be split evenly into {channels} channels",
+            samples.len()
+        );
+
+        Ok(Self {
+            channels,
+            sample_rate,
+            samples,
+        })
+    }
+
+    /// Decodes a WAV or Ogg Vorbis file.
+    pub fn decode(file: &[u8]) -> anyhow::Result<Self> {
+        if file.starts_with(b"RIFF") {
+            Self::decode_wav(file).context("failed to decode WAV file")
+        } else if file.starts_with(b"OggS") {
+            Self::decode_ogg(file).context("failed to decode Ogg Vorbis file")
+        } else {
+            anyhow::bail!("unrecognized audio format, expected a WAV or Ogg Vorbis file");
+        }
+    }
+
+    fn decode_wav(file: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = hound::WavReader::new(Cursor::new(file))?;
+        let spec = reader.spec();
+
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = 1. / (1u64 << (spec.bits_per_sample - 1)) as f32;
+
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        Self::from_pcm(samples, u32::from(spec.channels), spec.sample_rate)
+    }
+
+    fn decode_ogg(file: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(file))?;
+        let mut samples = Vec::new();
+
+        while let Some(packet) = reader.read_dec_packet_itl()? {
+            samples.extend(packet.into_iter().map(f32::from_sample));
+        }
+
+        Self::from_pcm(
+            samples,
+            u32::from(reader.ident_hdr.audio_channels),
+            reader.ident_hdr.audio_sample_rate,
+        )
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    /// The left and right samples of the frame at `idx`.
+    fn frame(&self, idx: usize) -> (f32, f32) {
+        if self.channels == 1 {
+            let sample = self.samples[idx];
+            (sample, sample)
+        } else {
+            (self.samples[idx * 2], self.samples[idx * 2 + 1])
+        }
+    }
+}
+
+// === Mixer === //
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VoiceParams {
+    pub volume: f32,
+    pub pan: f32,
+    pub pitch: f32,
+}
+
+impl Default for VoiceParams {
+    fn default() -> Self {
+        Self {
+            volume: 1.,
+            pan: 0.,
+            pitch: 1.,
+        }
+    }
+}
+
+/// Mixes the playing voices into interleaved stereo output. Buses and voices are identified by
+/// IDs chosen by the caller; the [`MASTER_BUS`] always exists.
+#[derive(Debug)]
+pub struct Mixer {
+    sample_rate: u32,
+    buses: BTreeMap<u32, Bus>,
+    voices: BTreeMap<u32, Voice>,
+}
+
+#[derive(Debug)]
+struct Bus {
+    parent: Option<u32>,
+    volume: f32,
+}
+
+#[derive(Debug)]
+struct Voice {
+    buffer: Arc<SoundBuffer>,
+    bus: u32,
+    params: VoiceParams,
+    looping: bool,
+    /// The position of the voice in the buffer, in frames.
+    position: f64,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            buses: BTreeMap::from_iter([(
+                MASTER_BUS,
+                Bus {
+                    parent: None,
+                    volume: 1.,
+                },
+            )]),
+            voices: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_bus(&mut self, id: u32, parent: u32) -> anyhow::Result<()> {
+        anyhow::ensure!(self.buses.contains_key(&parent), "unknown parent bus");
+        anyhow::ensure!(!self.buses.contains_key(&id), "bus already exists");
+
+        self.buses.insert(
+            id,
+            Bus {
+                parent: Some(parent),
+                volume: 1.,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Removes a bus which has no child buses, stopping every voice playing into it.
+    pub fn remove_bus(&mut self, id: u32) -> anyhow::Result<()> {
+        anyhow::ensure!(id != MASTER_BUS, "the master bus cannot be removed");
+        anyhow::ensure!(
+            !self.buses.values().any(|bus| bus.parent == Some(id)),
+            "cannot remove a bus which still has child buses"
+        );
+        anyhow::ensure!(self.buses.remove(&id).is_some(), "unknown bus");
+
+        self.voices.retain(|_, voice| voice.bus != id);
+
+        Ok(())
+    }
+
+    pub fn set_bus_volume(&mut self, id: u32, volume: f32) -> anyhow::Result<()> {
+        self.buses.get_mut(&id).context("unknown bus")?.volume = volume;
+
+        Ok(())
+    }
+
+    pub fn play(
+        &mut self,
+        id: u32,
+        buffer: Arc<SoundBuffer>,
+        bus: u32,
+        params: VoiceParams,
+        looping: bool,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(self.buses.contains_key(&bus), "unknown bus");
+
+        self.voices.insert(
+            id,
+            Voice {
+                buffer,
+                bus,
+                params,
+                looping,
+                position: 0.,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Updates the parameters of a voice. Voices which have finished playing are ignored.
+    pub fn set_voice_params(&mut self, id: u32, params: VoiceParams) {
+        if let Some(voice) = self.voices.get_mut(&id) {
+            voice.params = params;
+        }
+    }
+
+    /// Stops a voice. Voices which have finished playing are ignored.
+    pub fn stop(&mut self, id: u32) {
+        self.voices.remove(&id);
+    }
+
+    /// Removes every bus but the master one and stops every voice.
+    pub fn clear(&mut self) {
+        self.buses.retain(|&id, _| id == MASTER_BUS);
+        self.voices.clear();
+
+        if let Some(master) = self.buses.get_mut(&MASTER_BUS) {
+            master.volume = 1.;
+        }
+    }
+
+    /// The gain applied by a bus and all of its ancestors.
+    fn bus_gain(&self, mut id: u32) -> f32 {
+        let mut gain = 1.;
+
+        while let Some(bus) = self.buses.get(&id) {
+            gain *= bus.volume;
+
+            match bus.parent {
+                Some(parent) => id = parent,
+                None => break,
+            }
+        }
+
+        gain
+    }
+
+    /// Overwrites `out` with the next interleaved stereo frames of the mix, advancing every voice.
+    pub fn render(&mut self, out: &mut [f32]) {
+        out.fill(0.);
+
+        let gains = self
+            .buses
+            .keys()
+            .map(|&id| (id, self.bus_gain(id)))
+            .collect::<BTreeMap<_, _>>();
+
+        let sample_rate = self.sample_rate;
+
+        self.voices.retain(|_, voice| {
+            let frames = voice.buffer.frames();
+
+            if frames == 0 {
+                return false;
+            }
+
+            let gain = voice.params.volume * gains.get(&voice.bus).copied().unwrap_or(0.);
+            let left_gain = gain * (1. - voice.params.pan).min(1.);
+            let right_gain = gain * (1. + voice.params.pan).min(1.);
+            let step = f64::from(voice.buffer.sample_rate()) / f64::from(sample_rate)
+                * f64::from(voice.params.pitch);
+
+            for frame in out.as_chunks_mut::<2>().0 {
+                if voice.position >= frames as f64 {
+                    if !voice.looping {
+                        return false;
+                    }
+
+                    voice.position %= frames as f64;
+                }
+
+                // Interpolate linearly between the two nearest frames of the buffer.
+                let idx = voice.position as usize;
+                let frac = (voice.position - idx as f64) as f32;
+                let next = if idx + 1 < frames {
+                    idx + 1
+                } else if voice.looping {
+                    0
+                } else {
+                    idx
+                };
+
+                let (l0, r0) = voice.buffer.frame(idx);
+                let (l1, r1) = voice.buffer.frame(next);
+
+                frame[0] += (l0 + (l1 - l0) * frac) * left_gain;
+                frame[1] += (r0 + (r1 - r0) * frac) * right_gain;
+
+                voice.position += step;
+            }
+
+            voice.looping || voice.position < frames as f64
+        });
+
+        for sample in out {
+            *sample = sample.clamp(-1., 1.);
+        }
+    }
+}
+
+// === Audio === //
+
+/// The client's audio output. A [`Mixer`] is shared with a backend which either plays it through
+/// the system's output device or leaves it to be rendered by hand.
+#[derive(Debug)]
+pub struct Audio {
+    mixer: Arc<Mutex<Mixer>>,
+    backend: AudioBackend,
+}
+
+#[derive(Debug)]
+enum AudioBackend {
+    System(SystemStream),
+    Offline,
+}
+
+/// Keeps the output stream playing until dropped.
+struct SystemStream {
+    _stream: cpal::Stream,
+}
+
+impl fmt::Debug for SystemStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SystemStream")
+    }
+}
+
+impl Audio {
+    /// Plays through the system's default output device. If there is none, this falls back to an
+    /// [offline](Self::offline) mixer which is never rendered.
+    pub fn system() -> Self {
+        match Self::try_system() {
+            Ok(audio) => audio,
+            Err(err) => {
+                tracing::warn!("Failed to open an audio output device: {err:#}");
+
+                Self::offline(OFFLINE_SAMPLE_RATE)
+            }
+        }
+    }
+
+    fn try_system() -> anyhow::Result<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .context("no output device available")?;
+
+        let config = device.default_output_config()?;
+        let mixer = Arc::new(Mutex::new(Mixer::new(config.sample_rate().0)));
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::I8 => build_stream::<i8>(&device, &config.into(), &mixer),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config.into(), &mixer),
+            cpal::SampleFormat::I32 => build_stream::<i32>(&device, &config.into(), &mixer),
+            cpal::SampleFormat::U8 => build_stream::<u8>(&device, &config.into(), &mixer),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config.into(), &mixer),
+            cpal::SampleFormat::U32 => build_stream::<u32>(&device, &config.into(), &mixer),
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config.into(), &mixer),
+            cpal::SampleFormat::F64 => build_stream::<f64>(&device, &config.into(), &mixer),
+            format => anyhow::bail!("unsupported sample format {format:?}"),
+        }?;
+
+        stream.play()?;
+
+        Ok(Self {
+            mixer,
+            backend: AudioBackend::System(SystemStream { _stream: stream }),
+        })
+    }
+
+    /// Mixes without an output device. The mix only advances when
+    /// [`render_offline`](Self::render_offline) is called.
+    pub fn offline(sample_rate: u32) -> Self {
+        Self {
+            mixer: Arc::new(Mutex::new(Mixer::new(sample_rate))),
+            backend: AudioBackend::Offline,
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        matches!(self.backend, AudioBackend::Offline)
+    }
+
+    pub fn mixer(&self) -> MutexGuard<'_, Mixer> {
+        self.mixer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Renders the next interleaved stereo frames of an offline mix into `out`.
+    pub fn render_offline(&self, out: &mut [f32]) {
+        assert!(
+            self.is_offline(),
+            "cannot render a mix which is being played"
+        );
+
+        self.mixer().render(out);
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mixer: &Arc<Mutex<Mixer>>,
+) -> anyhow::Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let mixer = mixer.clone();
+    let channels = usize::from(config.channels);
+    let mut scratch = Vec::new();
+
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            let frames = data.len() / channels;
+            scratch.resize(frames * 2, 0.);
+
+            mixer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .render(&mut scratch);
+
+            let (mixed, _) = scratch.as_chunks::<2>();
+
+            for (out, mix) in data.chunks_exact_mut(channels).zip(mixed) {
+                if channels == 1 {
+                    out[0] = T::from_sample((mix[0] + mix[1]) * 0.5);
+                    continue;
+                }
+
+                for (idx, sample) in out.iter_mut().enumerate() {
+                    *sample = T::from_sample(mix.get(idx).copied().unwrap_or(0.));
+                }
+            }
+        },
+        |err| tracing::error!("Audio output stream failed: {err}"),
+        None,
+    )?;
+
+    Ok(stream)
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+/// The contents written to a clipboard.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ClipboardContents {
+    Text(String),
+    Image(ClipboardImage),
+}
+
+/// An image on the clipboard, with its pixels stored row by row in RGBA order.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClipboardImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// A clipboard the game can read from and write to.
+pub trait ClipboardBackend: fmt::Debug {
+    /// Reads the text on the clipboard, returning `None` if it holds something other than text.
+    fn read_text(&mut self) -> anyhow::Result<Option<String>>;
+
+    fn write(&mut self, contents: ClipboardContents) -> anyhow::Result<()>;
+}
+
+/// Opens the system clipboard. If the platform doesn't provide one, this falls back to a
+/// [`MemoryClipboard`] so that copying and pasting still works within the game.
+pub fn system_clipboard() -> Box<dyn ClipboardBackend> {
+    match arboard::Clipboard::new() {
+        Ok(clipboard) => Box::new(SystemClipboard(clipboard)),
+        Err(err) => {
+            tracing::warn!("Failed to open the system clipboard: {err}");
+
+            Box::new(MemoryClipboard::default())
+        }
+    }
+}
+
+// === SystemClipboard === //
+
+struct SystemClipboard(arboard::Clipboard);
+
+impl fmt::Debug for SystemClipboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SystemClipboard")
+    }
+}
+
+impl ClipboardBackend for SystemClipboard {
+    fn read_text(&mut self) -> anyhow::Result<Option<String>> {
+        match self.0.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&mut self, contents: ClipboardContents) -> anyhow::Result<()> {
+        match contents {
+            ClipboardContents::Text(text) => self.0.set_text(text)?,
+            ClipboardContents::Image(image) => self.0.set_image(arboard::ImageData {
+                width: image.width as usize,
+                height: image.height as usize,
+                bytes: image.rgba.into(),
+            })?,
+        }
+
+        Ok(())
+    }
+}
+
+// === MemoryClipboard === //
+
+/// A clipboard which only exists within the client, for sessions which must not touch the
+/// system's clipboard.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryClipboard {
+    pub contents: Option<ClipboardContents>,
+}
+
+impl ClipboardBackend for MemoryClipboard {
+    fn read_text(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(match &self.contents {
+            Some(ClipboardContents::Text(text)) => Some(text.clone()),
+            Some(ClipboardContents::Image(_)) | None => None,
+        })
+    }
+
+    fn write(&mut self, contents: ClipboardContents) -> anyhow::Result<()> {
+        self.contents = Some(contents);
+
+        Ok(())
+    }
+}
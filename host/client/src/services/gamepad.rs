@@ -0,0 +1,270 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::{Duration, Instant},
+};
+
+use gilrs::{
+    Axis, Button, EventType, Gilrs, GilrsBuilder,
+    ev::filter::{Filter as _, axis_dpad_to_button},
+};
+
+use crate::services::session::{GamepadAxisInput, GamepadButtonInput, GamepadInput};
+
+/// How often gamepads are polled while at least one is connected.
+const ACTIVE_POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+/// How often gamepads are polled for hot-plugged controllers while none are connected.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Tracks the connected gamepads and converts their events into guest inputs.
+#[derive(Debug)]
+pub struct Gamepads {
+    backend: GamepadBackend,
+    connected: BTreeMap<u32, String>,
+    next_poll: Instant,
+}
+
+enum GamepadBackend {
+    Gilrs(Gilrs),
+    Virtual(VirtualGamepads),
+}
+
+impl fmt::Debug for GamepadBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gilrs(_) => f.write_str("Gilrs"),
+            Self::Virtual(pads) => f.debug_tuple("Virtual").field(pads).finish(),
+        }
+    }
+}
+
+impl Gamepads {
+    /// Reads the system's gamepads. If the platform doesn't support them, this falls back to a set
+    /// of virtual gamepads with none connected.
+    pub fn system() -> Self {
+        // Deadzones are left to the guest so only the filter turning d-pad axes into buttons is
+        // applied.
+        match GilrsBuilder::new().with_default_filters(false).build() {
+            Ok(gilrs) => {
+                let connected = gilrs
+                    .gamepads()
+                    .map(|(id, pad)| (usize::from(id) as u32, pad.name().to_string()))
+                    .collect();
+
+                Self {
+                    backend: GamepadBackend::Gilrs(gilrs),
+                    connected,
+                    next_poll: Instant::now(),
+                }
+            }
+            Err(err) => {
+                tracing::warn!("Failed to set up gamepad support: {err}");
+
+                Self::new_virtual(VirtualGamepads::default())
+            }
+        }
+    }
+
+    /// Reads gamepads driven by the host rather than by a controller.
+    pub fn new_virtual(pads: VirtualGamepads) -> Self {
+        Self {
+            backend: GamepadBackend::Virtual(pads),
+            connected: BTreeMap::new(),
+            next_poll: Instant::now(),
+        }
+    }
+
+    /// The virtual gamepads being read, if any.
+    pub fn virtual_pads(&mut self) -> Option<&mut VirtualGamepads> {
+        match &mut self.backend {
+            GamepadBackend::Gilrs(_) => None,
+            GamepadBackend::Virtual(pads) => Some(pads),
+        }
+    }
+
+    /// Iterates over the IDs and names of the connected gamepads.
+    pub fn connected(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.connected.iter().map(|(&id, name)| (id, name.as_str()))
+    }
+
+    /// The time at which the gamepads should next be polled. Virtual gamepads never need to be
+    /// polled since they are only updated by the host.
+    pub fn next_poll(&self) -> Option<Instant> {
+        match self.backend {
+            GamepadBackend::Gilrs(_) => Some(self.next_poll),
+            GamepadBackend::Virtual(_) => None,
+        }
+    }
+
+    /// Takes the events which occurred since the last poll.
+    pub fn poll(&mut self) -> Vec<GamepadInput> {
+        let mut events = Vec::new();
+
+        match &mut self.backend {
+            GamepadBackend::Gilrs(gilrs) => {
+                let now = Instant::now();
+
+                if now < self.next_poll {
+                    return events;
+                }
+
+                while let Some(event) = gilrs.next_event().filter_ev(&axis_dpad_to_button, gilrs) {
+                    let id = usize::from(event.id) as u32;
+
+                    let event = match event.event {
+                        EventType::Connected => GamepadInput::Connected {
+                            id,
+                            name: gilrs.gamepad(event.id).name().to_string(),
+                        },
+                        EventType::Disconnected => GamepadInput::Disconnected { id },
+                        EventType::ButtonPressed(button, _) => {
+                            let Some(button) = button_input(button) else {
+                                continue;
+                            };
+
+                            GamepadInput::Button {
+                                id,
+                                button,
+                                pressed: true,
+                            }
+                        }
+                        EventType::ButtonReleased(button, _) => {
+                            let Some(button) = button_input(button) else {
+                                continue;
+                            };
+
+                            GamepadInput::Button {
+                                id,
+                                button,
+                                pressed: false,
+                            }
+                        }
+                        // Triggers report their analog position as button changes.
+                        EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                            GamepadInput::Axis {
+                                id,
+                                axis: GamepadAxisInput::LeftTrigger,
+                                value,
+                            }
+                        }
+                        EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                            GamepadInput::Axis {
+                                id,
+                                axis: GamepadAxisInput::RightTrigger,
+                                value,
+                            }
+                        }
+                        EventType::AxisChanged(axis, value, _) => {
+                            let Some(axis) = axis_input(axis) else {
+                                continue;
+                            };
+
+                            GamepadInput::Axis { id, axis, value }
+                        }
+                        _ => continue,
+                    };
+
+                    events.push(event);
+                }
+
+                self.next_poll = now
+                    + if gilrs.gamepads().next().is_some() {
+                        ACTIVE_POLL_INTERVAL
+                    } else {
+                        IDLE_POLL_INTERVAL
+                    };
+            }
+            GamepadBackend::Virtual(pads) => {
+                events.extend(pads.queue.drain(..));
+            }
+        }
+
+        // Gamepads which were connected at startup may be reported again once they are discovered
+        // so connections are deduplicated here. Events of unknown gamepads are dropped as well.
+        events.retain(|event| match event {
+            GamepadInput::Connected { id, name } => {
+                self.connected.insert(*id, name.clone()).is_none()
+            }
+            GamepadInput::Disconnected { id } => self.connected.remove(id).is_some(),
+            GamepadInput::Button { id, .. } | GamepadInput::Axis { id, .. } => {
+                self.connected.contains_key(id)
+            }
+        });
+
+        events
+    }
+}
+
+/// Gamepads driven by the host, for sessions which don't read real controllers.
+#[derive(Debug, Default)]
+pub struct VirtualGamepads {
+    queue: VecDeque<GamepadInput>,
+    next_id: u32,
+}
+
+impl VirtualGamepads {
+    /// Connects a gamepad called `name`, returning its ID.
+    pub fn connect(&mut self, name: impl Into<String>) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.queue.push_back(GamepadInput::Connected {
+            id,
+            name: name.into(),
+        });
+
+        id
+    }
+
+    pub fn disconnect(&mut self, id: u32) {
+        self.queue.push_back(GamepadInput::Disconnected { id });
+    }
+
+    pub fn set_button(&mut self, id: u32, button: GamepadButtonInput, pressed: bool) {
+        self.queue.push_back(GamepadInput::Button {
+            id,
+            button,
+            pressed,
+        });
+    }
+
+    pub fn set_axis(&mut self, id: u32, axis: GamepadAxisInput, value: f32) {
+        self.queue.push_back(GamepadInput::Axis { id, axis, value });
+    }
+}
+
+fn button_input(button: Button) -> Option<GamepadButtonInput> {
+    Some(match button {
+        Button::South => GamepadButtonInput::South,
+        Button::East => GamepadButtonInput::East,
+        Button::North => GamepadButtonInput::North,
+        Button::West => GamepadButtonInput::West,
+        Button::LeftTrigger => GamepadButtonInput::LeftBumper,
+        Button::RightTrigger => GamepadButtonInput::RightBumper,
+        Button::LeftTrigger2 => GamepadButtonInput::LeftTrigger,
+        Button::RightTrigger2 => GamepadButtonInput::RightTrigger,
+        Button::Select => GamepadButtonInput::Select,
+        Button::Start => GamepadButtonInput::Start,
+        Button::Mode => GamepadButtonInput::Mode,
+        Button::LeftThumb => GamepadButtonInput::LeftStick,
+        Button::RightThumb => GamepadButtonInput::RightStick,
+        Button::DPadUp => GamepadButtonInput::DPadUp,
+        Button::DPadDown => GamepadButtonInput::DPadDown,
+        Button::DPadLeft => GamepadButtonInput::DPadLeft,
+        Button::DPadRight => GamepadButtonInput::DPadRight,
+        _ => return None,
+    })
+}
+
+fn axis_input(axis: Axis) -> Option<GamepadAxisInput> {
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxisInput::LeftStickX,
+        Axis::LeftStickY => GamepadAxisInput::LeftStickY,
+        Axis::RightStickX => GamepadAxisInput::RightStickX,
+        Axis::RightStickY => GamepadAxisInput::RightStickY,
+        // D-pad axes are turned into button events and triggers report their position as button
+        // changes.
+        _ => return None,
+    })
+}
@@ -1,2 +1,9 @@
+pub mod audio;
+pub mod clipboard;
+pub mod gamepad;
 pub mod network;
+pub mod permissions;
+pub mod session;
+pub mod storage;
+pub mod watch;
 pub mod window;
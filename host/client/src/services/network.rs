@@ -9,7 +9,10 @@ use std::{
 use anyhow::Context as _;
 use crucible_host_shared::lang::{Promise, PromiseFuture, promise};
 use crucible_protocol::{
-    codec::{DecodeCodec, EncodeCodec, FrameDecoder, FrameEncoder, recv_packet, send_packet},
+    codec::{
+        DecodeCodec, EncodeCodec, FrameDecoder, FrameEncoder, encode_packet, flush_packets,
+        recv_packet, send_packet,
+    },
     game,
 };
 use quinn::{
@@ -21,15 +24,19 @@ use quinn::{
         pki_types::{CertificateDer, ServerName, UnixTime},
     },
 };
+use rustc_hash::FxHashMap;
 use smol::{
     channel,
     net::{self, AsyncToSocketAddrs},
 };
 use tracing::{Instrument, info_span};
+use wasmall::{
+    format::WasmallIndex,
+    utils::{ByteCursor, ByteParse as _},
+};
 use wasmlink::HostSlice;
-use wasmlink_wasmtime::WslStoreExt;
 
-use crate::app::BackgroundTasks;
+use crate::{app::BackgroundTasks, wsl::WslStoreExt};
 
 // === Type Definitions === //
 
@@ -45,7 +52,7 @@ pub enum CertValidationMode {
 
 // === LoginSocket === //
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LoginSocket {
     req_tx: channel::Sender<WorkerReq>,
     rtt: Arc<AtomicU64>,
@@ -106,6 +113,31 @@ impl LoginSocket {
         rx
     }
 
+    /// Downloads the blob with the given `hash` from the server, checking that its contents match
+    /// the hash.
+    pub fn download(&self, hash: blake3::Hash) -> NetworkPromiseFuture<Vec<u8>> {
+        let (tx, rx) = promise();
+        _ = self
+            .req_tx
+            .send_blocking(WorkerReq::Download { hash, callback: tx });
+        rx
+    }
+
+    /// Downloads the module whose `wasmall` index has the given `hash` along with the blobs it is
+    /// built from.
+    pub async fn download_module(&self, hash: blake3::Hash) -> anyhow::Result<Vec<u8>> {
+        let index = self.download(hash).await?;
+        let index = WasmallIndex::parse(&mut ByteCursor(&index))?;
+
+        let mut blobs = FxHashMap::default();
+
+        for hash in index.blob_hashes()? {
+            blobs.insert(hash, self.download(hash).await?);
+        }
+
+        index.rebuild(|hash| blobs.get(&hash).map(Vec::as_slice))
+    }
+
     pub fn play(
         &self,
         game_hash: blake3::Hash,
@@ -163,7 +195,7 @@ enum WorkerReq {
     },
     Download {
         hash: blake3::Hash,
-        callback: NetworkPromise<()>,
+        callback: NetworkPromise<Vec<u8>>,
     },
     Play {
         game_hash: blake3::Hash,
@@ -333,7 +365,9 @@ async fn run_worker(args: WorkerArgs, addr: impl AsyncToSocketAddrs) -> anyhow::
                         WorkerReq::GetInfo { callback } => {
                             callback.finish(process_get_info(conn).await);
                         }
-                        WorkerReq::Download { hash, callback } => todo!(),
+                        WorkerReq::Download { hash, callback } => {
+                            callback.finish(process_download(conn, hash).await);
+                        }
                         WorkerReq::Play {
                             game_hash,
                             callback,
@@ -423,6 +457,52 @@ async fn process_get_info(conn: quinn::Connection) -> anyhow::Result<game::CbSer
     Ok(info)
 }
 
+async fn process_download(conn: quinn::Connection, hash: blake3::Hash) -> anyhow::Result<Vec<u8>> {
+    let (stream_tx, stream_rx) = conn.open_bi().await?;
+
+    let mut stream_tx = FrameEncoder::new(stream_tx, EncodeCodec);
+    let mut stream_rx = FrameDecoder::new(
+        stream_rx,
+        DecodeCodec {
+            max_packet_size: u16::MAX as u32,
+        },
+    );
+
+    send_packet(&mut stream_tx, game::SbHello1::Download { hash }).await?;
+
+    let res = recv_packet::<game::CbDownloadRes>(&mut stream_rx)
+        .await?
+        .context("no download response sent")?;
+
+    let content_len = match res {
+        game::CbDownloadRes::Found { content_len } => content_len as usize,
+        game::CbDownloadRes::NotFound => anyhow::bail!("the server has no blob with hash {hash}"),
+        game::CbDownloadRes::NotSupported => {
+            anyhow::bail!("the server doesn't host its own content")
+        }
+    };
+
+    // The content follows the response unframed so its start may already have been buffered.
+    let parts = stream_rx.into_parts();
+    let mut content = parts.read_buf.to_vec();
+    let mut stream_rx = parts.io;
+
+    anyhow::ensure!(
+        content.len() <= content_len,
+        "the server sent too much content"
+    );
+
+    content.extend(stream_rx.read_to_end(content_len - content.len()).await?);
+
+    anyhow::ensure!(content.len() == content_len, "the download ended early");
+    anyhow::ensure!(
+        blake3::hash(&content) == hash,
+        "the downloaded blob doesn't match its hash {hash}"
+    );
+
+    Ok(content)
+}
+
 struct PlayArgs {
     id: u64,
     background: BackgroundTasks,
@@ -517,15 +597,18 @@ async fn process_play(args: PlayArgs) {
             PlayReq::SendMsg { data, callback } => {
                 callback
                     .resolve_cancellable(async {
-                        let data = background.acquire_state(|_, app| {
+                        // Serialize the message straight out of guest memory.
+                        background.acquire_state(|_, app| {
                             let init = app.init.as_mut().unwrap();
 
                             init.store.run_wsl_root(&mut app.world, |cx| {
-                                data.slice().read(cx).map(|v| v.to_vec())
+                                encode_packet(&mut stream_tx, data.slice().read(cx)?)?;
+
+                                anyhow::Ok(())
                             })
                         })?;
 
-                        send_packet(&mut stream_tx, data).await?;
+                        flush_packets(&mut stream_tx).await?;
 
                         Ok(())
                     })
@@ -0,0 +1,370 @@
+use std::{
+    fs,
+    io::{self, Write as _},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use arid::{Handle, Strong, W, Wr};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_abi::{Capability, MANIFEST_SECTION, Manifest};
+use rustc_hash::FxHashMap;
+
+use crate::utils::paths::data_dir;
+
+// === Manifest === //
+
+/// Reads the capability manifest embedded in `module`. Modules without a manifest request no
+/// capabilities.
+pub fn read_manifest(module: &[u8]) -> anyhow::Result<Manifest> {
+    for payload in wasmparser::Parser::new(0).parse_all(module) {
+        if let wasmparser::Payload::CustomSection(section) = payload?
+            && section.name() == MANIFEST_SECTION
+        {
+            let text = str::from_utf8(section.data()).context("manifest is not valid UTF-8")?;
+
+            return Ok(Manifest::parse(text)?);
+        }
+    }
+
+    Ok(Manifest::default())
+}
+
+/// Works out which of the capabilities requested by `module` are granted, asking the user about
+/// each of those they haven't decided on yet. Decisions are remembered per module hash and
+/// capability so updating a game asks again.
+///
+/// Storage ids are claimed by the first module path they are granted to. Modules loaded from
+/// anywhere else are never granted an id which is already claimed since they could otherwise read
+/// and overwrite another game's data just by naming its id in their manifest.
+pub fn resolve_permissions(module: &[u8], path: &Path) -> anyhow::Result<Vec<Capability>> {
+    resolve_permissions_in(module, path, &data_dir()?, prompt)
+}
+
+/// [`resolve_permissions`] with the decisions and claims kept in `dir` and the user asked about
+/// each capability through `prompt`.
+pub fn resolve_permissions_in(
+    module: &[u8],
+    path: &Path,
+    dir: &Path,
+    mut prompt: impl FnMut(&Capability) -> bool,
+) -> anyhow::Result<Vec<Capability>> {
+    let manifest = read_manifest(module).context("failed to read the module's manifest")?;
+
+    if manifest.capabilities.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let lineage = module_lineage(path)?;
+    let mut claims = StorageClaims::open(dir.join("storage-claims.txt"))?;
+
+    let requested = manifest
+        .capabilities
+        .into_iter()
+        .filter(|capability| {
+            let Capability::Storage { id } = capability else {
+                return true;
+            };
+
+            match claims.owner(id) {
+                Some(owner) if owner != lineage => {
+                    tracing::warn!(
+                        "The game asks to store data under `{id}` but that id belongs to the game \
+                         at `{owner}`. Remove its entry from `{}` to hand the id over.",
+                        claims.path.display(),
+                    );
+                    false
+                }
+                _ => true,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let hash = blake3::hash(module);
+    let mut decisions = DecisionStore::open(dir.join("permissions.txt"))?;
+    let mut asked = false;
+
+    for capability in &requested {
+        if decisions.get(hash, capability).is_none() {
+            let granted = prompt(capability);
+            decisions.set(hash, capability.clone(), granted);
+            asked = true;
+        }
+    }
+
+    if asked {
+        decisions.save()?;
+    }
+
+    let granted = requested
+        .into_iter()
+        .filter(|capability| decisions.get(hash, capability) == Some(true))
+        .collect::<Vec<_>>();
+
+    for capability in &granted {
+        tracing::info!("Granted permission to {}.", describe(capability));
+
+        if let Capability::Storage { id } = capability
+            && claims.owner(id).is_none()
+        {
+            claims.claim(id, &lineage);
+            claims.save()?;
+        }
+    }
+
+    Ok(granted)
+}
+
+/// Identifies the game loaded from `path` across updates to the module.
+fn module_lineage(path: &Path) -> anyhow::Result<String> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("failed to resolve the module path `{}`", path.display()))?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+fn prompt(capability: &Capability) -> bool {
+    let description = format!(
+        "This game is asking for permission to {}.\n\nDo you want to allow this?",
+        describe(capability)
+    );
+
+    let res = rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Crucible")
+        .set_description(description)
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show();
+
+    res == rfd::MessageDialogResult::Yes
+}
+
+/// Describes what `capability` lets a game do in a way which completes the sentence "The game may
+/// ...".
+fn describe(capability: &Capability) -> String {
+    match capability {
+        Capability::Network { host, port: None } if host == "*" => {
+            "connect to any server".to_string()
+        }
+        Capability::Network { host, port: None } if host.contains(':') => {
+            format!("connect to [{host}]")
+        }
+        Capability::Network { host, port: None } => format!("connect to {host}"),
+        Capability::Network {
+            host,
+            port: Some(port),
+        } if host.contains(':') => format!("connect to [{host}]:{port}"),
+        Capability::Network {
+            host,
+            port: Some(port),
+        } => format!("connect to {host}:{port}"),
+        Capability::Storage { id } => format!("store data on this device under `{id}`"),
+        Capability::Clipboard => "read the clipboard".to_string(),
+        Capability::Fullscreen => "make its window fullscreen".to_string(),
+    }
+}
+
+// === DecisionStore === //
+
+/// The user's answers to previous permission prompts. The file holds one decision per line in the
+/// form `<module hash>\t<allow|deny>\t<capability>`.
+#[derive(Debug)]
+pub struct DecisionStore {
+    path: PathBuf,
+    decisions: FxHashMap<(blake3::Hash, Capability), bool>,
+}
+
+impl DecisionStore {
+    pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("failed to read permissions from `{}`", path.display())
+                });
+            }
+        };
+
+        let decisions = text
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let parse = || {
+                    let [hash, decision, capability] = line
+                        .split('\t')
+                        .collect::<Vec<_>>()
+                        .try_into()
+                        .ok()
+                        .context("expected three tab-separated columns")?;
+
+                    let hash = blake3::Hash::from_hex(hash)?;
+                    let granted = match decision {
+                        "allow" => true,
+                        "deny" => false,
+                        _ => anyhow::bail!("unknown decision `{decision}`"),
+                    };
+                    let capability = capability
+                        .parse::<Capability>()
+                        .map_err(anyhow::Error::msg)?;
+
+                    anyhow::Ok(((hash, capability), granted))
+                };
+
+                parse().with_context(|| format!("invalid permission entry `{line}`"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { path, decisions })
+    }
+
+    pub fn get(&self, hash: blake3::Hash, capability: &Capability) -> Option<bool> {
+        self.decisions.get(&(hash, capability.clone())).copied()
+    }
+
+    pub fn set(&mut self, hash: blake3::Hash, capability: Capability, granted: bool) {
+        self.decisions.insert((hash, capability), granted);
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let mut text = Vec::new();
+
+        for ((hash, capability), granted) in &self.decisions {
+            let decision = if *granted { "allow" } else { "deny" };
+            writeln!(text, "{hash}\t{decision}\t{capability}")?;
+        }
+
+        write_file(&self.path, &text)
+            .with_context(|| format!("failed to save permissions to `{}`", self.path.display()))
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, data)
+}
+
+// === StorageClaims === //
+
+/// The games which claimed each storage id. The file holds one claim per line in the form
+/// `<storage id>\t<module lineage>`.
+#[derive(Debug)]
+pub struct StorageClaims {
+    path: PathBuf,
+    owners: FxHashMap<String, String>,
+}
+
+impl StorageClaims {
+    pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("failed to read storage claims from `{}`", path.display())
+                });
+            }
+        };
+
+        let owners = text
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (id, owner) = line
+                    .split_once('\t')
+                    .with_context(|| format!("invalid storage claim `{line}`"))?;
+
+                anyhow::Ok((id.to_string(), owner.to_string()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { path, owners })
+    }
+
+    /// The lineage of the game which claimed `id`, if any.
+    pub fn owner(&self, id: &str) -> Option<&str> {
+        self.owners.get(id).map(String::as_str)
+    }
+
+    pub fn claim(&mut self, id: &str, lineage: &str) {
+        self.owners.insert(id.to_string(), lineage.to_string());
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let mut text = Vec::new();
+
+        for (id, owner) in &self.owners {
+            writeln!(text, "{id}\t{owner}")?;
+        }
+
+        write_file(&self.path, &text)
+            .with_context(|| format!("failed to save storage claims to `{}`", self.path.display()))
+    }
+}
+
+// === Permissions === //
+
+/// The capabilities granted to the running game. Bindings check these before performing any
+/// operation the game has to ask for.
+#[derive(Debug)]
+pub struct Permissions {
+    granted: Vec<Capability>,
+}
+
+component!(pub Permissions);
+
+/// The error reported to the game when it uses a capability it wasn't granted.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("the game was not granted permission to {0}")]
+pub struct PermissionDenied(String);
+
+impl PermissionsHandle {
+    pub fn new(owner: EntityHandle, granted: Vec<Capability>, w: W) -> Strong<Self> {
+        Permissions { granted }.attach(owner, w)
+    }
+
+    pub fn check(self, capability: &Capability, w: Wr) -> Result<(), PermissionDenied> {
+        if self.r(w).granted.contains(capability) {
+            Ok(())
+        } else {
+            Err(PermissionDenied(describe(capability)))
+        }
+    }
+
+    /// Replaces the granted capabilities with those of a reloaded game, returning the previous
+    /// ones.
+    pub fn replace_granted(self, granted: Vec<Capability>, w: W) -> Vec<Capability> {
+        mem::replace(&mut self.m(w).granted, granted)
+    }
+
+    /// The id under which the game may store data.
+    pub fn storage_id(self, w: Wr) -> Result<String, PermissionDenied> {
+        self.r(w)
+            .granted
+            .iter()
+            .find_map(|capability| match capability {
+                Capability::Storage { id } => Some(id.clone()),
+                _ => None,
+            })
+            .ok_or_else(|| PermissionDenied("store data on this device".to_string()))
+    }
+
+    /// Checks whether the game may connect to `addr`, given as `<host>:<port>`.
+    pub fn check_connection(self, addr: &str, w: Wr) -> Result<(), PermissionDenied> {
+        if self
+            .r(w)
+            .granted
+            .iter()
+            .any(|capability| capability.allows_connection(addr))
+        {
+            Ok(())
+        } else {
+            Err(PermissionDenied(format!("connect to {addr}")))
+        }
+    }
+}
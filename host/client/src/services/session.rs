@@ -0,0 +1,698 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File},
+    io::{BufWriter, Write as _},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use arid::{Handle, Strong, W, Wr};
+use arid_entity::{Component, EntityHandle, component};
+use crucible_host_shared::lang::{Promise, PromiseFuture, promise};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use wasmlink::TraceEvent;
+use winit::{
+    event::{Ime, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard,
+};
+
+use crate::bindings::error::GuestError;
+
+// === Format === //
+
+const RECORDING_MAGIC: &[u8; 8] = b"CRUCREC\0";
+const RECORDING_VERSION: u16 = 4;
+
+/// An input the host fed to the guest. A guest's behavior is entirely determined by the sequence
+/// of inputs it receives so a session can be reproduced by feeding them back in the same order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SessionInput {
+    /// The value returned by `GET_CURRENT_TIME`.
+    CurrentTime(f64),
+
+    /// The host fired every timeout expiring at or before `now`.
+    PollTimeouts { now: f64 },
+
+    /// An event delivered to the handlers of the guest window with the handle `window`.
+    Window { window: u32, input: WindowInput },
+
+    /// The value returned by `LOGIN_SOCKET_GET_RTT`.
+    Rtt(Option<f64>),
+
+    /// The value returned by `GAME_SOCKET_GET_ID`.
+    SocketId(u64),
+
+    /// The outcome of the `call`-th async network call, delivered to the guest.
+    Net { call: u64, outcome: NetOutcome },
+
+    /// The value returned by a synchronous storage port.
+    Storage(StorageOutcome),
+
+    /// The outcome of the `call`-th async storage call, delivered to the guest.
+    StorageCall { call: u64, outcome: StorageOutcome },
+
+    /// An event delivered to the guest's gamepad handlers.
+    Gamepad(GamepadInput),
+
+    /// The value returned by `WINDOW_GET_INNER_SIZE`.
+    InnerSize { width: u32, height: u32 },
+
+    /// The value returned by `WINDOW_GET_SCALE_FACTOR`.
+    ScaleFactor(f64),
+
+    /// The value returned by `WINDOW_SET_FULLSCREEN`.
+    Fullscreen(Result<(), GuestError>),
+
+    /// The value returned by `CLIPBOARD_WRITE_TEXT` or `CLIPBOARD_WRITE_IMAGE`.
+    Clipboard(Result<(), GuestError>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WindowInput {
+    Redraw {
+        width: u32,
+        height: u32,
+    },
+    MouseMoved {
+        x: f64,
+        y: f64,
+    },
+    MouseInput {
+        button: MouseButtonInput,
+        pressed: bool,
+    },
+    Key(KeyInput),
+    CloseRequested,
+    MouseWheel(ScrollDeltaInput),
+    CursorEntered,
+    CursorLeft,
+    Focused(bool),
+    Occluded(bool),
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    ScaleFactorChanged(f64),
+    /// Raw mouse motion, which `winit` reports as a device event.
+    MouseMotion {
+        x: f64,
+        y: f64,
+    },
+    Ime(ImeInput),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ImeInput {
+    Enabled,
+    Preedit {
+        text: String,
+        cursor: Option<(u32, u32)>,
+    },
+    Commit(String),
+    Disabled,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScrollDeltaInput {
+    Line { x: f64, y: f64 },
+    Pixel { x: f64, y: f64 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MouseButtonInput {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyInput {
+    pub physical_key: Option<u32>,
+    pub logical_key: LogicalKeyInput,
+    pub text: Option<String>,
+    pub location: u32,
+    pub pressed: bool,
+    pub repeat: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LogicalKeyInput {
+    Named(u32),
+    Character(String),
+    Unidentified(NativeKeyInput),
+    Dead(Option<char>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NativeKeyInput {
+    Unidentified,
+    Android(u32),
+    MacOS(u16),
+    Windows(u16),
+    Xkb(u32),
+    Web(String),
+}
+
+impl WindowInput {
+    /// Converts a `winit` event into the input the guest sees. Returns `None` for events which
+    /// aren't forwarded to the guest. Redraws are handled by the caller since they require a
+    /// framebuffer.
+    pub fn from_winit(event: &WindowEvent) -> Option<Self> {
+        Some(match event {
+            WindowEvent::CursorMoved { position, .. } => WindowInput::MouseMoved {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::MouseInput { state, button, .. } => WindowInput::MouseInput {
+                button: match button {
+                    MouseButton::Left => MouseButtonInput::Left,
+                    MouseButton::Right => MouseButtonInput::Right,
+                    MouseButton::Middle => MouseButtonInput::Middle,
+                    MouseButton::Back => MouseButtonInput::Back,
+                    MouseButton::Forward => MouseButtonInput::Forward,
+                    MouseButton::Other(id) => MouseButtonInput::Other(*id),
+                },
+                pressed: state.is_pressed(),
+            },
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key,
+                        logical_key,
+                        text,
+                        location,
+                        state,
+                        repeat,
+                        ..
+                    },
+                ..
+            } => WindowInput::Key(KeyInput {
+                physical_key: match physical_key {
+                    keyboard::PhysicalKey::Code(code) => Some(*code as u32),
+                    keyboard::PhysicalKey::Unidentified(_) => None,
+                },
+                logical_key: match logical_key {
+                    keyboard::Key::Named(named_key) => LogicalKeyInput::Named(*named_key as u32),
+                    keyboard::Key::Character(ch) => LogicalKeyInput::Character(ch.to_string()),
+                    keyboard::Key::Unidentified(code) => {
+                        LogicalKeyInput::Unidentified(match code {
+                            keyboard::NativeKey::Unidentified => NativeKeyInput::Unidentified,
+                            keyboard::NativeKey::Android(v) => NativeKeyInput::Android(*v),
+                            keyboard::NativeKey::MacOS(v) => NativeKeyInput::MacOS(*v),
+                            keyboard::NativeKey::Windows(v) => NativeKeyInput::Windows(*v),
+                            keyboard::NativeKey::Xkb(v) => NativeKeyInput::Xkb(*v),
+                            keyboard::NativeKey::Web(v) => NativeKeyInput::Web(v.to_string()),
+                        })
+                    }
+                    keyboard::Key::Dead(ch) => LogicalKeyInput::Dead(*ch),
+                },
+                text: text.as_ref().map(|v| v.to_string()),
+                location: *location as u32,
+                pressed: state.is_pressed(),
+                repeat: *repeat,
+            }),
+            WindowEvent::CloseRequested => WindowInput::CloseRequested,
+            WindowEvent::MouseWheel { delta, .. } => WindowInput::MouseWheel(match *delta {
+                MouseScrollDelta::LineDelta(x, y) => ScrollDeltaInput::Line {
+                    x: x.into(),
+                    y: y.into(),
+                },
+                MouseScrollDelta::PixelDelta(delta) => ScrollDeltaInput::Pixel {
+                    x: delta.x,
+                    y: delta.y,
+                },
+            }),
+            WindowEvent::CursorEntered { .. } => WindowInput::CursorEntered,
+            WindowEvent::CursorLeft { .. } => WindowInput::CursorLeft,
+            WindowEvent::Focused(focused) => WindowInput::Focused(*focused),
+            WindowEvent::Occluded(occluded) => WindowInput::Occluded(*occluded),
+            WindowEvent::Resized(size) => WindowInput::Resized {
+                width: size.width,
+                height: size.height,
+            },
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                WindowInput::ScaleFactorChanged(*scale_factor)
+            }
+            WindowEvent::Ime(ime) => WindowInput::Ime(match ime {
+                Ime::Enabled => ImeInput::Enabled,
+                Ime::Preedit(text, cursor) => ImeInput::Preedit {
+                    text: text.clone(),
+                    cursor: cursor.map(|(start, end)| (start as u32, end as u32)),
+                },
+                Ime::Commit(text) => ImeInput::Commit(text.clone()),
+                Ime::Disabled => ImeInput::Disabled,
+            }),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GamepadInput {
+    Connected {
+        id: u32,
+        name: String,
+    },
+    Disconnected {
+        id: u32,
+    },
+    Button {
+        id: u32,
+        button: GamepadButtonInput,
+        pressed: bool,
+    },
+    Axis {
+        id: u32,
+        axis: GamepadAxisInput,
+        value: f32,
+    },
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GamepadButtonInput {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GamepadAxisInput {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// The guest-visible outcome of an async network call. Handles are recorded as-is since they are
+/// allocated deterministically.
+///
+/// Clipboard reads are numbered alongside network calls.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NetOutcome {
+    Connect(Result<u32, GuestError>),
+    Info(Result<ServerInfoInput, GuestError>),
+    Play(Result<Result<u32, [u8; blake3::OUT_LEN]>, GuestError>),
+    SendMsg(Result<(), GuestError>),
+    ClipboardRead(Result<Option<String>, GuestError>),
+}
+
+/// The guest-visible outcome of a storage call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StorageOutcome {
+    Read(Result<Option<Vec<u8>>, GuestError>),
+    Write(Result<(), GuestError>),
+    Delete(Result<(), GuestError>),
+    List(Result<Vec<String>, GuestError>),
+    Usage(Result<(u64, u64), GuestError>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfoInput {
+    pub motd: String,
+    pub content_hash: [u8; blake3::OUT_LEN],
+    pub content_server: Option<String>,
+    pub mods: Vec<ModInfoInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModInfoInput {
+    pub name: String,
+    pub content_hash: [u8; blake3::OUT_LEN],
+}
+
+/// The path of the host-call trace recorded alongside the recording at `path`.
+pub fn recording_trace_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".trace");
+    path.into()
+}
+
+// === Recorder === //
+
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").finish_non_exhaustive()
+    }
+}
+
+impl Recorder {
+    /// Starts a recording of a session of the module whose contents hash to `module_hash`.
+    pub fn create(path: &Path, module_hash: blake3::Hash) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create recording at `{}`", path.display()))?;
+
+        let mut out = BufWriter::new(file);
+        out.write_all(RECORDING_MAGIC)?;
+        out.write_all(&RECORDING_VERSION.to_le_bytes())?;
+        out.write_all(module_hash.as_bytes())?;
+
+        Ok(Self { out })
+    }
+
+    pub fn write(&mut self, input: &SessionInput) -> anyhow::Result<()> {
+        let frame = postcard::to_extend(input, Vec::new())?;
+
+        self.out
+            .write_all(&frame)
+            .context("failed to write to recording")
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.out.flush().context("failed to flush recording")
+    }
+}
+
+/// Reads back the inputs of a recording, checking that it was made with the module whose contents
+/// hash to `module_hash`.
+pub fn read_recording(
+    path: &Path,
+    module_hash: blake3::Hash,
+) -> anyhow::Result<VecDeque<SessionInput>> {
+    let data = fs::read(path)
+        .with_context(|| format!("failed to read recording at `{}`", path.display()))?;
+
+    let header_len = RECORDING_MAGIC.len() + 2 + blake3::OUT_LEN;
+
+    anyhow::ensure!(
+        data.len() >= header_len && data.starts_with(RECORDING_MAGIC),
+        "not a session recording"
+    );
+
+    let version = u16::from_le_bytes([data[8], data[9]]);
+
+    anyhow::ensure!(
+        version == RECORDING_VERSION,
+        "unsupported recording version {version} (expected {RECORDING_VERSION})"
+    );
+
+    anyhow::ensure!(
+        data[10..header_len] == *module_hash.as_bytes(),
+        "recording was made with a different module"
+    );
+
+    let mut remaining = &data[header_len..];
+    let mut inputs = VecDeque::new();
+
+    while !remaining.is_empty() {
+        let (input, rest) = postcard::take_from_bytes(remaining).context("malformed recording")?;
+
+        inputs.push_back(input);
+        remaining = rest;
+    }
+
+    Ok(inputs)
+}
+
+// === InputSession === //
+
+/// Tracks the inputs fed to the guest, either recording them as they come in or feeding them
+/// back from a previous recording.
+#[derive(Debug)]
+pub struct InputSession {
+    mode: SessionMode,
+    next_net_call: u64,
+    pending_net: FxHashMap<u64, Promise<NetOutcome>>,
+    next_storage_call: u64,
+    pending_storage: FxHashMap<u64, Promise<StorageOutcome>>,
+}
+
+#[derive(Debug)]
+enum SessionMode {
+    Live,
+    Recording(Recorder),
+    Replaying(VecDeque<SessionInput>),
+}
+
+component!(pub InputSession);
+
+impl InputSessionHandle {
+    pub fn live(owner: EntityHandle, w: W) -> Strong<Self> {
+        Self::new(owner, SessionMode::Live, w)
+    }
+
+    pub fn recording(owner: EntityHandle, recorder: Recorder, w: W) -> Strong<Self> {
+        Self::new(owner, SessionMode::Recording(recorder), w)
+    }
+
+    pub fn replaying(owner: EntityHandle, inputs: VecDeque<SessionInput>, w: W) -> Strong<Self> {
+        Self::new(owner, SessionMode::Replaying(inputs), w)
+    }
+
+    fn new(owner: EntityHandle, mode: SessionMode, w: W) -> Strong<Self> {
+        InputSession {
+            mode,
+            next_net_call: 0,
+            pending_net: FxHashMap::default(),
+            next_storage_call: 0,
+            pending_storage: FxHashMap::default(),
+        }
+        .attach(owner, w)
+    }
+
+    pub fn is_replaying(self, w: Wr) -> bool {
+        matches!(self.r(w).mode, SessionMode::Replaying(_))
+    }
+
+    pub fn is_recording(self, w: Wr) -> bool {
+        matches!(self.r(w).mode, SessionMode::Recording(_))
+    }
+
+    /// Records an input fed to the guest by a live session. This does nothing if the session
+    /// isn't being recorded.
+    pub fn record(self, input: SessionInput, w: W) -> anyhow::Result<()> {
+        match &mut self.m(w).mode {
+            SessionMode::Recording(recorder) => recorder.write(&input),
+            SessionMode::Live | SessionMode::Replaying(_) => Ok(()),
+        }
+    }
+
+    /// Produces a value requested by the guest. Live sessions obtain it from `live` and record it
+    /// as `wrap`ped while replays take it from the recording.
+    pub fn requested<T: Clone>(
+        self,
+        w: W,
+        live: impl FnOnce(W) -> anyhow::Result<T>,
+        wrap: impl FnOnce(T) -> SessionInput,
+        unwrap: impl FnOnce(SessionInput) -> Option<T>,
+    ) -> anyhow::Result<T> {
+        if self.is_replaying(w) {
+            return self.replayed(w, unwrap);
+        }
+
+        let value = live(&mut *w)?;
+        self.record(wrap(value.clone()), w)?;
+
+        Ok(value)
+    }
+
+    /// Takes the value of a guest request from the next input of a replay, which `unwrap` must
+    /// accept.
+    pub fn replayed<T>(
+        self,
+        w: W,
+        unwrap: impl FnOnce(SessionInput) -> Option<T>,
+    ) -> anyhow::Result<T> {
+        let input = self
+            .next_input(w)
+            .context("replay diverged: the guest requested an input past the recording's end")?;
+
+        let desc = format!("{input:?}");
+
+        unwrap(input).with_context(|| {
+            format!("replay diverged: the guest requested an input but the recording has {desc}")
+        })
+    }
+
+    /// Takes the next recorded input of a replay.
+    pub fn next_input(self, w: W) -> Option<SessionInput> {
+        match &mut self.m(w).mode {
+            SessionMode::Replaying(inputs) => inputs.pop_front(),
+            SessionMode::Live | SessionMode::Recording(_) => None,
+        }
+    }
+
+    /// Assigns an index to an async network call. Calls are numbered in the order the guest
+    /// makes them so that their outcomes can be matched up during a replay.
+    pub fn next_net_call(self, w: W) -> u64 {
+        let me = self.m(w);
+        let call = me.next_net_call;
+        me.next_net_call += 1;
+        call
+    }
+
+    /// Returns a future resolving to the recorded outcome of the `call`-th network call once the
+    /// replay reaches it.
+    pub fn replayed_net_outcome(self, call: u64, w: W) -> PromiseFuture<NetOutcome> {
+        let (tx, rx) = promise();
+        self.m(w).pending_net.insert(call, tx);
+        rx
+    }
+
+    pub fn resolve_net_outcome(self, call: u64, outcome: NetOutcome, w: W) -> anyhow::Result<()> {
+        self.m(w)
+            .pending_net
+            .remove(&call)
+            .with_context(|| format!("replay diverged: the guest never made network call #{call}"))?
+            .accept(outcome);
+
+        Ok(())
+    }
+
+    /// Assigns an index to an async storage call. Storage calls are numbered separately from
+    /// network calls so that each kind can be matched up during a replay on its own.
+    pub fn next_storage_call(self, w: W) -> u64 {
+        let me = self.m(w);
+        let call = me.next_storage_call;
+        me.next_storage_call += 1;
+        call
+    }
+
+    /// Returns a future resolving to the recorded outcome of the `call`-th storage call once the
+    /// replay reaches it.
+    pub fn replayed_storage_outcome(self, call: u64, w: W) -> PromiseFuture<StorageOutcome> {
+        let (tx, rx) = promise();
+        self.m(w).pending_storage.insert(call, tx);
+        rx
+    }
+
+    pub fn resolve_storage_outcome(
+        self,
+        call: u64,
+        outcome: StorageOutcome,
+        w: W,
+    ) -> anyhow::Result<()> {
+        self.m(w)
+            .pending_storage
+            .remove(&call)
+            .with_context(|| format!("replay diverged: the guest never made storage call #{call}"))?
+            .accept(outcome);
+
+        Ok(())
+    }
+
+    pub fn finish(self, w: W) -> anyhow::Result<()> {
+        match std::mem::replace(&mut self.m(w).mode, SessionMode::Live) {
+            SessionMode::Recording(recorder) => recorder.finish(),
+            SessionMode::Live | SessionMode::Replaying(_) => Ok(()),
+        }
+    }
+}
+
+// === Divergence === //
+
+/// The first host call at which a replay differed from its recording.
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub index: usize,
+    pub recorded: Option<TraceEvent>,
+    pub replayed: Option<TraceEvent>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "replay diverged at host call #{}:", self.index)?;
+
+        match &self.recorded {
+            Some(event) => writeln!(f, "  recorded: {event}")?,
+            None => writeln!(f, "  recorded: <end of trace>")?,
+        }
+
+        match &self.replayed {
+            Some(event) => write!(f, "  replayed: {event}"),
+            None => write!(f, "  replayed: <end of trace>"),
+        }
+    }
+}
+
+/// Compares the host calls of a recording and its replay, ignoring their timing.
+pub fn find_divergence(
+    mut recorded: impl Iterator<Item = anyhow::Result<TraceEvent>>,
+    mut replayed: impl Iterator<Item = anyhow::Result<TraceEvent>>,
+) -> anyhow::Result<Option<Divergence>> {
+    for index in 0.. {
+        let lhs = recorded.next().transpose()?;
+        let rhs = replayed.next().transpose()?;
+
+        let same = match (&lhs, &rhs) {
+            (None, None) => return Ok(None),
+            (Some(lhs), Some(rhs)) => same_call(lhs, rhs),
+            _ => false,
+        };
+
+        if !same {
+            return Ok(Some(Divergence {
+                index,
+                recorded: lhs,
+                replayed: rhs,
+            }));
+        }
+    }
+
+    unreachable!()
+}
+
+fn same_call(lhs: &TraceEvent, rhs: &TraceEvent) -> bool {
+    match (lhs, rhs) {
+        (
+            TraceEvent::PortCall {
+                module,
+                func_name,
+                args,
+                result,
+                ..
+            },
+            TraceEvent::PortCall {
+                module: rhs_module,
+                func_name: rhs_func_name,
+                args: rhs_args,
+                result: rhs_result,
+                ..
+            },
+        ) => {
+            module == rhs_module
+                && func_name == rhs_func_name
+                && args == rhs_args
+                && result == rhs_result
+        }
+        (
+            TraceEvent::Callback {
+                closure,
+                arg,
+                result,
+                ..
+            },
+            TraceEvent::Callback {
+                closure: rhs_closure,
+                arg: rhs_arg,
+                result: rhs_result,
+                ..
+            },
+        ) => closure == rhs_closure && arg == rhs_arg && result == rhs_result,
+        _ => false,
+    }
+}
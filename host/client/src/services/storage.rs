@@ -0,0 +1,206 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write as _},
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use anyhow::Context as _;
+
+use crate::utils::paths::data_dir;
+
+/// The number of bytes every game may store.
+pub const STORAGE_QUOTA: u64 = 64 * 1024 * 1024;
+
+const MAX_KEY_LEN: usize = 128;
+
+/// The error reported when a write would take a game over its [`STORAGE_QUOTA`].
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("writing {len} bytes to `{key}` would exceed the storage quota of {quota} bytes")]
+pub struct QuotaExceeded {
+    pub key: String,
+    pub len: u64,
+    pub quota: u64,
+}
+
+/// The keys stored by a single game. Each key is a file in the game's directory; values are
+/// written to a temporary file first and moved into place so that they are replaced atomically.
+///
+/// The space taken up by the values is measured once and then tracked in memory. Clones share that
+/// count, which is only updated under the same lock as the files so that concurrent writes can't
+/// both fit into the space left by the quota.
+#[derive(Debug, Clone)]
+pub struct GameStorage {
+    dir: PathBuf,
+    quota: u64,
+    usage: Arc<Mutex<Option<u64>>>,
+}
+
+impl GameStorage {
+    /// Opens the storage of the game with the storage id `id`, as validated by its manifest.
+    pub fn open(id: &str) -> anyhow::Result<Self> {
+        Ok(Self::at(
+            data_dir()?.join("storage").join(id),
+            STORAGE_QUOTA,
+        ))
+    }
+
+    /// Opens the storage kept in `dir`, letting it grow to at most `quota` bytes.
+    pub fn at(dir: PathBuf, quota: u64) -> Self {
+        Self {
+            dir,
+            quota,
+            usage: Arc::default(),
+        }
+    }
+
+    pub fn quota(&self) -> u64 {
+        self.quota
+    }
+
+    pub fn read(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let path = self.path(key)?;
+
+        match fs::read(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("failed to read `{key}`")),
+        }
+    }
+
+    pub fn write(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+        let used = match *usage {
+            Some(used) => used,
+            None => *usage.insert(self.measure_usage()?),
+        };
+
+        let replaced = match fs::metadata(&path) {
+            Ok(meta) => meta.len(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err).with_context(|| format!("failed to write `{key}`")),
+        };
+
+        let len = data.len() as u64;
+        let used = used.saturating_sub(replaced) + len;
+
+        if used > self.quota {
+            return Err(QuotaExceeded {
+                key: key.to_string(),
+                len,
+                quota: self.quota,
+            }
+            .into());
+        }
+
+        // Keys never start with a `.` so temporary files can't collide with them.
+        let temp_path = self.dir.join(format!(".{key}.tmp"));
+
+        let res = (|| {
+            fs::create_dir_all(&self.dir)?;
+
+            let mut file = File::create(&temp_path)?;
+            file.write_all(data)?;
+            file.sync_all()?;
+
+            fs::rename(&temp_path, &path)
+        })();
+
+        if res.is_err() {
+            _ = fs::remove_file(&temp_path);
+        }
+
+        res.with_context(|| format!("failed to write `{key}`"))?;
+        *usage = Some(used);
+
+        Ok(())
+    }
+
+    pub fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let res = fs::metadata(&path).and_then(|meta| {
+            fs::remove_file(&path)?;
+            Ok(meta.len())
+        });
+
+        match res {
+            Ok(removed) => {
+                if let Some(used) = &mut *usage {
+                    *used = used.saturating_sub(removed);
+                }
+
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("failed to delete `{key}`")),
+        }
+    }
+
+    pub fn list(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.entries()?.into_iter().map(|(key, _len)| key).collect())
+    }
+
+    /// The number of bytes taken up by the game's values.
+    pub fn usage(&self) -> anyhow::Result<u64> {
+        let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match *usage {
+            Some(used) => Ok(used),
+            None => Ok(*usage.insert(self.measure_usage()?)),
+        }
+    }
+
+    fn measure_usage(&self) -> anyhow::Result<u64> {
+        Ok(self.entries()?.into_iter().map(|(_key, len)| len).sum())
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<(String, u64)>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err).context("failed to list stored keys"),
+        };
+
+        let mut entries = Vec::new();
+
+        for entry in dir {
+            let entry = entry.context("failed to list stored keys")?;
+
+            let Some(key) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+
+            if !is_valid_key(&key) {
+                continue;
+            }
+
+            let meta = entry.metadata().context("failed to list stored keys")?;
+
+            if meta.is_file() {
+                entries.push((key, meta.len()));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        anyhow::ensure!(is_valid_key(key), "invalid storage key `{key}`");
+
+        Ok(self.dir.join(key))
+    }
+}
+
+/// Checks whether `key` may name a stored value.
+pub fn is_valid_key(key: &str) -> bool {
+    (1..=MAX_KEY_LEN).contains(&key.len())
+        && !key.starts_with('.')
+        && key
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'.' | b'-' | b'_'))
+}
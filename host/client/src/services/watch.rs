@@ -0,0 +1,66 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context as _;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Polls a module file for changes so that it can be hot reloaded.
+#[derive(Debug)]
+pub struct ModuleWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    next_poll: Instant,
+}
+
+impl ModuleWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let modified = modified_at(&path)
+            .with_context(|| format!("failed to watch module at `{}`", path.display()))?;
+
+        Ok(Self {
+            path,
+            modified,
+            next_poll: Instant::now() + POLL_INTERVAL,
+        })
+    }
+
+    /// The time at which the module should next be polled.
+    pub fn next_poll(&self) -> Instant {
+        self.next_poll
+    }
+
+    /// Checks whether the module has changed since the last poll, returning its new contents if
+    /// so. Modules which can't currently be read, e.g. because they are still being written out,
+    /// are picked up on a later poll.
+    pub fn poll(&mut self) -> Option<Vec<u8>> {
+        let now = Instant::now();
+
+        if now < self.next_poll {
+            return None;
+        }
+
+        self.next_poll = now + POLL_INTERVAL;
+
+        let modified = modified_at(&self.path).ok()?;
+
+        if modified == self.modified {
+            return None;
+        }
+
+        let module = fs::read(&self.path).ok().filter(|v| !v.is_empty())?;
+
+        self.modified = modified;
+
+        Some(module)
+    }
+}
+
+fn modified_at(path: &Path) -> io::Result<Option<SystemTime>> {
+    // Platforms which don't track modification times never report a change.
+    Ok(fs::metadata(path)?.modified().ok())
+}
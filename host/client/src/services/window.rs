@@ -3,9 +3,13 @@ use std::sync::Arc;
 use anyhow::Context as _;
 use arid::{Destructor, Handle, Strong, W, Wr};
 use arid_entity::{Component, ComponentHandle, EntityHandle, component};
+use crucible_abi::FullscreenMode;
 use crucible_renderer::{Renderer, TEXTURE_FORMAT};
 use rustc_hash::FxHashMap;
-use winit::window::{Window, WindowId};
+use winit::{
+    event_loop::ActiveEventLoop,
+    window::{Fullscreen, Window, WindowAttributes, WindowId},
+};
 
 use crate::utils::winit::is_in_live_resize;
 
@@ -26,22 +30,40 @@ pub struct GfxContextInner {
 pub async fn create_gfx_context(
     compatible_window: Arc<Window>,
 ) -> anyhow::Result<(GfxContext, wgpu::Surface<'static>)> {
-    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-        backends: wgpu::Backends::PRIMARY,
-        flags: wgpu::InstanceFlags::default(),
-        memory_budget_thresholds: wgpu::MemoryBudgetThresholds::default(),
-        backend_options: wgpu::BackendOptions::default(),
-    });
+    let instance = create_instance();
 
     let surface = instance
         .create_surface(compatible_window)
         .context("failed to create main surface")?;
 
+    let gfx = create_gfx_context_inner(instance, Some(&surface)).await?;
+
+    Ok((gfx, surface))
+}
+
+/// Creates a graphics context which isn't tied to any window, for rendering offscreen.
+pub async fn create_headless_gfx_context() -> anyhow::Result<GfxContext> {
+    create_gfx_context_inner(create_instance(), None).await
+}
+
+fn create_instance() -> wgpu::Instance {
+    wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::PRIMARY,
+        flags: wgpu::InstanceFlags::default(),
+        memory_budget_thresholds: wgpu::MemoryBudgetThresholds::default(),
+        backend_options: wgpu::BackendOptions::default(),
+    })
+}
+
+async fn create_gfx_context_inner(
+    instance: wgpu::Instance,
+    compatible_surface: Option<&wgpu::Surface<'static>>,
+) -> anyhow::Result<GfxContext> {
     let adapter = instance
         .request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::HighPerformance,
             force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
+            compatible_surface,
         })
         .await?;
 
@@ -58,17 +80,14 @@ pub async fn create_gfx_context(
         })
         .await?;
 
-    Ok((
-        Arc::new(GfxContextInner {
-            adapter,
-            instance,
-            queue,
-            features: device.features(),
-            limits: device.limits(),
-            device,
-        }),
-        surface,
-    ))
+    Ok(Arc::new(GfxContextInner {
+        adapter,
+        instance,
+        queue,
+        features: device.features(),
+        limits: device.limits(),
+        device,
+    }))
 }
 
 // === WindowManager === //
@@ -106,6 +125,12 @@ impl WindowManagerHandle {
         &mut self.m(w).renderer
     }
 
+    /// Submits the rendering work queued so far without presenting to any window.
+    pub fn submit(self, w: W) {
+        let me = self.m(w);
+        me.renderer.submit(&me.gfx.queue);
+    }
+
     pub fn create_window(
         self,
         window: Arc<Window>,
@@ -127,6 +152,30 @@ impl WindowManagerHandle {
         window_state_ref
     }
 
+    /// Creates a window presenting through a surface of the manager's graphics context.
+    pub fn open_window(
+        self,
+        event_loop: &ActiveEventLoop,
+        attributes: WindowAttributes,
+        w: W,
+    ) -> anyhow::Result<WindowStateHandle> {
+        let window = Arc::new(event_loop.create_window(attributes)?);
+
+        let surface = self
+            .gfx(w)
+            .instance
+            .create_surface(window.clone())
+            .context("failed to create window surface")?;
+
+        Ok(self.create_window(window, surface, w))
+    }
+
+    /// Closes a window created by the manager.
+    pub fn close_window(self, window: WindowStateHandle, w: W) {
+        let window_id = window.window(w).id();
+        self.m(w).windows.remove(&window_id);
+    }
+
     pub fn lookup(self, id: WindowId, w: Wr) -> WindowStateHandle {
         self.r(w).windows[&id].as_weak()
     }
@@ -154,6 +203,35 @@ impl WindowStateHandle {
         is_in_live_resize(self.window(w))
     }
 
+    /// Enters or leaves fullscreen on the window's current monitor. Exclusive fullscreen uses the
+    /// monitor's native video mode with the highest refresh rate and falls back to borderless
+    /// fullscreen if the monitor doesn't report one.
+    pub fn set_fullscreen(self, mode: FullscreenMode, w: Wr) {
+        let window = self.window(w);
+
+        let fullscreen = match mode {
+            FullscreenMode::Windowed => None,
+            FullscreenMode::Borderless => Some(Fullscreen::Borderless(None)),
+            FullscreenMode::Exclusive => {
+                let video_mode = window.current_monitor().and_then(|monitor| {
+                    let size = monitor.size();
+
+                    monitor
+                        .video_modes()
+                        .filter(|mode| mode.size() == size)
+                        .max_by_key(|mode| (mode.refresh_rate_millihertz(), mode.bit_depth()))
+                });
+
+                Some(match video_mode {
+                    Some(video_mode) => Fullscreen::Exclusive(video_mode),
+                    None => Fullscreen::Borderless(None),
+                })
+            }
+        };
+
+        window.set_fullscreen(fullscreen);
+    }
+
     pub fn start_redraw(self, w: W) -> anyhow::Result<Option<wgpu::Texture>> {
         if self.r(w).surface_texture.is_some() {
             return Ok(None);
@@ -0,0 +1,1088 @@
+#![cfg(test)]
+
+use std::{
+    collections::VecDeque,
+    env,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use arid::World;
+use arid_entity::EntityHandle;
+use crucible_abi::{Capability, ErrorKind, MANIFEST_SECTION};
+use wasmlink::{
+    FfiPtr, FfiSlice, HostClosure, HostStr, TraceEvent, Tracer, backend_tests::with_custom_section,
+};
+
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceId, ElementState, Ime, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent},
+};
+
+use crate::{
+    bindings::{
+        clipboard::ClipboardBindingsHandle,
+        mods::{GAME_PEER, LoadedMod, ModBindingsHandle},
+    },
+    services::{
+        audio::{Audio, MASTER_BUS, SoundBuffer, VoiceParams},
+        clipboard::{ClipboardBackend as _, ClipboardContents, ClipboardImage, MemoryClipboard},
+        gamepad::{Gamepads, VirtualGamepads},
+        permissions::{DecisionStore, PermissionsHandle, StorageClaims, resolve_permissions_in},
+        session::{
+            GamepadAxisInput, GamepadButtonInput, GamepadInput, ImeInput, InputSessionHandle,
+            MouseButtonInput, NetOutcome, Recorder, ScrollDeltaInput, SessionInput, StorageOutcome,
+            WindowInput, find_divergence, read_recording,
+        },
+        storage::{GameStorage, QuotaExceeded, is_valid_key},
+        watch::ModuleWatcher,
+    },
+};
+
+// === Helpers === //
+
+/// A file or directory in the temporary directory which is deleted when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        Self(env::temp_dir().join(format!("crucible-test-{}-{name}", std::process::id())))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// === Session === //
+
+fn current_time(input: SessionInput) -> Option<f64> {
+    match input {
+        SessionInput::CurrentTime(time) => Some(time),
+        _ => None,
+    }
+}
+
+fn port_call(args: &str, start_ms: u64) -> TraceEvent {
+    TraceEvent::PortCall {
+        module: "crucible".to_string(),
+        func_name: "log_message".to_string(),
+        start: Duration::from_millis(start_ms),
+        duration: Duration::from_micros(start_ms * 3),
+        args: args.to_string(),
+        result: Ok("()".to_string()),
+    }
+}
+
+#[test]
+fn sessions_replay_recorded_inputs() {
+    let file = TempFile::new("replay.rec");
+    let module_hash = blake3::hash(b"module");
+    let mut world = World::new();
+
+    let root = EntityHandle::new(None, &mut world);
+    let recorder = Recorder::create(file.path(), module_hash).unwrap();
+    let session = InputSessionHandle::recording(root.as_weak(), recorder, &mut world);
+
+    let time = session
+        .requested(
+            &mut world,
+            |_| Ok(1.5),
+            SessionInput::CurrentTime,
+            current_time,
+        )
+        .unwrap();
+
+    assert_eq!(time, 1.5);
+
+    session
+        .record(SessionInput::PollTimeouts { now: 2.0 }, &mut world)
+        .unwrap();
+    session.finish(&mut world).unwrap();
+
+    assert!(read_recording(file.path(), blake3::hash(b"other module")).is_err());
+
+    let inputs = read_recording(file.path(), module_hash).unwrap();
+    let root = EntityHandle::new(None, &mut world);
+    let session = InputSessionHandle::replaying(root.as_weak(), inputs, &mut world);
+
+    assert!(session.is_replaying(&world));
+
+    let time = session
+        .requested(
+            &mut world,
+            |_| panic!("replays should not query live values"),
+            SessionInput::CurrentTime,
+            current_time,
+        )
+        .unwrap();
+
+    assert_eq!(time, 1.5);
+
+    // The guest requesting the wrong input, or one past the end of the recording, diverges.
+    assert!(session.replayed(&mut world, current_time).is_err());
+    assert!(session.replayed(&mut world, current_time).is_err());
+}
+
+#[test]
+fn recordings_reject_malformed_files() {
+    let file = TempFile::new("malformed.rec");
+    let module_hash = blake3::hash(b"module");
+
+    fs::write(file.path(), b"not a recording").unwrap();
+    assert!(read_recording(file.path(), module_hash).is_err());
+
+    let mut recorder = Recorder::create(file.path(), module_hash).unwrap();
+    recorder.write(&SessionInput::SocketId(7)).unwrap();
+    recorder.finish().unwrap();
+
+    let mut data = fs::read(file.path()).unwrap();
+    data.push(0xFF);
+    fs::write(file.path(), data).unwrap();
+
+    assert!(read_recording(file.path(), module_hash).is_err());
+}
+
+#[test]
+fn divergence_ignores_timing_and_addresses() {
+    // The same string, traced at different guest addresses.
+    let mut recorded_memory = vec![0; 64];
+    recorded_memory[8..13].copy_from_slice(b"hello");
+
+    let mut replayed_memory = vec![0; 64];
+    replayed_memory[40..45].copy_from_slice(b"hello");
+
+    let recorded_args = Tracer::format_args(
+        &recorded_memory,
+        &HostStr::new(FfiSlice::new(FfiPtr::new(8), 5)),
+    );
+    let replayed_args = Tracer::format_args(
+        &replayed_memory,
+        &HostStr::new(FfiSlice::new(FfiPtr::new(40), 5)),
+    );
+
+    assert_eq!(recorded_args, r#""hello""#);
+
+    let recorded = [port_call(&recorded_args, 1)];
+    let replayed = [port_call(&replayed_args, 20)];
+
+    let divergence =
+        find_divergence(recorded.into_iter().map(Ok), replayed.into_iter().map(Ok)).unwrap();
+
+    assert!(divergence.is_none());
+}
+
+#[test]
+fn divergence_reports_first_differing_call() {
+    let recorded = [port_call("a", 1), port_call("b", 2), port_call("c", 3)];
+    let replayed = [port_call("a", 1), port_call("x", 2)];
+
+    let divergence = find_divergence(
+        recorded.clone().into_iter().map(Ok),
+        replayed.clone().into_iter().map(Ok),
+    )
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(divergence.index, 1);
+    assert_eq!(divergence.recorded.as_ref(), Some(&recorded[1]));
+    assert_eq!(divergence.replayed.as_ref(), Some(&replayed[1]));
+
+    // A replay which stops early diverges where it stops.
+    let divergence = find_divergence(
+        recorded.clone().into_iter().map(Ok),
+        recorded[..2].iter().cloned().map(Ok),
+    )
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(divergence.index, 2);
+    assert_eq!(divergence.replayed, None);
+}
+
+#[test]
+fn storage_calls_are_numbered_apart_from_network_calls() {
+    let mut world = World::new();
+    let root = EntityHandle::new(None, &mut world);
+    let session = InputSessionHandle::replaying(root.as_weak(), VecDeque::new(), &mut world);
+
+    assert_eq!(session.next_net_call(&mut world), 0);
+    assert_eq!(session.next_storage_call(&mut world), 0);
+    assert_eq!(session.next_storage_call(&mut world), 1);
+    assert_eq!(session.next_net_call(&mut world), 1);
+
+    let read = session.replayed_storage_outcome(0, &mut world);
+
+    // Recorded storage outcomes only resolve storage calls.
+    assert!(
+        session
+            .resolve_net_outcome(0, NetOutcome::SendMsg(Ok(())), &mut world)
+            .is_err()
+    );
+    assert!(
+        session
+            .resolve_storage_outcome(1, StorageOutcome::Read(Ok(None)), &mut world)
+            .is_err()
+    );
+
+    session
+        .resolve_storage_outcome(
+            0,
+            StorageOutcome::Read(Ok(Some(b"save".to_vec()))),
+            &mut world,
+        )
+        .unwrap();
+
+    let Ok(StorageOutcome::Read(Ok(Some(data)))) = smol::block_on(read) else {
+        panic!("the storage call resolved to the wrong outcome");
+    };
+    assert_eq!(data, b"save");
+}
+
+// === Storage === //
+
+#[test]
+fn storage_keys_are_validated() {
+    for key in ["save", "save-2.json", "A_b.c", "k".repeat(128).as_str()] {
+        assert!(is_valid_key(key), "{key:?} should be valid");
+    }
+
+    for key in [
+        "",
+        ".save",
+        ".save.tmp",
+        "../save",
+        "dir/save",
+        "dir\\save",
+        "save game",
+        "sävé",
+        "k".repeat(129).as_str(),
+    ] {
+        assert!(!is_valid_key(key), "{key:?} should be invalid");
+    }
+
+    let dir = TempFile::new("storage-keys");
+    let storage = GameStorage::at(dir.path().to_path_buf(), 1024);
+
+    assert!(storage.write("../escape", b"data").is_err());
+    assert!(storage.read(".hidden").is_err());
+    assert!(storage.delete("dir/save").is_err());
+    assert!(!dir.path().parent().unwrap().join("escape").exists());
+}
+
+#[test]
+fn storage_enforces_its_quota() {
+    let dir = TempFile::new("storage-quota");
+    let storage = GameStorage::at(dir.path().to_path_buf(), 100);
+
+    storage.write("a", &[0; 60]).unwrap();
+    storage.write("b", &[0; 40]).unwrap();
+    assert_eq!(storage.usage().unwrap(), 100);
+
+    let err = storage.write("c", &[0; 1]).unwrap_err();
+    let err = err.downcast_ref::<QuotaExceeded>().unwrap();
+    assert_eq!((err.key.as_str(), err.len, err.quota), ("c", 1, 100));
+    assert_eq!(storage.read("c").unwrap(), None);
+
+    // Replacing a value only counts the difference in size.
+    storage.write("a", &[1; 50]).unwrap();
+    storage.write("b", &[1; 50]).unwrap();
+    assert!(storage.write("b", &[1; 51]).is_err());
+    assert_eq!(storage.usage().unwrap(), 100);
+
+    storage.delete("a").unwrap();
+    storage.write("c", &[2; 50]).unwrap();
+
+    let mut keys = storage.list().unwrap();
+    keys.sort();
+    assert_eq!(keys, ["b", "c"]);
+}
+
+#[test]
+fn storage_replaces_values_atomically() {
+    let dir = TempFile::new("storage-atomic");
+    let storage = GameStorage::at(dir.path().to_path_buf(), 100);
+
+    storage.write("save", b"first").unwrap();
+    storage.write("save", b"second").unwrap();
+    assert_eq!(storage.read("save").unwrap(), Some(b"second".to_vec()));
+
+    // A rejected write leaves the old value in place.
+    assert!(storage.write("save", &[0; 101]).is_err());
+    assert_eq!(storage.read("save").unwrap(), Some(b"second".to_vec()));
+
+    // No temporary files are left behind and those left by an interrupted write are ignored.
+    let files = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(files, ["save"]);
+
+    fs::write(dir.path().join(".save.tmp"), [0; 50]).unwrap();
+    assert_eq!(storage.list().unwrap(), ["save"]);
+    assert_eq!(storage.usage().unwrap(), 6);
+    assert_eq!(storage.read("save").unwrap(), Some(b"second".to_vec()));
+}
+
+#[test]
+fn storage_usage_is_shared_between_clones() {
+    let dir = TempFile::new("storage-usage");
+    let storage = GameStorage::at(dir.path().to_path_buf(), 100);
+
+    // Concurrent writes can't jointly go over the quota.
+    let writers = (0..8)
+        .map(|i| {
+            let storage = storage.clone();
+            thread::spawn(move || storage.write(&format!("key-{i}"), &[0; 30]).is_ok())
+        })
+        .collect::<Vec<_>>();
+
+    let written = writers
+        .into_iter()
+        .map(|writer| writer.join().unwrap())
+        .filter(|&ok| ok)
+        .count();
+
+    assert_eq!(written, 3);
+    assert_eq!(storage.usage().unwrap(), 90);
+
+    // The tracked usage matches what is on disk.
+    let reopened = GameStorage::at(dir.path().to_path_buf(), 100);
+    assert_eq!(reopened.usage().unwrap(), 90);
+
+    let keys = storage.list().unwrap();
+    storage.delete(&keys[0]).unwrap();
+    storage.delete(&keys[0]).unwrap();
+    assert_eq!(storage.usage().unwrap(), 60);
+
+    storage.write("other", &[0; 40]).unwrap();
+    assert_eq!(storage.usage().unwrap(), 100);
+}
+
+#[test]
+fn storage_claims_persist() {
+    let file = TempFile::new("storage-claims.txt");
+
+    let mut claims = StorageClaims::open(file.path().to_path_buf()).unwrap();
+    assert_eq!(claims.owner("my-game"), None);
+
+    claims.claim("my-game", "/games/my-game.wasm");
+    claims.save().unwrap();
+
+    let claims = StorageClaims::open(file.path().to_path_buf()).unwrap();
+    assert_eq!(claims.owner("my-game"), Some("/games/my-game.wasm"));
+    assert_eq!(claims.owner("other-game"), None);
+
+    fs::write(file.path(), "no tab here\n").unwrap();
+    assert!(StorageClaims::open(file.path().to_path_buf()).is_err());
+}
+
+// === Permissions === //
+
+fn module_with_manifest(manifest: &str) -> Vec<u8> {
+    with_custom_section(b"\0asm\x01\0\0\0".to_vec(), MANIFEST_SECTION, manifest)
+}
+
+#[test]
+fn permission_decisions_persist() {
+    let file = TempFile::new("permissions.txt");
+    let hash = blake3::hash(b"game");
+    let net = "net [::1]:8080".parse::<Capability>().unwrap();
+
+    let mut decisions = DecisionStore::open(file.path().to_path_buf()).unwrap();
+    assert_eq!(decisions.get(hash, &Capability::Clipboard), None);
+
+    decisions.set(hash, Capability::Clipboard, false);
+    decisions.set(hash, net.clone(), true);
+    decisions.save().unwrap();
+
+    let decisions = DecisionStore::open(file.path().to_path_buf()).unwrap();
+    assert_eq!(decisions.get(hash, &Capability::Clipboard), Some(false));
+    assert_eq!(decisions.get(hash, &net), Some(true));
+    assert_eq!(decisions.get(hash, &Capability::Fullscreen), None);
+    assert_eq!(decisions.get(blake3::hash(b"update"), &net), None);
+
+    fs::write(file.path(), format!("{hash}\tmaybe\tclipboard\n")).unwrap();
+    assert!(DecisionStore::open(file.path().to_path_buf()).is_err());
+}
+
+#[test]
+fn permissions_are_asked_for_per_capability() {
+    let dir = TempFile::new("permissions");
+    let data = dir.path().join("data");
+    fs::create_dir_all(&data).unwrap();
+
+    let resolve = |name: &str, module: &[u8], answer: &dyn Fn(&Capability) -> bool| {
+        let path = dir.path().join(name);
+        fs::write(&path, module).unwrap();
+
+        let mut asked = Vec::new();
+        let granted = resolve_permissions_in(module, &path, &data, |capability| {
+            asked.push(capability.clone());
+            answer(capability)
+        })
+        .unwrap();
+
+        (granted, asked)
+    };
+
+    let net = "net example.com:8080".parse::<Capability>().unwrap();
+    let storage = "storage my-game".parse::<Capability>().unwrap();
+    let game = module_with_manifest("net example.com:8080\nclipboard\nstorage my-game\n");
+
+    // Every capability is asked about on its own.
+    let (granted, asked) = resolve("game.wasm", &game, &|capability| {
+        *capability != Capability::Clipboard
+    });
+    assert_eq!(granted, [net.clone(), storage.clone()]);
+    assert_eq!(asked, [net.clone(), Capability::Clipboard, storage.clone()]);
+
+    // Decisions are remembered, including denials.
+    let (granted, asked) = resolve("game.wasm", &game, &|_| unreachable!());
+    assert_eq!(granted, [net.clone(), storage.clone()]);
+    assert!(asked.is_empty());
+
+    // Decisions are kept per module hash so updating the game asks again.
+    let update = module_with_manifest("net example.com:8080\nstorage my-game\nfullscreen\n");
+    let (granted, asked) = resolve("game.wasm", &update, &|_| true);
+    assert_eq!(
+        granted,
+        [net.clone(), storage.clone(), Capability::Fullscreen]
+    );
+    assert_eq!(
+        asked,
+        [net.clone(), storage.clone(), Capability::Fullscreen]
+    );
+
+    // Games loaded from elsewhere can't take over a claimed storage id.
+    let impostor = module_with_manifest("storage my-game\nnet example.com:8080\n");
+    let (granted, asked) = resolve("impostor.wasm", &impostor, &|_| true);
+    assert_eq!(granted, [net.clone()]);
+    assert_eq!(asked, [net.clone()]);
+
+    // Modules without a manifest ask for nothing.
+    let (granted, asked) = resolve("plain.wasm", b"\0asm\x01\0\0\0", &|_| unreachable!());
+    assert!(granted.is_empty() && asked.is_empty());
+}
+
+// === Hot Reloading === //
+
+#[test]
+fn module_watcher_skips_unreadable_modules() {
+    let file = TempFile::new("watched.wasm");
+    fs::write(file.path(), b"first").unwrap();
+
+    let set_modified = |secs| {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        File::options()
+            .write(true)
+            .open(file.path())
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    };
+
+    let poll = |watcher: &mut ModuleWatcher| {
+        thread::sleep(
+            watcher
+                .next_poll()
+                .saturating_duration_since(Instant::now()),
+        );
+        watcher.poll()
+    };
+
+    set_modified(1_000);
+    let mut watcher = ModuleWatcher::new(file.path()).unwrap();
+    assert_eq!(poll(&mut watcher), None);
+
+    // Modules which are still being written out are left for a later poll rather than reloaded,
+    // keeping the old instance running.
+    fs::write(file.path(), b"").unwrap();
+    set_modified(2_000);
+    assert_eq!(poll(&mut watcher), None);
+
+    fs::write(file.path(), b"second").unwrap();
+    set_modified(3_000);
+    assert_eq!(poll(&mut watcher), Some(b"second".to_vec()));
+    assert_eq!(poll(&mut watcher), None);
+
+    fs::remove_file(file.path()).unwrap();
+    assert_eq!(poll(&mut watcher), None);
+}
+
+// === Gamepads === //
+
+#[test]
+fn virtual_gamepads_report_connections() {
+    let mut gamepads = Gamepads::new_virtual(VirtualGamepads::default());
+    assert_eq!(gamepads.next_poll(), None);
+
+    let pads = gamepads.virtual_pads().unwrap();
+    let left = pads.connect("Left");
+    let right = pads.connect("Right");
+    assert_ne!(left, right);
+
+    pads.set_button(left, GamepadButtonInput::South, true);
+    pads.set_axis(right, GamepadAxisInput::LeftStickX, 0.05);
+    pads.disconnect(right);
+
+    // Events of gamepads which aren't connected are dropped.
+    pads.set_axis(right, GamepadAxisInput::LeftStickX, 1.0);
+    pads.disconnect(right);
+    pads.set_button(42, GamepadButtonInput::East, true);
+
+    // Axes are reported raw since deadzones are left to the guest.
+    assert_eq!(
+        gamepads.poll(),
+        [
+            GamepadInput::Connected {
+                id: left,
+                name: "Left".to_string(),
+            },
+            GamepadInput::Connected {
+                id: right,
+                name: "Right".to_string(),
+            },
+            GamepadInput::Button {
+                id: left,
+                button: GamepadButtonInput::South,
+                pressed: true,
+            },
+            GamepadInput::Axis {
+                id: right,
+                axis: GamepadAxisInput::LeftStickX,
+                value: 0.05,
+            },
+            GamepadInput::Disconnected { id: right },
+        ]
+    );
+    assert!(gamepads.poll().is_empty());
+    assert_eq!(gamepads.connected().collect::<Vec<_>>(), [(left, "Left")]);
+
+    gamepads.virtual_pads().unwrap().disconnect(left);
+
+    assert_eq!(gamepads.poll(), [GamepadInput::Disconnected { id: left }]);
+    assert_eq!(gamepads.connected().count(), 0);
+}
+
+// === Windows === //
+
+#[test]
+fn window_events_are_converted_for_the_guest() {
+    // SAFETY: the ID is only compared, never passed to the platform.
+    let device_id = unsafe { DeviceId::dummy() };
+
+    let cases = [
+        (WindowEvent::Focused(true), WindowInput::Focused(true)),
+        (WindowEvent::Occluded(false), WindowInput::Occluded(false)),
+        (
+            WindowEvent::Resized(PhysicalSize::new(320, 240)),
+            WindowInput::Resized {
+                width: 320,
+                height: 240,
+            },
+        ),
+        (
+            WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(1.5, 2.),
+            },
+            WindowInput::MouseMoved { x: 1.5, y: 2. },
+        ),
+        (
+            WindowEvent::CursorEntered { device_id },
+            WindowInput::CursorEntered,
+        ),
+        (
+            WindowEvent::CursorLeft { device_id },
+            WindowInput::CursorLeft,
+        ),
+        (
+            WindowEvent::MouseInput {
+                device_id,
+                state: ElementState::Pressed,
+                button: MouseButton::Other(7),
+            },
+            WindowInput::MouseInput {
+                button: MouseButtonInput::Other(7),
+                pressed: true,
+            },
+        ),
+        (
+            WindowEvent::MouseWheel {
+                device_id,
+                delta: MouseScrollDelta::LineDelta(0.5, -1.),
+                phase: TouchPhase::Moved,
+            },
+            WindowInput::MouseWheel(ScrollDeltaInput::Line { x: 0.5, y: -1. }),
+        ),
+        (
+            WindowEvent::MouseWheel {
+                device_id,
+                delta: MouseScrollDelta::PixelDelta(PhysicalPosition::new(3., 4.)),
+                phase: TouchPhase::Moved,
+            },
+            WindowInput::MouseWheel(ScrollDeltaInput::Pixel { x: 3., y: 4. }),
+        ),
+        (
+            WindowEvent::Ime(Ime::Preedit("kana".to_string(), Some((1, 3)))),
+            WindowInput::Ime(ImeInput::Preedit {
+                text: "kana".to_string(),
+                cursor: Some((1, 3)),
+            }),
+        ),
+        (
+            WindowEvent::Ime(Ime::Preedit(String::new(), None)),
+            WindowInput::Ime(ImeInput::Preedit {
+                text: String::new(),
+                cursor: None,
+            }),
+        ),
+        (
+            WindowEvent::Ime(Ime::Commit("hello".to_string())),
+            WindowInput::Ime(ImeInput::Commit("hello".to_string())),
+        ),
+        (WindowEvent::CloseRequested, WindowInput::CloseRequested),
+    ];
+
+    for (event, expected) in cases {
+        assert_eq!(WindowInput::from_winit(&event), Some(expected), "{event:?}");
+    }
+
+    // Redraws need a framebuffer and the window's position is none of the guest's business.
+    assert_eq!(WindowInput::from_winit(&WindowEvent::RedrawRequested), None);
+    assert_eq!(
+        WindowInput::from_winit(&WindowEvent::Moved(PhysicalPosition::new(0, 0))),
+        None
+    );
+}
+
+// === Clipboard === //
+
+#[test]
+fn memory_clipboards_round_trip_contents() {
+    let mut clipboard = MemoryClipboard::default();
+    assert_eq!(clipboard.read_text().unwrap(), None);
+
+    clipboard
+        .write(ClipboardContents::Text("hello".to_string()))
+        .unwrap();
+    assert_eq!(clipboard.read_text().unwrap().as_deref(), Some("hello"));
+
+    let image = ClipboardImage {
+        width: 2,
+        height: 1,
+        rgba: vec![255, 0, 0, 255, 0, 0, 255, 128],
+    };
+
+    clipboard
+        .write(ClipboardContents::Image(image.clone()))
+        .unwrap();
+    assert_eq!(clipboard.contents, Some(ClipboardContents::Image(image)));
+
+    // Images can't be pasted as text.
+    assert_eq!(clipboard.read_text().unwrap(), None);
+}
+
+#[test]
+fn clipboard_reads_require_permission() {
+    let mut world = World::new();
+    let root = EntityHandle::new(None, &mut world);
+    let session = InputSessionHandle::live(root.as_weak(), &mut world);
+
+    let clipboard = |owner: EntityHandle, granted: Vec<Capability>, world: &mut World| {
+        let permissions = PermissionsHandle::new(owner, granted, world);
+        let clipboard = MemoryClipboard {
+            contents: Some(ClipboardContents::Text("secret".to_string())),
+        };
+
+        let bindings = ClipboardBindingsHandle::new(
+            owner,
+            session.as_weak(),
+            permissions.as_weak(),
+            Box::new(clipboard),
+            world,
+        );
+
+        (permissions, bindings)
+    };
+
+    // Writing needs no permission but reading does.
+    let denied = EntityHandle::new(None, &mut world);
+    let (_permissions, bindings) = clipboard(denied.as_weak(), Vec::new(), &mut world);
+
+    let err = bindings.read_text(&mut world).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::PermissionDenied);
+    assert!(!err.message.contains("secret"));
+
+    bindings
+        .write(ClipboardContents::Text("copied".to_string()), &mut world)
+        .unwrap()
+        .unwrap();
+    assert!(bindings.read_text(&mut world).is_err());
+
+    let granted = EntityHandle::new(None, &mut world);
+    let (_permissions, bindings) =
+        clipboard(granted.as_weak(), vec![Capability::Clipboard], &mut world);
+    assert_eq!(
+        bindings.read_text(&mut world).unwrap().as_deref(),
+        Some("secret")
+    );
+
+    bindings
+        .write(ClipboardContents::Text("copied".to_string()), &mut world)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        bindings.read_text(&mut world).unwrap().as_deref(),
+        Some("copied")
+    );
+}
+
+// === Mods === //
+
+fn loaded_mod(name: &str) -> LoadedMod {
+    LoadedMod {
+        name: name.to_string(),
+        content_hash: blake3::hash(name.as_bytes()),
+    }
+}
+
+fn drain_messages(mods: ModBindingsHandle, world: &mut World) -> Vec<(u32, u32, Vec<u8>)> {
+    std::iter::from_fn(|| mods.take_message(world))
+        .map(|msg| (msg.from, msg.to, msg.message))
+        .collect()
+}
+
+#[test]
+fn extension_points_are_declared_by_the_game() {
+    let mut world = World::new();
+    let root = EntityHandle::new(None, &mut world);
+    let mods = ModBindingsHandle::new(root.as_weak(), vec![loaded_mod("a")], &mut world);
+    let mods = mods.as_weak();
+
+    let err = mods
+        .open(1, "ext".to_string(), HostClosure::new(1), true, &mut world)
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Other);
+
+    let err = mods
+        .open(1, "ext".to_string(), HostClosure::new(1), false, &mut world)
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotFound);
+
+    mods.open(
+        GAME_PEER,
+        "ext".to_string(),
+        HostClosure::new(0),
+        true,
+        &mut world,
+    )
+    .unwrap();
+    assert!(
+        mods.open(
+            GAME_PEER,
+            "ext".to_string(),
+            HostClosure::new(0),
+            true,
+            &mut world
+        )
+        .is_err()
+    );
+
+    mods.open(1, "ext".to_string(), HostClosure::new(1), false, &mut world)
+        .unwrap();
+    assert!(
+        mods.open(1, "ext".to_string(), HostClosure::new(1), false, &mut world)
+            .is_err()
+    );
+}
+
+#[test]
+fn extension_messages_reach_their_recipients() {
+    let mut world = World::new();
+    let root = EntityHandle::new(None, &mut world);
+    let mods = ModBindingsHandle::new(
+        root.as_weak(),
+        vec![loaded_mod("a"), loaded_mod("b")],
+        &mut world,
+    );
+    let mods = mods.as_weak();
+
+    let game = mods
+        .open(
+            GAME_PEER,
+            "ext".to_string(),
+            HostClosure::new(0),
+            true,
+            &mut world,
+        )
+        .unwrap();
+    let a = mods
+        .open(1, "ext".to_string(), HostClosure::new(1), false, &mut world)
+        .unwrap();
+    let b = mods
+        .open(2, "ext".to_string(), HostClosure::new(2), false, &mut world)
+        .unwrap();
+
+    // Broadcasts reach every other member.
+    mods.send(GAME_PEER, game, None, b"all".to_vec(), &mut world)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        drain_messages(mods, &mut world),
+        [(0, 1, b"all".to_vec()), (0, 2, b"all".to_vec())]
+    );
+
+    // Direct messages reach their recipient alone.
+    mods.send(1, a, Some(2), b"direct".to_vec(), &mut world)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        drain_messages(mods, &mut world),
+        [(1, 2, b"direct".to_vec())]
+    );
+
+    let err = mods
+        .send(1, a, Some(3), Vec::new(), &mut world)
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotFound);
+
+    // Messages to peers which left before delivery are dropped.
+    mods.send(2, b, None, b"late".to_vec(), &mut world)
+        .unwrap()
+        .unwrap();
+    mods.close(1, a, &mut world).unwrap();
+    assert_eq!(drain_messages(mods, &mut world), [(2, 0, b"late".to_vec())]);
+    assert_eq!(mods.pending_count(&world), 0);
+
+    let err = mods.send(1, a, None, Vec::new(), &mut world).unwrap_err();
+    assert!(err.to_string().contains("invalid extension handle"));
+
+    // The game closing an extension point removes it for everyone.
+    mods.close(GAME_PEER, game, &mut world).unwrap();
+
+    let err = mods
+        .send(2, b, None, Vec::new(), &mut world)
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotFound);
+
+    let err = mods
+        .open(1, "ext".to_string(), HostClosure::new(1), false, &mut world)
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::NotFound);
+}
+
+#[test]
+fn downloaded_mods_are_started_once() {
+    let mut world = World::new();
+    let root = EntityHandle::new(None, &mut world);
+    let mods = ModBindingsHandle::new(root.as_weak(), vec![loaded_mod("a")], &mut world);
+    let mods = mods.as_weak();
+
+    // Mods which are already loaded or queued are skipped.
+    mods.push_downloaded(loaded_mod("a"), Vec::new(), &mut world);
+    mods.push_downloaded(loaded_mod("b"), b"b".to_vec(), &mut world);
+    mods.push_downloaded(loaded_mod("b"), b"b".to_vec(), &mut world);
+
+    let downloaded = mods.take_downloaded(&mut world);
+    assert_eq!(downloaded.len(), 1);
+    assert_eq!(downloaded[0].0.name, "b");
+
+    let (info, _module) = downloaded.into_iter().next().unwrap();
+    assert_eq!(mods.add_mod(info, &mut world), 2);
+    assert_eq!(
+        mods.known_mods(&world),
+        [loaded_mod("a").content_hash, loaded_mod("b").content_hash]
+    );
+
+    mods.push_downloaded(loaded_mod("b"), b"b".to_vec(), &mut world);
+    assert!(mods.take_downloaded(&mut world).is_empty());
+
+    // Mods started late can join extension points like any other.
+    mods.open(
+        GAME_PEER,
+        "ext".to_string(),
+        HostClosure::new(0),
+        true,
+        &mut world,
+    )
+    .unwrap();
+    mods.open(2, "ext".to_string(), HostClosure::new(2), false, &mut world)
+        .unwrap();
+}
+
+// === Audio === //
+
+fn mono(samples: &[f32], sample_rate: u32) -> Arc<SoundBuffer> {
+    Arc::new(SoundBuffer::from_pcm(samples.to_vec(), 1, sample_rate).unwrap())
+}
+
+fn render(audio: &Audio, frames: usize) -> Vec<f32> {
+    let mut out = vec![f32::NAN; frames * 2];
+    audio.render_offline(&mut out);
+    out
+}
+
+#[track_caller]
+fn assert_samples(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+
+    for (actual_sample, expected_sample) in actual.iter().zip(expected) {
+        assert!(
+            (actual_sample - expected_sample).abs() < 1e-5,
+            "expected {expected:?}, got {actual:?}"
+        );
+    }
+}
+
+#[test]
+fn offline_mixes_apply_bus_gain_and_pan() {
+    let audio = Audio::offline(4);
+    assert!(audio.is_offline());
+
+    {
+        let mut mixer = audio.mixer();
+        mixer.add_bus(1, MASTER_BUS).unwrap();
+        mixer.set_bus_volume(1, 0.5).unwrap();
+        mixer.set_bus_volume(MASTER_BUS, 0.8).unwrap();
+        mixer
+            .play(0, mono(&[0.5; 8], 4), 1, VoiceParams::default(), false)
+            .unwrap();
+    }
+
+    // Gains multiply along the chain of buses.
+    assert_samples(&render(&audio, 2), &[0.2, 0.2, 0.2, 0.2]);
+
+    let panned = |pan| VoiceParams {
+        pan,
+        ..VoiceParams::default()
+    };
+
+    audio.mixer().set_voice_params(0, panned(-1.));
+    assert_samples(&render(&audio, 1), &[0.2, 0.]);
+
+    audio.mixer().set_voice_params(0, panned(0.5));
+    assert_samples(&render(&audio, 1), &[0.1, 0.2]);
+
+    audio.mixer().set_voice_params(
+        0,
+        VoiceParams {
+            volume: 0.5,
+            ..VoiceParams::default()
+        },
+    );
+    assert_samples(&render(&audio, 1), &[0.1, 0.1]);
+
+    // Removing a bus stops the voices playing into it.
+    audio.mixer().remove_bus(1).unwrap();
+    assert_samples(&render(&audio, 1), &[0., 0.]);
+
+    assert!(audio.mixer().remove_bus(MASTER_BUS).is_err());
+    assert!(
+        audio
+            .mixer()
+            .play(1, mono(&[0.5], 4), 1, VoiceParams::default(), false)
+            .is_err()
+    );
+}
+
+#[test]
+fn offline_mixes_resample_by_pitch() {
+    let ramp = mono(&[0., 0.25, 0.5, 0.75], 4);
+    let audio = Audio::offline(8);
+
+    // Playing a buffer at twice its sample rate interpolates between its frames.
+    audio
+        .mixer()
+        .play(0, ramp.clone(), MASTER_BUS, VoiceParams::default(), false)
+        .unwrap();
+
+    let out = render(&audio, 9);
+    let left = out.iter().step_by(2).copied().collect::<Vec<_>>();
+    assert_samples(&left, &[0., 0.125, 0.25, 0.375, 0.5, 0.625, 0.75, 0.75, 0.]);
+
+    // Doubling the pitch makes up for the difference in sample rates.
+    audio
+        .mixer()
+        .play(
+            0,
+            ramp.clone(),
+            MASTER_BUS,
+            VoiceParams {
+                pitch: 2.,
+                ..VoiceParams::default()
+            },
+            false,
+        )
+        .unwrap();
+
+    let out = render(&audio, 5);
+    let left = out.iter().step_by(2).copied().collect::<Vec<_>>();
+    assert_samples(&left, &[0., 0.25, 0.5, 0.75, 0.]);
+
+    // Buffers with a higher sample rate than the mix skip frames.
+    let audio = Audio::offline(2);
+    audio
+        .mixer()
+        .play(0, ramp, MASTER_BUS, VoiceParams::default(), false)
+        .unwrap();
+
+    let out = render(&audio, 3);
+    let left = out.iter().step_by(2).copied().collect::<Vec<_>>();
+    assert_samples(&left, &[0., 0.5, 0.]);
+}
+
+#[test]
+fn offline_voices_end_unless_looping() {
+    let audio = Audio::offline(4);
+    let sound = mono(&[0.75, 0.25], 4);
+
+    for (id, looping) in [(0, false), (1, true)] {
+        audio
+            .mixer()
+            .play(
+                id,
+                sound.clone(),
+                MASTER_BUS,
+                VoiceParams::default(),
+                looping,
+            )
+            .unwrap();
+    }
+
+    // Both voices play at first, clamping the mix, until the one-shot voice runs out.
+    assert_samples(
+        &render(&audio, 4),
+        &[1., 1., 0.5, 0.5, 0.75, 0.75, 0.25, 0.25],
+    );
+
+    // The finished voice is gone for good while the looping one keeps wrapping around.
+    audio.mixer().set_voice_params(0, VoiceParams::default());
+    assert_samples(&render(&audio, 3), &[0.75, 0.75, 0.25, 0.25, 0.75, 0.75]);
+
+    // Looping voices interpolate across the end of the buffer.
+    audio.mixer().set_voice_params(
+        1,
+        VoiceParams {
+            pitch: 0.5,
+            ..VoiceParams::default()
+        },
+    );
+    assert_samples(&render(&audio, 3), &[0.25, 0.25, 0.5, 0.5, 0.75, 0.75]);
+
+    audio.mixer().stop(1);
+    assert_samples(&render(&audio, 2), &[0.; 4]);
+}
@@ -0,0 +1,15 @@
+use std::{env, path::PathBuf};
+
+use anyhow::Context as _;
+
+/// The directory in which the client keeps its per-user data. Can be overridden through the
+/// `CRUCIBLE_DATA_DIR` environment variable.
+pub fn data_dir() -> anyhow::Result<PathBuf> {
+    if let Some(dir) = env::var_os("CRUCIBLE_DATA_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let dir = dirs::data_dir().context("failed to find the user's data directory")?;
+
+    Ok(dir.join("crucible"))
+}
@@ -33,7 +33,7 @@ pub fn run_winit(event_loop: EventLoop<()>, handler: &mut impl WinitHandler) ->
         handler: &'a mut H,
         _waker: Arc<WinitWaker>,
         erased_waker: task::Waker,
-        background: BackgroundTasksExecutor<ActiveEventLoop, H, ()>,
+        background: BackgroundTasksExecutor<(), H, ()>,
         error: Option<anyhow::Error>,
     }
 
@@ -112,7 +112,7 @@ pub fn run_winit(event_loop: EventLoop<()>, handler: &mut impl WinitHandler) ->
                     .about_to_wait(event_loop, this.background.handle())?;
 
                 let res = this.background.poll(
-                    event_loop,
+                    &(),
                     this.handler,
                     &mut task::Context::from_waker(&this.erased_waker),
                 );
@@ -172,7 +172,7 @@ pub trait WinitHandler: Sized + 'static {
     fn new_events(
         &mut self,
         event_loop: &ActiveEventLoop,
-        background: &BackgroundTasks<ActiveEventLoop, Self>,
+        background: &BackgroundTasks<(), Self>,
         cause: StartCause,
     ) -> anyhow::Result<()> {
         let _ = (event_loop, background, cause);
@@ -183,13 +183,13 @@ pub trait WinitHandler: Sized + 'static {
     fn resumed(
         &mut self,
         event_loop: &ActiveEventLoop,
-        background: &BackgroundTasks<ActiveEventLoop, Self>,
+        background: &BackgroundTasks<(), Self>,
     ) -> anyhow::Result<()>;
 
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
-        background: &BackgroundTasks<ActiveEventLoop, Self>,
+        background: &BackgroundTasks<(), Self>,
         window_id: WindowId,
         event: WindowEvent,
     ) -> anyhow::Result<()>;
@@ -197,7 +197,7 @@ pub trait WinitHandler: Sized + 'static {
     fn device_event(
         &mut self,
         event_loop: &ActiveEventLoop,
-        background: &BackgroundTasks<ActiveEventLoop, Self>,
+        background: &BackgroundTasks<(), Self>,
         device_id: DeviceId,
         event: DeviceEvent,
     ) -> anyhow::Result<()> {
@@ -209,7 +209,7 @@ pub trait WinitHandler: Sized + 'static {
     fn about_to_wait(
         &mut self,
         event_loop: &ActiveEventLoop,
-        background: &BackgroundTasks<ActiveEventLoop, Self>,
+        background: &BackgroundTasks<(), Self>,
     ) -> anyhow::Result<()> {
         let _ = (event_loop, background);
 
@@ -219,7 +219,7 @@ pub trait WinitHandler: Sized + 'static {
     fn suspended(
         &mut self,
         event_loop: &ActiveEventLoop,
-        background: &BackgroundTasks<ActiveEventLoop, Self>,
+        background: &BackgroundTasks<(), Self>,
     ) -> anyhow::Result<()> {
         let _ = (event_loop, background);
 
@@ -229,7 +229,7 @@ pub trait WinitHandler: Sized + 'static {
     fn exiting(
         &mut self,
         event_loop: &ActiveEventLoop,
-        background: &BackgroundTasks<ActiveEventLoop, Self>,
+        background: &BackgroundTasks<(), Self>,
     ) -> anyhow::Result<()> {
         let _ = (event_loop, background);
 
@@ -239,7 +239,7 @@ pub trait WinitHandler: Sized + 'static {
     fn memory_warning(
         &mut self,
         event_loop: &ActiveEventLoop,
-        background: &BackgroundTasks<ActiveEventLoop, Self>,
+        background: &BackgroundTasks<(), Self>,
     ) -> anyhow::Result<()> {
         let _ = (event_loop, background);
 
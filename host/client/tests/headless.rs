@@ -0,0 +1,298 @@
+//! Drives the demo game through a headless session connected to a local server. The game and the
+//! server have to be built beforehand, which `just headless-test` takes care of, and a GPU must be
+//! available. Their paths can be overridden through the `CRUCIBLE_TEST_GAME` and
+//! `CRUCIBLE_TEST_SERVER` environment variables.
+
+use std::{
+    env, fs,
+    io::{BufRead as _, BufReader},
+    path::{Path, PathBuf},
+    process::{self, Child, Command, Stdio},
+    sync::{Mutex, MutexGuard, PoisonError},
+    thread,
+};
+
+/// Held by the test running a server since every server listens on the same port.
+static SERVER_LOCK: Mutex<()> = Mutex::new(());
+
+/// A child process which is killed when dropped.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// A directory in the temporary directory which is deleted when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("crucible-test-{}-{name}", process::id()));
+        fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn built_artifact(var: &str, default: &str) -> PathBuf {
+    env::var_os(var).map_or_else(
+        || {
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../target")
+                .join(default)
+        },
+        PathBuf::from,
+    )
+}
+
+/// A running server, killed before the next test may start its own.
+struct Server {
+    _process: KillOnDrop,
+    _lock: MutexGuard<'static, ()>,
+}
+
+/// Starts the server for `game` and waits until it accepts connections.
+fn start_server(game: &Path) -> Server {
+    // A test failing while holding the lock must not fail the others.
+    let lock = SERVER_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let path = built_artifact("CRUCIBLE_TEST_SERVER", "debug/crucible-host-server");
+
+    let mut server = KillOnDrop(
+        Command::new(&path)
+            .arg(game)
+            .env("NO_COLOR", "1")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|err| panic!("failed to start `{}`: {err}", path.display())),
+    );
+
+    let mut lines = BufReader::new(server.0.stdout.take().unwrap()).lines();
+
+    assert!(
+        lines
+            .by_ref()
+            .map_while(Result::ok)
+            .any(|line| line.contains("Listening on")),
+        "the server exited before listening"
+    );
+
+    // Keep draining the server's output so that it never blocks on a full pipe.
+    thread::spawn(move || lines.for_each(drop));
+
+    Server {
+        _process: server,
+        _lock: lock,
+    }
+}
+
+/// Parses the position the demo game logs when it exits, e.g. `Exiting at [250, 0]`.
+fn exit_position(log: &str) -> Option<(f32, f32)> {
+    let line = log.lines().find(|line| line.contains("Exiting at ["))?;
+    let (_, pos) = line.split_once("Exiting at [")?;
+    let (x, y) = pos.split_once(']')?.0.split_once(", ")?;
+
+    Some((x.parse().ok()?, y.parse().ok()?))
+}
+
+/// Runs the demo game through a headless session following `script`, returning what it logged.
+fn run_headless(game: &Path, dir: &TempDir, script: &str) -> String {
+    let script_path = dir.path().join("script.txt");
+    fs::write(&script_path, script).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_crucible-host-client"))
+        .arg("--headless")
+        .arg(&script_path)
+        .arg(game)
+        .env("NO_COLOR", "1")
+        .env("CRUCIBLE_DATA_DIR", dir.path().join("data"))
+        .output()
+        .unwrap();
+
+    let log = String::from_utf8_lossy(&output.stdout).into_owned();
+
+    assert!(
+        output.status.success(),
+        "the headless session failed:\n{log}\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    log
+}
+
+/// Asserts that holding `D` for half a second of virtual time moved the player right at 500 pixels
+/// per second before the game exited.
+fn assert_moved_right(log: &str) {
+    let (x, y) = exit_position(log).unwrap_or_else(|| panic!("the game never exited:\n{log}"));
+
+    assert!((200. ..=300.).contains(&x), "unexpected position {x}");
+    assert_eq!(y, 0.);
+}
+
+#[test]
+fn demo_game_follows_scripted_input() {
+    let game = built_artifact(
+        "CRUCIBLE_TEST_GAME",
+        "wasm32-unknown-unknown/debug/demo-game.wasm",
+    );
+
+    let _server = start_server(&game);
+
+    let dir = TempDir::new("headless");
+
+    // The real-time sleep lets the game finish connecting before it starts reading input.
+    let log = run_headless(
+        &game,
+        &dir,
+        "size 320 240\n\
+         grant net 127.0.0.1:8080\n\
+         sleep 3\n\
+         frames 10\n\
+         key press KeyD\n\
+         frames 30\n\
+         key release KeyD\n\
+         frames 10\n\
+         close\n\
+         frames 1\n",
+    );
+
+    assert!(log.contains("socket ID"), "the game never joined:\n{log}");
+    assert!(log.contains("Ping"), "the game never drew a frame:\n{log}");
+
+    assert_moved_right(&log);
+}
+
+#[test]
+fn bad_reloads_keep_the_old_instance() {
+    let game = built_artifact(
+        "CRUCIBLE_TEST_GAME",
+        "wasm32-unknown-unknown/debug/demo-game.wasm",
+    );
+
+    let _server = start_server(&game);
+
+    let dir = TempDir::new("reload");
+
+    // One module doesn't compile while the other compiles but lacks the game's exports.
+    let garbage = dir.path().join("garbage.wasm");
+    fs::write(&garbage, b"not a module").unwrap();
+
+    let empty = dir.path().join("empty.wasm");
+    fs::write(&empty, b"\0asm\x01\0\0\0").unwrap();
+
+    let log = run_headless(
+        &game,
+        &dir,
+        &format!(
+            "size 320 240\n\
+             grant net 127.0.0.1:8080\n\
+             sleep 3\n\
+             frames 10\n\
+             reload {}\n\
+             reload {}\n\
+             frames 10\n\
+             key press KeyD\n\
+             frames 30\n\
+             key release KeyD\n\
+             frames 10\n\
+             close\n\
+             frames 1\n",
+            garbage.display(),
+            empty.display(),
+        ),
+    );
+
+    assert!(
+        log.contains("Failed to load the changed module, keeping the old one"),
+        "the garbage module was loaded:\n{log}"
+    );
+    assert!(
+        log.contains("Failed to instantiate the changed module, keeping the old one"),
+        "the empty module was instantiated:\n{log}"
+    );
+    assert!(
+        !log.contains("Reloaded module."),
+        "a bad module replaced the game:\n{log}"
+    );
+
+    // The old instance still reacts to input after the failed reloads.
+    assert_moved_right(&log);
+}
+
+#[test]
+fn secondary_windows_receive_their_own_events() {
+    let game = built_artifact(
+        "CRUCIBLE_TEST_GAME",
+        "wasm32-unknown-unknown/debug/demo-game.wasm",
+    );
+
+    let _server = start_server(&game);
+
+    let dir = TempDir::new("windows");
+
+    // Pressing `I` opens the inspector as the 1st window. Raw motion follows focus rather than the
+    // window the script targets.
+    let log = run_headless(
+        &game,
+        &dir,
+        "size 320 240\n\
+         grant net 127.0.0.1:8080\n\
+         sleep 3\n\
+         frames 10\n\
+         focus true\n\
+         motion 1 2\n\
+         key press KeyI\n\
+         frames 1\n\
+         key release KeyI\n\
+         frames 2\n\
+         focus false\n\
+         window 1\n\
+         focus true\n\
+         motion 3 4\n\
+         text hello\n\
+         window 0\n\
+         motion 5 6\n\
+         window 1\n\
+         focus false\n\
+         motion 7 8\n\
+         window 0\n\
+         close\n\
+         frames 1\n",
+    );
+
+    assert!(
+        log.contains("Main window received mouse motion [1, 2]"),
+        "the focused main window missed motion:\n{log}"
+    );
+    assert!(
+        log.contains("Inspector received mouse motion [3, 4]")
+            && log.contains("Inspector received mouse motion [5, 6]"),
+        "the focused inspector missed motion:\n{log}"
+    );
+    assert!(
+        log.contains(r#"Inspector received Ime(Commit("hello"))"#),
+        "the inspector missed its text input:\n{log}"
+    );
+    assert!(
+        !log.contains("Main window received mouse motion [3, 4]")
+            && !log.contains("Main window received mouse motion [5, 6]"),
+        "motion reached a window without focus:\n{log}"
+    );
+    assert!(
+        !log.contains("mouse motion [7, 8]"),
+        "motion was delivered while no window had focus:\n{log}"
+    );
+}
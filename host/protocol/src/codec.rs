@@ -84,6 +84,16 @@ pub async fn send_packet<P: serde::Serialize>(
     SinkExt::send(encoder, packet).await
 }
 
+/// Encodes a packet directly into the encoder's write buffer without waiting on the underlying
+/// stream, allowing the packet to borrow data which cannot be held across an `await`. The packet is
+/// sent by the next [`flush_packets`].
+pub fn encode_packet<P: serde::Serialize>(
+    encoder: &mut FrameEncoder<impl AsyncWrite>,
+    packet: P,
+) -> Result<(), CodecError> {
+    EncodeCodec.encode(packet, encoder.write_buffer_mut())
+}
+
 pub async fn flush_packets(
     encoder: &mut FrameEncoder<impl AsyncWrite + Unpin>,
 ) -> Result<(), CodecError> {
@@ -142,6 +152,9 @@ impl<I: serde::Serialize> Encoder<I> for EncodeCodec {
     type Error = CodecError;
 
     fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        // The buffer may still hold frames which have yet to be flushed.
+        let start = dst.len();
+
         dst.put_u32(0xABADF00D);
 
         struct ExtendAdapter<'a, E>(&'a mut E);
@@ -156,11 +169,11 @@ impl<I: serde::Serialize> Encoder<I> for EncodeCodec {
             return Err(SerdeError::BadMsg(err).into());
         };
 
-        let Ok(len) = u32::try_from(dst.len() - 4) else {
+        let Ok(len) = u32::try_from(dst.len() - start - 4) else {
             return Err(SerdeError::MsgTooLarge.into());
         };
 
-        dst[0..4].copy_from_slice(&len.to_be_bytes());
+        dst[start..start + 4].copy_from_slice(&len.to_be_bytes());
 
         tracing::trace!("encoded packet with size {len:?}");
 
@@ -39,6 +39,18 @@ pub struct CbServerList1 {
 
     /// The hash of the game's blob.
     pub content_hash: blake3::Hash,
+
+    /// The mods which must be loaded alongside the game, in load order. Mods are always downloaded
+    /// from the server directly.
+    pub mods: Vec<ModEntry1>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModEntry1 {
+    pub name: String,
+
+    /// The hash of the mod's blob.
+    pub content_hash: blake3::Hash,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,9 +7,14 @@ use quinn::{
     crypto::rustls::QuicServerConfig,
     rustls::{self, crypto, pki_types::PrivatePkcs8KeyDer},
 };
-use wasmall::encode::{SplitModuleArgs, split_module};
+use wasmall::{
+    encode::{SplitModuleArgs, split_module},
+    format::WasmallArchive,
+};
+
+use crate::worker::{ContentConfig, GlobalState, ModConfig};
 
-use crate::worker::{ContentConfig, GlobalState};
+const USAGE: &str = "usage: crucible-host-server <module> [--mod <name> <module>]...";
 
 pub type BackgroundTasks = lang::BackgroundTasks<(), App>;
 
@@ -24,10 +29,17 @@ pub async fn main_task(background: BackgroundTasks) -> anyhow::Result<()> {
     let args = args.iter().map(|v| v.as_str()).collect::<Vec<_>>();
     let args = args.as_slice();
 
-    let [_bin_name, mod_path] = *args else {
-        anyhow::bail!("invalid usage");
+    let [_bin_name, game_path, mod_args @ ..] = args else {
+        anyhow::bail!("{USAGE}");
     };
 
+    let mod_args = mod_args.chunks(3).map(|chunk| match *chunk {
+        ["--mod", name, path] => Ok((name, path)),
+        _ => anyhow::bail!("{USAGE}"),
+    });
+
+    let mod_args = mod_args.collect::<anyhow::Result<Vec<_>>>()?;
+
     // Setup crypto
     crypto::aws_lc_rs::default_provider()
         .install_default()
@@ -57,18 +69,23 @@ pub async fn main_task(background: BackgroundTasks) -> anyhow::Result<()> {
     tracing::info!("Listening on {bind_addr}");
 
     // Create global state
-    let archive = split_module(SplitModuleArgs {
-        src: &smol::fs::read(mod_path)
-            .await
-            .context("failed to read module")?,
-        truncate_relocations: true,
-        truncate_debug: false,
-    })?
-    .archive;
-    let archive = Rc::new(archive);
+    let archive = Rc::new(load_archive(game_path).await?);
+
+    let mut mods = Vec::new();
+
+    for (name, path) in mod_args {
+        tracing::info!("Serving mod `{name}` from `{path}`");
+
+        mods.push(ModConfig {
+            name: name.to_string(),
+            archive: Rc::new(load_archive(path).await?),
+        });
+    }
+
     let globals = Rc::new(GlobalState::new(
         background.clone(),
         ContentConfig::SelfHosted(archive),
+        mods,
     ));
 
     // Run workers
@@ -81,3 +98,18 @@ pub async fn main_task(background: BackgroundTasks) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+async fn load_archive(path: &str) -> anyhow::Result<WasmallArchive> {
+    let src = smol::fs::read(path)
+        .await
+        .with_context(|| format!("failed to read module at `{path}`"))?;
+
+    let archive = split_module(SplitModuleArgs {
+        src: &src,
+        truncate_relocations: true,
+        truncate_debug: false,
+    })?
+    .archive;
+
+    Ok(archive)
+}
@@ -26,6 +26,13 @@ pub enum ContentConfig {
     },
 }
 
+/// A mod which clients must load alongside the game. Mods are always hosted by the server itself.
+#[derive(Debug, Clone)]
+pub struct ModConfig {
+    pub name: String,
+    pub archive: Rc<WasmallArchive>,
+}
+
 // === GlobalState === //
 
 /// Engine state that can be shared across multiple worker tasks.
@@ -33,6 +40,7 @@ pub enum ContentConfig {
 pub struct GlobalState {
     background: BackgroundTasks,
     content: ContentState,
+    mods: Vec<ModState>,
 }
 
 #[derive(Debug)]
@@ -41,8 +49,18 @@ struct ContentState {
     index_hash: blake3::Hash,
 }
 
+#[derive(Debug)]
+struct ModState {
+    config: ModConfig,
+    index_hash: blake3::Hash,
+}
+
 impl GlobalState {
-    pub fn new(background: BackgroundTasks, content_config: ContentConfig) -> Self {
+    pub fn new(
+        background: BackgroundTasks,
+        content_config: ContentConfig,
+        mods: Vec<ModConfig>,
+    ) -> Self {
         Self {
             background,
             content: ContentState {
@@ -52,9 +70,42 @@ impl GlobalState {
                 },
                 config: content_config,
             },
+            mods: mods
+                .into_iter()
+                .map(|config| ModState {
+                    index_hash: blake3::hash(&config.archive.index_buf),
+                    config,
+                })
+                .collect(),
         }
     }
 
+    /// Looks up a self-hosted blob by its hash.
+    fn find_content(&self, hash: blake3::Hash) -> Option<&[u8]> {
+        let game = match &self.content.config {
+            ContentConfig::SelfHosted(archive) => Some((self.content.index_hash, archive)),
+            ContentConfig::Content { .. } => None,
+        };
+
+        let mods = self
+            .mods
+            .iter()
+            .map(|state| (state.index_hash, &state.config.archive));
+
+        game.into_iter()
+            .chain(mods)
+            .find_map(|(index_hash, archive)| {
+                if hash == index_hash {
+                    Some(&archive.index_buf[..])
+                } else {
+                    archive
+                        .blobs
+                        .get(&hash)
+                        .map(|range| &archive.blob_buf[range.clone()])
+                }
+            })
+    }
+
     pub async fn listen(self: Rc<Self>, endpoint: quinn::Endpoint) -> anyhow::Result<()> {
         let mut id_gen = 0u64;
 
@@ -142,6 +193,14 @@ impl GlobalState {
                             ContentConfig::Content { server_url, .. } => Some(server_url.clone()),
                         },
                         content_hash: self.content.index_hash,
+                        mods: self
+                            .mods
+                            .iter()
+                            .map(|state| game::ModEntry1 {
+                                name: state.config.name.clone(),
+                                content_hash: state.index_hash,
+                            })
+                            .collect(),
                     },
                 )
                 .await?;
@@ -149,23 +208,15 @@ impl GlobalState {
             game::SbHello1::Download { hash } => 'dl: {
                 tracing::info!("client wants to download {hash}");
 
-                let archive = match &self.content.config {
-                    ContentConfig::SelfHosted(archive) => archive,
-                    ContentConfig::Content { .. } => {
+                let Some(content) = self.find_content(hash) else {
+                    if let ContentConfig::Content { .. } = &self.content.config {
                         tracing::warn!("download not supported");
-
                         send_packet(&mut tx, game::CbDownloadRes::NotSupported).await?;
-                        break 'dl;
+                    } else {
+                        tracing::warn!("blob with hash {hash} not found");
+                        send_packet(&mut tx, game::CbDownloadRes::NotFound).await?;
                     }
-                };
 
-                let content = if hash == self.content.index_hash {
-                    &archive.index_buf[..]
-                } else if let Some(range) = archive.blobs.get(&hash) {
-                    &archive.index_buf[range.clone()]
-                } else {
-                    tracing::warn!("blob with has {hash} not found");
-                    send_packet(&mut tx, game::CbDownloadRes::NotFound).await?;
                     break 'dl;
                 };
 
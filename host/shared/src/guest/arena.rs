@@ -1,3 +1,5 @@
+use std::mem;
+
 use anyhow::Context;
 use derive_where::derive_where;
 
@@ -57,6 +59,27 @@ impl<T> GuestArena<T> {
         Ok(value)
     }
 
+    /// Removes every value from the arena, invalidating all outstanding handles.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> {
+        self.free.clear();
+        mem::take(&mut self.slots).into_iter().flatten()
+    }
+
+    /// Iterates over the values in the arena along with their handles.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, slot)| Some((idx as u32 + 1, slot.as_ref()?)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u32, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(idx, slot)| Some((idx as u32 + 1, slot.as_mut()?)))
+    }
+
     pub fn get(&self, handle: u32) -> anyhow::Result<&T> {
         self.slots
             .get(Self::handle_to_idx(handle)?)
@@ -3,7 +3,7 @@ use std::io::Write as _;
 use anyhow::Context;
 use wasmall::{
     encode::{SplitModuleArgs, split_module},
-    format::{WasmallBlob, WasmallIndex, WasmallModChunk},
+    format::WasmallIndex,
     utils::{ByteCursor, ByteParse as _, OffsetTracker},
 };
 
@@ -23,24 +23,11 @@ fn main() -> anyhow::Result<()> {
     let _guard = OffsetTracker::new(&archive.index_buf);
     let reader = WasmallIndex::parse(&mut ByteCursor(&archive.index_buf))?;
 
-    let mut out = Vec::new();
+    let out = reader.rebuild(|hash| {
+        let blob = &archive.blob_buf[archive.blobs.get(&hash)?.clone()];
 
-    for chunk in reader.chunks() {
-        let chunk = chunk?;
-
-        match chunk {
-            WasmallModChunk::Verbatim(chunk) => {
-                out.extend_from_slice(chunk.data());
-            }
-            WasmallModChunk::Blob(chunk) => {
-                let blob = &archive.blob_buf[archive.blobs[&chunk.hash()].clone()];
-
-                let _guard = OffsetTracker::new(blob);
-
-                chunk.write(&WasmallBlob::parse(&mut ByteCursor(blob))?, &mut out)?;
-            }
-        }
-    }
+        Some(blob)
+    })?;
 
     std::io::stdout().write_all(&out)?;
 
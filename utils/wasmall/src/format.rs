@@ -2,7 +2,7 @@ use std::{collections::hash_map, ops::Range};
 
 use anyhow::Context as _;
 use blake3::Hash;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use wasmparser::RelocationType;
 
 use crate::utils::{
@@ -312,6 +312,46 @@ impl<'a> WasmallIndex<'a> {
     pub fn chunks(&self) -> ByteParseList<'a, WasmallModChunk<'a>> {
         ByteParseList::new(ByteCursor(self.chunks))
     }
+
+    /// The hashes of the blobs the module is built from, in the order they first appear.
+    pub fn blob_hashes(&self) -> anyhow::Result<Vec<Hash>> {
+        let mut seen = FxHashSet::default();
+        let mut hashes = Vec::new();
+
+        for chunk in self.chunks() {
+            if let WasmallModChunk::Blob(chunk) = chunk?
+                && seen.insert(chunk.hash())
+            {
+                hashes.push(chunk.hash());
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    /// Rebuilds the module, looking up the blobs its chunks refer to through `blob`.
+    pub fn rebuild<'b>(
+        &self,
+        mut blob: impl FnMut(Hash) -> Option<&'b [u8]>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut out = Vec::new();
+
+        for chunk in self.chunks() {
+            match chunk? {
+                WasmallModChunk::Verbatim(chunk) => {
+                    out.extend_from_slice(chunk.data());
+                }
+                WasmallModChunk::Blob(chunk) => {
+                    let data = blob(chunk.hash())
+                        .with_context(|| format!("missing blob {}", chunk.hash()))?;
+
+                    chunk.write(&WasmallBlob::parse(&mut ByteCursor(data))?, &mut out)?;
+                }
+            }
+        }
+
+        Ok(out)
+    }
 }
 
 #[derive(Debug, Clone)]
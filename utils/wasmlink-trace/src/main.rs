@@ -0,0 +1,73 @@
+use std::{collections::BTreeMap, env, time::Duration};
+
+use anyhow::Context;
+use wasmlink::{TraceEvent, TraceReader};
+
+fn main() -> anyhow::Result<()> {
+    let args = env::args().collect::<Vec<String>>();
+    let args = args.iter().map(|v| v.as_str()).collect::<Vec<_>>();
+
+    let (path, stats) = match *args.as_slice() {
+        [_bin_name, path] => (path, false),
+        [_bin_name, "--stats", path] => (path, true),
+        _ => anyhow::bail!("usage: wasmlink-trace [--stats] <trace>"),
+    };
+
+    let events = TraceReader::open(path)?;
+
+    if !stats {
+        for event in events {
+            println!("{}", event.context("failed to read trace")?);
+        }
+
+        return Ok(());
+    }
+
+    let mut summary = BTreeMap::<String, CallStats>::new();
+
+    for event in events {
+        let event = event.context("failed to read trace")?;
+
+        let (key, failed) = match &event {
+            TraceEvent::PortCall {
+                module,
+                func_name,
+                result,
+                ..
+            } => (format!("{module}.{func_name}"), result.is_err()),
+            TraceEvent::Callback { result, .. } => ("<callbacks>".to_string(), result.is_err()),
+        };
+
+        let entry = summary.entry(key).or_default();
+        entry.calls += 1;
+        entry.failures += u64::from(failed);
+        entry.total += event.duration();
+        entry.max = entry.max.max(event.duration());
+    }
+
+    println!(
+        "{:<40} {:>8} {:>8} {:>12} {:>12}",
+        "port", "calls", "failed", "total (ms)", "max (ms)"
+    );
+
+    for (key, entry) in summary {
+        println!(
+            "{:<40} {:>8} {:>8} {:>12.3} {:>12.3}",
+            key,
+            entry.calls,
+            entry.failures,
+            entry.total.as_secs_f64() * 1000.,
+            entry.max.as_secs_f64() * 1000.,
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct CallStats {
+    calls: u64,
+    failures: u64,
+    total: Duration,
+    max: Duration,
+}
@@ -0,0 +1,698 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    pin::Pin,
+    ptr::NonNull,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+
+use anyhow::Context;
+use arid::World;
+use wasmlink::{
+    AbiDesc, AbiMismatch, AsyncPort, BUILTIN_ASYNC_CANCEL, BUILTIN_CLOSURE_INVOKE,
+    BUILTIN_CLOSURE_REPORT, BUILTIN_MEM_ALLOC, BUILTIN_REPORT_CLOSURES, ClosureStats, FfiPtr,
+    GuestInvokeContext, GuestMemory, GuestMemoryContext, GuestboundViewOf, HostClosure, HostTasks,
+    HostboundViewOf, Marshal, OPTIONAL_IMPORT_SUFFIX, Port, Strategy, Tracer,
+};
+
+mod tests;
+
+// === Aliases === //
+
+pub type WslEngine = wasmi::Engine;
+pub type WslModule = wasmi::Module;
+pub type WslInstance = wasmi::Instance;
+pub type WslStore = wasmi::Store<WslStoreState>;
+
+// === ABI Checks === //
+
+/// Checks the ABI descriptor embedded in the guest's `binary` against the `host`'s ABI. See
+/// [`AbiDesc::check_module`].
+pub fn check_wsl_abi(
+    binary: &[u8],
+    module: &WslModule,
+    host: &AbiDesc,
+) -> anyhow::Result<Vec<AbiMismatch>> {
+    host.check_module(
+        binary,
+        module
+            .imports()
+            .map(|import| (import.module(), import.name())),
+    )
+}
+
+// === WslStoreState === //
+
+#[derive(Default)]
+pub struct WslStoreState {
+    world: Option<NonNull<World>>,
+    exports: Option<WslExports>,
+    tasks: HostTasks,
+    spawner: Option<Box<dyn Fn(WslTask)>>,
+    closure_report: Option<ClosureStats>,
+    tracer: Option<Tracer>,
+}
+
+impl fmt::Debug for WslStoreState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WslStoreState").finish_non_exhaustive()
+    }
+}
+
+struct WslExports {
+    memory: wasmi::Memory,
+    mem_alloc: wasmi::TypedFunc<(u32, u32), u32>,
+    closure_invoke: wasmi::TypedFunc<(u64, u32), ()>,
+    closure_report: Option<wasmi::TypedFunc<(), ()>>,
+}
+
+pub trait WslStoreExt {
+    fn setup_wsl_exports(&mut self, instance: WslInstance) -> anyhow::Result<()>;
+
+    fn run_wsl_root<R>(&mut self, world: &mut World, f: impl FnOnce(&mut WslContext<'_>) -> R)
+    -> R;
+
+    /// Sets the function used to drive the futures of async ports. The completions they resolve
+    /// to must be handed back to [`complete_wsl_task`](WslStoreExt::complete_wsl_task).
+    fn set_wsl_spawner(&mut self, spawner: impl 'static + Fn(WslTask));
+
+    /// Delivers the output of an async port call to the guest unless the guest has since cancelled
+    /// the call.
+    fn complete_wsl_task(
+        &mut self,
+        world: &mut World,
+        completion: WslTaskCompletion,
+    ) -> anyhow::Result<()>;
+
+    /// Asks the guest to report the closures it has alive. Returns `None` if the guest doesn't
+    /// export [`BUILTIN_CLOSURE_REPORT`].
+    fn wsl_closure_report(&mut self, world: &mut World) -> anyhow::Result<Option<ClosureStats>>;
+
+    /// Starts recording every port call and guest closure invocation made through this store.
+    fn set_wsl_tracer(&mut self, tracer: Tracer);
+
+    /// Stops recording guest-host interactions, returning the tracer so that it can be
+    /// [`finish`](Tracer::finish)ed.
+    fn take_wsl_tracer(&mut self) -> Option<Tracer>;
+}
+
+impl WslStoreExt for WslStore {
+    fn setup_wsl_exports(&mut self, instance: WslInstance) -> anyhow::Result<()> {
+        let exports = WslExports {
+            memory: instance
+                .get_memory(&*self, "memory")
+                .context("failed to find guest memory export")?,
+            mem_alloc: instance
+                .get_typed_func(&*self, BUILTIN_MEM_ALLOC)
+                .with_context(|| format!("failed to find {BUILTIN_MEM_ALLOC}"))?,
+            closure_invoke: instance
+                .get_typed_func(&*self, BUILTIN_CLOSURE_INVOKE)
+                .with_context(|| format!("failed to find {BUILTIN_CLOSURE_INVOKE}"))?,
+            closure_report: instance
+                .get_func(&*self, BUILTIN_CLOSURE_REPORT)
+                .map(|func| func.typed(&*self))
+                .transpose()
+                .with_context(|| format!("{BUILTIN_CLOSURE_REPORT} has the wrong signature"))?,
+        };
+
+        self.data_mut().exports = Some(exports);
+
+        Ok(())
+    }
+
+    fn run_wsl_root<R>(
+        &mut self,
+        world: &mut World,
+        f: impl FnOnce(&mut WslContext<'_>) -> R,
+    ) -> R {
+        assert!(self.data().world.is_none());
+
+        self.data_mut().world = Some(NonNull::from(world));
+
+        let mut me = scopeguard::guard(self, |me| {
+            me.data_mut().world = None;
+        });
+
+        f(&mut WslContext(WslContextInner::Root(&mut me)))
+    }
+
+    fn set_wsl_spawner(&mut self, spawner: impl 'static + Fn(WslTask)) {
+        self.data_mut().spawner = Some(Box::new(spawner));
+    }
+
+    fn complete_wsl_task(
+        &mut self,
+        world: &mut World,
+        completion: WslTaskCompletion,
+    ) -> anyhow::Result<()> {
+        if !self.data_mut().tasks.finish(completion.id) {
+            return Ok(());
+        }
+
+        self.run_wsl_root(world, completion.finish)
+    }
+
+    fn wsl_closure_report(&mut self, world: &mut World) -> anyhow::Result<Option<ClosureStats>> {
+        let Some(report) = self
+            .data()
+            .exports
+            .as_ref()
+            .expect("exports never initialized with `WslStoreExt::setup_exports")
+            .closure_report
+        else {
+            return Ok(None);
+        };
+
+        self.data_mut().closure_report = None;
+
+        self.run_wsl_root(world, |cx| report.call(cx.cx_mut(), ()))
+            .context("failed to request closure report")?;
+
+        let report = self
+            .data_mut()
+            .closure_report
+            .take()
+            .with_context(|| format!("{BUILTIN_CLOSURE_REPORT} did not report any closures"))?;
+
+        Ok(Some(report))
+    }
+
+    fn set_wsl_tracer(&mut self, tracer: Tracer) {
+        self.data_mut().tracer = Some(tracer);
+    }
+
+    fn take_wsl_tracer(&mut self) -> Option<Tracer> {
+        self.data_mut().tracer.take()
+    }
+}
+
+// === WslContext === //
+
+pub struct WslContext<'a>(WslContextInner<'a>);
+
+enum WslContextInner<'a> {
+    Root(&'a mut wasmi::Store<WslStoreState>),
+    Call(wasmi::Caller<'a, WslStoreState>),
+}
+
+impl WslContext<'_> {
+    pub fn cx(&self) -> wasmi::StoreContext<'_, WslStoreState> {
+        match &self.0 {
+            WslContextInner::Root(store) => store.into(),
+            WslContextInner::Call(caller) => caller.into(),
+        }
+    }
+
+    pub fn cx_mut(&mut self) -> wasmi::StoreContextMut<'_, WslStoreState> {
+        match &mut self.0 {
+            WslContextInner::Root(store) => store.into(),
+            WslContextInner::Call(caller) => caller.into(),
+        }
+    }
+
+    pub fn wr(&self) -> &World {
+        unsafe { self.cx().data().world.expect("no world bound").as_ref() }
+    }
+
+    pub fn w(&mut self) -> &mut World {
+        unsafe {
+            self.cx_mut()
+                .data_mut()
+                .world
+                .expect("no world bound")
+                .as_mut()
+        }
+    }
+
+    pub fn world_and_memory(&mut self) -> (&mut World, &mut GuestMemory) {
+        let main_memory = self.exports().memory;
+
+        let (memory, store) = main_memory.data_and_store_mut(self.cx_mut());
+        let world = unsafe { store.world.expect("no world bound").as_mut() };
+
+        (world, GuestMemory::wrap_mut(memory))
+    }
+
+    fn exports(&self) -> &WslExports {
+        let state = match &self.0 {
+            WslContextInner::Root(store) => store.data(),
+            WslContextInner::Call(caller) => caller.data(),
+        };
+
+        state
+            .exports
+            .as_ref()
+            .expect("exports never initialized with `WslStoreExt::setup_exports")
+    }
+}
+
+impl GuestMemoryContext for WslContext<'_> {
+    fn memory(&self) -> &[u8] {
+        self.exports().memory.data(self.cx())
+    }
+
+    fn memory_mut(&mut self) -> &mut [u8] {
+        let memory = self.exports().memory;
+        memory.data_mut(self.cx_mut())
+    }
+}
+
+impl GuestInvokeContext for WslContext<'_> {
+    fn alloc(&mut self, align: u32, size: u32) -> anyhow::Result<FfiPtr<()>> {
+        self.exports()
+            .mem_alloc
+            .clone()
+            .call(self.cx_mut(), (align, size))
+            .map(FfiPtr::new)
+            .context("failed to allocate memory on guest")
+    }
+
+    fn invoke(&mut self, id: u64, boxed_arg: u32) -> anyhow::Result<()> {
+        self.exports()
+            .closure_invoke
+            .clone()
+            .call(self.cx_mut(), (id, boxed_arg))
+            .context("failed to invoke guest closure")
+    }
+
+    fn tracer(&mut self) -> Option<&mut Tracer> {
+        let state = match &mut self.0 {
+            WslContextInner::Root(store) => store.data_mut(),
+            WslContextInner::Call(caller) => caller.data_mut(),
+        };
+
+        state.tracer.as_mut()
+    }
+}
+
+// === Function Definitions === //
+
+/// A linker for guest modules.
+///
+/// Unlike `wasmtime`, `wasmi` can't enumerate the host functions defined in its linker so we
+/// record their names ourselves.
+pub struct WslLinker {
+    linker: wasmi::Linker<WslStoreState>,
+    definitions: Vec<(String, String)>,
+    /// The unknown imports defined as traps, or as functions reporting missing optional ports, by
+    /// previous instantiations. They aren't reported as definitions since the host doesn't
+    /// actually provide them.
+    traps: Vec<(String, String)>,
+}
+
+impl fmt::Debug for WslLinker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WslLinker")
+            .field("definitions", &self.definitions)
+            .field("traps", &self.traps)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WslLinker {
+    pub fn new(engine: &WslEngine) -> Self {
+        Self {
+            linker: wasmi::Linker::new(engine),
+            definitions: Vec::new(),
+            traps: Vec::new(),
+        }
+    }
+}
+
+pub trait WslLinkerExt {
+    fn define_wsl<I, O, F>(&mut self, port: Port<I, O>, func: F) -> anyhow::Result<()>
+    where
+        I: Marshal,
+        O: Marshal,
+        F: 'static
+            + Send
+            + Sync
+            + for<'t> Fn(
+                &mut WslContext<'_>,
+                HostboundViewOf<I>,
+                Returner<'t, O>,
+            ) -> anyhow::Result<RetVal<'t>>;
+
+    /// Defines an async port. `start` begins the call and returns the future to await, whose output
+    /// is then delivered to the guest by `finish`. The future is driven by the store's spawner.
+    fn define_wsl_async<I, O, F, Fut, R>(
+        &mut self,
+        port: AsyncPort<I, O>,
+        start: F,
+        finish: R,
+    ) -> anyhow::Result<()>
+    where
+        I: Marshal,
+        O: Marshal,
+        F: 'static
+            + Send
+            + Sync
+            + Fn(&mut WslContext<'_>, HostboundViewOf<I>) -> anyhow::Result<Fut>,
+        Fut: 'static + Future,
+        R: 'static
+            + Send
+            + Sync
+            + for<'t> Fn(
+                &mut WslContext<'_>,
+                Fut::Output,
+                AsyncReturner<'t, O>,
+            ) -> anyhow::Result<RetVal<'t>>;
+
+    /// Lists the `(module, name)` pairs of every function defined so far. This should be called
+    /// before [`instantiate_wsl`](WslLinkerExt::instantiate_wsl) so that the traps it defines are
+    /// excluded.
+    fn wsl_definitions(&self, store: &mut WslStore) -> Vec<(String, String)>;
+
+    /// Instantiates the `module`, defining the builtin port used to cancel async calls and its
+    /// unknown function imports as traps. Unknown [optional](OPTIONAL_IMPORT_SUFFIX) imports are
+    /// defined as functions reporting that the port is missing instead.
+    fn instantiate_wsl(
+        &mut self,
+        store: &mut WslStore,
+        module: &WslModule,
+    ) -> anyhow::Result<WslInstance>;
+}
+
+impl WslLinkerExt for WslLinker {
+    fn define_wsl<I, O, F>(&mut self, port: Port<I, O>, func: F) -> anyhow::Result<()>
+    where
+        I: Marshal,
+        O: Marshal,
+        F: 'static
+            + Send
+            + Sync
+            + for<'t> Fn(
+                &mut WslContext<'_>,
+                HostboundViewOf<I>,
+                Returner<'t, O>,
+            ) -> anyhow::Result<RetVal<'t>>,
+    {
+        let import_name = port.import_name();
+
+        let handler = Arc::new(
+            move |cx: wasmi::Caller<'_, WslStoreState>,
+                  in_addr: u32,
+                  out_addr: u32|
+                  -> Result<(), wasmi::Error> {
+                let mut cx = WslContext(WslContextInner::Call(cx));
+                let in_view = <I::Strategy>::decode_hostbound(&cx, FfiPtr::new(in_addr))
+                    .map_err(HostTrap::wrap)?;
+                let returner = Returner {
+                    _ty: PhantomData,
+                    _invariant: PhantomData,
+                    out_addr,
+                };
+
+                let Some(start) = cx.tracer().map(Tracer::begin_port_call) else {
+                    return func(&mut cx, in_view, returner)
+                        .map(|_| ())
+                        .map_err(HostTrap::wrap);
+                };
+
+                let args = Tracer::format_args(cx.memory(), &in_view);
+                let res = func(&mut cx, in_view, returner).map(|_| ());
+
+                if let Some(tracer) = cx.tracer() {
+                    tracer.end_port_call(
+                        port.module(),
+                        &import_name,
+                        start,
+                        args,
+                        res.as_ref().map(|_| ()).map_err(|err| format!("{err:#}")),
+                    );
+                }
+
+                res.map_err(HostTrap::wrap)
+            },
+        );
+
+        self.linker.func_wrap(port.module(), &port.import_name(), {
+            let handler = handler.clone();
+            move |cx: wasmi::Caller<'_, WslStoreState>, in_addr: u32, out_addr: u32| {
+                handler(cx, in_addr, out_addr)
+            }
+        })?;
+
+        // Guests probing for the port import it under its optional name as well.
+        self.linker.func_wrap(
+            port.module(),
+            &format!("{}{OPTIONAL_IMPORT_SUFFIX}", port.import_name()),
+            move |cx: wasmi::Caller<'_, WslStoreState>,
+                  in_addr: u32,
+                  out_addr: u32|
+                  -> Result<u32, wasmi::Error> {
+                handler(cx, in_addr, out_addr)?;
+                Ok(1)
+            },
+        )?;
+
+        self.definitions
+            .push((port.module().to_string(), port.import_name().into_owned()));
+
+        Ok(())
+    }
+
+    fn define_wsl_async<I, O, F, Fut, R>(
+        &mut self,
+        port: AsyncPort<I, O>,
+        start: F,
+        finish: R,
+    ) -> anyhow::Result<()>
+    where
+        I: Marshal,
+        O: Marshal,
+        F: 'static
+            + Send
+            + Sync
+            + Fn(&mut WslContext<'_>, HostboundViewOf<I>) -> anyhow::Result<Fut>,
+        Fut: 'static + Future,
+        R: 'static
+            + Send
+            + Sync
+            + for<'t> Fn(
+                &mut WslContext<'_>,
+                Fut::Output,
+                AsyncReturner<'t, O>,
+            ) -> anyhow::Result<RetVal<'t>>,
+    {
+        let finish = Arc::new(finish);
+
+        self.define_wsl(port.port(), move |cx, (args, callback), ret| {
+            let fut = start(cx, args)?;
+
+            let mut store = cx.cx_mut();
+            let state = store.data_mut();
+
+            let spawner = state
+                .spawner
+                .as_ref()
+                .context("no spawner was set up for async ports")?;
+
+            let (id, task) = state.tasks.spawn(fut);
+            let finish = finish.clone();
+
+            spawner(WslTask(Box::pin(async move {
+                let output = task.await?;
+
+                Some(WslTaskCompletion {
+                    id,
+                    finish: Box::new(move |cx| {
+                        finish(cx, output, AsyncReturner::new(callback)).map(|_| ())
+                    }),
+                })
+            })));
+
+            ret.finish(cx, &id)
+        })
+    }
+
+    fn wsl_definitions(&self, _store: &mut WslStore) -> Vec<(String, String)> {
+        self.definitions.clone()
+    }
+
+    fn instantiate_wsl(
+        &mut self,
+        store: &mut WslStore,
+        module: &WslModule,
+    ) -> anyhow::Result<WslInstance> {
+        if !self.definitions.iter().any(|(module, name)| {
+            module == BUILTIN_ASYNC_CANCEL.module() && name == BUILTIN_ASYNC_CANCEL.func_name()
+        }) {
+            self.define_wsl(BUILTIN_ASYNC_CANCEL, |cx, task, ret| {
+                cx.cx_mut().data_mut().tasks.cancel(task);
+                ret.finish(cx, &())
+            })?;
+        }
+
+        if !self.definitions.iter().any(|(module, name)| {
+            module == BUILTIN_REPORT_CLOSURES.module()
+                && name == BUILTIN_REPORT_CLOSURES.func_name()
+        }) {
+            self.define_wsl(BUILTIN_REPORT_CLOSURES, |cx, report, ret| {
+                let report = ClosureStats::decode(cx, report)?;
+                cx.cx_mut().data_mut().closure_report = Some(report);
+                ret.finish(cx, &())
+            })?;
+        }
+
+        for import in module.imports() {
+            let Some(ty) = import.ty().func() else {
+                continue;
+            };
+
+            // Defined ports are also defined under their optional import name.
+            let defined_name = import
+                .name()
+                .strip_suffix(OPTIONAL_IMPORT_SUFFIX)
+                .unwrap_or(import.name());
+
+            if self
+                .definitions
+                .iter()
+                .any(|(module, name)| module == import.module() && name == defined_name)
+                || self
+                    .traps
+                    .iter()
+                    .any(|(module, name)| module == import.module() && name == import.name())
+            {
+                continue;
+            }
+
+            if import.name().ends_with(OPTIONAL_IMPORT_SUFFIX) {
+                self.linker.func_wrap(
+                    import.module(),
+                    import.name(),
+                    |_: wasmi::Caller<'_, WslStoreState>, _: u32, _: u32| 0u32,
+                )?;
+            } else {
+                let trap = format!(
+                    "unknown import `{}.{}` called",
+                    import.module(),
+                    import.name()
+                );
+
+                self.linker.func_new(
+                    import.module(),
+                    import.name(),
+                    ty.clone(),
+                    move |_, _, _| Err(wasmi::Error::new(trap.clone())),
+                )?;
+            }
+
+            self.traps
+                .push((import.module().to_string(), import.name().to_string()));
+        }
+
+        let instance = self
+            .linker
+            .instantiate(&mut *store, module)?
+            .start(&mut *store)?;
+
+        Ok(instance)
+    }
+}
+
+#[derive(Debug)]
+struct HostTrap(anyhow::Error);
+
+impl HostTrap {
+    fn wrap(err: anyhow::Error) -> wasmi::Error {
+        wasmi::Error::host(Self(err))
+    }
+}
+
+impl fmt::Display for HostTrap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl wasmi::core::HostError for HostTrap {}
+
+pub struct Returner<'t, O: Marshal> {
+    _ty: PhantomData<fn(O) -> O>,
+    _invariant: PhantomData<fn(&'t ()) -> &'t ()>,
+    out_addr: u32,
+}
+
+impl<'t, O: Marshal> Returner<'t, O> {
+    pub fn finish(
+        self,
+        cx: &mut WslContext<'_>,
+        value: &GuestboundViewOf<'_, O>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        <O::Strategy>::encode_guestbound(cx, FfiPtr::new(self.out_addr), value)?;
+
+        if let Some(tracer) = cx.tracer() {
+            tracer.record_return(value);
+        }
+
+        Ok(RetVal {
+            _invariant: PhantomData,
+        })
+    }
+}
+
+pub struct RetVal<'t> {
+    _invariant: PhantomData<fn(&'t ()) -> &'t ()>,
+}
+
+pub struct AsyncReturner<'t, O: Marshal> {
+    _invariant: PhantomData<fn(&'t ()) -> &'t ()>,
+    callback: HostClosure<O>,
+}
+
+impl<'t, O: Marshal> AsyncReturner<'t, O> {
+    fn new(callback: HostClosure<O>) -> Self {
+        Self {
+            _invariant: PhantomData,
+            callback,
+        }
+    }
+
+    pub fn finish(
+        self,
+        cx: &mut WslContext<'_>,
+        value: &GuestboundViewOf<'_, O>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        self.callback.call(cx, value)?;
+
+        Ok(RetVal {
+            _invariant: PhantomData,
+        })
+    }
+}
+
+// === Async Tasks === //
+
+/// The future of an async port call, which resolves to `None` if the guest cancels the call.
+pub struct WslTask(Pin<Box<dyn Future<Output = Option<WslTaskCompletion>>>>);
+
+impl fmt::Debug for WslTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WslTask").finish_non_exhaustive()
+    }
+}
+
+impl Future for WslTask {
+    type Output = Option<WslTaskCompletion>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+pub struct WslTaskCompletion {
+    id: u64,
+    #[expect(clippy::type_complexity)]
+    finish: Box<dyn FnOnce(&mut WslContext<'_>) -> anyhow::Result<()>>,
+}
+
+impl fmt::Debug for WslTaskCompletion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WslTaskCompletion")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
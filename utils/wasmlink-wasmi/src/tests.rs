@@ -0,0 +1,4 @@
+#![cfg(test)]
+
+// Every backend runs the same suite since they implement the same interface.
+wasmlink::backend_tests!();
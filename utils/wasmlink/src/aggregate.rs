@@ -4,8 +4,8 @@ use bytemuck::TransparentWrapper;
 use derive_where::derive_where;
 
 use crate::{
-    FfiPtr, GuestInvokeContext, GuestMemoryContext, Marshal, Strategy, ffi_offset,
-    utils::impl_tuples,
+    FfiPtr, FfiSlice, GuestInvokeContext, GuestMemoryContext, Marshal, Strategy, TypeDesc,
+    ffi_offset, utils::impl_tuples,
 };
 
 // === Tuple Marshalling === //
@@ -132,6 +132,10 @@ const _: () = {
                 type Guestbound = FfiTuple<($($para::Guestbound,)*)>;
                 type GuestboundView<'a> = ($($para::GuestboundView<'a>,)*);
 
+                fn describe() -> TypeDesc {
+                    TypeDesc::Tuple(vec![$($para::describe(),)*])
+                }
+
                 fn decode_hostbound(
                     cx: &(impl ?Sized + GuestMemoryContext),
                     ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -170,6 +174,45 @@ const _: () = {
     impl_tuples!(impl_tuple_marshal; no_unit);
 };
 
+// === Array Marshalling === //
+
+impl<T: Marshal, const N: usize> Marshal for [T; N] {
+    type Strategy = [T::Strategy; N];
+}
+
+impl<T: Strategy, const N: usize> Strategy for [T; N] {
+    type Hostbound<'a> = [T::Hostbound<'a>; N];
+    type HostboundView = [T::HostboundView; N];
+    type Guestbound = [T::Guestbound; N];
+    type GuestboundView<'a> = [T::GuestboundView<'a>; N];
+
+    fn describe() -> TypeDesc {
+        TypeDesc::Array(Box::new(T::describe()), N as u32)
+    }
+
+    fn decode_hostbound(
+        cx: &(impl ?Sized + GuestMemoryContext),
+        ptr: FfiPtr<Self::Hostbound<'static>>,
+    ) -> anyhow::Result<Self::HostboundView> {
+        let base = ptr.cast::<T::Hostbound<'static>>();
+        let mut values = Vec::with_capacity(N);
+
+        for i in 0..N {
+            values.push(T::decode_hostbound(cx, base.add(i as u32))?);
+        }
+
+        Ok(values.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+
+    fn encode_guestbound(
+        cx: &mut impl GuestInvokeContext,
+        out_ptr: FfiPtr<Self::Guestbound>,
+        value: &Self::GuestboundView<'_>,
+    ) -> anyhow::Result<()> {
+        T::encode_guestbound_slice(cx, FfiSlice::new(out_ptr.cast(), N as u32), value)
+    }
+}
+
 // === Struct Marshalling === //
 
 // VariantSelector
@@ -224,9 +267,10 @@ pub mod marshal_struct_internals {
         crate::{
             FfiPtr, GuestInvokeContext, GuestMemoryContext, GuestboundVariant,
             GuestboundViewVariant, HostboundVariant, HostboundViewVariant, MarkerVariant, Marshal,
-            Strategy, VariantSelector, ffi_offset,
+            Strategy, TypeDesc, VariantSelector, ffi_offset,
         },
         anyhow::Result,
+        std::{fmt, string::ToString, stringify, vec},
     };
 }
 
@@ -242,7 +286,7 @@ macro_rules! marshal_struct {
             $(,)?
         }
     )*) => {$(
-        $(#[$($item_meta:tt)*])*
+        $(#[$($item_meta)*])*
         #[repr(C)]
         $item_vis struct $item_name<
             V: $crate::marshal_struct_internals::VariantSelector = $crate::marshal_struct_internals::MarkerVariant
@@ -253,6 +297,25 @@ macro_rules! marshal_struct {
             >,
         )*}
 
+        impl<V> $crate::marshal_struct_internals::fmt::Debug for $item_name<V>
+        where
+            V: $crate::marshal_struct_internals::VariantSelector,
+            $(
+                <V as $crate::marshal_struct_internals::VariantSelector>::Output<
+                    <$field_ty as $crate::marshal_struct_internals::Marshal>::Strategy
+                >: $crate::marshal_struct_internals::fmt::Debug,
+            )*
+        {
+            fn fmt(
+                &self,
+                f: &mut $crate::marshal_struct_internals::fmt::Formatter<'_>,
+            ) -> $crate::marshal_struct_internals::fmt::Result {
+                f.debug_struct($crate::marshal_struct_internals::stringify!($item_name))
+                    $(.field($crate::marshal_struct_internals::stringify!($field_name), &self.$field_name))*
+                    .finish()
+            }
+        }
+
         impl $crate::marshal_struct_internals::Marshal for $item_name {
             type Strategy = Self;
         }
@@ -263,6 +326,21 @@ macro_rules! marshal_struct {
             type Guestbound = $item_name<$crate::marshal_struct_internals::GuestboundVariant>;
             type GuestboundView<'a> = $item_name<$crate::marshal_struct_internals::GuestboundViewVariant<'a>>;
 
+            fn describe() -> $crate::marshal_struct_internals::TypeDesc {
+                $crate::marshal_struct_internals::TypeDesc::Struct {
+                    name: $crate::marshal_struct_internals::ToString::to_string(
+                        $crate::marshal_struct_internals::stringify!($item_name),
+                    ),
+                    fields: $crate::marshal_struct_internals::vec![$((
+                        $crate::marshal_struct_internals::ToString::to_string(
+                            $crate::marshal_struct_internals::stringify!($field_name),
+                        ),
+                        <<$field_ty as $crate::marshal_struct_internals::Marshal>::Strategy
+                            as $crate::marshal_struct_internals::Strategy>::describe(),
+                    ),)*],
+                }
+            }
+
             fn decode_hostbound(
                 cx: &(impl ?Sized + $crate::marshal_struct_internals::GuestMemoryContext),
                 ptr: $crate::marshal_struct_internals::FfiPtr<Self::Hostbound<'static>>,
@@ -303,7 +381,7 @@ macro_rules! marshal_struct {
 // Macro
 pub mod marshal_enum_internals {
     pub use {
-        crate::{FfiPtr, GuestInvokeContext, GuestMemoryContext, Marshal, Strategy},
+        crate::{FfiPtr, GuestInvokeContext, GuestMemoryContext, Marshal, Strategy, TypeDesc},
         anyhow::{Result, bail},
         std::{
             clone::Clone,
@@ -311,6 +389,8 @@ pub mod marshal_enum_internals {
             fmt::Debug,
             hash::Hash,
             marker::Copy,
+            string::ToString,
+            stringify, vec,
         },
     };
 
@@ -342,10 +422,13 @@ macro_rules! marshal_enum {
             $crate::marshal_enum_internals::Ord,
             $crate::marshal_enum_internals::PartialOrd,
         )]
-        $(#[$($item_meta:tt)*])*
+        $(#[$($item_meta)*])*
         #[repr($repr)]
         $item_vis enum $item_name {
-            $($variant_name $(= $variant_val)?,)*
+            $(
+                $(#[$($field_meta)*])*
+                $variant_name $(= $variant_val)?,
+            )*
         }
 
         impl $crate::marshal_enum_internals::Marshal for $item_name {
@@ -358,6 +441,23 @@ macro_rules! marshal_enum {
             type Guestbound = Self;
             type GuestboundView<'a> = Self;
 
+            fn describe() -> $crate::marshal_enum_internals::TypeDesc {
+                $crate::marshal_enum_internals::TypeDesc::Enum {
+                    name: $crate::marshal_enum_internals::ToString::to_string(
+                        $crate::marshal_enum_internals::stringify!($item_name),
+                    ),
+                    repr: $crate::marshal_enum_internals::ToString::to_string(
+                        $crate::marshal_enum_internals::stringify!($repr),
+                    ),
+                    variants: $crate::marshal_enum_internals::vec![$((
+                        $crate::marshal_enum_internals::ToString::to_string(
+                            $crate::marshal_enum_internals::stringify!($variant_name),
+                        ),
+                        $item_name::$variant_name as $crate::marshal_enum_internals::primitives::i128,
+                    ),)*],
+                }
+            }
+
             #[allow(non_upper_case_globals)]
             fn decode_hostbound(
                 cx: &(impl ?Sized + $crate::marshal_enum_internals::GuestMemoryContext),
@@ -395,9 +495,10 @@ pub mod marshal_tagged_union_internals {
         crate::{
             FfiPtr, GuestInvokeContext, GuestMemoryContext, GuestboundOf, GuestboundVariant,
             GuestboundViewVariant, HostboundOf, HostboundVariant, HostboundViewVariant,
-            MarkerVariant, Marshal, Strategy, VariantSelector, ffi_offset,
+            MarkerVariant, Marshal, Strategy, TypeDesc, VariantSelector, ffi_offset,
         },
         anyhow::{Result, bail},
+        std::{fmt, string::ToString, stringify, vec},
     };
 
     pub mod primitives {
@@ -424,7 +525,7 @@ macro_rules! marshal_tagged_union {
             $(,)?
         }
     )*) => {$(
-        $(#[$($item_meta:tt)*])*
+        $(#[$($item_meta)*])*
         // Represent the enum as a union of `(tag, value)` variants.
         // See: https://github.com/rust-lang/rfcs/blob/2ad779b99015e2a609ef416877485604d4799069/text/2195-really-tagged-unions.md#guide-level-explanation
         #[repr($repr)]
@@ -438,6 +539,28 @@ macro_rules! marshal_tagged_union {
             )*
         }
 
+        impl<V> $crate::marshal_tagged_union_internals::fmt::Debug for $item_name<V>
+        where
+            V: $crate::marshal_tagged_union_internals::VariantSelector,
+            $(
+                <V as $crate::marshal_tagged_union_internals::VariantSelector>::Output<
+                    <$variant_ty as $crate::marshal_tagged_union_internals::Marshal>::Strategy
+                >: $crate::marshal_tagged_union_internals::fmt::Debug,
+            )*
+        {
+            fn fmt(
+                &self,
+                f: &mut $crate::marshal_tagged_union_internals::fmt::Formatter<'_>,
+            ) -> $crate::marshal_tagged_union_internals::fmt::Result {
+                match self {$(
+                    Self::$variant_name(value) => f
+                        .debug_tuple($crate::marshal_tagged_union_internals::stringify!($variant_name))
+                        .field(value)
+                        .finish(),
+                )*}
+            }
+        }
+
         impl $crate::marshal_tagged_union_internals::Marshal for $item_name {
             type Strategy = Self;
         }
@@ -448,6 +571,24 @@ macro_rules! marshal_tagged_union {
             type Guestbound = $item_name<$crate::marshal_tagged_union_internals::GuestboundVariant>;
             type GuestboundView<'a> = $item_name<$crate::marshal_tagged_union_internals::GuestboundViewVariant<'a>>;
 
+            fn describe() -> $crate::marshal_tagged_union_internals::TypeDesc {
+                $crate::marshal_tagged_union_internals::TypeDesc::TaggedUnion {
+                    name: $crate::marshal_tagged_union_internals::ToString::to_string(
+                        $crate::marshal_tagged_union_internals::stringify!($item_name),
+                    ),
+                    repr: $crate::marshal_tagged_union_internals::ToString::to_string(
+                        $crate::marshal_tagged_union_internals::stringify!($repr),
+                    ),
+                    variants: $crate::marshal_tagged_union_internals::vec![$((
+                        $crate::marshal_tagged_union_internals::ToString::to_string(
+                            $crate::marshal_tagged_union_internals::stringify!($variant_name),
+                        ),
+                        <<$variant_ty as $crate::marshal_tagged_union_internals::Marshal>::Strategy
+                            as $crate::marshal_tagged_union_internals::Strategy>::describe(),
+                    ),)*],
+                }
+            }
+
             fn decode_hostbound(
                 cx: &(impl ?Sized + $crate::marshal_tagged_union_internals::GuestMemoryContext),
                 ptr: $crate::marshal_tagged_union_internals::FfiPtr<Self::Hostbound<'static>>,
@@ -0,0 +1,359 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    convert::Infallible,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use derive_where::derive_where;
+
+use crate::{
+    FfiPtr, GuestClosure_, GuestInvokeContext, GuestMemoryContext, GuestboundOf, HostClosure_,
+    HostboundOf, Marshal, OwnedGuestClosure, Port, PortDesc, Strategy, TypeDesc, ffi_offset,
+};
+
+// === AsyncPort === //
+
+/// A port whose output is delivered asynchronously.
+///
+/// On the wire, an async port is a regular [`Port`] taking an [`AsyncCall`] and returning the ID
+/// of the task the host spawned for it. The task's output is passed to the call's callback once it
+/// completes and the guest can cancel the task through [`BUILTIN_ASYNC_CANCEL`] before then.
+#[derive_where(Debug, Copy, Clone)]
+pub struct AsyncPort<I, O = ()>
+where
+    I: Marshal,
+    O: Marshal,
+{
+    port: Port<AsyncCall<I, O>, u64>,
+}
+
+impl<I, O> AsyncPort<I, O>
+where
+    I: Marshal,
+    O: Marshal,
+{
+    pub const fn new(module: &'static str, func_name: &'static str) -> Self {
+        Self {
+            port: Port::new(module, func_name),
+        }
+    }
+
+    /// Gives the port a version. See [`Port::with_version`].
+    pub const fn with_version(self, version: u32) -> Self {
+        Self {
+            port: self.port.with_version(version),
+        }
+    }
+
+    /// The synchronous port used to start the async call.
+    pub const fn port(self) -> Port<AsyncCall<I, O>, u64> {
+        self.port
+    }
+
+    pub const fn module(self) -> &'static str {
+        self.port.module()
+    }
+
+    pub const fn func_name(self) -> &'static str {
+        self.port.func_name()
+    }
+
+    pub const fn version(self) -> u32 {
+        self.port.version()
+    }
+
+    pub fn describe(self) -> PortDesc {
+        self.port.describe()
+    }
+
+    pub const fn is_compatible(self, other: AsyncPort<I, O>) -> bool {
+        self.port.is_compatible(other.port)
+    }
+
+    pub const fn assert_compatible(self, other: AsyncPort<I, O>) {
+        self.port.assert_compatible(other.port);
+    }
+}
+
+/// Cancels the in-flight async port call with the given task ID. Cancelling a task which has
+/// already completed does nothing.
+pub const BUILTIN_ASYNC_CANCEL: Port<u64> = Port::new("wasmlink", "cancel_async");
+
+// === AsyncCall === //
+
+/// The input of the port behind an [`AsyncPort`]: a pointer to the call's arguments and the
+/// callback receiving its output.
+///
+/// This type can only be sent to the host.
+#[derive_where(Debug, Copy, Clone)]
+#[expect(clippy::type_complexity)]
+pub struct AsyncCall<I, O> {
+    _ty: PhantomData<fn(I, O) -> (I, O)>,
+}
+
+#[derive_where(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct FfiAsyncCall<'a, I: Strategy, O: Strategy> {
+    pub args: FfiPtr<I::Hostbound<'a>>,
+    pub callback: GuestClosure_<O>,
+}
+
+impl<'a, I: Strategy, O: Strategy> FfiAsyncCall<'a, I, O> {
+    pub fn new(args: &I::Hostbound<'a>, callback: GuestClosure_<O>) -> Self {
+        Self {
+            args: FfiPtr::new_guest(args),
+            callback,
+        }
+    }
+}
+
+impl<I: Marshal, O: Marshal> Marshal for AsyncCall<I, O> {
+    type Strategy = AsyncCall<I::Strategy, O::Strategy>;
+}
+
+impl<I: Strategy, O: Strategy> Strategy for AsyncCall<I, O> {
+    type Hostbound<'a> = FfiAsyncCall<'a, I, O>;
+    type HostboundView = (I::HostboundView, HostClosure_<O>);
+    type Guestbound = Infallible;
+    type GuestboundView<'a> = Infallible;
+
+    fn describe() -> TypeDesc {
+        TypeDesc::Struct {
+            name: "AsyncCall".to_string(),
+            fields: vec![
+                ("args".to_string(), TypeDesc::Box(Box::new(I::describe()))),
+                (
+                    "callback".to_string(),
+                    TypeDesc::Closure(Box::new(O::describe())),
+                ),
+            ],
+        }
+    }
+
+    fn decode_hostbound(
+        cx: &(impl ?Sized + GuestMemoryContext),
+        ptr: FfiPtr<Self::Hostbound<'static>>,
+    ) -> anyhow::Result<Self::HostboundView> {
+        let args = *ptr
+            .field(ffi_offset!(FfiAsyncCall<'static, I, O>, args))
+            .read(cx)?;
+
+        let callback = *ptr
+            .field(ffi_offset!(FfiAsyncCall<'static, I, O>, callback))
+            .cast::<u64>()
+            .read(cx)?;
+
+        Ok((I::decode_hostbound(cx, args)?, HostClosure_::new(callback)))
+    }
+
+    fn encode_guestbound(
+        _cx: &mut impl GuestInvokeContext,
+        _out_ptr: FfiPtr<Self::Guestbound>,
+        value: &Self::GuestboundView<'_>,
+    ) -> anyhow::Result<()> {
+        match *value {}
+    }
+}
+
+// === Guest Calls === //
+
+thread_local! {
+    static COMPLETION_HOOK: Cell<Option<fn()>> = const { Cell::new(None) };
+}
+
+/// Sets a function to run whenever an async port call completes, after the task awaiting it has
+/// been woken. Guests whose executors only make progress when explicitly driven should use this to
+/// drive them.
+pub fn set_async_completion_hook(hook: fn()) {
+    COMPLETION_HOOK.set(Some(hook));
+}
+
+/// An in-flight async port call, as returned by `async fn` bindings in [`bind_port!`](crate::bind_port).
+///
+/// Dropping the call before it completes cancels it on the host.
+#[derive_where(Debug)]
+pub struct AsyncPortCall<O: Marshal> {
+    state: Rc<AsyncCallState<O>>,
+    task: u64,
+    _callback: OwnedGuestClosure<O>,
+}
+
+#[derive_where(Debug)]
+struct AsyncCallState<O: Marshal> {
+    #[derive_where(skip)]
+    output: RefCell<Option<GuestboundOf<O>>>,
+    completed: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl<O: Marshal> AsyncPortCall<O> {
+    #[doc(hidden)]
+    #[track_caller]
+    pub fn start<I: Marshal>(
+        args: &HostboundOf<'_, I>,
+        call: impl FnOnce(&HostboundOf<'_, AsyncCall<I, O>>) -> u64,
+    ) -> Self {
+        let state = Rc::new(AsyncCallState {
+            output: RefCell::new(None),
+            completed: Cell::new(false),
+            waker: RefCell::new(None),
+        });
+
+        let callback = OwnedGuestClosure::<O>::new_once({
+            let state = state.clone();
+
+            move |output| {
+                *state.output.borrow_mut() = Some(output);
+                state.completed.set(true);
+
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+
+                if let Some(hook) = COMPLETION_HOOK.get() {
+                    hook();
+                }
+            }
+        });
+
+        let task = call(&FfiAsyncCall::new(args, callback.handle()));
+
+        Self {
+            state,
+            task,
+            _callback: callback,
+        }
+    }
+
+    pub fn task(&self) -> u64 {
+        self.task
+    }
+}
+
+impl<O: Marshal> Future for AsyncPortCall<O> {
+    type Output = GuestboundOf<O>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(output) = self.state.output.take() {
+            return Poll::Ready(output);
+        }
+
+        assert!(
+            !self.state.completed.get(),
+            "async port call polled after completion"
+        );
+
+        *self.state.waker.borrow_mut() = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
+
+impl<O: Marshal> Drop for AsyncPortCall<O> {
+    fn drop(&mut self) {
+        crate::bind_port! {
+            fn [BUILTIN_ASYNC_CANCEL] "wasmlink".cancel_async(u64);
+        }
+
+        if !self.state.completed.get() {
+            cancel_async(&self.task);
+        }
+    }
+}
+
+// === Host Tasks === //
+
+/// Tracks the async port calls a host has in flight so that guests can cancel them.
+#[derive(Debug, Default)]
+pub struct HostTasks {
+    next_id: u64,
+    running: HashMap<u64, Rc<TaskAbort>>,
+}
+
+#[derive(Debug, Default)]
+struct TaskAbort {
+    aborted: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl HostTasks {
+    /// Registers a new task, returning its ID and a future which drives `fut` until the task is
+    /// cancelled. The future resolves to `None` and drops `fut` once that happens.
+    pub fn spawn<F: Future>(&mut self, fut: F) -> (u64, HostTask<F>) {
+        self.next_id += 1;
+
+        let id = self.next_id;
+        let abort = Rc::new(TaskAbort::default());
+
+        self.running.insert(id, abort.clone());
+
+        (
+            id,
+            HostTask {
+                fut: Some(Box::pin(fut)),
+                abort,
+            },
+        )
+    }
+
+    /// Cancels a running task, returning whether it was running.
+    pub fn cancel(&mut self, id: u64) -> bool {
+        let Some(abort) = self.running.remove(&id) else {
+            return false;
+        };
+
+        abort.aborted.set(true);
+
+        if let Some(waker) = abort.waker.take() {
+            waker.wake();
+        }
+
+        true
+    }
+
+    /// Marks a task as completed, returning whether its output should still be delivered to the
+    /// guest. This is not the case if the guest cancelled the task after it had already completed
+    /// but before its output could be delivered.
+    pub fn finish(&mut self, id: u64) -> bool {
+        self.running.remove(&id).is_some()
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.len()
+    }
+}
+
+#[derive_where(Debug)]
+pub struct HostTask<F: Future> {
+    #[derive_where(skip)]
+    fut: Option<Pin<Box<F>>>,
+    abort: Rc<TaskAbort>,
+}
+
+impl<F: Future> Future for HostTask<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.abort.aborted.get() {
+            self.fut = None;
+            return Poll::Ready(None);
+        }
+
+        let Some(fut) = &mut self.fut else {
+            return Poll::Ready(None);
+        };
+
+        if let Poll::Ready(output) = fut.as_mut().poll(cx) {
+            self.fut = None;
+            return Poll::Ready(Some(output));
+        }
+
+        *self.abort.waker.borrow_mut() = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
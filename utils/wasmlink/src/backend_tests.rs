@@ -0,0 +1,515 @@
+//! The test suite shared by the wasmlink backends. Each backend expands [`backend_tests!`] into
+//! its `tests` module, whose parent provides the backend's `Wsl*` types.
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{AsyncPort, Port};
+
+// === Fixtures === //
+
+pub const ADD: Port<(u32, u32), u32> = Port::new("test", "add");
+pub const CALL_BACK: Port<fn(u32)> = Port::new("test", "call_back");
+pub const DOUBLE: AsyncPort<u32, u32> = AsyncPort::new("test", "double");
+pub const TALLY: Port<(String, Vec<u32>), u32> = Port::new("test", "tally");
+
+/// A hand-written guest with a bump allocator whose closure invoker records the ID of the closure
+/// and its `u32` argument at addresses `64` and `72`. Its `tally` export passes the label `hello`
+/// and the values `[1, 2, 3]` to the host.
+pub const GUEST: &str = r#"
+(module
+    (import "test" "add" (func $add (param i32 i32)))
+    (import "test" "call_back" (func $call_back (param i32 i32)))
+    (import "test" "missing" (func $missing (param i32 i32)))
+    (import "test" "double" (func $double (param i32 i32)))
+    (import "test" "tally" (func $tally (param i32 i32)))
+    (import "test" "add?" (func $add_optional (param i32 i32) (result i32)))
+    (import "test" "missing?" (func $missing_optional (param i32 i32) (result i32)))
+    (import "wasmlink" "cancel_async" (func $cancel_async (param i32 i32)))
+    (import "wasmlink" "report_closures" (func $report_closures (param i32 i32)))
+
+    (memory (export "memory") 1)
+    (global $heap (mut i32) (i32.const 1024))
+
+    ;; A closure report of two closures created at `main.rs:12:5`.
+    (data (i32.const 256) "\02\00\00\00\10\01\00\00\01\00\00\00")
+    (data (i32.const 272) "\30\01\00\00\07\00\00\00\0c\00\00\00\05\00\00\00\02\00\00\00")
+    (data (i32.const 304) "main.rs")
+
+    (data (i32.const 320) "hello")
+    (data (i32.const 336) "\01\00\00\00\02\00\00\00\03\00\00\00")
+
+    (func (export "rust_wasmlink_mem_alloc") (param $align i32) (param $size i32) (result i32)
+        (local $addr i32)
+        (local.set $addr
+            (i32.and
+                (i32.add (global.get $heap) (i32.sub (local.get $align) (i32.const 1)))
+                (i32.sub (i32.const 0) (local.get $align))))
+        (global.set $heap (i32.add (local.get $addr) (local.get $size)))
+        (local.get $addr))
+
+    (func (export "rust_wasmlink_closure_invoke") (param $id i64) (param $arg i32)
+        (i64.store (i32.const 64) (local.get $id))
+        (i32.store (i32.const 72) (i32.load (local.get $arg))))
+
+    (func (export "add") (param $a i32) (param $b i32) (result i32)
+        (i32.store (i32.const 16) (local.get $a))
+        (i32.store (i32.const 20) (local.get $b))
+        (call $add (i32.const 16) (i32.const 32))
+        (i32.load (i32.const 32)))
+
+    (func (export "call_back") (param $id i64)
+        (i64.store (i32.const 40) (local.get $id))
+        (call $call_back (i32.const 40) (i32.const 48)))
+
+    (func (export "missing")
+        (call $missing (i32.const 0) (i32.const 0)))
+
+    (func (export "add_optional") (param $a i32) (param $b i32) (result i32)
+        (i32.store (i32.const 16) (local.get $a))
+        (i32.store (i32.const 20) (local.get $b))
+        (call $add_optional (i32.const 16) (i32.const 32)))
+
+    (func (export "missing_optional") (result i32)
+        (call $missing_optional (i32.const 0) (i32.const 0)))
+
+    (func (export "double") (param $value i32) (param $callback i64) (result i64)
+        (i32.store (i32.const 88) (local.get $value))
+        (i32.store (i32.const 96) (i32.const 88))
+        (i64.store (i32.const 104) (local.get $callback))
+        (call $double (i32.const 96) (i32.const 112))
+        (i64.load (i32.const 112)))
+
+    (func (export "tally") (result i32)
+        (i32.store (i32.const 144) (i32.const 320))
+        (i32.store (i32.const 148) (i32.const 5))
+        (i32.store (i32.const 152) (i32.const 336))
+        (i32.store (i32.const 156) (i32.const 3))
+        (call $tally (i32.const 144) (i32.const 160))
+        (i32.load (i32.const 160)))
+
+    (func (export "cancel") (param $task i64)
+        (i64.store (i32.const 120) (local.get $task))
+        (call $cancel_async (i32.const 120) (i32.const 128)))
+
+    (func (export "rust_wasmlink_closure_report")
+        (call $report_closures (i32.const 256) (i32.const 0))))
+"#;
+
+#[derive(Clone, Default)]
+pub struct SharedBuffer(pub Rc<RefCell<Vec<u8>>>);
+
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Appends a custom section to a module's `binary`. Both lengths are assumed to fit in a single
+/// LEB128 byte.
+pub fn with_custom_section(mut binary: Vec<u8>, name: &str, data: &str) -> Vec<u8> {
+    binary.push(0);
+    binary.push((1 + name.len() + data.len()) as u8);
+    binary.push(name.len() as u8);
+    binary.extend_from_slice(name.as_bytes());
+    binary.extend_from_slice(data.as_bytes());
+    binary
+}
+
+// === Suite === //
+
+/// Expands to the test suite, which must be run from a module whose parent provides the backend's
+/// `Wsl*` types.
+#[macro_export]
+macro_rules! backend_tests {
+    () => {
+        use std::{
+            cell::RefCell,
+            pin::pin,
+            rc::Rc,
+            task::{Context, Poll, Waker},
+        };
+
+        use arid::World;
+        use $crate::{
+            ABI_DESCRIPTOR_SECTION, ClosureSiteStats, ClosureStats, GuestMemoryContext, TraceEvent,
+            TraceReader,
+            backend_tests::{
+                ADD, CALL_BACK, DOUBLE, GUEST, SharedBuffer, TALLY, with_custom_section,
+            },
+        };
+
+        use super::*;
+
+        // === Harness === //
+
+        type SpawnedTasks = Rc<RefCell<Vec<WslTask>>>;
+
+        fn create_linker(engine: &WslEngine) -> WslLinker {
+            let mut linker = WslLinker::new(engine);
+
+            linker
+                .define_wsl(ADD, |cx, (lhs, rhs), out| out.finish(cx, &(lhs + rhs)))
+                .unwrap();
+
+            linker
+                .define_wsl(CALL_BACK, |cx, callback, out| {
+                    callback.call(cx, &7)?;
+                    out.finish(cx, &())
+                })
+                .unwrap();
+
+            linker
+                .define_wsl(TALLY, |cx, (label, values), out| {
+                    let total = values.map(|v| v.decode(cx)).sum::<anyhow::Result<u32>>()?;
+                    out.finish(cx, &(label.len() + total))
+                })
+                .unwrap();
+
+            linker
+                .define_wsl_async(
+                    DOUBLE,
+                    |_cx, value| Ok(async move { value * 2 }),
+                    |cx, value, ret| ret.finish(cx, &value),
+                )
+                .unwrap();
+
+            linker
+        }
+
+        fn instantiate_with(
+            engine: &WslEngine,
+            linker: &mut WslLinker,
+            guest: &str,
+        ) -> (WslStore, WslInstance, SpawnedTasks) {
+            let binary = wat::parse_str(guest).unwrap();
+            let module = WslModule::new(engine, &binary).unwrap();
+            let mut store = WslStore::new(engine, WslStoreState::default());
+            let spawned = SpawnedTasks::default();
+
+            store.set_wsl_spawner({
+                let spawned = spawned.clone();
+                move |task| spawned.borrow_mut().push(task)
+            });
+
+            let instance = linker.instantiate_wsl(&mut store, &module).unwrap();
+
+            store.setup_wsl_exports(instance).unwrap();
+            check_wsl_abi(&binary, &module, &AbiDesc::new([])).unwrap();
+
+            (store, instance, spawned)
+        }
+
+        fn instantiate() -> (WslStore, WslInstance, Vec<(String, String)>, SpawnedTasks) {
+            let engine = WslEngine::default();
+            let mut linker = create_linker(&engine);
+
+            let definitions =
+                linker.wsl_definitions(&mut WslStore::new(&engine, WslStoreState::default()));
+            let (store, instance, spawned) = instantiate_with(&engine, &mut linker, GUEST);
+
+            (store, instance, definitions, spawned)
+        }
+
+        fn call_add(store: &mut WslStore, instance: WslInstance, lhs: u32, rhs: u32) -> u32 {
+            store.run_wsl_root(&mut World::new(), |cx| {
+                instance
+                    .get_typed_func::<(u32, u32), u32>(cx.cx_mut(), "add")
+                    .unwrap()
+                    .call(cx.cx_mut(), (lhs, rhs))
+                    .unwrap()
+            })
+        }
+
+        fn poll_task(task: WslTask) -> Option<WslTaskCompletion> {
+            match pin!(task).poll(&mut Context::from_waker(Waker::noop())) {
+                Poll::Ready(completion) => completion,
+                Poll::Pending => panic!("task should not block"),
+            }
+        }
+
+        // === Tests === //
+
+        #[test]
+        fn lists_definitions() {
+            let (_store, _instance, mut definitions, _) = instantiate();
+
+            definitions.sort();
+
+            assert_eq!(
+                definitions,
+                [
+                    ("test".to_string(), "add".to_string()),
+                    ("test".to_string(), "call_back".to_string()),
+                    ("test".to_string(), "double".to_string()),
+                    ("test".to_string(), "tally".to_string()),
+                ]
+            );
+        }
+
+        #[test]
+        fn calls_host_ports() {
+            let (mut store, instance, _, _) = instantiate();
+
+            assert_eq!(call_add(&mut store, instance, 2, 3), 5);
+        }
+
+        #[test]
+        fn invokes_guest_closures() {
+            let (mut store, instance, _, _) = instantiate();
+
+            store.run_wsl_root(&mut World::new(), |cx| {
+                instance
+                    .get_typed_func::<u64, ()>(cx.cx_mut(), "call_back")
+                    .unwrap()
+                    .call(cx.cx_mut(), 42)
+                    .unwrap();
+
+                assert_eq!(cx.read_array::<u64, 1>(64).unwrap(), &[42]);
+                assert_eq!(cx.read_array::<u32, 1>(72).unwrap(), &[7]);
+            });
+        }
+
+        #[test]
+        fn traps_on_unknown_imports() {
+            let (mut store, instance, _, _) = instantiate();
+
+            let res = store.run_wsl_root(&mut World::new(), |cx| {
+                instance
+                    .get_typed_func::<(), ()>(cx.cx_mut(), "missing")
+                    .unwrap()
+                    .call(cx.cx_mut(), ())
+            });
+
+            assert!(res.is_err());
+        }
+
+        #[test]
+        fn probes_optional_imports() {
+            let (mut store, instance, _, _) = instantiate();
+
+            store.run_wsl_root(&mut World::new(), |cx| {
+                let add = instance
+                    .get_typed_func::<(u32, u32), u32>(cx.cx_mut(), "add_optional")
+                    .unwrap();
+
+                assert_eq!(add.call(cx.cx_mut(), (2, 3)).unwrap(), 1);
+                assert_eq!(cx.read_array::<u32, 1>(32).unwrap(), &[5]);
+
+                let missing = instance
+                    .get_typed_func::<(), u32>(cx.cx_mut(), "missing_optional")
+                    .unwrap();
+
+                assert_eq!(missing.call(cx.cx_mut(), ()).unwrap(), 0);
+            });
+        }
+
+        #[test]
+        fn reuses_linkers_across_instances() {
+            let engine = WslEngine::default();
+            let mut linker = create_linker(&engine);
+
+            // The second instantiation must not redefine the traps of the first.
+            for _ in 0..2 {
+                let (mut store, instance, _) = instantiate_with(&engine, &mut linker, GUEST);
+
+                assert_eq!(call_add(&mut store, instance, 2, 3), 5);
+            }
+        }
+
+        #[test]
+        fn tolerates_ports_missing_on_host() {
+            // A guest built against a newer ABI which imports a second version of `add`.
+            let add_v2 = ADD.with_version(2);
+            let binary = with_custom_section(
+                wat::parse_str(
+                    r#"
+                    (module
+                        (import "test" "add@v2" (func $add_v2 (param i32 i32)))
+
+                        (memory (export "memory") 1)
+
+                        (func (export "rust_wasmlink_mem_alloc") (param i32 i32) (result i32)
+                            (i32.const 0))
+
+                        (func (export "rust_wasmlink_closure_invoke") (param i64 i32))
+
+                        (func (export "add_v2")
+                            (call $add_v2 (i32.const 0) (i32.const 0))))
+                    "#,
+                )
+                .unwrap(),
+                ABI_DESCRIPTOR_SECTION,
+                &AbiDesc::new([add_v2.describe()]).to_string(),
+            );
+
+            let engine = WslEngine::default();
+            let mut linker = create_linker(&engine);
+            let module = WslModule::new(&engine, &binary).unwrap();
+            let mut store = WslStore::new(&engine, WslStoreState::default());
+
+            let missing = check_wsl_abi(&binary, &module, &AbiDesc::new([ADD.describe()])).unwrap();
+
+            let instance = linker.instantiate_wsl(&mut store, &module).unwrap();
+            store.setup_wsl_exports(instance).unwrap();
+
+            assert_eq!(
+                missing,
+                [AbiMismatch::MissingOnHost {
+                    module: "test".to_string(),
+                    func_name: "add@v2".to_string(),
+                }]
+            );
+
+            // Guests answer `supports` from the host's definitions, which must not list the trap.
+            let definitions = linker.wsl_definitions(&mut store);
+            assert!(
+                !definitions.contains(&("test".to_string(), add_v2.import_name().into_owned()))
+            );
+
+            let res = store.run_wsl_root(&mut World::new(), |cx| {
+                instance
+                    .get_typed_func::<(), ()>(cx.cx_mut(), "add_v2")
+                    .unwrap()
+                    .call(cx.cx_mut(), ())
+            });
+
+            assert!(res.is_err());
+        }
+
+        #[test]
+        fn completes_async_calls() {
+            let (mut store, instance, _, spawned) = instantiate();
+            let world = &mut World::new();
+
+            store.run_wsl_root(world, |cx| {
+                instance
+                    .get_typed_func::<(u32, u64), u64>(cx.cx_mut(), "double")
+                    .unwrap()
+                    .call(cx.cx_mut(), (21, 42))
+                    .unwrap()
+            });
+
+            let task = spawned.borrow_mut().pop().unwrap();
+            let completion = poll_task(task).unwrap();
+
+            store.complete_wsl_task(world, completion).unwrap();
+
+            store.run_wsl_root(world, |cx| {
+                assert_eq!(cx.read_array::<u64, 1>(64).unwrap(), &[42]);
+                assert_eq!(cx.read_array::<u32, 1>(72).unwrap(), &[42]);
+            });
+        }
+
+        #[test]
+        fn cancels_async_calls() {
+            let (mut store, instance, _, spawned) = instantiate();
+
+            store.run_wsl_root(&mut World::new(), |cx| {
+                let task = instance
+                    .get_typed_func::<(u32, u64), u64>(cx.cx_mut(), "double")
+                    .unwrap()
+                    .call(cx.cx_mut(), (21, 42))
+                    .unwrap();
+
+                instance
+                    .get_typed_func::<u64, ()>(cx.cx_mut(), "cancel")
+                    .unwrap()
+                    .call(cx.cx_mut(), task)
+                    .unwrap();
+            });
+
+            let task = spawned.borrow_mut().pop().unwrap();
+            assert!(poll_task(task).is_none());
+        }
+
+        #[test]
+        fn collects_closure_reports() {
+            let (mut store, _, _, _) = instantiate();
+
+            let report = store.wsl_closure_report(&mut World::new()).unwrap();
+
+            assert_eq!(
+                report,
+                Some(ClosureStats {
+                    live: 2,
+                    sites: vec![ClosureSiteStats {
+                        file: "main.rs".to_string(),
+                        line: 12,
+                        column: 5,
+                        live: 2,
+                    }],
+                })
+            );
+        }
+
+        #[test]
+        fn traces_port_calls_and_callbacks() {
+            let (mut store, instance, _, _) = instantiate();
+            let buf = SharedBuffer::default();
+
+            store.set_wsl_tracer(Tracer::new(buf.clone()).unwrap());
+
+            store.run_wsl_root(&mut World::new(), |cx| {
+                instance
+                    .get_typed_func::<(u32, u32), u32>(cx.cx_mut(), "add")
+                    .unwrap()
+                    .call(cx.cx_mut(), (2, 3))
+                    .unwrap();
+
+                instance
+                    .get_typed_func::<u64, ()>(cx.cx_mut(), "call_back")
+                    .unwrap()
+                    .call(cx.cx_mut(), 42)
+                    .unwrap();
+
+                let tally = instance
+                    .get_typed_func::<(), u32>(cx.cx_mut(), "tally")
+                    .unwrap()
+                    .call(cx.cx_mut(), ())
+                    .unwrap();
+
+                assert_eq!(tally, 11);
+            });
+
+            store.take_wsl_tracer().unwrap().finish().unwrap();
+
+            let events = TraceReader::new(buf.0.borrow().as_slice())
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap();
+
+            let [add, callback, call_back, tally] = events.as_slice() else {
+                panic!("unexpected trace {events:#?}");
+            };
+
+            assert!(matches!(
+                add,
+                TraceEvent::PortCall { module, func_name, args, result: Ok(ret), .. }
+                    if module == "test" && func_name == "add" && args == "(2, 3)" && ret == "5",
+            ));
+
+            assert!(matches!(
+                callback,
+                TraceEvent::Callback { closure: 42, arg, result: Ok(()), .. } if arg == "7",
+            ));
+
+            assert!(matches!(
+                call_back,
+                TraceEvent::PortCall { func_name, result: Ok(ret), .. }
+                    if func_name == "call_back" && ret == "()",
+            ));
+
+            // Arguments are read out of guest memory rather than recorded as addresses.
+            assert!(matches!(
+                tally,
+                TraceEvent::PortCall { func_name, args, result: Ok(ret), .. }
+                    if func_name == "tally" && args == r#"("hello", [1, 2, 3])"# && ret == "11",
+            ));
+        }
+    };
+}
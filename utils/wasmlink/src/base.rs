@@ -4,7 +4,10 @@ use anyhow::Context;
 use bytemuck::{Pod, TransparentWrapper, Zeroable};
 use derive_where::derive_where;
 
-use crate::utils::{guest_usize_to_u32, is_wasm, size_of_u32};
+use crate::{
+    Tracer, TypeDesc,
+    utils::{align_of_u32, guest_usize_to_u32, is_wasm, size_of_u32},
+};
 
 // === Marshal Trait === //
 
@@ -112,6 +115,24 @@ pub trait GuestInvokeContext: Sized + GuestMemoryContext {
     fn alloc(&mut self, align: u32, size: u32) -> anyhow::Result<FfiPtr<()>>;
 
     fn invoke(&mut self, id: u64, boxed_arg: u32) -> anyhow::Result<()>;
+
+    /// The tracer recording the guest-host interactions made through this context, if any.
+    fn tracer(&mut self) -> Option<&mut Tracer> {
+        None
+    }
+
+    /// Allocates room for `len` values of type `T` in guest memory. Bulk data can be written into
+    /// the buffer in place through [`FfiSlice::write`].
+    fn alloc_slice<T>(&mut self, len: u32) -> anyhow::Result<FfiSlice<T>> {
+        let size = size_of_u32::<T>()
+            .checked_mul(len)
+            .context("slice too big for guest")?;
+
+        Ok(FfiSlice::new(
+            self.alloc(align_of_u32::<T>(), size)?.cast(),
+            len,
+        ))
+    }
 }
 
 pub trait Marshal: Sized + 'static {
@@ -123,15 +144,18 @@ pub trait Strategy: Sized + 'static + Marshal<Strategy = Self> {
     /// interpreted on the host.
     type Hostbound<'a>;
 
-    /// A view of a hostbound value on the host.
-    type HostboundView;
+    /// A view of a hostbound value on the host. Views are `Debug` so that port calls can be traced.
+    type HostboundView: fmt::Debug;
 
     /// An FFI-safe representation of the value written by the host into the guest's memory and
     /// interpreted by the guest.
     type Guestbound;
 
     /// A view of a guestbound value to be encoded by host.
-    type GuestboundView<'a>;
+    type GuestboundView<'a>: fmt::Debug;
+
+    /// Describes the layout of this strategy's FFI representation for ABI compatibility checks.
+    fn describe() -> TypeDesc;
 
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
@@ -143,6 +167,23 @@ pub trait Strategy: Sized + 'static + Marshal<Strategy = Self> {
         out_ptr: FfiPtr<Self::Guestbound>,
         value: &Self::GuestboundView<'_>,
     ) -> anyhow::Result<()>;
+
+    /// Encodes `values` into consecutive elements of `out`, which must have the same length.
+    /// Strategies whose guestbound values are plain copies of their views override this to write
+    /// the entire run in one bulk copy.
+    fn encode_guestbound_slice(
+        cx: &mut impl GuestInvokeContext,
+        out: FfiSlice<Self::Guestbound>,
+        values: &[Self::GuestboundView<'_>],
+    ) -> anyhow::Result<()> {
+        assert_eq!(out.len() as usize, values.len());
+
+        for (out_ptr, value) in out.zip(values) {
+            Self::encode_guestbound(cx, out_ptr, value)?;
+        }
+
+        Ok(())
+    }
 }
 
 pub trait UnifiedStrategy:
@@ -254,20 +295,6 @@ impl<T> FfiPtr<T> {
         }
     }
 
-    pub fn new_guest(ptr: *const T) -> Self {
-        Self::new(guest_usize_to_u32(ptr as usize))
-    }
-
-    pub const fn guest_ptr(self) -> *mut T {
-        cfgenius::cond! {
-            if macro(is_wasm) {
-                self.addr as usize as *mut T
-            } else {
-                unimplemented!()
-            }
-        }
-    }
-
     pub const fn addr(self) -> u32 {
         self.addr
     }
@@ -322,23 +349,6 @@ impl<T> FfiSlice<T> {
         }
     }
 
-    pub fn new_guest(ptr: *const [T]) -> Self {
-        Self::new(
-            FfiPtr::new_guest(ptr as *const T),
-            guest_usize_to_u32(ptr.len()),
-        )
-    }
-
-    pub const fn guest_ptr(self) -> *mut [T] {
-        cfgenius::cond! {
-            if macro(is_wasm) {
-                std::ptr::slice_from_raw_parts_mut(self.base.guest_ptr(), self.len as usize)
-            } else {
-                unimplemented!()
-            }
-        }
-    }
-
     pub const fn base(self) -> FfiPtr<T> {
         self.base
     }
@@ -360,6 +370,60 @@ impl<T> FfiSlice<T> {
     }
 }
 
+// Natively-run guests map their pointers into a simulated address space instead.
+cfgenius::cond! {
+    if macro(is_wasm) {
+        impl<T> FfiPtr<T> {
+            pub fn new_guest(ptr: *const T) -> Self {
+                Self::new(guest_usize_to_u32(ptr as usize))
+            }
+
+            pub const fn guest_ptr(self) -> *mut T {
+                self.addr as usize as *mut T
+            }
+        }
+
+        impl<T> FfiSlice<T> {
+            pub fn new_guest(ptr: *const [T]) -> Self {
+                Self::new(
+                    FfiPtr::new_guest(ptr as *const T),
+                    guest_usize_to_u32(ptr.len()),
+                )
+            }
+
+            pub const fn guest_ptr(self) -> *mut [T] {
+                std::ptr::slice_from_raw_parts_mut(self.base.guest_ptr(), self.len as usize)
+            }
+        }
+    } else {
+        impl<T> FfiPtr<T> {
+            pub fn new_guest(ptr: *const T) -> Self {
+                Self::new(crate::native::map_guest(ptr.cast(), mem::size_of::<T>()))
+            }
+
+            pub fn guest_ptr(self) -> *mut T {
+                crate::native::guest_ptr(self.addr).cast()
+            }
+        }
+
+        impl<T> FfiSlice<T> {
+            pub fn new_guest(ptr: *const [T]) -> Self {
+                Self::new(
+                    FfiPtr::new(crate::native::map_guest(
+                        ptr.cast(),
+                        mem::size_of::<T>() * ptr.len(),
+                    )),
+                    guest_usize_to_u32(ptr.len()),
+                )
+            }
+
+            pub fn guest_ptr(self) -> *mut [T] {
+                std::ptr::slice_from_raw_parts_mut(self.base.guest_ptr(), self.len as usize)
+            }
+        }
+    }
+}
+
 impl<T> FfiSliceIndexable for FfiSlice<T> {
     type RawElem = T;
     type RichPtr = FfiPtr<T>;
@@ -378,6 +442,8 @@ impl<T> FfiSliceIndexable for FfiSlice<T> {
 }
 
 impl<T: Pod> FfiSlice<T> {
+    /// Borrows the elements straight out of guest memory without copying them. The borrow holds
+    /// onto `cx` so the guest cannot run, and thus cannot grow or reuse its memory, while it lives.
     pub fn read(self, cx: &(impl ?Sized + GuestMemoryContext)) -> anyhow::Result<&[T]> {
         cx.read_slice(self.base().addr(), self.len())
     }
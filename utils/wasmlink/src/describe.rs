@@ -0,0 +1,908 @@
+use std::{fmt, fmt::Write as _, hash, str};
+
+use anyhow::Context as _;
+
+use crate::OPTIONAL_IMPORT_SUFFIX;
+
+// === TypeDesc === //
+
+/// A structural description of the FFI layout a [`Strategy`](crate::Strategy) gives to its values.
+///
+/// Type, field, and variant names are carried along to produce readable reports but are ignored
+/// when checking compatibility and when computing signature hashes since they have no bearing on
+/// the layout of a value.
+#[derive(Debug, Clone)]
+pub enum TypeDesc {
+    Pod {
+        size: u32,
+        align: u32,
+    },
+    Bool,
+    Char,
+    String,
+    Option(Box<TypeDesc>),
+    Result(Box<TypeDesc>, Box<TypeDesc>),
+    Box(Box<TypeDesc>),
+    Vec(Box<TypeDesc>),
+    Map(Box<TypeDesc>, Box<TypeDesc>),
+    Array(Box<TypeDesc>, u32),
+    Tuple(Vec<TypeDesc>),
+    Closure(Box<TypeDesc>),
+    Struct {
+        name: String,
+        fields: Vec<(String, TypeDesc)>,
+    },
+    Enum {
+        name: String,
+        repr: String,
+        variants: Vec<(String, i128)>,
+    },
+    TaggedUnion {
+        name: String,
+        repr: String,
+        variants: Vec<(String, TypeDesc)>,
+    },
+}
+
+impl TypeDesc {
+    pub fn pod<T>() -> Self {
+        Self::Pod {
+            size: crate::utils::size_of_u32::<T>(),
+            align: crate::utils::align_of_u32::<T>(),
+        }
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser { remaining: text };
+        let desc = parser.ty()?;
+
+        anyhow::ensure!(
+            parser.remaining.is_empty(),
+            "unexpected trailing input {:?}",
+            parser.remaining
+        );
+
+        Ok(desc)
+    }
+
+    pub fn signature(&self) -> impl fmt::Display + '_ {
+        Rendered {
+            desc: self,
+            with_names: false,
+        }
+    }
+
+    pub fn signature_hash(&self) -> u64 {
+        fnv_hash(&self.signature().to_string())
+    }
+
+    pub fn is_compatible(&self, other: &TypeDesc) -> bool {
+        self.first_difference(other).is_none()
+    }
+
+    /// Finds the first place in which the two descriptions disagree, returning a path to the
+    /// offending member (named after `self`) alongside the two mismatched descriptions.
+    pub fn first_difference<'a>(
+        &'a self,
+        other: &'a TypeDesc,
+    ) -> Option<(String, &'a TypeDesc, &'a TypeDesc)> {
+        let mut path = String::new();
+        let (lhs, rhs) = self.first_difference_inner(other, &mut path)?;
+
+        Some((path, lhs, rhs))
+    }
+
+    fn first_difference_inner<'a>(
+        &'a self,
+        other: &'a TypeDesc,
+        path: &mut String,
+    ) -> Option<(&'a TypeDesc, &'a TypeDesc)> {
+        fn nested<'a>(
+            path: &mut String,
+            segment: fmt::Arguments<'_>,
+            lhs: &'a TypeDesc,
+            rhs: &'a TypeDesc,
+        ) -> Option<(&'a TypeDesc, &'a TypeDesc)> {
+            let len = path.len();
+            fmt::write(path, segment).unwrap();
+
+            let res = lhs.first_difference_inner(rhs, path);
+
+            if res.is_none() {
+                path.truncate(len);
+            }
+
+            res
+        }
+
+        let mismatch = Some((self, other));
+
+        match (self, other) {
+            (
+                TypeDesc::Pod { size, align },
+                TypeDesc::Pod {
+                    size: other_size,
+                    align: other_align,
+                },
+            ) => (size != other_size || align != other_align).then_some((self, other)),
+            (TypeDesc::Bool, TypeDesc::Bool)
+            | (TypeDesc::Char, TypeDesc::Char)
+            | (TypeDesc::String, TypeDesc::String) => None,
+            (TypeDesc::Option(lhs), TypeDesc::Option(rhs)) => {
+                nested(path, format_args!(".some"), lhs, rhs)
+            }
+            (TypeDesc::Result(lhs_ok, lhs_err), TypeDesc::Result(rhs_ok, rhs_err)) => {
+                nested(path, format_args!(".ok"), lhs_ok, rhs_ok)
+                    .or_else(|| nested(path, format_args!(".err"), lhs_err, rhs_err))
+            }
+            (TypeDesc::Box(lhs), TypeDesc::Box(rhs)) => nested(path, format_args!(".*"), lhs, rhs),
+            (TypeDesc::Vec(lhs), TypeDesc::Vec(rhs)) => nested(path, format_args!("[_]"), lhs, rhs),
+            (TypeDesc::Map(lhs_k, lhs_v), TypeDesc::Map(rhs_k, rhs_v)) => {
+                nested(path, format_args!(".key"), lhs_k, rhs_k)
+                    .or_else(|| nested(path, format_args!(".value"), lhs_v, rhs_v))
+            }
+            (TypeDesc::Array(lhs, lhs_len), TypeDesc::Array(rhs, rhs_len)) => {
+                if lhs_len != rhs_len {
+                    return mismatch;
+                }
+
+                nested(path, format_args!("[_]"), lhs, rhs)
+            }
+            (TypeDesc::Tuple(lhs), TypeDesc::Tuple(rhs)) => {
+                if lhs.len() != rhs.len() {
+                    return mismatch;
+                }
+
+                (0..)
+                    .zip(lhs.iter().zip(rhs))
+                    .find_map(|(i, (lhs, rhs))| nested(path, format_args!(".{i}"), lhs, rhs))
+            }
+            (TypeDesc::Closure(lhs), TypeDesc::Closure(rhs)) => {
+                nested(path, format_args!("(_)"), lhs, rhs)
+            }
+            (
+                TypeDesc::Struct { fields, .. },
+                TypeDesc::Struct {
+                    fields: other_fields,
+                    ..
+                },
+            ) => {
+                if fields.len() != other_fields.len() {
+                    return mismatch;
+                }
+
+                fields
+                    .iter()
+                    .zip(other_fields)
+                    .find_map(|((name, lhs), (_, rhs))| {
+                        nested(path, format_args!(".{name}"), lhs, rhs)
+                    })
+            }
+            (
+                TypeDesc::Enum { repr, variants, .. },
+                TypeDesc::Enum {
+                    repr: other_repr,
+                    variants: other_variants,
+                    ..
+                },
+            ) => {
+                let same = repr == other_repr
+                    && variants.len() == other_variants.len()
+                    && variants
+                        .iter()
+                        .zip(other_variants)
+                        .all(|((_, lhs), (_, rhs))| lhs == rhs);
+
+                (!same).then_some((self, other))
+            }
+            (
+                TypeDesc::TaggedUnion { repr, variants, .. },
+                TypeDesc::TaggedUnion {
+                    repr: other_repr,
+                    variants: other_variants,
+                    ..
+                },
+            ) => {
+                if repr != other_repr || variants.len() != other_variants.len() {
+                    return mismatch;
+                }
+
+                variants
+                    .iter()
+                    .zip(other_variants)
+                    .find_map(|((name, lhs), (_, rhs))| {
+                        nested(path, format_args!("::{name}"), lhs, rhs)
+                    })
+            }
+            _ => mismatch,
+        }
+    }
+}
+
+impl fmt::Display for TypeDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Rendered {
+            desc: self,
+            with_names: true,
+        }
+        .fmt(f)
+    }
+}
+
+impl Eq for TypeDesc {}
+
+impl PartialEq for TypeDesc {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_compatible(other)
+    }
+}
+
+impl hash::Hash for TypeDesc {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.signature_hash().hash(state);
+    }
+}
+
+struct Rendered<'a> {
+    desc: &'a TypeDesc,
+    with_names: bool,
+}
+
+impl Rendered<'_> {
+    fn child<'b>(&self, desc: &'b TypeDesc) -> Rendered<'b> {
+        Rendered {
+            desc,
+            with_names: self.with_names,
+        }
+    }
+
+    fn name<'b>(&self, name: &'b str) -> &'b str {
+        if self.with_names { name } else { "_" }
+    }
+}
+
+impl fmt::Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.desc {
+            TypeDesc::Pod { size, align } => write!(f, "pod({size},{align})"),
+            TypeDesc::Bool => f.write_str("bool"),
+            TypeDesc::Char => f.write_str("char"),
+            TypeDesc::String => f.write_str("string"),
+            TypeDesc::Option(inner) => write!(f, "option({})", self.child(inner)),
+            TypeDesc::Result(ok, err) => {
+                write!(f, "result({},{})", self.child(ok), self.child(err))
+            }
+            TypeDesc::Box(inner) => write!(f, "box({})", self.child(inner)),
+            TypeDesc::Vec(inner) => write!(f, "vec({})", self.child(inner)),
+            TypeDesc::Map(key, value) => {
+                write!(f, "map({},{})", self.child(key), self.child(value))
+            }
+            TypeDesc::Array(inner, len) => write!(f, "array({},{len})", self.child(inner)),
+            TypeDesc::Tuple(elems) => {
+                f.write_str("tuple(")?;
+
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+
+                    self.child(elem).fmt(f)?;
+                }
+
+                f.write_str(")")
+            }
+            TypeDesc::Closure(arg) => write!(f, "fn({})", self.child(arg)),
+            TypeDesc::Struct { name, fields } => {
+                write!(f, "struct {}{{", self.name(name))?;
+
+                for (i, (field, ty)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+
+                    write!(f, "{}:{}", self.name(field), self.child(ty))?;
+                }
+
+                f.write_str("}")
+            }
+            TypeDesc::Enum {
+                name,
+                repr,
+                variants,
+            } => {
+                write!(f, "enum {}:{repr}{{", self.name(name))?;
+
+                for (i, (variant, discriminant)) in variants.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+
+                    write!(f, "{}={discriminant}", self.name(variant))?;
+                }
+
+                f.write_str("}")
+            }
+            TypeDesc::TaggedUnion {
+                name,
+                repr,
+                variants,
+            } => {
+                write!(f, "union {}:{repr}{{", self.name(name))?;
+
+                for (i, (variant, ty)) in variants.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+
+                    write!(f, "{}({})", self.name(variant), self.child(ty))?;
+                }
+
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    remaining: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn eat(&mut self, token: &str) -> bool {
+        match self.remaining.strip_prefix(token) {
+            Some(rest) => {
+                self.remaining = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.eat(token),
+            "expected {token:?} at {:?}",
+            self.remaining
+        );
+
+        Ok(())
+    }
+
+    fn word(&mut self) -> anyhow::Result<&'a str> {
+        let len = self
+            .remaining
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(self.remaining.len());
+
+        anyhow::ensure!(len > 0, "expected identifier at {:?}", self.remaining);
+
+        let (word, rest) = self.remaining.split_at(len);
+        self.remaining = rest;
+
+        Ok(word)
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> anyhow::Result<T>
+    where
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let word = self.word()?;
+
+        word.parse()
+            .with_context(|| format!("invalid number {word:?}"))
+    }
+
+    fn list<T>(
+        &mut self,
+        close: &str,
+        mut elem: impl FnMut(&mut Self) -> anyhow::Result<T>,
+    ) -> anyhow::Result<Vec<T>> {
+        let mut out = Vec::new();
+
+        if self.eat(close) {
+            return Ok(out);
+        }
+
+        loop {
+            out.push(elem(self)?);
+
+            if self.eat(close) {
+                break Ok(out);
+            }
+
+            self.expect(",")?;
+        }
+    }
+
+    fn ty(&mut self) -> anyhow::Result<TypeDesc> {
+        let keyword = self.word()?;
+
+        Ok(match keyword {
+            "pod" => {
+                self.expect("(")?;
+                let size = self.number()?;
+                self.expect(",")?;
+                let align = self.number()?;
+                self.expect(")")?;
+
+                TypeDesc::Pod { size, align }
+            }
+            "bool" => TypeDesc::Bool,
+            "char" => TypeDesc::Char,
+            "string" => TypeDesc::String,
+            "option" | "box" | "vec" | "fn" => {
+                self.expect("(")?;
+                let inner = Box::new(self.ty()?);
+                self.expect(")")?;
+
+                match keyword {
+                    "option" => TypeDesc::Option(inner),
+                    "box" => TypeDesc::Box(inner),
+                    "vec" => TypeDesc::Vec(inner),
+                    _ => TypeDesc::Closure(inner),
+                }
+            }
+            "result" | "map" => {
+                self.expect("(")?;
+                let lhs = Box::new(self.ty()?);
+                self.expect(",")?;
+                let rhs = Box::new(self.ty()?);
+                self.expect(")")?;
+
+                match keyword {
+                    "result" => TypeDesc::Result(lhs, rhs),
+                    _ => TypeDesc::Map(lhs, rhs),
+                }
+            }
+            "array" => {
+                self.expect("(")?;
+                let inner = Box::new(self.ty()?);
+                self.expect(",")?;
+                let len = self.number()?;
+                self.expect(")")?;
+
+                TypeDesc::Array(inner, len)
+            }
+            "tuple" => {
+                self.expect("(")?;
+                TypeDesc::Tuple(self.list(")", Self::ty)?)
+            }
+            "struct" => {
+                self.expect(" ")?;
+                let name = self.word()?.to_string();
+                self.expect("{")?;
+
+                let fields = self.list("}", |me| {
+                    let field = me.word()?.to_string();
+                    me.expect(":")?;
+                    Ok((field, me.ty()?))
+                })?;
+
+                TypeDesc::Struct { name, fields }
+            }
+            "enum" | "union" => {
+                self.expect(" ")?;
+                let name = self.word()?.to_string();
+                self.expect(":")?;
+                let repr = self.word()?.to_string();
+                self.expect("{")?;
+
+                if keyword == "enum" {
+                    let variants = self.list("}", |me| {
+                        let variant = me.word()?.to_string();
+                        me.expect("=")?;
+                        Ok((variant, me.number()?))
+                    })?;
+
+                    TypeDesc::Enum {
+                        name,
+                        repr,
+                        variants,
+                    }
+                } else {
+                    let variants = self.list("}", |me| {
+                        let variant = me.word()?.to_string();
+                        me.expect("(")?;
+                        let ty = me.ty()?;
+                        me.expect(")")?;
+                        Ok((variant, ty))
+                    })?;
+
+                    TypeDesc::TaggedUnion {
+                        name,
+                        repr,
+                        variants,
+                    }
+                }
+            }
+            _ => anyhow::bail!("unknown type kind {keyword:?}"),
+        })
+    }
+}
+
+fn fnv_hash(text: &str) -> u64 {
+    // FNV-1a is used in place of `std`'s hashers since their output is not guaranteed to be stable
+    // across Rust releases.
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// === PortDesc === //
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct PortDesc {
+    pub module: String,
+    /// The port's import name, including its version suffix if it has one.
+    pub func_name: String,
+    pub input: TypeDesc,
+    pub output: TypeDesc,
+}
+
+impl PortDesc {
+    pub fn signature_hash(&self) -> u64 {
+        fnv_hash(&format!(
+            "{}.{}:{}->{}",
+            self.module,
+            self.func_name,
+            self.input.signature(),
+            self.output.signature(),
+        ))
+    }
+}
+
+impl fmt::Display for PortDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.module, self.func_name, self.input, self.output
+        )
+    }
+}
+
+// === AbiDesc === //
+
+/// The set of ports making up an ABI.
+///
+/// Its textual form lists one port per line as tab-separated `module`, `func_name`, input type, and
+/// output type columns and is what guests embed through [`embed_abi_descriptor!`].
+#[derive(Debug, Clone, Default)]
+pub struct AbiDesc {
+    pub ports: Vec<PortDesc>,
+}
+
+impl AbiDesc {
+    pub fn new(ports: impl IntoIterator<Item = PortDesc>) -> Self {
+        Self {
+            ports: ports.into_iter().collect(),
+        }
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let ports = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(i, line)| {
+                let parse = || {
+                    let [module, func_name, input, output] = line
+                        .split('\t')
+                        .collect::<Vec<_>>()
+                        .try_into()
+                        .ok()
+                        .context("expected four tab-separated columns")?;
+
+                    anyhow::Ok(PortDesc {
+                        module: module.to_string(),
+                        func_name: func_name.to_string(),
+                        input: TypeDesc::parse(input).context("invalid input type")?,
+                        output: TypeDesc::parse(output).context("invalid output type")?,
+                    })
+                };
+
+                parse().with_context(|| format!("failed to parse port on line {}", i + 1))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self { ports })
+    }
+
+    pub fn find(&self, module: &str, func_name: &str) -> Option<&PortDesc> {
+        self.ports
+            .iter()
+            .find(|port| port.module == module && port.func_name == func_name)
+    }
+
+    pub fn signature_hash(&self) -> u64 {
+        let mut hashes = self
+            .ports
+            .iter()
+            .map(PortDesc::signature_hash)
+            .collect::<Vec<_>>();
+
+        hashes.sort_unstable();
+
+        fnv_hash(
+            &hashes
+                .iter()
+                .map(|v| format!("{v:016x}"))
+                .collect::<String>(),
+        )
+    }
+
+    /// Compares the ports `guest` claims to use against those `self` provides. Only ports in
+    /// `used` are checked so that guests can be compiled against ABIs containing ports the host
+    /// has never heard of as long as they don't actually import them. Even then, only
+    /// [fatal](AbiMismatch::is_fatal) mismatches should prevent the guest from running. Ports
+    /// imported under their [optional](crate::OPTIONAL_IMPORT_SUFFIX) name are never reported as
+    /// missing.
+    pub fn check<'a>(
+        &self,
+        guest: &AbiDesc,
+        used: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Vec<AbiMismatch> {
+        let mut mismatches = Vec::new();
+
+        for (module, import_name) in used {
+            let (func_name, optional) = match import_name.strip_suffix(OPTIONAL_IMPORT_SUFFIX) {
+                Some(func_name) => (func_name, true),
+                None => (import_name, false),
+            };
+
+            let Some(guest_port) = guest.find(module, func_name) else {
+                // The port was bound without being described by the guest. This can happen if it
+                // was declared outside the ABI crate and there's nothing we can check about it.
+                continue;
+            };
+
+            let Some(host_port) = self.find(module, func_name) else {
+                // Guests expect optional ports to be missing and check for them on every call.
+                if !optional {
+                    mismatches.push(AbiMismatch::MissingOnHost {
+                        module: module.to_string(),
+                        func_name: func_name.to_string(),
+                    });
+                }
+                continue;
+            };
+
+            for (direction, host, guest) in [
+                (PortDirection::Input, &host_port.input, &guest_port.input),
+                (PortDirection::Output, &host_port.output, &guest_port.output),
+            ] {
+                if let Some((path, host, guest)) = host.first_difference(guest) {
+                    mismatches.push(AbiMismatch::TypeMismatch {
+                        module: module.to_string(),
+                        func_name: func_name.to_string(),
+                        direction,
+                        path,
+                        host: host.to_string(),
+                        guest: guest.to_string(),
+                    });
+                }
+            }
+        }
+
+        mismatches
+    }
+}
+
+impl fmt::Display for AbiDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for port in &self.ports {
+            writeln!(f, "{port}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum PortDirection {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum AbiMismatch {
+    MissingOnHost {
+        module: String,
+        func_name: String,
+    },
+    TypeMismatch {
+        module: String,
+        func_name: String,
+        direction: PortDirection,
+        path: String,
+        host: String,
+        guest: String,
+    },
+}
+
+impl AbiMismatch {
+    /// Whether the mismatch prevents the guest from running. Ports missing on the host don't since
+    /// their imports are defined as traps and guests can check for them with a supported-ports
+    /// query before calling them.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, AbiMismatch::MissingOnHost { .. })
+    }
+}
+
+impl fmt::Display for AbiMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbiMismatch::MissingOnHost { module, func_name } => {
+                write!(f, "port `{module}.{func_name}` is not provided by the host")
+            }
+            AbiMismatch::TypeMismatch {
+                module,
+                func_name,
+                direction,
+                path,
+                host,
+                guest,
+            } => {
+                let direction = match direction {
+                    PortDirection::Input => "input",
+                    PortDirection::Output => "output",
+                };
+
+                write!(
+                    f,
+                    "port `{module}.{func_name}` disagrees on its {direction}{}: host expects \
+                     `{host}` but guest provides `{guest}`",
+                    if path.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" at `{path}`")
+                    },
+                )
+            }
+        }
+    }
+}
+
+// === Guest Embedding === //
+
+/// The name of the custom section through which guests embed their [`AbiDesc`].
+pub const ABI_DESCRIPTOR_SECTION: &str = "wasmlink_abi";
+
+impl AbiDesc {
+    /// Reads the descriptor a guest embedded through [`embed_abi_descriptor!`] from its `binary`.
+    /// Returns `None` if the guest didn't embed one.
+    pub fn from_module(binary: &[u8]) -> anyhow::Result<Option<Self>> {
+        let Some(section) = find_custom_section(binary, ABI_DESCRIPTOR_SECTION)
+            .context("malformed WebAssembly module")?
+        else {
+            return Ok(None);
+        };
+
+        let text = str::from_utf8(section).context("ABI descriptor is not valid UTF-8")?;
+
+        Self::parse(text).map(Some)
+    }
+
+    /// Checks the descriptor embedded in the guest's `binary` against `self`, failing with every
+    /// [fatal](AbiMismatch::is_fatal) mismatch among the `imports` the guest makes. The imported
+    /// ports the host doesn't provide are returned so that the caller can warn about them. Guests
+    /// which don't embed a descriptor are not checked.
+    pub fn check_module<'a>(
+        &self,
+        binary: &[u8],
+        imports: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> anyhow::Result<Vec<AbiMismatch>> {
+        let Some(guest) =
+            Self::from_module(binary).context("guest embedded a malformed ABI descriptor")?
+        else {
+            return Ok(Vec::new());
+        };
+
+        let (fatal, missing) = self
+            .check(&guest, imports)
+            .into_iter()
+            .partition::<Vec<_>, _>(AbiMismatch::is_fatal);
+
+        if fatal.is_empty() {
+            return Ok(missing);
+        }
+
+        let mut report = String::from("guest ABI is incompatible with the host:");
+
+        for mismatch in fatal {
+            write!(report, "\n- {mismatch}").unwrap();
+        }
+
+        anyhow::bail!(report)
+    }
+}
+
+/// Finds the contents of the first custom section named `name` in a WebAssembly `binary`.
+fn find_custom_section<'a>(binary: &'a [u8], name: &str) -> Option<Option<&'a [u8]>> {
+    fn leb128(data: &mut &[u8]) -> Option<usize> {
+        let mut value = 0usize;
+
+        for shift in (0..35).step_by(7) {
+            let (&byte, rest) = data.split_first()?;
+            *data = rest;
+            value |= ((byte & 0x7f) as usize) << shift;
+
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+
+        None
+    }
+
+    fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        let (taken, rest) = data.split_at_checked(len)?;
+        *data = rest;
+        Some(taken)
+    }
+
+    let mut data = binary.strip_prefix(b"\0asm")?;
+    take(&mut data, 4)?;
+
+    while let Some((&id, rest)) = data.split_first() {
+        data = rest;
+
+        let len = leb128(&mut data)?;
+        let mut section = take(&mut data, len)?;
+
+        if id != 0 {
+            continue;
+        }
+
+        let name_len = leb128(&mut section)?;
+
+        if take(&mut section, name_len)? == name.as_bytes() {
+            return Some(Some(section));
+        }
+    }
+
+    Some(None)
+}
+
+#[doc(hidden)]
+pub mod embed_abi_descriptor_internals {
+    pub const fn to_array<const N: usize>(text: &str) -> [u8; N] {
+        let bytes = text.as_bytes();
+        let mut out = [0; N];
+        let mut i = 0;
+
+        while i < N {
+            out[i] = bytes[i];
+            i += 1;
+        }
+
+        out
+    }
+}
+
+/// Embeds the ABI the guest was compiled against into its module as the
+/// [`ABI_DESCRIPTOR_SECTION`] custom section so that hosts can check it for compatibility without
+/// running any of the guest's code. The argument is the textual form of the [`AbiDesc`], which must
+/// be known at compile time and is usually generated by a build script.
+///
+/// ```ignore
+/// wasmlink::embed_abi_descriptor!(include_str!(concat!(env!("OUT_DIR"), "/abi.txt")));
+/// ```
+#[macro_export]
+macro_rules! embed_abi_descriptor {
+    ($text:expr) => {
+        #[cfg(target_arch = "wasm32")]
+        const _: () = {
+            const TEXT: &str = $text;
+
+            // `#[used]` alone doesn't stop the linker from dropping the static if it's defined
+            // in a dependency of the guest's final crate. Giving it an unmangled name does since
+            // rustc then asks the linker to keep the symbol around.
+            #[used]
+            #[unsafe(no_mangle)]
+            #[unsafe(link_section = "wasmlink_abi")]
+            static rust_wasmlink_abi_section: [u8; TEXT.len()] =
+                $crate::embed_abi_descriptor_internals::to_array(TEXT);
+        };
+    };
+}
@@ -1,4 +1,6 @@
 use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
     marker::PhantomData,
     mem::{ManuallyDrop, MaybeUninit},
 };
@@ -8,27 +10,32 @@ use bytemuck::Pod;
 use derive_where::derive_where;
 
 use crate::{
-    FfiPtr, FfiSlice, FfiSliceIndexable, GuestInvokeContext, GuestMemoryContext, Marshal, Strategy,
-    StrategyOf, UnifiedStrategy, ffi_offset,
+    FfiPtr, FfiSlice, FfiSliceIndexable, FfiTuple, GuestInvokeContext, GuestMemoryContext, Marshal,
+    Strategy, StrategyOf, TypeDesc, UnifiedStrategy, ffi_offset,
+    trace::with_traced_memory,
     utils::{align_of_u32, size_of_u32},
 };
 
 // === PodMarshal === //
 
-pub struct PodMarshal<T: Pod> {
+pub struct PodMarshal<T: Pod + fmt::Debug> {
     _ty: PhantomData<fn(T) -> T>,
 }
 
-impl<T: Pod> Marshal for PodMarshal<T> {
+impl<T: Pod + fmt::Debug> Marshal for PodMarshal<T> {
     type Strategy = Self;
 }
 
-impl<T: Pod> Strategy for PodMarshal<T> {
+impl<T: Pod + fmt::Debug> Strategy for PodMarshal<T> {
     type Hostbound<'a> = T;
     type HostboundView = T;
     type Guestbound = T;
     type GuestboundView<'a> = T;
 
+    fn describe() -> TypeDesc {
+        TypeDesc::pod::<T>()
+    }
+
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
         ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -45,6 +52,16 @@ impl<T: Pod> Strategy for PodMarshal<T> {
 
         Ok(())
     }
+
+    fn encode_guestbound_slice(
+        cx: &mut impl GuestInvokeContext,
+        out: FfiSlice<Self::Guestbound>,
+        values: &[Self::GuestboundView<'_>],
+    ) -> anyhow::Result<()> {
+        out.write(cx)?.copy_from_slice(values);
+
+        Ok(())
+    }
 }
 
 macro_rules! alias_pod_marshal {
@@ -77,9 +94,11 @@ alias_pod_marshal! {
 ///
 /// All values of `Self` must be directly transmutable to `Self::Raw`'s `Unified` type.
 ///
-pub unsafe trait ConvertMarshal: Sized + 'static {
+pub unsafe trait ConvertMarshal: Sized + 'static + fmt::Debug {
     type Raw: UnifiedStrategy;
 
+    fn describe() -> TypeDesc;
+
     fn try_from_raw(raw: <Self::Raw as UnifiedStrategy>::Unified) -> anyhow::Result<Self>;
 }
 
@@ -97,6 +116,10 @@ impl<T: ConvertMarshal> Strategy for ConvertMarshalStrategy<T> {
     type Guestbound = T;
     type GuestboundView<'a> = T;
 
+    fn describe() -> TypeDesc {
+        T::describe()
+    }
+
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
         ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -129,6 +152,10 @@ impl Marshal for bool {
 unsafe impl ConvertMarshal for bool {
     type Raw = StrategyOf<u8>;
 
+    fn describe() -> TypeDesc {
+        TypeDesc::Bool
+    }
+
     fn try_from_raw(raw: <Self::Raw as UnifiedStrategy>::Unified) -> anyhow::Result<Self> {
         match raw {
             0 => Ok(false),
@@ -145,6 +172,10 @@ impl Marshal for char {
 unsafe impl ConvertMarshal for char {
     type Raw = StrategyOf<u32>;
 
+    fn describe() -> TypeDesc {
+        TypeDesc::Char
+    }
+
     fn try_from_raw(raw: <Self::Raw as UnifiedStrategy>::Unified) -> anyhow::Result<Self> {
         char::try_from(raw).context("invalid unicode codepoint")
     }
@@ -219,6 +250,10 @@ impl<T: Strategy> Strategy for Option<T> {
     type Guestbound = FfiOption<T::Guestbound>;
     type GuestboundView<'a> = Option<T::GuestboundView<'a>>;
 
+    fn describe() -> TypeDesc {
+        TypeDesc::Option(Box::new(T::describe()))
+    }
+
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
         ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -345,6 +380,10 @@ impl<T: Strategy, E: Strategy> Strategy for Result<T, E> {
     type Guestbound = FfiResult<T::Guestbound, E::Guestbound>;
     type GuestboundView<'a> = Result<T::GuestboundView<'a>, E::GuestboundView<'a>>;
 
+    fn describe() -> TypeDesc {
+        TypeDesc::Result(Box::new(T::describe()), Box::new(E::describe()))
+    }
+
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
         ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -449,6 +488,16 @@ impl<T: Strategy> HostPtr_<T> {
     }
 }
 
+impl<T: Strategy> fmt::Debug for HostPtr_<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Traces show the value pointed to rather than its address.
+        match with_traced_memory(|cx| self.decode(cx)) {
+            Some(Ok(value)) => value.fmt(f),
+            _ => f.debug_struct("HostPtr_").field("ptr", &self.ptr).finish(),
+        }
+    }
+}
+
 // Strategy
 impl<T: Marshal> Marshal for Box<T> {
     type Strategy = Box<T::Strategy>;
@@ -460,6 +509,10 @@ impl<T: Strategy> Strategy for Box<T> {
     type Guestbound = Box<T::Guestbound>;
     type GuestboundView<'a> = &'a T::GuestboundView<'a>;
 
+    fn describe() -> TypeDesc {
+        TypeDesc::Box(Box::new(T::describe()))
+    }
+
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
         ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -553,7 +606,7 @@ impl<'a, T> From<&'a [T]> for GuestSliceRef<'a, T> {
 
 pub type HostSlice<T> = HostSlice_<StrategyOf<T>>;
 
-#[derive_where(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[derive_where(Copy, Clone, Hash, Eq, PartialEq)]
 #[repr(transparent)]
 pub struct HostSlice_<T: Strategy> {
     slice: FfiSlice<T::Hostbound<'static>>,
@@ -577,6 +630,30 @@ impl<T: Strategy> HostSlice_<T> {
     }
 }
 
+impl<T: Strategy> fmt::Debug for HostSlice_<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Traces show the elements rather than their address, up to a limit.
+        const MAX_TRACED: usize = 64;
+
+        let elems = with_traced_memory(|cx| {
+            self.take(MAX_TRACED)
+                .map(|elem| elem.decode(cx))
+                .collect::<anyhow::Result<Vec<_>>>()
+        });
+
+        match elems {
+            Some(Ok(elems)) if elems.len() < self.len() as usize => {
+                f.debug_list().entries(&elems).finish_non_exhaustive()
+            }
+            Some(Ok(elems)) => f.debug_list().entries(&elems).finish(),
+            _ => f
+                .debug_struct("HostSlice_")
+                .field("slice", &self.slice)
+                .finish(),
+        }
+    }
+}
+
 impl<T: Strategy> Iterator for HostSlice_<T> {
     type Item = HostPtr_<T>;
 
@@ -613,6 +690,10 @@ impl<T: Strategy> Strategy for Vec<T> {
     type Guestbound = GuestSlice<T::Guestbound>;
     type GuestboundView<'a> = &'a [T::GuestboundView<'a>];
 
+    fn describe() -> TypeDesc {
+        TypeDesc::Vec(Box::new(T::describe()))
+    }
+
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
         ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -627,18 +708,10 @@ impl<T: Strategy> Strategy for Vec<T> {
         out_ptr: FfiPtr<Self::Guestbound>,
         value: &Self::GuestboundView<'_>,
     ) -> anyhow::Result<()> {
-        let allocated = cx.alloc(
-            align_of_u32::<T::Guestbound>(),
-            size_of_u32::<T::Guestbound>()
-                .checked_mul(u32::try_from(value.len()).expect("too many elements"))
-                .expect("slice too big for guest"),
-        )?;
-
-        let allocated = FfiSlice::<T::Guestbound>::new(allocated.cast(), value.len() as u32);
+        let allocated = cx
+            .alloc_slice::<T::Guestbound>(u32::try_from(value.len()).expect("too many elements"))?;
 
-        for (value, out_ptr) in value.iter().zip(allocated) {
-            T::encode_guestbound(cx, out_ptr, value)?;
-        }
+        T::encode_guestbound_slice(cx, allocated, value)?;
 
         *out_ptr.cast::<FfiSlice<T::Guestbound>>().write(cx)? = allocated;
 
@@ -646,6 +719,141 @@ impl<T: Strategy> Strategy for Vec<T> {
     }
 }
 
+// === Map === //
+
+// Views
+#[repr(transparent)]
+pub struct GuestMap<K, V> {
+    entries: GuestSlice<FfiTuple<(K, V)>>,
+}
+
+impl<K, V> GuestMap<K, V> {
+    pub unsafe fn new_unchecked(entries: FfiSlice<FfiTuple<(K, V)>>) -> Self {
+        Self {
+            entries: unsafe { GuestSlice::new_unchecked(entries) },
+        }
+    }
+
+    pub fn decode(self) -> Vec<(K, V)> {
+        self.entries
+            .decode()
+            .into_iter()
+            .map(FfiTuple::decode)
+            .collect()
+    }
+
+    pub fn decode_ref(&self) -> &[FfiTuple<(K, V)>] {
+        self.entries.decode_ref()
+    }
+
+    pub fn decode_mut(&mut self) -> &mut [FfiTuple<(K, V)>] {
+        self.entries.decode_mut()
+    }
+}
+
+#[derive_where(Copy, Clone, Hash, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct GuestMapRef<'a, K, V> {
+    entries: GuestSliceRef<'a, FfiTuple<(K, V)>>,
+}
+
+impl<'a, K, V> GuestMapRef<'a, K, V> {
+    pub fn new(entries: &'a [FfiTuple<(K, V)>]) -> Self {
+        Self {
+            entries: GuestSliceRef::new(entries),
+        }
+    }
+
+    pub fn decode(self) -> &'a [FfiTuple<(K, V)>] {
+        self.entries.decode()
+    }
+}
+
+impl<'a, K, V> From<&'a [FfiTuple<(K, V)>]> for GuestMapRef<'a, K, V> {
+    fn from(entries: &'a [FfiTuple<(K, V)>]) -> Self {
+        Self::new(entries)
+    }
+}
+
+pub type HostMap<K, V> = HostMap_<StrategyOf<K>, StrategyOf<V>>;
+
+#[derive_where(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct HostMap_<K: Strategy, V: Strategy> {
+    entries: HostSlice_<(K, V)>,
+}
+
+impl<K: Strategy, V: Strategy> HostMap_<K, V> {
+    pub const fn new(entries: HostSlice_<(K, V)>) -> Self {
+        Self { entries }
+    }
+
+    pub const fn entries(self) -> HostSlice_<(K, V)> {
+        self.entries
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub const fn len(self) -> u32 {
+        self.entries.len()
+    }
+
+    pub fn decode<C>(self, cx: &(impl ?Sized + GuestMemoryContext)) -> anyhow::Result<C>
+    where
+        C: FromIterator<(K::HostboundView, V::HostboundView)>,
+    {
+        self.entries.map(|entry| entry.decode(cx)).collect()
+    }
+}
+
+// Strategy
+impl<K: Marshal, V: Marshal, S: 'static> Marshal for HashMap<K, V, S> {
+    type Strategy = HashMap<K::Strategy, V::Strategy>;
+}
+
+impl<K: Marshal, V: Marshal> Marshal for BTreeMap<K, V> {
+    type Strategy = BTreeMap<K::Strategy, V::Strategy>;
+}
+
+// Both map kinds share the representation of a `Vec` of key-value pairs. Duplicate keys are not
+// rejected while decoding; collecting into a map simply keeps the last entry.
+macro_rules! impl_map_strategy {
+    ($($ty:ident),*$(,)?) => {$(
+        impl<K: Strategy, V: Strategy> Strategy for $ty<K, V> {
+            type Hostbound<'a> = GuestMapRef<'a, K::Hostbound<'a>, V::Hostbound<'a>>;
+            type HostboundView = HostMap_<K, V>;
+            type Guestbound = GuestMap<K::Guestbound, V::Guestbound>;
+            type GuestboundView<'a> = &'a [(K::GuestboundView<'a>, V::GuestboundView<'a>)];
+
+            fn describe() -> TypeDesc {
+                TypeDesc::Map(Box::new(K::describe()), Box::new(V::describe()))
+            }
+
+            fn decode_hostbound(
+                cx: &(impl ?Sized + GuestMemoryContext),
+                ptr: FfiPtr<Self::Hostbound<'static>>,
+            ) -> anyhow::Result<Self::HostboundView> {
+                Ok(HostMap_::new(<Vec<(K, V)>>::decode_hostbound(
+                    cx,
+                    ptr.cast(),
+                )?))
+            }
+
+            fn encode_guestbound(
+                cx: &mut impl GuestInvokeContext,
+                out_ptr: FfiPtr<Self::Guestbound>,
+                value: &Self::GuestboundView<'_>,
+            ) -> anyhow::Result<()> {
+                <Vec<(K, V)>>::encode_guestbound(cx, out_ptr.cast(), value)
+            }
+        }
+    )*};
+}
+
+impl_map_strategy!(HashMap, BTreeMap);
+
 // === String === //
 
 // Views
@@ -730,6 +938,19 @@ impl HostStr {
     }
 }
 
+impl fmt::Debug for HostStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Traces show the string rather than its address.
+        let traced = with_traced_memory(|cx| self.read(cx).map(|v| v.fmt(f)).ok()).flatten();
+
+        traced.unwrap_or_else(|| {
+            f.debug_struct("HostStr")
+                .field("slice", &self.slice)
+                .finish()
+        })
+    }
+}
+
 // Strategy
 impl Marshal for String {
     type Strategy = Self;
@@ -741,6 +962,10 @@ impl Strategy for String {
     type Guestbound = GuestStr;
     type GuestboundView<'a> = &'a str;
 
+    fn describe() -> TypeDesc {
+        TypeDesc::String
+    }
+
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
         ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -753,9 +978,8 @@ impl Strategy for String {
         out_ptr: FfiPtr<Self::Guestbound>,
         value: &Self::GuestboundView<'_>,
     ) -> anyhow::Result<()> {
-        let allocated = cx.alloc(1, u32::try_from(value.len()).expect("string too long"))?;
-
-        let allocated = FfiSlice::<u8>::new(allocated.cast(), value.len() as u32);
+        let allocated =
+            cx.alloc_slice::<u8>(u32::try_from(value.len()).expect("string too long"))?;
 
         allocated.write(cx)?.copy_from_slice(value.as_bytes());
 
@@ -3,13 +3,38 @@
 mod aggregate;
 pub use self::aggregate::*;
 
+mod async_port;
+pub use self::async_port::*;
+
 mod base;
 pub use self::base::*;
 
+cfgenius::cond! {
+    if not(macro(is_wasm)) {
+        #[doc(hidden)]
+        pub mod backend_tests;
+    }
+}
+
+mod describe;
+pub use self::describe::*;
+
 mod fundamental;
 pub use self::fundamental::*;
 
 mod port;
 pub use self::port::*;
 
+cfgenius::cond! {
+    if not(macro(is_wasm)) {
+        pub mod native;
+    }
+}
+
+mod tests;
+
+mod trace;
+pub use self::trace::*;
+
 mod utils;
+use self::utils::is_wasm;
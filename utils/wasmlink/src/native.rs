@@ -0,0 +1,617 @@
+//! A backend which runs guest code natively in the same process as its host so that both sides of
+//! an ABI can be exercised from a regular `cargo test`.
+//!
+//! Guest pointers are mapped into a simulated 32-bit address space as they are passed to the host
+//! so FFI values keep the layout they have on `wasm32`. The exception is `Box`, whose layout
+//! depends on the guest's pointer width and is therefore rejected when defining a port.
+
+use std::{
+    alloc::{Layout, handle_alloc_error},
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    mem,
+    pin::Pin,
+    ptr,
+    rc::Rc,
+    slice,
+    task::{Context, Poll, Waker},
+};
+
+use anyhow::Context as _;
+use bytemuck::Pod;
+
+use crate::{
+    AsyncPort, BUILTIN_ASYNC_CANCEL, FfiPtr, GuestInvokeContext, GuestMemoryContext,
+    GuestboundViewOf, HostClosure, HostTasks, HostboundViewOf, Marshal, Port, Strategy, TypeDesc,
+    port::call_raw_closure,
+};
+
+// === Address Space === //
+
+thread_local! {
+    static ADDRESS_SPACE: RefCell<AddressSpace> = const { RefCell::new(AddressSpace::new()) };
+
+    static PORTS: RefCell<HashMap<&'static str, HashMap<String, Rc<PortHandler>>>> =
+        RefCell::new(HashMap::new());
+
+    static HOST_INSTALLED: Cell<bool> = const { Cell::new(false) };
+
+    static TASKS: RefCell<HostTasks> = RefCell::new(HostTasks::default());
+
+    static PENDING_TASKS: RefCell<Vec<NativeTask>> = const { RefCell::new(Vec::new()) };
+}
+
+type PortHandler = dyn Fn(&mut NativeContext, u32, u32) -> anyhow::Result<()>;
+
+type NativeTask = Pin<Box<dyn Future<Output = Option<NativeTaskCompletion>>>>;
+
+struct NativeTaskCompletion {
+    id: u64,
+    #[expect(clippy::type_complexity)]
+    finish: Box<dyn FnOnce(&mut NativeContext) -> anyhow::Result<()>>,
+}
+
+#[derive(Default)]
+struct AddressSpace {
+    /// Every mapped region by guest address.
+    regions: BTreeMap<u32, Region>,
+
+    /// The guest addresses of the regions which aren't aliases, by native address. Their native
+    /// ranges never overlap.
+    by_native: BTreeMap<usize, u32>,
+}
+
+#[derive(Copy, Clone)]
+struct Region {
+    native: *mut u8,
+    len: u32,
+
+    /// The region which came to contain this one. Aliases are unmapped alongside it.
+    alias_of: Option<u32>,
+}
+
+impl Region {
+    fn native_range(&self) -> (usize, usize) {
+        let start = self.native as usize;
+        (start, start + self.len as usize)
+    }
+}
+
+impl AddressSpace {
+    const BASE_ADDR: u32 = 0x1000;
+
+    const fn new() -> Self {
+        Self {
+            regions: BTreeMap::new(),
+            by_native: BTreeMap::new(),
+        }
+    }
+
+    /// Maps `len` bytes of native memory at `native`. Memory which is already mapped keeps its
+    /// address, and regions whose memory the new one partially overlaps are unmapped since that
+    /// memory must have been freed and reused. Hence, the address space only holds as many regions
+    /// as there are native objects which have been passed to the host and not reused since.
+    fn map(&mut self, native: *mut u8, len: usize) -> u32 {
+        let len = u32::try_from(len).expect("region is too large for guest");
+        let start = native as usize;
+        let end = start + len as usize;
+
+        if let Some((&base, &addr)) = self.by_native.range(..=start).next_back()
+            && end <= self.regions[&addr].native_range().1
+        {
+            return addr + (start - base) as u32;
+        }
+
+        // The native ranges of non-aliases are disjoint so their ends are sorted too.
+        let mut contained = Vec::new();
+        let mut stale = Vec::new();
+
+        for (_, &addr) in self.by_native.range(..end.max(start + 1)).rev() {
+            let (other_start, other_end) = self.regions[&addr].native_range();
+
+            if other_end < start || (other_end == start && other_start < start) {
+                break;
+            }
+
+            if start <= other_start && other_end <= end {
+                contained.push(addr);
+            } else if other_start < end && start < other_end {
+                stale.push(addr);
+            }
+        }
+
+        for addr in stale {
+            self.unmap(addr);
+        }
+
+        let addr = self.find_free(len);
+
+        // Regions the new one contains may be referenced by the same call, such as a sub-slice
+        // passed alongside its slice, so they stay mapped for as long as the new region does.
+        for other in contained {
+            self.by_native
+                .remove(&(self.regions[&other].native as usize));
+
+            for region in self.regions.values_mut() {
+                if region.alias_of == Some(other) {
+                    region.alias_of = Some(addr);
+                }
+            }
+
+            self.regions.get_mut(&other).unwrap().alias_of = Some(addr);
+        }
+
+        self.regions.insert(
+            addr,
+            Region {
+                native,
+                len,
+                alias_of: None,
+            },
+        );
+        self.by_native.insert(start, addr);
+
+        addr
+    }
+
+    fn unmap(&mut self, addr: u32) {
+        let region = self.regions.remove(&addr).unwrap();
+        self.by_native.remove(&(region.native as usize));
+        self.regions.retain(|_, v| v.alias_of != Some(addr));
+    }
+
+    fn find_free(&self, len: u32) -> u32 {
+        // Leave a gap after each region so that one-past-the-end pointers remain unambiguous.
+        let footprint = |len: u32| {
+            len.checked_add(1)
+                .and_then(|v| v.checked_next_multiple_of(16))
+                .expect("native guest address space exhausted")
+        };
+
+        let needed = footprint(len);
+        let mut cursor = Self::BASE_ADDR;
+
+        for (&addr, region) in &self.regions {
+            if addr.checked_sub(cursor).is_some_and(|gap| gap >= needed) {
+                break;
+            }
+
+            cursor = cursor.max(
+                addr.checked_add(footprint(region.len))
+                    .expect("native guest address space exhausted"),
+            );
+        }
+
+        assert!(
+            cursor.checked_add(needed).is_some(),
+            "native guest address space exhausted"
+        );
+
+        cursor
+    }
+
+    fn translate(&self, addr: u32, len: usize) -> Option<*mut u8> {
+        let (&base, region) = self.regions.range(..=addr).next_back()?;
+        let offset = (addr - base) as usize;
+
+        (offset.checked_add(len)? <= region.len as usize)
+            .then(|| region.native.wrapping_add(offset))
+    }
+}
+
+pub(crate) fn map_guest(native: *const u8, len: usize) -> u32 {
+    ADDRESS_SPACE.with_borrow_mut(|v| v.map(native.cast_mut(), len))
+}
+
+pub(crate) fn guest_ptr(addr: u32) -> *mut u8 {
+    ADDRESS_SPACE
+        .with_borrow(|v| v.translate(addr, 0))
+        .unwrap_or_else(|| panic!("guest address {addr:#x} is not mapped"))
+}
+
+#[cfg(test)]
+pub(crate) fn mapped_regions() -> usize {
+    ADDRESS_SPACE.with_borrow(|v| v.regions.len())
+}
+
+// === NativeContext === //
+
+/// The context given to native port handlers.
+///
+/// Memory is accessed through the simulated address space so [`memory`](Self::memory) and
+/// [`memory_mut`](Self::memory_mut) always return an empty slice. Mapped guest memory must still be
+/// alive when accessed, which always holds for a port's input and its output buffer.
+#[derive(Debug)]
+pub struct NativeContext {
+    _no_send_sync: PhantomData<*const ()>,
+}
+
+impl NativeContext {
+    fn translate<T>(base: u32, len: u32) -> anyhow::Result<*mut T> {
+        let len = mem::size_of::<T>()
+            .checked_mul(len as usize)
+            .context("arithmetic overflow during addressing")?;
+
+        let ptr = ADDRESS_SPACE
+            .with_borrow(|v| v.translate(base, len))
+            .context("access outside of mapped guest memory")?
+            .cast::<T>();
+
+        anyhow::ensure!(ptr.is_aligned(), "misaligned access to guest memory");
+
+        Ok(ptr)
+    }
+}
+
+impl GuestMemoryContext for NativeContext {
+    fn memory(&self) -> &[u8] {
+        &[]
+    }
+
+    fn memory_mut(&mut self) -> &mut [u8] {
+        &mut []
+    }
+
+    fn read_slice<T: Pod>(&self, base: u32, len: u32) -> anyhow::Result<&[T]> {
+        if mem::size_of::<T>() == 0 {
+            return Ok(unsafe { slice::from_raw_parts(ptr::dangling::<T>(), len as usize) });
+        }
+
+        Ok(unsafe { slice::from_raw_parts(Self::translate(base, len)?, len as usize) })
+    }
+
+    fn write_slice<T: Pod>(&mut self, base: u32, len: u32) -> anyhow::Result<&mut [T]> {
+        if mem::size_of::<T>() == 0 {
+            return Ok(unsafe {
+                slice::from_raw_parts_mut(ptr::dangling_mut::<T>(), len as usize)
+            });
+        }
+
+        Ok(unsafe { slice::from_raw_parts_mut(Self::translate(base, len)?, len as usize) })
+    }
+}
+
+impl GuestInvokeContext for NativeContext {
+    fn alloc(&mut self, align: u32, size: u32) -> anyhow::Result<FfiPtr<()>> {
+        let layout = Layout::from_size_align(size as usize, align as usize)?;
+
+        let native = if layout.size() == 0 {
+            align as usize as *mut u8
+        } else {
+            let native = unsafe { std::alloc::alloc(layout) };
+
+            if native.is_null() {
+                handle_alloc_error(layout);
+            }
+
+            native
+        };
+
+        Ok(FfiPtr::new(map_guest(native, layout.size())))
+    }
+
+    fn invoke(&mut self, id: u64, boxed_arg: u32) -> anyhow::Result<()> {
+        let boxed_arg = ADDRESS_SPACE
+            .with_borrow(|v| v.translate(boxed_arg, 0))
+            .context("closure argument is not mapped")?;
+
+        unsafe { call_raw_closure(id, boxed_arg.cast()) };
+
+        Ok(())
+    }
+}
+
+// === NativeHost === //
+
+/// Serves ports to guest code running natively on the current thread. Ports bound with
+/// [`bind_port!`](crate::bind_port) call into the handlers defined here until the host is dropped.
+#[derive(Debug)]
+pub struct NativeHost {
+    _no_send_sync: PhantomData<*const ()>,
+}
+
+impl Default for NativeHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NativeHost {
+    pub fn new() -> Self {
+        assert!(
+            !HOST_INSTALLED.replace(true),
+            "a `NativeHost` is already installed on this thread"
+        );
+
+        let host = Self {
+            _no_send_sync: PhantomData,
+        };
+
+        host.define(BUILTIN_ASYNC_CANCEL, |cx, task, out| {
+            TASKS.with_borrow_mut(|v| v.cancel(task));
+            out.finish(cx, &())
+        })
+        .unwrap();
+
+        host
+    }
+
+    pub fn define<I, O, F>(&self, port: Port<I, O>, func: F) -> anyhow::Result<()>
+    where
+        I: Marshal,
+        O: Marshal,
+        F: 'static
+            + for<'t> Fn(
+                &mut NativeContext,
+                HostboundViewOf<I>,
+                Returner<'t, O>,
+            ) -> anyhow::Result<RetVal<'t>>,
+    {
+        ensure_no_box::<I, O>(port.module(), &port.import_name())?;
+        self.define_unchecked(port, func)
+    }
+
+    fn define_unchecked<I, O, F>(&self, port: Port<I, O>, func: F) -> anyhow::Result<()>
+    where
+        I: Marshal,
+        O: Marshal,
+        F: 'static
+            + for<'t> Fn(
+                &mut NativeContext,
+                HostboundViewOf<I>,
+                Returner<'t, O>,
+            ) -> anyhow::Result<RetVal<'t>>,
+    {
+        let handler = Rc::new(
+            move |cx: &mut NativeContext, in_addr: u32, out_addr: u32| -> anyhow::Result<()> {
+                let in_view = <I::Strategy>::decode_hostbound(cx, FfiPtr::new(in_addr))?;
+                let returner = Returner {
+                    _ty: PhantomData,
+                    _invariant: PhantomData,
+                    out_addr,
+                };
+
+                func(cx, in_view, returner).map(|_| ())
+            },
+        );
+
+        PORTS.with_borrow_mut(|v| {
+            let ports = v.entry(port.module()).or_default();
+
+            anyhow::ensure!(
+                !ports.contains_key(port.import_name().as_ref()),
+                "port `{}.{}` is already defined",
+                port.module(),
+                port.import_name(),
+            );
+
+            ports.insert(port.import_name().into_owned(), handler);
+
+            Ok(())
+        })
+    }
+
+    /// Defines an async port. `start` begins the call and returns the future to await, whose output
+    /// is then delivered to the guest by `finish`. The futures are only polled by
+    /// [`run_tasks`](Self::run_tasks).
+    pub fn define_async<I, O, F, Fut, R>(
+        &self,
+        port: AsyncPort<I, O>,
+        start: F,
+        finish: R,
+    ) -> anyhow::Result<()>
+    where
+        I: Marshal,
+        O: Marshal,
+        F: 'static + Fn(&mut NativeContext, HostboundViewOf<I>) -> anyhow::Result<Fut>,
+        Fut: 'static + Future,
+        R: 'static
+            + for<'t> Fn(
+                &mut NativeContext,
+                Fut::Output,
+                AsyncReturner<'t, O>,
+            ) -> anyhow::Result<RetVal<'t>>,
+    {
+        // The arguments are passed through an `FfiPtr`, which keeps its layout natively.
+        ensure_no_box::<I, O>(port.port().module(), &port.port().import_name())?;
+
+        let finish = Rc::new(finish);
+
+        self.define_unchecked(port.port(), move |cx, (args, callback), out| {
+            let fut = start(cx, args)?;
+            let (id, task) = TASKS.with_borrow_mut(|v| v.spawn(fut));
+            let finish = finish.clone();
+
+            PENDING_TASKS.with_borrow_mut(|v| {
+                v.push(Box::pin(async move {
+                    let output = task.await?;
+
+                    Some(NativeTaskCompletion {
+                        id,
+                        finish: Box::new(move |cx| {
+                            finish(cx, output, AsyncReturner::new(callback)).map(|_| ())
+                        }),
+                    })
+                }))
+            });
+
+            out.finish(cx, &id)
+        })
+    }
+
+    /// Polls the futures of in-flight async port calls until none of them can make progress,
+    /// delivering the outputs of those which complete to the guest.
+    pub fn run_tasks(&self) -> anyhow::Result<()> {
+        let mut cx = NativeContext {
+            _no_send_sync: PhantomData,
+        };
+
+        loop {
+            let tasks = PENDING_TASKS.take();
+
+            if tasks.is_empty() {
+                break;
+            }
+
+            let mut made_progress = false;
+            let mut still_pending = Vec::new();
+
+            for mut task in tasks {
+                match task.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+                    Poll::Ready(Some(completion)) => {
+                        made_progress = true;
+
+                        if TASKS.with_borrow_mut(|v| v.finish(completion.id)) {
+                            (completion.finish)(&mut cx)?;
+                        }
+                    }
+                    Poll::Ready(None) => made_progress = true,
+                    Poll::Pending => still_pending.push(task),
+                }
+            }
+
+            PENDING_TASKS.with_borrow_mut(|v| v.extend(still_pending));
+
+            if !made_progress {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The number of async port calls which have neither completed nor been cancelled.
+    pub fn running_tasks(&self) -> usize {
+        TASKS.with_borrow(|v| v.running())
+    }
+}
+
+impl Drop for NativeHost {
+    fn drop(&mut self) {
+        PORTS.with_borrow_mut(|v| v.clear());
+        PENDING_TASKS.take();
+        TASKS.take();
+
+        ADDRESS_SPACE.take();
+
+        HOST_INSTALLED.set(false);
+    }
+}
+
+fn ensure_no_box<I: Marshal, O: Marshal>(module: &str, import_name: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !contains_box(&<I::Strategy>::describe()) && !contains_box(&<O::Strategy>::describe()),
+        "port `{module}.{import_name}` uses `Box`, which cannot be marshalled natively",
+    );
+
+    Ok(())
+}
+
+fn contains_box(desc: &TypeDesc) -> bool {
+    match desc {
+        TypeDesc::Pod { .. }
+        | TypeDesc::Bool
+        | TypeDesc::Char
+        | TypeDesc::String
+        | TypeDesc::Enum { .. } => false,
+        TypeDesc::Box(_) => true,
+        TypeDesc::Option(inner)
+        | TypeDesc::Vec(inner)
+        | TypeDesc::Array(inner, _)
+        | TypeDesc::Closure(inner) => contains_box(inner),
+        TypeDesc::Result(lhs, rhs) | TypeDesc::Map(lhs, rhs) => {
+            contains_box(lhs) || contains_box(rhs)
+        }
+        TypeDesc::Tuple(fields) => fields.iter().any(contains_box),
+        TypeDesc::Struct { fields, .. } => fields.iter().any(|(_, field)| contains_box(field)),
+        TypeDesc::TaggedUnion { variants, .. } => {
+            variants.iter().any(|(_, variant)| contains_box(variant))
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn call_port(module: &str, import_name: &str, in_addr: u32, out_addr: u32) {
+    let handler = PORTS
+        .with_borrow(|v| v.get(module)?.get(import_name).cloned())
+        .unwrap_or_else(|| {
+            panic!("port `{module}.{import_name}` is not defined on the native host")
+        });
+
+    let mut cx = NativeContext {
+        _no_send_sync: PhantomData,
+    };
+
+    if let Err(err) = handler(&mut cx, in_addr, out_addr) {
+        panic!("port `{module}.{import_name}` failed: {err:?}");
+    }
+}
+
+/// Calls the port if the native host defines it, returning whether it did. See
+/// [`OPTIONAL_IMPORT_SUFFIX`](crate::OPTIONAL_IMPORT_SUFFIX).
+#[doc(hidden)]
+pub fn call_optional_port(module: &str, import_name: &str, in_addr: u32, out_addr: u32) -> bool {
+    let defined = PORTS.with_borrow(|v| {
+        v.get(module)
+            .is_some_and(|ports| ports.contains_key(import_name))
+    });
+
+    if defined {
+        call_port(module, import_name, in_addr, out_addr);
+    }
+
+    defined
+}
+
+// === Returner === //
+
+pub struct Returner<'t, O: Marshal> {
+    _ty: PhantomData<fn(O) -> O>,
+    _invariant: PhantomData<fn(&'t ()) -> &'t ()>,
+    out_addr: u32,
+}
+
+impl<'t, O: Marshal> Returner<'t, O> {
+    pub fn finish(
+        self,
+        cx: &mut NativeContext,
+        value: &GuestboundViewOf<'_, O>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        <O::Strategy>::encode_guestbound(cx, FfiPtr::new(self.out_addr), value)?;
+
+        Ok(RetVal {
+            _invariant: PhantomData,
+        })
+    }
+}
+
+pub struct RetVal<'t> {
+    _invariant: PhantomData<fn(&'t ()) -> &'t ()>,
+}
+
+pub struct AsyncReturner<'t, O: Marshal> {
+    _invariant: PhantomData<fn(&'t ()) -> &'t ()>,
+    callback: HostClosure<O>,
+}
+
+impl<'t, O: Marshal> AsyncReturner<'t, O> {
+    fn new(callback: HostClosure<O>) -> Self {
+        Self {
+            _invariant: PhantomData,
+            callback,
+        }
+    }
+
+    pub fn finish(
+        self,
+        cx: &mut NativeContext,
+        value: &GuestboundViewOf<'_, O>,
+    ) -> anyhow::Result<RetVal<'t>> {
+        self.callback.call(cx, value)?;
+
+        Ok(RetVal {
+            _invariant: PhantomData,
+        })
+    }
+}
@@ -1,15 +1,21 @@
+#[cfg(feature = "closure-diagnostics")]
+use std::collections::{HashMap, VecDeque};
 use std::{
+    borrow::Cow,
     cell::{Cell, RefCell},
+    fmt,
     marker::PhantomData,
     mem,
     ops::Deref,
+    panic::Location,
+    rc::Rc,
 };
 
 use derive_where::derive_where;
 
 use crate::{
-    FfiPtr, GuestInvokeContext, GuestMemoryContext, GuestboundOf, GuestboundViewOf, Marshal,
-    Strategy, StrategyOf,
+    FfiPtr, GuestInvokeContext, GuestMemoryContext, GuestboundOf, GuestboundViewOf,
+    HostboundViewOf, Marshal, PortDesc, Strategy, StrategyOf, TraceEvent, TypeDesc,
     utils::{align_of_u32, is_wasm, size_of_u32},
 };
 
@@ -50,22 +56,48 @@ impl<I: Strategy> HostClosure_<I> {
 
         <I::Strategy>::encode_guestbound(cx, arg_out, arg)?;
 
-        cx.invoke(self.id, arg_out.addr())?;
+        let Some(tracer) = cx.tracer() else {
+            return cx.invoke(self.id, arg_out.addr());
+        };
+
+        let start = tracer.now();
+        let res = cx.invoke(self.id, arg_out.addr());
+
+        if let Some(tracer) = cx.tracer() {
+            tracer.record(&TraceEvent::Callback {
+                closure: self.id,
+                start,
+                duration: tracer.now().saturating_sub(start),
+                arg: format!("{arg:?}"),
+                result: res.as_ref().map(|_| ()).map_err(|err| format!("{err:#}")),
+            });
+        }
 
-        Ok(())
+        res
     }
 }
 
 // Guest Closure
 
-cfgenius::cond! {
-    if macro(is_wasm) {
-        thread_local! {
-            #[expect(clippy::type_complexity)]
-            static CLOSURES: std::cell::RefCell<thunderdome::Arena<std::rc::Rc<dyn Fn(*mut ())>>> =
-                const { std::cell::RefCell::new(thunderdome::Arena::new()) };
-        }
-    }
+thread_local! {
+    static CLOSURES: RefCell<thunderdome::Arena<ClosureSlot>> =
+        const { RefCell::new(thunderdome::Arena::new()) };
+
+    /// The creation sites of the most recently destroyed closures, used to explain calls to them.
+    #[cfg(feature = "closure-diagnostics")]
+    static DEAD_CLOSURES: RefCell<VecDeque<(u64, &'static Location<'static>)>> =
+        const { RefCell::new(VecDeque::new()) };
+}
+
+/// How many destroyed closures have their creation sites remembered. Older ones are forgotten so
+/// that long sessions don't accumulate them.
+#[cfg(feature = "closure-diagnostics")]
+pub(crate) const DEAD_CLOSURE_CAPACITY: usize = 1024;
+
+struct ClosureSlot {
+    func: Rc<dyn Fn(*mut ())>,
+    #[cfg(feature = "closure-diagnostics")]
+    created_at: &'static Location<'static>,
 }
 
 pub type OwnedGuestClosure<I> = OwnedGuestClosure_<StrategyOf<I>>;
@@ -79,17 +111,20 @@ pub struct OwnedGuestClosure_<I: Strategy> {
 
 impl<I: Strategy> OwnedGuestClosure_<I> {
     #[must_use]
+    #[track_caller]
     pub fn new(f: impl 'static + Fn(GuestboundOf<I>)) -> Self {
         Self::wrap(GuestClosure_::new_unmanaged(f))
     }
 
     #[must_use]
+    #[track_caller]
     pub fn new_mut(f: impl 'static + FnMut(GuestboundOf<I>)) -> Self {
         let f = RefCell::new(f);
         Self::new(move |v| f.borrow_mut()(v))
     }
 
     #[must_use]
+    #[track_caller]
     pub fn new_once(f: impl 'static + FnOnce(GuestboundOf<I>)) -> Self {
         let f = Cell::new(Some(f));
         Self::new(move |v| f.take().expect("closure can only be called once")(v))
@@ -135,89 +170,186 @@ pub struct GuestClosure_<I: Strategy> {
 
 impl<I: Strategy> GuestClosure_<I> {
     #[must_use]
+    #[track_caller]
     fn new_unmanaged(f: impl 'static + Fn(GuestboundOf<I>)) -> Self {
-        cfgenius::cond! {
-            if macro(is_wasm) {
-                Self {
-                    _no_send_sync: PhantomData,
-                    _ty: PhantomData,
-                    raw_handle: CLOSURES.with_borrow_mut(|v| {
-                        v.insert(std::rc::Rc::new(move |ptr| {
-                            f(*unsafe { Box::from_raw(ptr.cast::<GuestboundOf<I>>()) })
-                        }))
-                        .to_bits()
-                    }),
-                }
-            } else {
-                _ = f;
+        let slot = ClosureSlot {
+            func: Rc::new(move |ptr| f(*unsafe { Box::from_raw(ptr.cast::<GuestboundOf<I>>()) })),
+            #[cfg(feature = "closure-diagnostics")]
+            created_at: Location::caller(),
+        };
 
-                unimplemented!()
-            }
+        Self {
+            _no_send_sync: PhantomData,
+            _ty: PhantomData,
+            raw_handle: CLOSURES.with_borrow_mut(|v| v.insert(slot).to_bits()),
         }
     }
 
     #[must_use]
     pub fn is_alive(self) -> bool {
-        cfgenius::cond! {
-            if macro(is_wasm) {
-                CLOSURES.with_borrow(|v| v.contains(self.handle()))
-            } else {
-                unreachable!()
-            }
-        }
+        CLOSURES.with_borrow(|v| v.contains(self.handle()))
     }
 
     pub fn unmanaged_destroy(self) -> bool {
-        cfgenius::cond! {
-            if macro(is_wasm) {
-                CLOSURES
-                    .with_borrow_mut(|v| v.remove(self.handle()))
-                    .is_some()
-            } else {
-                unreachable!()
+        let Some(slot) = CLOSURES.with_borrow_mut(|v| v.remove(self.handle())) else {
+            return false;
+        };
+
+        #[cfg(feature = "closure-diagnostics")]
+        DEAD_CLOSURES.with_borrow_mut(|v| {
+            if v.len() == DEAD_CLOSURE_CAPACITY {
+                v.pop_front();
             }
-        }
+
+            v.push_back((self.raw_handle, slot.created_at));
+        });
+
+        drop(slot);
+
+        true
     }
 
-    #[allow(clippy::boxed_local)] // (for non-wasm variant)
     pub fn call(self, arg: Box<GuestboundOf<I>>) {
-        cfgenius::cond! {
-            if macro(is_wasm) {
-                unsafe { call_raw_closure(self.raw_handle, Box::into_raw(arg).cast::<()>()) }
-            } else {
-                _ = arg;
+        unsafe { call_raw_closure(self.raw_handle, Box::into_raw(arg).cast::<()>()) }
+    }
 
-                unreachable!()
-            }
-        }
+    fn handle(self) -> thunderdome::Index {
+        thunderdome::Index::from_bits(self.raw_handle).unwrap()
     }
 }
 
-cfgenius::cond! {
-    if macro(is_wasm) {
-        impl<I: Strategy> GuestClosure_<I> {
-            fn handle(self) -> thunderdome::Index {
-                thunderdome::Index::from_bits(self.raw_handle).unwrap()
-            }
+pub(crate) unsafe fn call_raw_closure(raw_handle: u64, boxed_arg: *mut ()) {
+    let handle = thunderdome::Index::from_bits(raw_handle).unwrap();
+
+    let Some(func) = CLOSURES.with_borrow(|v| v.get(handle).map(|v| v.func.clone())) else {
+        match dead_closure_site(raw_handle) {
+            Some(site) => panic!("attempted to call dead closure {handle:?} created at {site}"),
+            None => panic!("attempted to call dead closure {handle:?}"),
         }
+    };
 
-        unsafe fn call_raw_closure(handle: u64, boxed_arg: *mut ()) {
-            cfgenius::cond! {
-                if macro(is_wasm) {
-                    let handle = thunderdome::Index::from_bits(handle).unwrap();
+    func(boxed_arg);
+}
 
-                    let slot = CLOSURES
-                        .with_borrow(|v| v.get(handle).cloned())
-                        .unwrap_or_else(|| panic!("attempted to call dead closure {handle:?}"));
+#[cfg(feature = "closure-diagnostics")]
+fn dead_closure_site(raw_handle: u64) -> Option<&'static Location<'static>> {
+    DEAD_CLOSURES.with_borrow(|v| {
+        v.iter()
+            .find(|&&(handle, _)| handle == raw_handle)
+            .map(|&(_, site)| site)
+    })
+}
 
-                    slot(boxed_arg);
-                } else {
-                    _ = boxed_arg;
+#[cfg(not(feature = "closure-diagnostics"))]
+fn dead_closure_site(_raw_handle: u64) -> Option<&'static Location<'static>> {
+    None
+}
 
-                    unreachable!();
-                }
-            }
-        }
+// Diagnostics
+
+/// A summary of the guest's live closures as returned by [`closure_stats`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ClosureStats {
+    pub live: u32,
+    /// The live closures grouped by the site which created them, most common first. This is only
+    /// populated when the guest is built with the `closure-diagnostics` feature.
+    pub sites: Vec<ClosureSiteStats>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClosureSiteStats {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub live: u32,
+}
+
+impl fmt::Display for ClosureSiteStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+impl ClosureStats {
+    /// Reads the report a guest sent through [`BUILTIN_REPORT_CLOSURES`].
+    pub fn decode(
+        cx: &(impl ?Sized + GuestMemoryContext),
+        report: HostboundViewOf<ClosureReport>,
+    ) -> anyhow::Result<Self> {
+        let sites = report
+            .sites
+            .map(|site| {
+                let site = site.decode(cx)?;
+
+                Ok(ClosureSiteStats {
+                    file: site.file.read(cx)?.to_string(),
+                    line: site.line,
+                    column: site.column,
+                    live: site.live,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            live: report.live,
+            sites,
+        })
+    }
+}
+
+/// Summarizes the closures the guest currently has alive.
+pub fn closure_stats() -> ClosureStats {
+    CLOSURES.with_borrow(|closures| ClosureStats {
+        live: closures.len() as u32,
+        sites: closure_sites(closures),
+    })
+}
+
+#[cfg(feature = "closure-diagnostics")]
+fn closure_sites(closures: &thunderdome::Arena<ClosureSlot>) -> Vec<ClosureSiteStats> {
+    let mut counts = HashMap::<&'static Location<'static>, u32>::new();
+
+    for (_, slot) in closures.iter() {
+        *counts.entry(slot.created_at).or_default() += 1;
+    }
+
+    let mut sites = counts.into_iter().collect::<Vec<_>>();
+    sites.sort_by(|(a_site, a_live), (b_site, b_live)| {
+        b_live.cmp(a_live).then_with(|| a_site.cmp(b_site))
+    });
+
+    sites
+        .into_iter()
+        .map(|(site, live)| ClosureSiteStats {
+            file: site.file().to_string(),
+            line: site.line(),
+            column: site.column(),
+            live,
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "closure-diagnostics"))]
+fn closure_sites(_closures: &thunderdome::Arena<ClosureSlot>) -> Vec<ClosureSiteStats> {
+    Vec::new()
+}
+
+/// Delivers a [`ClosureReport`] to the host. Guests send one whenever the host calls their
+/// [`BUILTIN_CLOSURE_REPORT`] export.
+pub const BUILTIN_REPORT_CLOSURES: Port<ClosureReport> = Port::new("wasmlink", "report_closures");
+
+crate::marshal_struct! {
+    /// The FFI form of [`ClosureStats`].
+    pub struct ClosureReport {
+        pub live: u32,
+        pub sites: Vec<ClosureReportSite>,
+    }
+
+    pub struct ClosureReportSite {
+        pub file: String,
+        pub line: u32,
+        pub column: u32,
+        pub live: u32,
     }
 }
 
@@ -232,6 +364,10 @@ impl<I: Strategy> Strategy for fn(I) {
     type Guestbound = GuestClosure_<I>;
     type GuestboundView<'a> = HostClosure_<I>;
 
+    fn describe() -> TypeDesc {
+        TypeDesc::Closure(Box::new(I::describe()))
+    }
+
     fn decode_hostbound(
         cx: &(impl ?Sized + GuestMemoryContext),
         ptr: FfiPtr<Self::Hostbound<'static>>,
@@ -262,6 +398,7 @@ where
     _ty: PhantomData<fn(I, O) -> (I, O)>,
     module: &'static str,
     func_name: &'static str,
+    version: u32,
 }
 
 impl<I, O> Port<I, O>
@@ -274,9 +411,16 @@ where
             _ty: PhantomData,
             module,
             func_name,
+            version: 0,
         }
     }
 
+    /// Gives the port a version, which becomes part of its import name. Version `0` denotes an
+    /// unversioned port.
+    pub const fn with_version(self, version: u32) -> Self {
+        Self { version, ..self }
+    }
+
     pub const fn module(self) -> &'static str {
         self.module
     }
@@ -285,6 +429,33 @@ where
         self.func_name
     }
 
+    pub const fn version(self) -> u32 {
+        self.version
+    }
+
+    /// The name under which the port is imported by the guest. This is `func_name` for unversioned
+    /// ports and `func_name@v{version}` for versioned ones.
+    pub fn import_name(self) -> Cow<'static, str> {
+        if self.version == 0 {
+            Cow::Borrowed(self.func_name)
+        } else {
+            Cow::Owned(format!("{}@v{}", self.func_name, self.version))
+        }
+    }
+
+    pub fn describe(self) -> PortDesc {
+        PortDesc {
+            module: self.module.to_string(),
+            func_name: self.import_name().into_owned(),
+            input: <I::Strategy>::describe(),
+            output: <O::Strategy>::describe(),
+        }
+    }
+
+    pub fn signature_hash(self) -> u64 {
+        self.describe().signature_hash()
+    }
+
     pub const fn is_compatible(self, other: Port<I, O>) -> bool {
         const fn str_eq(a: &str, b: &str) -> bool {
             if a.len() != b.len() {
@@ -304,7 +475,9 @@ where
             true
         }
 
-        str_eq(self.module, other.module) && str_eq(self.func_name, other.func_name)
+        str_eq(self.module, other.module)
+            && str_eq(self.func_name, other.func_name)
+            && self.version == other.version
     }
 
     pub const fn assert_compatible(self, other: Port<I, O>) {
@@ -314,35 +487,95 @@ where
     }
 }
 
+/// Appended to the import name of ports the guest binds as `optional` (see [`bind_port!`]). Hosts
+/// define these imports as functions forwarding the call and returning `1` if they provide the
+/// port and as functions returning `0` without doing anything otherwise so that guests can probe
+/// for ports without trapping.
+pub const OPTIONAL_IMPORT_SUFFIX: &str = "?";
+
+/// Splits an import name produced by [`Port::import_name`] into its function name and version.
+pub fn split_import_name(import_name: &str) -> (&str, u32) {
+    import_name
+        .rsplit_once("@v")
+        .and_then(|(func_name, version)| Some((func_name, version.parse().ok()?)))
+        .filter(|&(_, version)| version != 0)
+        .unwrap_or((import_name, 0))
+}
+
 #[doc(hidden)]
 pub mod bind_port_internals {
     pub use {
-        crate::{GuestboundOf, HostboundOf, Port},
+        crate::{AsyncCall, AsyncPort, AsyncPortCall, FfiPtr, GuestboundOf, HostboundOf, Port},
         std::{mem::MaybeUninit, stringify},
     };
+
+    pub type OrUnit<T = ()> = T;
+
+    cfgenius::cond! {
+        if not(macro(super::is_wasm)) {
+            pub use crate::native::{call_optional_port, call_port};
+        }
+    }
 }
 
 #[macro_export]
 macro_rules! bind_port {
-    ($(
+    () => {};
+    (
         $(#[$($meta:tt)*])*
-        $vis:vis fn[$matches_port:expr] $module:literal.$name:ident($input:ty) $(-> $out:ty)?;
-    )*) => {$(
+        $vis:vis async fn[$matches_port:expr] $module:literal.$name:ident $(@ $version:literal)? ($input:ty) $(-> $out:ty)?;
+        $($rest:tt)*
+    ) => {
         $(#[$meta])*
-        $vis fn $name(input: &$crate::bind_port_internals::HostboundOf<$input>) -> ($($crate::bind_port_internals::GuestboundOf<$out>)?) {
+        $vis fn $name(
+            input: &$crate::bind_port_internals::HostboundOf<$input>,
+        ) -> $crate::bind_port_internals::AsyncPortCall<$crate::bind_port_internals::OrUnit<$($out)?>> {
+            $crate::bind_port! {
+                fn [$crate::bind_port_internals::AsyncPort::<$input, $crate::bind_port_internals::OrUnit<$($out)?>>::port($matches_port)]
+                    $module.$name $(@ $version)? ($crate::bind_port_internals::AsyncCall<$input, $crate::bind_port_internals::OrUnit<$($out)?>>) -> u64;
+            }
+
+            $crate::bind_port_internals::AsyncPortCall::start::<$input>(input, |call| $name(call))
+        }
+
+        $crate::bind_port! { $($rest)* }
+    };
+    (
+        $(#[$($meta:tt)*])*
+        $vis:vis fn[$matches_port:expr] $module:literal.$name:ident $(@ $version:literal)? ($input:ty) $(-> $out:ty)?;
+        $($rest:tt)*
+    ) => {
+        $(#[$meta])*
+        $vis fn $name(input: &$crate::bind_port_internals::HostboundOf<$input>) -> $crate::bind_port_internals::GuestboundOf<$crate::bind_port_internals::OrUnit<$($out)?>> {
             const _: () = {
-                $crate::bind_port_internals::Port::<$input, ($($out)?)>::new(
+                $crate::bind_port_internals::Port::<$input, $crate::bind_port_internals::OrUnit<$($out)?>>::new(
                     $module,
                     $crate::bind_port_internals::stringify!($name),
                 )
+                $(.with_version($version))?
                 .assert_compatible($matches_port)
             };
 
+            #[cfg(target_arch = "wasm32")]
             #[link(wasm_import_module = $module)]
             unsafe extern "C" {
+                #[link_name = concat!(stringify!($name) $(, "@v", stringify!($version))?)]
                 fn $name(
                     input: &$crate::bind_port_internals::HostboundOf<$input>,
-                    output: *mut $crate::bind_port_internals::GuestboundOf<($($out)?)>,
+                    output: *mut $crate::bind_port_internals::GuestboundOf<$crate::bind_port_internals::OrUnit<$($out)?>>,
+                );
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            unsafe fn $name(
+                input: &$crate::bind_port_internals::HostboundOf<$input>,
+                output: *mut $crate::bind_port_internals::GuestboundOf<$crate::bind_port_internals::OrUnit<$($out)?>>,
+            ) {
+                $crate::bind_port_internals::call_port(
+                    $module,
+                    concat!(stringify!($name) $(, "@v", stringify!($version))?),
+                    $crate::bind_port_internals::FfiPtr::new_guest(input).addr(),
+                    $crate::bind_port_internals::FfiPtr::new_guest(output).addr(),
                 );
             }
 
@@ -352,13 +585,64 @@ macro_rules! bind_port {
                 output.assume_init()
             }
         }
-    )*};
+
+        $crate::bind_port! { $($rest)* }
+    };
+    (
+        $(#[$($meta:tt)*])*
+        $vis:vis optional fn[$matches_port:expr] $module:literal.$name:ident $(@ $version:literal)? ($input:ty) $(-> $out:ty)?;
+        $($rest:tt)*
+    ) => {
+        $(#[$meta])*
+        $vis fn $name(input: &$crate::bind_port_internals::HostboundOf<$input>) -> Option<$crate::bind_port_internals::GuestboundOf<$crate::bind_port_internals::OrUnit<$($out)?>>> {
+            const _: () = {
+                $crate::bind_port_internals::Port::<$input, $crate::bind_port_internals::OrUnit<$($out)?>>::new(
+                    $module,
+                    $crate::bind_port_internals::stringify!($name),
+                )
+                $(.with_version($version))?
+                .assert_compatible($matches_port)
+            };
+
+            #[cfg(target_arch = "wasm32")]
+            #[link(wasm_import_module = $module)]
+            unsafe extern "C" {
+                #[link_name = concat!(stringify!($name) $(, "@v", stringify!($version))?, "?")]
+                fn $name(
+                    input: &$crate::bind_port_internals::HostboundOf<$input>,
+                    output: *mut $crate::bind_port_internals::GuestboundOf<$crate::bind_port_internals::OrUnit<$($out)?>>,
+                ) -> u32;
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            unsafe fn $name(
+                input: &$crate::bind_port_internals::HostboundOf<$input>,
+                output: *mut $crate::bind_port_internals::GuestboundOf<$crate::bind_port_internals::OrUnit<$($out)?>>,
+            ) -> u32 {
+                $crate::bind_port_internals::call_optional_port(
+                    $module,
+                    concat!(stringify!($name) $(, "@v", stringify!($version))?),
+                    $crate::bind_port_internals::FfiPtr::new_guest(input).addr(),
+                    $crate::bind_port_internals::FfiPtr::new_guest(output).addr(),
+                ) as u32
+            }
+
+            unsafe {
+                let mut output = $crate::bind_port_internals::MaybeUninit::uninit();
+
+                ($name(input, output.as_mut_ptr()) != 0).then(|| output.assume_init())
+            }
+        }
+
+        $crate::bind_port! { $($rest)* }
+    };
 }
 
 // === Guest Exports === //
 
 pub const BUILTIN_MEM_ALLOC: &str = "rust_wasmlink_mem_alloc";
 pub const BUILTIN_CLOSURE_INVOKE: &str = "rust_wasmlink_closure_invoke";
+pub const BUILTIN_CLOSURE_REPORT: &str = "rust_wasmlink_closure_report";
 
 cfgenius::cond! {
     if macro(is_wasm) {
@@ -377,5 +661,30 @@ cfgenius::cond! {
         unsafe extern "C" fn rust_wasmlink_closure_invoke(handle: u64, boxed_arg: *mut ()) {
             unsafe { call_raw_closure(handle, boxed_arg) };
         }
+
+        #[unsafe(no_mangle)]
+        extern "C" fn rust_wasmlink_closure_report() {
+            crate::bind_port! {
+                fn [BUILTIN_REPORT_CLOSURES] "wasmlink".report_closures(ClosureReport);
+            }
+
+            let stats = closure_stats();
+
+            let sites = stats
+                .sites
+                .iter()
+                .map(|site| ClosureReportSite {
+                    file: crate::GuestStrRef::new(&site.file),
+                    line: site.line,
+                    column: site.column,
+                    live: site.live,
+                })
+                .collect::<Vec<_>>();
+
+            report_closures(&ClosureReport {
+                live: stats.live,
+                sites: crate::GuestSliceRef::new(&sites),
+            });
+        }
     }
 }
@@ -0,0 +1,707 @@
+#![cfg(test)]
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use super::{backend_tests::with_custom_section, *};
+
+// === Harness === //
+
+#[derive(Debug, Default)]
+struct TestMemory {
+    bytes: Vec<u8>,
+}
+
+impl GuestMemoryContext for TestMemory {
+    fn memory(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+impl GuestInvokeContext for TestMemory {
+    fn alloc(&mut self, align: u32, size: u32) -> anyhow::Result<FfiPtr<()>> {
+        // Never hand out the null address.
+        let base = self.bytes.len().max(1).next_multiple_of(align as usize);
+        self.bytes.resize(base + size as usize, 0);
+
+        Ok(FfiPtr::new(u32::try_from(base)?))
+    }
+
+    fn invoke(&mut self, id: u64, _boxed_arg: u32) -> anyhow::Result<()> {
+        anyhow::bail!("test memory cannot invoke closure {id}")
+    }
+}
+
+/// Encodes a value as the host would for the guest and decodes the resulting bytes as if the guest
+/// had sent them back. This works because every strategy gives its hostbound and guestbound
+/// representations the same layout.
+fn round_trip<S: Strategy>(
+    cx: &mut TestMemory,
+    value: &S::GuestboundView<'_>,
+) -> anyhow::Result<S::HostboundView> {
+    let ptr = cx
+        .alloc(
+            utils::align_of_u32::<S::Guestbound>(),
+            utils::size_of_u32::<S::Guestbound>(),
+        )?
+        .cast::<S::Guestbound>();
+
+    S::encode_guestbound(cx, ptr, value)?;
+    S::decode_hostbound(cx, ptr.cast())
+}
+
+fn poll_once<F: Future + Unpin>(fut: &mut F) -> Poll<F::Output> {
+    Pin::new(fut).poll(&mut Context::from_waker(Waker::noop()))
+}
+
+fn read_vec<T: bytemuck::Pod + std::fmt::Debug>(
+    cx: &TestMemory,
+    slice: HostSlice_<PodMarshal<T>>,
+) -> Vec<T> {
+    slice.slice().read(cx).unwrap().to_vec()
+}
+
+#[derive(Debug, Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// === Tests === //
+
+#[test]
+fn round_trip_array() {
+    let cx = &mut TestMemory::default();
+
+    let values = round_trip::<StrategyOf<[Option<u16>; 3]>>(cx, &[Some(1), None, Some(3)]).unwrap();
+    assert_eq!(values, [Some(1), None, Some(3)]);
+
+    let strings = round_trip::<StrategyOf<[String; 2]>>(cx, &["foo", "barbaz"]).unwrap();
+    assert_eq!(
+        strings.map(|v| v.read(cx).unwrap().to_string()),
+        ["foo", "barbaz"]
+    );
+
+    let empty = round_trip::<StrategyOf<[String; 0]>>(cx, &[]).unwrap();
+    assert_eq!(empty.len(), 0);
+}
+
+#[test]
+fn round_trip_nested_vec() {
+    let cx = &mut TestMemory::default();
+
+    let value: &[&[u32]] = &[&[1, 2, 3], &[], &[4]];
+    let outer = round_trip::<StrategyOf<Vec<Vec<u32>>>>(cx, &value).unwrap();
+
+    assert_eq!(outer.len(), 3);
+
+    let inner = outer
+        .map(|v| v.decode(cx).map(|v| read_vec(cx, v)))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(inner, [vec![1, 2, 3], vec![], vec![4]]);
+}
+
+#[test]
+fn round_trip_maps() {
+    let cx = &mut TestMemory::default();
+
+    let entries: &[(&str, u64)] = &[("apples", 3), ("pears", 5)];
+
+    let hash = round_trip::<StrategyOf<HashMap<String, u64>>>(cx, &entries)
+        .unwrap()
+        .decode::<Vec<_>>(cx)
+        .unwrap()
+        .into_iter()
+        .map(|(k, v)| (k.read(cx).unwrap().to_string(), v))
+        .collect::<HashMap<_, _>>();
+
+    assert_eq!(
+        hash,
+        HashMap::from([("apples".to_string(), 3), ("pears".to_string(), 5)])
+    );
+
+    let entries: &[(u8, &[u16])] = &[(2, &[20, 21]), (1, &[10])];
+
+    let tree = round_trip::<StrategyOf<BTreeMap<u8, Vec<u16>>>>(cx, &entries)
+        .unwrap()
+        .decode::<BTreeMap<_, _>>(cx)
+        .unwrap()
+        .into_iter()
+        .map(|(k, v)| (k, read_vec(cx, v)))
+        .collect::<Vec<_>>();
+
+    assert_eq!(tree, [(1, vec![10]), (2, vec![20, 21])]);
+
+    let empty = round_trip::<StrategyOf<HashMap<u32, u32>>>(cx, &[].as_slice()).unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn round_trip_tuple_of_collections() {
+    let cx = &mut TestMemory::default();
+
+    let entries: &[(u32, bool)] = &[(7, true)];
+    let list: &[&str] = &["a", "bc"];
+
+    let (map, list, flag) = round_trip::<StrategyOf<(BTreeMap<u32, bool>, Vec<String>, bool)>>(
+        cx,
+        &(entries, list, true),
+    )
+    .unwrap();
+
+    assert_eq!(map.decode::<Vec<_>>(cx).unwrap(), [(7, true)]);
+    assert_eq!(
+        list.map(|v| v.decode(cx).unwrap().read(cx).unwrap().to_string())
+            .collect::<Vec<_>>(),
+        ["a", "bc"]
+    );
+    assert!(flag);
+}
+
+#[test]
+fn bulk_writes_pod_slices() {
+    let cx = &mut TestMemory::default();
+    let values = (0..4096u32).collect::<Vec<_>>();
+
+    let slice = round_trip::<StrategyOf<Vec<u32>>>(cx, &values.as_slice()).unwrap();
+    assert_eq!(slice.slice().read(cx).unwrap(), values);
+
+    let buf = cx.alloc_slice::<u16>(3).unwrap();
+    buf.write(cx).unwrap().copy_from_slice(&[1, 2, 3]);
+
+    let slice = HostSlice_::<PodMarshal<u16>>::new(buf);
+    assert_eq!(slice.slice().read(cx).unwrap(), [1, 2, 3]);
+}
+
+#[test]
+fn rejects_out_of_bounds_map() {
+    let cx = &mut TestMemory::default();
+
+    let ptr = cx
+        .alloc(4, 8)
+        .unwrap()
+        .cast::<FfiSlice<FfiTuple<(u32, u32)>>>();
+
+    *ptr.write(cx).unwrap() = FfiSlice::new(FfiPtr::new(0x1000), 4);
+
+    let map = <StrategyOf<HashMap<u32, u32>>>::decode_hostbound(cx, ptr.cast()).unwrap();
+    assert!(map.decode::<HashMap<_, _>>(cx).is_err());
+}
+
+#[test]
+fn describe_round_trips_through_text() {
+    crate::marshal_struct! {
+        struct Config {
+            name: String,
+            limits: BTreeMap<String, [u32; 2]>,
+            callback: fn(Result<Vec<u8>, String>),
+        }
+    }
+
+    crate::marshal_enum! {
+        /// Attributes on items and variants are forwarded.
+        enum Mode : u8 {
+            /// The default.
+            Fast = 1,
+            Slow = 4,
+        }
+    }
+
+    crate::marshal_tagged_union! {
+        /// Attributes on items are forwarded.
+        enum Event : u16 {
+            Configured(Box<Config>),
+            Switched((Mode, Option<char>, bool)),
+        }
+    }
+
+    let port = Port::<Event, HashMap<u64, f32>>::new("test", "send_event").describe();
+    let abi = AbiDesc::new([port.clone()]);
+    let parsed = AbiDesc::parse(&abi.to_string()).unwrap();
+
+    assert_eq!(parsed.ports.len(), 1);
+    assert_eq!(parsed.ports[0].to_string(), port.to_string());
+    assert_eq!(parsed.ports[0].signature_hash(), port.signature_hash());
+    assert_eq!(parsed.signature_hash(), abi.signature_hash());
+}
+
+#[test]
+fn describe_reports_first_mismatch() {
+    crate::marshal_struct! {
+        struct HostInfo {
+            motd: String,
+            server: Option<String>,
+        }
+
+        struct GuestInfo {
+            motd: String,
+            server: Option<Vec<u8>>,
+        }
+
+        struct RenamedInfo {
+            message: String,
+            content_server: Option<String>,
+        }
+    }
+
+    let host = AbiDesc::new([
+        Port::<fn(HostInfo)>::new("test", "get_info").describe(),
+        Port::<u32, u64>::new("test", "get_id").describe(),
+    ]);
+
+    let guest = AbiDesc::new([
+        Port::<fn(GuestInfo)>::new("test", "get_info").describe(),
+        Port::<u32, u64>::new("test", "get_id").describe(),
+        Port::<u32, u64>::new("test", "get_rtt").describe(),
+    ]);
+
+    let mismatches = host.check(
+        &guest,
+        [
+            ("test", "get_info"),
+            ("test", "get_id"),
+            ("test", "get_rtt"),
+            ("test", "undescribed"),
+        ],
+    );
+
+    assert_eq!(
+        mismatches,
+        [
+            AbiMismatch::TypeMismatch {
+                module: "test".to_string(),
+                func_name: "get_info".to_string(),
+                direction: PortDirection::Input,
+                path: "(_).server.some".to_string(),
+                host: "string".to_string(),
+                guest: "vec(pod(1,1))".to_string(),
+            },
+            AbiMismatch::MissingOnHost {
+                module: "test".to_string(),
+                func_name: "get_rtt".to_string(),
+            },
+        ]
+    );
+
+    // Optional imports are expected to be missing but must still agree on their types.
+    assert!(host.check(&guest, [("test", "get_rtt?")]).is_empty());
+    assert_eq!(host.check(&guest, [("test", "get_info?")]).len(), 1);
+
+    // Names don't affect layout and are therefore ignored.
+    let renamed = AbiDesc::new([Port::<fn(RenamedInfo)>::new("test", "get_info").describe()]);
+    assert!(host.check(&renamed, [("test", "get_info")]).is_empty());
+    assert_eq!(host.ports[0].signature_hash(), renamed.ports[0].signature_hash());
+}
+
+#[test]
+fn describe_reads_custom_sections() {
+    let host = AbiDesc::new([Port::<u32, u64>::new("test", "get_id").describe()]);
+    let guest = AbiDesc::new([Port::<u64, u64>::new("test", "get_id").describe()]);
+
+    let empty = b"\0asm\x01\0\0\0".to_vec();
+    let binary = with_custom_section(
+        with_custom_section(empty.clone(), "name", "unrelated"),
+        ABI_DESCRIPTOR_SECTION,
+        &guest.to_string(),
+    );
+
+    assert!(AbiDesc::from_module(&empty).unwrap().is_none());
+    assert_eq!(
+        AbiDesc::from_module(&binary).unwrap().unwrap().ports,
+        guest.ports
+    );
+    assert!(AbiDesc::from_module(&binary[..binary.len() - 1]).is_err());
+
+    assert!(host.check_module(&empty, [("test", "get_id")]).is_ok());
+    assert!(host.check_module(&binary, [("test", "get_id")]).is_err());
+    assert!(host.check_module(&binary, []).unwrap().is_empty());
+}
+
+#[test]
+fn versioned_import_names() {
+    let unversioned = Port::<u32>::new("test", "get_id");
+    let versioned = unversioned.with_version(2);
+
+    assert_eq!(unversioned.import_name(), "get_id");
+    assert_eq!(versioned.import_name(), "get_id@v2");
+    assert_eq!(versioned.describe().func_name, "get_id@v2");
+    assert!(!unversioned.is_compatible(versioned));
+
+    assert_eq!(split_import_name("get_id"), ("get_id", 0));
+    assert_eq!(split_import_name("get_id@v2"), ("get_id", 2));
+    assert_eq!(split_import_name("get_id@v0"), ("get_id@v0", 0));
+    assert_eq!(split_import_name("get_id@vx"), ("get_id@vx", 0));
+}
+
+#[test]
+fn native_ports_call_handlers() {
+    crate::marshal_struct! {
+        struct Greeting {
+            name: String,
+            on_reply: fn(Vec<u32>),
+        }
+    }
+
+    const GREET: Port<Greeting, Option<String>> = Port::new("test", "greet");
+
+    crate::bind_port! {
+        fn[GREET] "test".greet(Greeting) -> Option<String>;
+    }
+
+    let host = native::NativeHost::new();
+
+    host.define(GREET, |cx, req, out| {
+        let name = req.name.read(cx)?.to_string();
+
+        req.on_reply.call(cx, &[1, 2, 3].as_slice())?;
+
+        out.finish(cx, &Some(format!("hello, {name}").as_str()))
+    })
+    .unwrap();
+
+    assert!(
+        host.define(GREET, |cx, _, out| out.finish(cx, &None))
+            .is_err()
+    );
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let on_reply = OwnedGuestClosure::<Vec<u32>>::new({
+        let received = received.clone();
+        move |v| received.borrow_mut().extend(v.decode())
+    });
+
+    let reply = greet(&Greeting {
+        name: GuestStrRef::new("world"),
+        on_reply: on_reply.handle(),
+    });
+
+    assert_eq!(
+        reply.decode().map(|v| v.decode()).as_deref(),
+        Some("hello, world")
+    );
+    assert_eq!(*received.borrow(), [1, 2, 3]);
+}
+
+#[test]
+fn native_ports_reject_boxes() {
+    let host = native::NativeHost::new();
+
+    let result = host.define(Port::<Box<u32>>::new("test", "boxed"), |cx, _, out| {
+        out.finish(cx, &())
+    });
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn native_ports_reuse_guest_addresses() {
+    const MEASURE: Port<String, u32> = Port::new("test", "measure");
+    const FORMAT: Port<u32, String> = Port::new("test", "format");
+
+    crate::bind_port! {
+        fn[MEASURE] "test".measure(String) -> u32;
+        fn[FORMAT] "test".format(u32) -> String;
+    }
+
+    let host = native::NativeHost::new();
+
+    host.define(MEASURE, |cx, text, out| {
+        let len = text.read(cx)?.len() as u32;
+        out.finish(cx, &len)
+    })
+    .unwrap();
+
+    host.define(FORMAT, |cx, value, out| {
+        out.finish(cx, &value.to_string().as_str())
+    })
+    .unwrap();
+
+    // Mapping a new region for every call would exhaust the 4 GiB address space long before this.
+    let large = "x".repeat(1 << 20);
+
+    for _ in 0..5000 {
+        assert_eq!(measure(&GuestStrRef::new(&large)), 1 << 20);
+    }
+
+    for i in 0..10_000u32 {
+        let text = format(&i).decode();
+        assert_eq!(measure(&GuestStrRef::new(&text)), text.len() as u32);
+    }
+
+    assert!(native::mapped_regions() < 64);
+}
+
+#[test]
+fn native_async_ports_complete() {
+    const DOUBLE: AsyncPort<u32, Option<u32>> = AsyncPort::new("test", "double");
+
+    crate::bind_port! {
+        async fn[DOUBLE] "test".double(u32) -> Option<u32>;
+    }
+
+    let host = native::NativeHost::new();
+
+    host.define_async(
+        DOUBLE,
+        |_cx, value| Ok(async move { value.checked_mul(2) }),
+        |cx, value, out| out.finish(cx, &value),
+    )
+    .unwrap();
+
+    let mut call = double(&21);
+    assert!(poll_once(&mut call).is_pending());
+    assert_eq!(host.running_tasks(), 1);
+
+    host.run_tasks().unwrap();
+    assert_eq!(host.running_tasks(), 0);
+
+    let Poll::Ready(value) = poll_once(&mut call) else {
+        panic!("call did not complete");
+    };
+
+    assert_eq!(value.decode(), Some(42));
+
+    // The arguments are passed by pointer, which the description must reflect.
+    let TypeDesc::Struct { fields, .. } = DOUBLE.describe().input else {
+        panic!("async calls should be described as structs");
+    };
+
+    assert_eq!(
+        fields[0],
+        (
+            "args".to_string(),
+            TypeDesc::Box(Box::new(<<u32 as Marshal>::Strategy>::describe()))
+        )
+    );
+}
+
+#[test]
+fn native_async_ports_cancel_on_drop() {
+    const STALL: AsyncPort<()> = AsyncPort::new("test", "stall");
+
+    crate::bind_port! {
+        async fn[STALL] "test".stall(());
+    }
+
+    struct DropFlag(Rc<Cell<bool>>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let host = native::NativeHost::new();
+    let dropped = Rc::new(Cell::new(false));
+
+    host.define_async(
+        STALL,
+        {
+            let dropped = dropped.clone();
+
+            move |_cx, ()| {
+                let flag = DropFlag(dropped.clone());
+
+                Ok(async move {
+                    let _flag = flag;
+                    future::pending::<()>().await;
+                })
+            }
+        },
+        |_cx, (), _out| unreachable!(),
+    )
+    .unwrap();
+
+    let mut call = stall(&());
+    host.run_tasks().unwrap();
+    assert!(poll_once(&mut call).is_pending());
+    assert!(!dropped.get());
+
+    drop(call);
+    assert_eq!(host.running_tasks(), 0);
+
+    host.run_tasks().unwrap();
+    assert!(dropped.get());
+}
+
+#[test]
+fn closure_stats_count_live_closures() {
+    let before = closure_stats().live;
+
+    let closure = OwnedGuestClosure::<u32>::new(|_| {});
+    assert_eq!(closure_stats().live, before + 1);
+
+    drop(closure);
+    assert_eq!(closure_stats().live, before);
+}
+
+#[test]
+#[cfg(feature = "closure-diagnostics")]
+fn closure_stats_track_creation_sites() {
+    let line = line!() + 1;
+    let _closure = OwnedGuestClosure::<u32>::new(|_| {});
+
+    let stats = closure_stats();
+    let site = stats
+        .sites
+        .iter()
+        .find(|site| site.file == file!() && site.line == line)
+        .unwrap();
+
+    assert_eq!(site.live, 1);
+}
+
+#[test]
+#[cfg(feature = "closure-diagnostics")]
+#[should_panic(expected = "created at")]
+fn dead_closure_calls_report_creation_site() {
+    let closure = OwnedGuestClosure::<u32>::new(|_| {});
+    let handle = closure.handle();
+
+    drop(closure);
+    handle.call(Box::new(1));
+}
+
+#[test]
+#[cfg(feature = "closure-diagnostics")]
+fn dead_closure_sites_are_bounded() {
+    let closure = OwnedGuestClosure::<u32>::new(|_| {});
+    let handle = closure.handle();
+
+    drop(closure);
+
+    for _ in 0..DEAD_CLOSURE_CAPACITY {
+        drop(OwnedGuestClosure::<u32>::new(|_| {}));
+    }
+
+    let err = std::panic::catch_unwind(|| handle.call(Box::new(1))).unwrap_err();
+    let message = err.downcast_ref::<String>().unwrap();
+
+    assert!(message.contains("dead closure"));
+    assert!(!message.contains("created at"));
+}
+
+#[test]
+fn traces_round_trip() {
+    crate::marshal_struct! {
+        struct Point {
+            x: i32,
+            label: Option<String>,
+        }
+    }
+
+    let buf = SharedBuffer::default();
+    let mut tracer = Tracer::new(buf.clone()).unwrap();
+
+    let args = Point::<GuestboundViewVariant<'_>> {
+        x: 3,
+        label: Some("origin"),
+    };
+
+    for _ in 0..2 {
+        let start = tracer.begin_port_call();
+        tracer.record_return(&Some(4u32));
+        tracer.end_port_call("test", "move", start, format!("{args:?}"), Ok(()));
+    }
+
+    let start = tracer.begin_port_call();
+    tracer.end_port_call(
+        "test",
+        "move",
+        start,
+        "()".to_string(),
+        Err("oops".to_string()),
+    );
+
+    tracer.record(&TraceEvent::Callback {
+        closure: 42,
+        start: Duration::from_millis(1),
+        duration: Duration::from_micros(5),
+        arg: "7".to_string(),
+        result: Ok(()),
+    });
+
+    tracer.finish().unwrap();
+
+    let bytes = buf.0.borrow().clone();
+
+    // Port names are only written out once.
+    assert_eq!(bytes.windows(4).filter(|w| w == b"move").count(), 1);
+
+    let events = TraceReader::new(bytes.as_slice())
+        .unwrap()
+        .collect::<anyhow::Result<Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(events.len(), 4);
+
+    for event in &events[..2] {
+        let TraceEvent::PortCall {
+            module,
+            func_name,
+            args,
+            result,
+            ..
+        } = event
+        else {
+            panic!("expected a port call, got {event:?}");
+        };
+
+        assert_eq!((module.as_str(), func_name.as_str()), ("test", "move"));
+        assert_eq!(args, r#"Point { x: 3, label: Some("origin") }"#);
+        assert_eq!(result.as_deref(), Ok("Some(4)"));
+    }
+
+    assert!(matches!(
+        &events[2],
+        TraceEvent::PortCall { result: Err(err), .. } if err == "oops",
+    ));
+
+    assert_eq!(
+        events[3],
+        TraceEvent::Callback {
+            closure: 42,
+            start: Duration::from_millis(1),
+            duration: Duration::from_micros(5),
+            arg: "7".to_string(),
+            result: Ok(()),
+        }
+    );
+}
+
+#[test]
+fn trace_reader_rejects_malformed_traces() {
+    assert!(TraceReader::new(b"not a trace".as_slice()).is_err());
+
+    let buf = SharedBuffer::default();
+    let mut tracer = Tracer::new(buf.clone()).unwrap();
+    let start = tracer.begin_port_call();
+    tracer.end_port_call("test", "move", start, "()".to_string(), Ok(()));
+    tracer.finish().unwrap();
+
+    let bytes = buf.0.borrow().clone();
+    let mut reader = TraceReader::new(&bytes[..bytes.len() - 1]).unwrap();
+
+    assert!(reader.next().unwrap().is_err());
+}
@@ -0,0 +1,470 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+
+use crate::GuestMemory;
+
+// === TraceEvent === //
+
+const TRACE_MAGIC: &[u8; 8] = b"WSLTRACE";
+const TRACE_VERSION: u16 = 1;
+
+const RECORD_DEFINE_PORT: u8 = 0;
+const RECORD_PORT_CALL: u8 = 1;
+const RECORD_CALLBACK: u8 = 2;
+
+/// A single guest-host interaction recorded by a [`Tracer`]. Arguments and return values are
+/// stored as their `Debug` representation.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TraceEvent {
+    /// The guest called a port on the host.
+    PortCall {
+        module: String,
+        func_name: String,
+        /// The time at which the call began, relative to the creation of the tracer.
+        start: Duration,
+        duration: Duration,
+        args: String,
+        result: Result<String, String>,
+    },
+
+    /// The host invoked a closure owned by the guest.
+    Callback {
+        closure: u64,
+        start: Duration,
+        duration: Duration,
+        arg: String,
+        result: Result<(), String>,
+    },
+}
+
+impl TraceEvent {
+    pub fn start(&self) -> Duration {
+        match self {
+            TraceEvent::PortCall { start, .. } | TraceEvent::Callback { start, .. } => *start,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            TraceEvent::PortCall { duration, .. } | TraceEvent::Callback { duration, .. } => {
+                *duration
+            }
+        }
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:>12.6}s +{:>9.3}ms] ",
+            self.start().as_secs_f64(),
+            self.duration().as_secs_f64() * 1000.,
+        )?;
+
+        match self {
+            TraceEvent::PortCall {
+                module,
+                func_name,
+                args,
+                result,
+                ..
+            } => {
+                write!(f, "{module}.{func_name}({args})")?;
+
+                match result {
+                    Ok(ret) => write!(f, " -> {ret}"),
+                    Err(err) => write!(f, " failed: {err}"),
+                }
+            }
+            TraceEvent::Callback {
+                closure,
+                arg,
+                result,
+                ..
+            } => {
+                write!(f, "closure {closure:#x}({arg})")?;
+
+                match result {
+                    Ok(()) => Ok(()),
+                    Err(err) => write!(f, " failed: {err}"),
+                }
+            }
+        }
+    }
+}
+
+// === Tracer === //
+
+/// Records guest-host interactions into a compact binary trace which can be read back with a
+/// [`TraceReader`].
+///
+/// The trace starts with a magic number and a format version followed by a sequence of records.
+/// Port names are only written out the first time they are called and are referred to by index
+/// afterwards.
+///
+/// Write errors stop the recording rather than interrupting the guest. The first such error is
+/// reported by [`finish`](Tracer::finish).
+pub struct Tracer {
+    out: Box<dyn Write>,
+    epoch: Instant,
+    ports: HashMap<(String, String), u32>,
+    returns: Vec<Option<String>>,
+    error: Option<io::Error>,
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("epoch", &self.epoch)
+            .field("ports", &self.ports.len())
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tracer {
+    pub fn new(mut out: impl 'static + Write) -> anyhow::Result<Self> {
+        out.write_all(TRACE_MAGIC)?;
+        out.write_all(&TRACE_VERSION.to_le_bytes())?;
+
+        Ok(Self {
+            out: Box::new(out),
+            epoch: Instant::now(),
+            ports: HashMap::new(),
+            returns: Vec::new(),
+            error: None,
+        })
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("failed to create trace file at `{}`", path.display()))?;
+
+        Self::new(BufWriter::new(file))
+    }
+
+    /// The time elapsed since the tracer was created.
+    pub fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    /// Marks the start of a port call, returning its start time. Every call to this method should be
+    /// matched by a call to [`end_port_call`](Tracer::end_port_call).
+    pub fn begin_port_call(&mut self) -> Duration {
+        self.returns.push(None);
+        self.now()
+    }
+
+    /// Formats the arguments of a port call. Host views usually only print the guest addresses of
+    /// the values they refer to but read them out of `memory` here so that the trace records the
+    /// arguments themselves.
+    pub fn format_args(memory: &[u8], args: &impl fmt::Debug) -> String {
+        struct Restore(Option<*const [u8]>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                TRACED_MEMORY.set(self.0);
+            }
+        }
+
+        let _restore = Restore(TRACED_MEMORY.replace(Some(memory)));
+
+        format!("{args:?}")
+    }
+
+    /// Records the value returned by the innermost port call in progress.
+    pub fn record_return(&mut self, value: &impl fmt::Debug) {
+        if let Some(slot) = self.returns.last_mut() {
+            *slot = Some(format!("{value:?}"));
+        }
+    }
+
+    pub fn end_port_call(
+        &mut self,
+        module: &str,
+        func_name: &str,
+        start: Duration,
+        args: String,
+        result: Result<(), String>,
+    ) {
+        let ret = self.returns.pop().flatten();
+
+        let event = TraceEvent::PortCall {
+            module: module.to_string(),
+            func_name: func_name.to_string(),
+            start,
+            duration: self.now().saturating_sub(start),
+            args,
+            result: result.map(|()| ret.unwrap_or_default()),
+        };
+
+        self.record(&event);
+    }
+
+    pub fn record(&mut self, event: &TraceEvent) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Err(err) = self.write_event(event) {
+            self.error = Some(err);
+        }
+    }
+
+    fn write_event(&mut self, event: &TraceEvent) -> io::Result<()> {
+        match event {
+            TraceEvent::PortCall {
+                module,
+                func_name,
+                start,
+                duration,
+                args,
+                result,
+            } => {
+                let next_id = self.ports.len() as u32;
+                let port_id = match self.ports.get(&(module.clone(), func_name.clone())) {
+                    Some(&id) => id,
+                    None => {
+                        self.out.write_all(&[RECORD_DEFINE_PORT])?;
+                        write_str(&mut self.out, module)?;
+                        write_str(&mut self.out, func_name)?;
+                        self.ports
+                            .insert((module.clone(), func_name.clone()), next_id);
+                        next_id
+                    }
+                };
+
+                self.out.write_all(&[RECORD_PORT_CALL])?;
+                self.out.write_all(&port_id.to_le_bytes())?;
+                write_duration(&mut self.out, *start)?;
+                write_duration(&mut self.out, *duration)?;
+                write_str(&mut self.out, args)?;
+
+                match result {
+                    Ok(ret) => {
+                        self.out.write_all(&[0])?;
+                        write_str(&mut self.out, ret)?;
+                    }
+                    Err(err) => {
+                        self.out.write_all(&[1])?;
+                        write_str(&mut self.out, err)?;
+                    }
+                }
+            }
+            TraceEvent::Callback {
+                closure,
+                start,
+                duration,
+                arg,
+                result,
+            } => {
+                self.out.write_all(&[RECORD_CALLBACK])?;
+                self.out.write_all(&closure.to_le_bytes())?;
+                write_duration(&mut self.out, *start)?;
+                write_duration(&mut self.out, *duration)?;
+                write_str(&mut self.out, arg)?;
+
+                match result {
+                    Ok(()) => {
+                        self.out.write_all(&[0])?;
+                    }
+                    Err(err) => {
+                        self.out.write_all(&[1])?;
+                        write_str(&mut self.out, err)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the trace, reporting the first error encountered while recording it.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err).context("failed to write trace");
+        }
+
+        self.out.flush().context("failed to flush trace")
+    }
+}
+
+fn write_duration(out: &mut impl Write, duration: Duration) -> io::Result<()> {
+    out.write_all(&(duration.as_nanos() as u64).to_le_bytes())
+}
+
+fn write_str(out: &mut impl Write, value: &str) -> io::Result<()> {
+    let len = u32::try_from(value.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "string too long to trace"))?;
+
+    out.write_all(&len.to_le_bytes())?;
+    out.write_all(value.as_bytes())
+}
+
+thread_local! {
+    /// The guest memory being read by [`Tracer::format_args`], if it is in progress.
+    static TRACED_MEMORY: Cell<Option<*const [u8]>> = const { Cell::new(None) };
+}
+
+/// Runs `f` on the guest memory being read by [`Tracer::format_args`], if any.
+pub(crate) fn with_traced_memory<R>(f: impl FnOnce(&GuestMemory) -> R) -> Option<R> {
+    // The memory outlives the call to `format_args` which installed it.
+    TRACED_MEMORY
+        .get()
+        .map(|memory| f(GuestMemory::wrap_ref(unsafe { &*memory })))
+}
+
+// === TraceReader === //
+
+/// Reads back the events of a trace written by a [`Tracer`].
+pub struct TraceReader<R> {
+    input: R,
+    ports: Vec<(String, String)>,
+}
+
+impl<R> fmt::Debug for TraceReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceReader")
+            .field("ports", &self.ports)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TraceReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("failed to open trace file at `{}`", path.display()))?;
+
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(mut input: R) -> anyhow::Result<Self> {
+        let mut magic = [0; TRACE_MAGIC.len()];
+        input
+            .read_exact(&mut magic)
+            .context("failed to read trace header")?;
+
+        anyhow::ensure!(&magic == TRACE_MAGIC, "not a wasmlink trace");
+
+        let version = u16::from_le_bytes(read_array(&mut input)?);
+
+        anyhow::ensure!(
+            version == TRACE_VERSION,
+            "unsupported trace version {version} (expected {TRACE_VERSION})"
+        );
+
+        Ok(Self {
+            input,
+            ports: Vec::new(),
+        })
+    }
+
+    fn read_event(&mut self) -> anyhow::Result<Option<TraceEvent>> {
+        loop {
+            let mut tag = [0];
+
+            if self.input.read(&mut tag)? == 0 {
+                return Ok(None);
+            }
+
+            match tag[0] {
+                RECORD_DEFINE_PORT => {
+                    let module = read_str(&mut self.input)?;
+                    let func_name = read_str(&mut self.input)?;
+                    self.ports.push((module, func_name));
+                }
+                RECORD_PORT_CALL => {
+                    let port_id = u32::from_le_bytes(read_array(&mut self.input)?);
+                    let (module, func_name) = self
+                        .ports
+                        .get(port_id as usize)
+                        .with_context(|| format!("call to undefined port {port_id}"))?
+                        .clone();
+
+                    let start = read_duration(&mut self.input)?;
+                    let duration = read_duration(&mut self.input)?;
+                    let args = read_str(&mut self.input)?;
+                    let result = match read_array::<1>(&mut self.input)? {
+                        [0] => Ok(read_str(&mut self.input)?),
+                        [1] => Err(read_str(&mut self.input)?),
+                        [other] => anyhow::bail!("invalid result tag {other}"),
+                    };
+
+                    return Ok(Some(TraceEvent::PortCall {
+                        module,
+                        func_name,
+                        start,
+                        duration,
+                        args,
+                        result,
+                    }));
+                }
+                RECORD_CALLBACK => {
+                    let closure = u64::from_le_bytes(read_array(&mut self.input)?);
+                    let start = read_duration(&mut self.input)?;
+                    let duration = read_duration(&mut self.input)?;
+                    let arg = read_str(&mut self.input)?;
+                    let result = match read_array::<1>(&mut self.input)? {
+                        [0] => Ok(()),
+                        [1] => Err(read_str(&mut self.input)?),
+                        [other] => anyhow::bail!("invalid result tag {other}"),
+                    };
+
+                    return Ok(Some(TraceEvent::Callback {
+                        closure,
+                        start,
+                        duration,
+                        arg,
+                        result,
+                    }));
+                }
+                other => anyhow::bail!("unknown record tag {other}"),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = anyhow::Result<TraceEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_event().transpose()
+    }
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> anyhow::Result<[u8; N]> {
+    let mut buf = [0; N];
+    input
+        .read_exact(&mut buf)
+        .context("trace ended unexpectedly")?;
+
+    Ok(buf)
+}
+
+fn read_duration(input: &mut impl Read) -> anyhow::Result<Duration> {
+    Ok(Duration::from_nanos(u64::from_le_bytes(read_array(input)?)))
+}
+
+fn read_str(input: &mut impl Read) -> anyhow::Result<String> {
+    let len = u32::from_le_bytes(read_array(input)?);
+    let mut buf = vec![0; len as usize];
+    input
+        .read_exact(&mut buf)
+        .context("trace ended unexpectedly")?;
+
+    String::from_utf8(buf).context("invalid UTF-8 sequence in trace")
+}
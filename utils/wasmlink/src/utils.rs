@@ -33,9 +33,11 @@ pub const fn guest_usize_to_u32(val: usize) -> u32 {
         if macro(is_wasm) {
             val as u32
         } else {
-            _ = val;
+            if val > u32::MAX as usize {
+                panic!("value is too large for guest")
+            }
 
-            unimplemented!();
+            val as u32
         }
     }
 }